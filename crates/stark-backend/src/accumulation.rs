@@ -0,0 +1,28 @@
+//! Strategies folding the constraints of an AIR into the single constraint divided by the
+//! vanishing polynomial to obtain the quotient polynomial.
+
+use itertools::Itertools;
+use p3_field::Field;
+
+/// Computes the coefficients of the random linear combination of the constraints of an AIR from
+/// its `alpha` challenge. The prover and the verifier must use the same strategy.
+///
+/// For soundness, a nonzero combination of the constraints must vanish only with negligible
+/// probability over `alpha`.
+pub trait AccumulationStrategy<EF>: Send + Sync {
+    /// Returns the coefficient of each of the `num_constraints` constraints, in constraint order.
+    fn coefficients(&self, alpha: EF, num_constraints: usize) -> Vec<EF>;
+}
+
+/// Folds constraint `i` of `n` with `alpha^(n - 1 - i)`, as in Horner's method. This is the
+/// default strategy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlphaPowers;
+
+impl<EF: Field> AccumulationStrategy<EF> for AlphaPowers {
+    fn coefficients(&self, alpha: EF, num_constraints: usize) -> Vec<EF> {
+        let mut coefficients = alpha.powers().take(num_constraints).collect_vec();
+        coefficients.reverse();
+        coefficients
+    }
+}
@@ -1,11 +1,11 @@
-use itertools::izip;
+use itertools::{izip, Itertools};
 use p3_air::BaseAir;
 use p3_field::{Field, FieldAlgebra};
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair, Matrix};
+use p3_matrix::{dense::RowMajorMatrixView, Matrix};
 use p3_maybe_rayon::prelude::*;
 
 use crate::{
-    air_builders::debug::DebugConstraintBuilder,
+    air_builders::debug::{DebugConstraintBuilder, FailedConstraint},
     config::{StarkGenericConfig, Val},
     interaction::{
         debug::{generate_logical_interactions, LogicalInteractions},
@@ -14,7 +14,8 @@ use crate::{
     rap::{PartitionedBaseAir, Rap},
 };
 
-/// Check that all constraints vanish on the subgroup.
+/// Check that all constraints vanish on the subgroup. The constraints may read `num_rotations`
+/// consecutive rows.
 #[allow(clippy::too_many_arguments)]
 pub fn check_constraints<R, SC>(
     rap: &R,
@@ -22,6 +23,7 @@ pub fn check_constraints<R, SC>(
     preprocessed: &Option<RowMajorMatrixView<Val<SC>>>,
     partitioned_main: &[RowMajorMatrixView<Val<SC>>],
     public_values: &[Val<SC>],
+    num_rotations: usize,
 ) where
     R: for<'a> Rap<DebugConstraintBuilder<'a, SC>>
         + BaseAir<Val<SC>>
@@ -29,65 +31,135 @@ pub fn check_constraints<R, SC>(
         + ?Sized,
     SC: StarkGenericConfig,
 {
-    let height = partitioned_main[0].height();
-    assert!(partitioned_main.iter().all(|mat| mat.height() == height));
+    let height = trace_height(preprocessed, partitioned_main);
 
     // Check that constraints are satisfied.
     (0..height).into_par_iter().for_each(|i| {
-        let i_next = (i + 1) % height;
+        eval_on_row(
+            rap,
+            rap_name,
+            preprocessed,
+            partitioned_main,
+            public_values,
+            i,
+            num_rotations,
+            None,
+        );
+    });
+}
 
-        let (preprocessed_local, preprocessed_next) = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                (
-                    preprocessed.row_slice(i).to_vec(),
-                    preprocessed.row_slice(i_next).to_vec(),
-                )
-            })
-            .unwrap_or((vec![], vec![]));
+/// Evaluates the constraints of `rap` on the row with index `row_index` only, and returns the
+/// constraints which do not vanish there, instead of panicking on the first one.
+///
+/// This pinpoints the failing constraints of a row known to be wrong, e.g. after a verification
+/// failure, without checking the whole trace. Constraints are indexed in the order `eval` asserts
+/// them, which is also their order in the symbolic constraints of the verifying key.
+pub fn debug_constraints_on_row<R, SC>(
+    rap: &R,
+    preprocessed: &Option<RowMajorMatrixView<Val<SC>>>,
+    partitioned_main: &[RowMajorMatrixView<Val<SC>>],
+    public_values: &[Val<SC>],
+    row_index: usize,
+) -> Vec<FailedConstraint<SC::Challenge>>
+where
+    R: for<'a> Rap<DebugConstraintBuilder<'a, SC>>
+        + BaseAir<Val<SC>>
+        + PartitionedBaseAir<Val<SC>>
+        + ?Sized,
+    SC: StarkGenericConfig,
+{
+    let height = trace_height(preprocessed, partitioned_main);
+    assert!(row_index < height, "row {row_index} is out of bounds");
+    eval_on_row(
+        rap,
+        "",
+        preprocessed,
+        partitioned_main,
+        public_values,
+        row_index,
+        height,
+        Some(vec![]),
+    )
+    .unwrap()
+}
 
-        let partitioned_main_row_pair = partitioned_main
-            .iter()
-            .map(|part| (part.row_slice(i), part.row_slice(i_next)))
-            .collect::<Vec<_>>();
-        let partitioned_main = partitioned_main_row_pair
-            .iter()
-            .map(|(local, next)| {
-                VerticalPair::new(
-                    RowMajorMatrixView::new_row(local),
-                    RowMajorMatrixView::new_row(next),
-                )
-            })
-            .collect::<Vec<_>>();
+fn trace_height<F>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    partitioned_main: &[RowMajorMatrixView<F>],
+) -> usize {
+    let height = partitioned_main
+        .first()
+        .or(preprocessed.as_ref())
+        .expect("no main or preprocessed trace")
+        .height();
+    assert!(partitioned_main.iter().all(|mat| mat.height() == height));
+    height
+}
 
-        let mut builder = DebugConstraintBuilder {
-            air_name: rap_name,
-            row_index: i,
-            preprocessed: VerticalPair::new(
-                RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
-                RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
-            ),
-            partitioned_main,
-            after_challenge: vec![], // unreachable
-            challenges: &[],         // unreachable
-            public_values,
-            exposed_values_after_challenge: &[], // unreachable
-            is_first_row: Val::<SC>::ZERO,
-            is_last_row: Val::<SC>::ZERO,
-            is_transition: Val::<SC>::ONE,
-            rap_phase_seq_kind: RapPhaseSeqKind::FriLogUp, // unused
-            has_common_main: rap.common_main_width() > 0,
-        };
-        if i == 0 {
-            builder.is_first_row = Val::<SC>::ONE;
-        }
-        if i == height - 1 {
-            builder.is_last_row = Val::<SC>::ONE;
-            builder.is_transition = Val::<SC>::ZERO;
-        }
+/// Evaluates the constraints on row `i`, reading the `num_rotations` rows starting at row `i`.
+/// Returns the failed constraints if `failed_constraints` is set, otherwise panics on the first
+/// failed constraint.
+#[allow(clippy::too_many_arguments)]
+fn eval_on_row<R, SC>(
+    rap: &R,
+    rap_name: &str,
+    preprocessed: &Option<RowMajorMatrixView<Val<SC>>>,
+    partitioned_main: &[RowMajorMatrixView<Val<SC>>],
+    public_values: &[Val<SC>],
+    i: usize,
+    num_rotations: usize,
+    failed_constraints: Option<Vec<FailedConstraint<SC::Challenge>>>,
+) -> Option<Vec<FailedConstraint<SC::Challenge>>>
+where
+    R: for<'a> Rap<DebugConstraintBuilder<'a, SC>>
+        + BaseAir<Val<SC>>
+        + PartitionedBaseAir<Val<SC>>
+        + ?Sized,
+    SC: StarkGenericConfig,
+{
+    let height = trace_height(preprocessed, partitioned_main);
 
-        rap.eval(&mut builder);
-    });
+    // The rows `i, ..., i + num_rotations - 1` of a matrix, wrapping around, and its width
+    let window = |mat: &RowMajorMatrixView<Val<SC>>| {
+        let rows = (0..num_rotations)
+            .flat_map(|offset| mat.row_slice((i + offset) % height).to_vec())
+            .collect_vec();
+        (rows, mat.width())
+    };
+    let preprocessed_window = preprocessed.as_ref().map(window).unwrap_or_default();
+    let partitioned_main_windows = partitioned_main.iter().map(window).collect_vec();
+
+    let mut builder = DebugConstraintBuilder {
+        air_name: rap_name,
+        row_index: i,
+        height,
+        preprocessed: RowMajorMatrixView::new(&preprocessed_window.0, preprocessed_window.1),
+        partitioned_main: partitioned_main_windows
+            .iter()
+            .map(|(rows, width)| RowMajorMatrixView::new(rows, *width))
+            .collect(),
+        after_challenge: vec![], // unreachable
+        challenges: &[],         // unreachable
+        public_values,
+        exposed_values_after_challenge: &[], // unreachable
+        is_first_row: Val::<SC>::ZERO,
+        is_last_row: Val::<SC>::ZERO,
+        is_transition: Val::<SC>::ONE,
+        rap_phase_seq_kind: RapPhaseSeqKind::FriLogUp, // unused
+        has_common_main: rap.common_main_width() > 0,
+        constraint_idx: 0,
+        failed_constraints,
+    };
+    if i == 0 {
+        builder.is_first_row = Val::<SC>::ONE;
+    }
+    if i == height - 1 {
+        builder.is_last_row = Val::<SC>::ONE;
+        builder.is_transition = Val::<SC>::ZERO;
+    }
+
+    rap.eval(&mut builder);
+    builder.failed_constraints
 }
 
 pub fn check_logup<F: Field>(
@@ -111,27 +183,21 @@ pub fn check_logup<F: Field>(
         );
     }
 
-    let mut logup_failed = false;
-    // For each bus, check each `fields` key by summing up multiplicities.
-    for (bus_idx, bus_interactions) in logical_interactions.at_bus.into_iter() {
-        for (fields, connections) in bus_interactions.into_iter() {
-            let sum: F = connections.iter().map(|(_, count)| *count).sum();
-            if !sum.is_zero() {
-                logup_failed = true;
-                println!(
-                    "Bus {} failed to balance the multiplicities for fields={:?}. The bus connections for this were:",
-                    bus_idx, fields
-                );
-                for (air_idx, count) in connections {
-                    println!(
-                        "   Air idx: {}, Air name: {}, count: {:?}",
-                        air_idx, air_names[air_idx], count
-                    );
-                }
-            }
+    let imbalances = logical_interactions.imbalances();
+    for imbalance in &imbalances {
+        println!(
+            "Bus {} failed to balance the multiplicities for fields={:?}. The bus connections for this were:",
+            imbalance.bus_index, imbalance.fields
+        );
+        for &(air_idx, count) in &imbalance.connections {
+            println!(
+                "   Air idx: {}, Air name: {}, count: {:?}",
+                air_idx, air_names[air_idx], count
+            );
         }
     }
-    if logup_failed {
+
+    if !imbalances.is_empty() {
         panic!("LogUp multiset equality check failed.");
     }
 }
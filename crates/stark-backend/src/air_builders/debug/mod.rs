@@ -2,17 +2,18 @@ use std::sync::{Arc, Mutex};
 
 use itertools::{izip, Itertools};
 use p3_air::{
-    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
+    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, FilteredAirBuilder, PairBuilder,
+    PermutationAirBuilder,
 };
-use p3_field::FieldAlgebra;
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrixView;
 
-use super::{symbolic::SymbolicConstraints, PartitionedAirBuilder, ViewPair};
+use super::{symbolic::SymbolicConstraints, ConditionalAirBuilder, PartitionedAirBuilder};
 use crate::{
     config::{StarkGenericConfig, Val},
     interaction::{
-        rap::InteractionPhaseAirBuilder, Interaction, InteractionBuilder, RapPhaseSeqKind,
-        SymbolicInteraction,
+        fri_log_up::FingerprintScheme, rap::InteractionPhaseAirBuilder, Interaction,
+        InteractionBuilder, PermutationInteractionType, RapPhaseSeqKind, SymbolicInteraction,
     },
     keygen::types::StarkProvingKey,
     rap::{AnyRap, PermutationAirBuilderWithExposedValues},
@@ -21,6 +22,7 @@ use crate::{
 mod check_constraints;
 
 use check_constraints::*;
+pub use check_constraints::debug_constraints_on_row;
 
 use crate::interaction::BusIndex;
 
@@ -54,6 +56,7 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
                         &preprocessed_trace,
                         main,
                         public_values,
+                        pk.vk.num_rotations(),
                     );
                     preprocessed_trace
                 })
@@ -63,7 +66,7 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
                 .iter()
                 .map(|pk| {
                     let sym_constraints = SymbolicConstraints::from(&pk.vk.symbolic_constraints);
-                    (pk.air_name.clone(), sym_constraints.interactions)
+                    (pk.name().to_owned(), sym_constraints.interactions)
                 })
                 .unzip();
             check_logup(
@@ -82,9 +85,12 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
 pub struct DebugConstraintBuilder<'a, SC: StarkGenericConfig> {
     pub air_name: &'a str,
     pub row_index: usize,
-    pub preprocessed: ViewPair<'a, Val<SC>>,
-    pub partitioned_main: Vec<ViewPair<'a, Val<SC>>>,
-    pub after_challenge: Vec<ViewPair<'a, SC::Challenge>>,
+    /// Height of the trace.
+    pub height: usize,
+    /// Rows of each trace matrix starting at `row_index`, wrapping around.
+    pub preprocessed: RowMajorMatrixView<'a, Val<SC>>,
+    pub partitioned_main: Vec<RowMajorMatrixView<'a, Val<SC>>>,
+    pub after_challenge: Vec<RowMajorMatrixView<'a, SC::Challenge>>,
     pub challenges: &'a [Vec<SC::Challenge>],
     pub is_first_row: Val<SC>,
     pub is_last_row: Val<SC>,
@@ -93,6 +99,40 @@ pub struct DebugConstraintBuilder<'a, SC: StarkGenericConfig> {
     pub exposed_values_after_challenge: &'a [Vec<SC::Challenge>],
     pub rap_phase_seq_kind: RapPhaseSeqKind,
     pub has_common_main: bool,
+    /// Index of the next constraint to be asserted.
+    pub constraint_idx: usize,
+    /// If set, failed constraints are collected here instead of panicking.
+    pub failed_constraints: Option<Vec<FailedConstraint<SC::Challenge>>>,
+}
+
+/// A constraint which does not vanish on a row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailedConstraint<EF> {
+    pub row_index: usize,
+    /// Index of the constraint in the order it is asserted by `eval`.
+    pub constraint_idx: usize,
+    /// The nonzero value of the constraint.
+    pub value: EF,
+}
+
+impl<SC: StarkGenericConfig> DebugConstraintBuilder<'_, SC> {
+    /// Advances to the next constraint. When collecting failed constraints, records the
+    /// constraint if `value` is nonzero and returns `true`, so the caller does not panic.
+    fn collect_constraint(&mut self, value: SC::Challenge) -> bool {
+        let constraint_idx = self.constraint_idx;
+        self.constraint_idx += 1;
+        let Some(failed_constraints) = &mut self.failed_constraints else {
+            return false;
+        };
+        if !value.is_zero() {
+            failed_constraints.push(FailedConstraint {
+                row_index: self.row_index,
+                constraint_idx,
+                value,
+            });
+        }
+        true
+    }
 }
 
 impl<'a, SC> AirBuilder for DebugConstraintBuilder<'a, SC>
@@ -102,7 +142,7 @@ where
     type F = Val<SC>;
     type Expr = Val<SC>;
     type Var = Val<SC>;
-    type M = VerticalPair<RowMajorMatrixView<'a, Val<SC>>, RowMajorMatrixView<'a, Val<SC>>>;
+    type M = RowMajorMatrixView<'a, Val<SC>>;
 
     /// It is difficult to horizontally concatenate matrices when the main trace is partitioned, so we disable this method in that case.
     fn main(&self) -> Self::M {
@@ -122,16 +162,20 @@ where
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
+        match size {
+            2 => self.is_transition,
+            3.. => Val::<SC>::from_bool(self.row_index + size <= self.height),
+            _ => panic!("transition window size must be at least 2"),
         }
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        let x = x.into();
+        if self.collect_constraint(x.into()) {
+            return;
+        }
         assert_eq!(
-            x.into(),
+            x,
             Val::<SC>::ZERO,
             "constraints had nonzero value on air {},row {}",
             self.air_name,
@@ -142,6 +186,9 @@ where
     fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
         let x = x.into();
         let y = y.into();
+        if self.collect_constraint((x - y).into()) {
+            return;
+        }
         assert_eq!(
             x, y,
             "values didn't match on air {}, row {}: {} != {}",
@@ -171,8 +218,12 @@ where
     where
         I: Into<Self::ExprEF>,
     {
+        let x = x.into();
+        if self.collect_constraint(x) {
+            return;
+        }
         assert_eq!(
-            x.into(),
+            x,
             SC::Challenge::ZERO,
             "constraints had nonzero value on row {}",
             self.row_index
@@ -186,6 +237,9 @@ where
     {
         let x = x.into();
         let y = y.into();
+        if self.collect_constraint(x - y) {
+            return;
+        }
         assert_eq!(
             x, y,
             "values didn't match on air {}, row {}: {} != {}",
@@ -198,7 +252,7 @@ impl<'a, SC> PermutationAirBuilder for DebugConstraintBuilder<'a, SC>
 where
     SC: StarkGenericConfig,
 {
-    type MP = ViewPair<'a, SC::Challenge>;
+    type MP = RowMajorMatrixView<'a, SC::Challenge>;
 
     type RandomVar = SC::Challenge;
 
@@ -255,17 +309,37 @@ where
     }
 }
 
+impl<SC> ConditionalAirBuilder for DebugConstraintBuilder<'_, SC>
+where
+    SC: StarkGenericConfig,
+{
+    /// Skips `eval` on rows where `condition` is zero. When collecting failed constraints, `eval`
+    /// is evaluated regardless, so that the constraint indices are the same on all rows.
+    fn when_lazy<I: Into<Self::Expr>>(
+        &mut self,
+        condition: I,
+        eval: impl FnOnce(&mut FilteredAirBuilder<'_, Self>),
+    ) {
+        let condition = condition.into();
+        if condition.is_zero() && self.failed_constraints.is_none() {
+            return;
+        }
+        eval(&mut self.when(condition));
+    }
+}
+
 // No-op implementation
 impl<SC> InteractionBuilder for DebugConstraintBuilder<'_, SC>
 where
     SC: StarkGenericConfig,
 {
-    fn push_interaction<E: Into<Self::Expr>>(
+    fn push_interaction_with_kind<E: Into<Self::Expr>>(
         &mut self,
         _bus_index: BusIndex,
         _fields: impl IntoIterator<Item = E>,
         _count: impl Into<Self::Expr>,
         _count_weight: u32,
+        _kind: Option<PermutationInteractionType>,
     ) {
         // no-op, interactions are debugged elsewhere
     }
@@ -291,6 +365,10 @@ impl<SC: StarkGenericConfig> InteractionPhaseAirBuilder for DebugConstraintBuild
         self.rap_phase_seq_kind
     }
 
+    fn fingerprint_scheme(&self) -> FingerprintScheme {
+        FingerprintScheme::default()
+    }
+
     fn symbolic_interactions(&self) -> Vec<SymbolicInteraction<Val<SC>>> {
         vec![]
     }
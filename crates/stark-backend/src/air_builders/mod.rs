@@ -1,4 +1,4 @@
-use p3_air::AirBuilder;
+use p3_air::{AirBuilder, FilteredAirBuilder};
 use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
 
 pub mod debug;
@@ -17,3 +17,20 @@ pub trait PartitionedAirBuilder: AirBuilder {
     /// Common main trace matrix. Panic if there is no common main trace.
     fn common_main(&self) -> &Self::M;
 }
+
+/// AIR builder which may skip the constraints filtered by a condition on rows where the
+/// condition is zero, instead of evaluating them and multiplying them by the condition.
+pub trait ConditionalAirBuilder: AirBuilder {
+    /// Asserts the constraints of `eval` when `condition` is nonzero, like
+    /// `eval(&mut self.when(condition))`.
+    ///
+    /// Builders evaluating the constraints on concrete rows may skip `eval` on rows where
+    /// `condition` is zero, so expensive constraints guarded by a rare selector are cheap to check.
+    fn when_lazy<I: Into<Self::Expr>>(
+        &mut self,
+        condition: I,
+        eval: impl FnOnce(&mut FilteredAirBuilder<'_, Self>),
+    ) {
+        eval(&mut self.when(condition));
+    }
+}
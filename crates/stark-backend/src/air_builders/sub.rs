@@ -5,9 +5,16 @@ use std::{
     ops::{Deref, Range},
 };
 
-use p3_air::{AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
 use p3_matrix::Matrix;
 
+use crate::{
+    interaction::{
+        BusIndex, Interaction, InteractionBuilder, InteractionSpec, PermutationInteractionType,
+    },
+    rap::{BaseAirWithPublicValues, ColumnsAir, InteractionsAir, PartitionedBaseAir},
+};
+
 /// A submatrix of a matrix.  The matrix will contain a subset of the columns of `self.inner`.
 pub struct SubMatrixRowSlices<M: Matrix<T>, T: Send + Sync> {
     inner: M,
@@ -62,6 +69,8 @@ impl<M: Matrix<T>, T: Send + Sync> Matrix<T> for SubMatrixRowSlices<M, T> {
 pub struct SubAirBuilder<'a, AB: AirBuilder, SubAir: BaseAir<T>, T> {
     inner: &'a mut AB,
     column_range: Range<usize>,
+    /// The subset of the public values of `inner` visible to the sub-air. All if `None`.
+    public_values_range: Option<Range<usize>>,
     _phantom: std::marker::PhantomData<(SubAir, T)>,
 }
 
@@ -70,9 +79,16 @@ impl<'a, AB: AirBuilder, SubAir: BaseAir<T>, T> SubAirBuilder<'a, AB, SubAir, T>
         Self {
             inner,
             column_range,
+            public_values_range: None,
             _phantom: std::marker::PhantomData,
         }
     }
+
+    /// Restricts the public values visible to the sub-air to `public_values_range`.
+    pub fn with_public_values(mut self, public_values_range: Range<usize>) -> Self {
+        self.public_values_range = Some(public_values_range);
+        self
+    }
 }
 
 /// Implement `AirBuilder` for `SubAirBuilder`.
@@ -104,3 +120,121 @@ impl<AB: AirBuilder, SubAir: BaseAir<F>, F> AirBuilder for SubAirBuilder<'_, AB,
         self.inner.assert_zero(x.into());
     }
 }
+
+impl<AB, SubAir, F> AirBuilderWithPublicValues for SubAirBuilder<'_, AB, SubAir, F>
+where
+    AB: AirBuilderWithPublicValues,
+    SubAir: BaseAir<F>,
+{
+    type PublicVar = AB::PublicVar;
+
+    fn public_values(&self) -> &[Self::PublicVar] {
+        let public_values = self.inner.public_values();
+        match &self.public_values_range {
+            Some(range) => &public_values[range.clone()],
+            None => public_values,
+        }
+    }
+}
+
+impl<AB: InteractionBuilder, SubAir: BaseAir<F>, F> InteractionBuilder
+    for SubAirBuilder<'_, AB, SubAir, F>
+{
+    fn push_interaction_with_kind<E: Into<Self::Expr>>(
+        &mut self,
+        bus_index: BusIndex,
+        fields: impl IntoIterator<Item = E>,
+        count: impl Into<Self::Expr>,
+        count_weight: u32,
+        kind: Option<PermutationInteractionType>,
+    ) {
+        self.inner
+            .push_interaction_with_kind(bus_index, fields, count, count_weight, kind);
+    }
+
+    fn num_interactions(&self) -> usize {
+        self.inner.num_interactions()
+    }
+
+    /// Returns all interactions of the parent builder, including those not from this sub-air.
+    fn all_interactions(&self) -> &[Interaction<Self::Expr>] {
+        self.inner.all_interactions()
+    }
+}
+
+/// Two AIRs of the same trace height placed side-by-side as one wider AIR, to reduce the
+/// number of committed matrices and quotient polynomials.
+///
+/// The main trace of the merged AIR is the horizontal concatenation of the main traces of `left`
+/// and `right`, and its public values are the concatenation of their public values. Each AIR's
+/// constraints and interactions are evaluated over its own column and public value ranges.
+/// More than two AIRs can be merged by nesting.
+///
+/// Both AIRs must only have a common main trace (no preprocessed or cached main traces) and must
+/// not have [variable public values](BaseAirWithPublicValues::has_variable_public_values).
+pub struct MergedAir<A, B> {
+    pub left: A,
+    pub right: B,
+}
+
+impl<A, B> MergedAir<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        Self { left, right }
+    }
+}
+
+impl<F, A: BaseAir<F>, B: BaseAir<F>> BaseAir<F> for MergedAir<A, B> {
+    fn width(&self) -> usize {
+        self.left.width() + self.right.width()
+    }
+}
+
+impl<F, A, B> BaseAirWithPublicValues<F> for MergedAir<A, B>
+where
+    A: BaseAirWithPublicValues<F>,
+    B: BaseAirWithPublicValues<F>,
+{
+    fn num_public_values(&self) -> usize {
+        self.left.num_public_values() + self.right.num_public_values()
+    }
+}
+
+impl<F, A: BaseAir<F>, B: BaseAir<F>> PartitionedBaseAir<F> for MergedAir<A, B> {}
+
+impl<F, A: ColumnsAir<F>, B: ColumnsAir<F>> ColumnsAir<F> for MergedAir<A, B> {
+    fn columns(&self) -> Option<Vec<String>> {
+        let mut columns = self.left.columns()?;
+        columns.extend(self.right.columns()?);
+        Some(columns)
+    }
+}
+
+impl<F, A: InteractionsAir<F>, B: InteractionsAir<F>> InteractionsAir<F> for MergedAir<A, B> {
+    fn declare_interactions(&self) -> Vec<InteractionSpec> {
+        let mut interactions = self.left.declare_interactions();
+        interactions.extend(self.right.declare_interactions());
+        interactions
+    }
+}
+
+impl<AB, A, B> Air<AB> for MergedAir<A, B>
+where
+    AB: InteractionBuilder + AirBuilderWithPublicValues,
+    A: BaseAirWithPublicValues<AB::F> + for<'a> Air<SubAirBuilder<'a, AB, A, AB::F>>,
+    B: BaseAirWithPublicValues<AB::F> + for<'a> Air<SubAirBuilder<'a, AB, B, AB::F>>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let left_width = self.left.width();
+        let left_num_pvs = self.left.num_public_values();
+        let width = left_width + self.right.width();
+        let num_pvs = left_num_pvs + self.right.num_public_values();
+
+        let mut left_builder = SubAirBuilder::<AB, A, AB::F>::new(builder, 0..left_width)
+            .with_public_values(0..left_num_pvs);
+        self.left.eval(&mut left_builder);
+
+        let mut right_builder = SubAirBuilder::<AB, B, AB::F>::new(builder, left_width..width)
+            .with_public_values(left_num_pvs..num_pvs);
+        self.right.eval(&mut right_builder);
+    }
+}
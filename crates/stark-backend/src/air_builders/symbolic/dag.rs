@@ -1,5 +1,6 @@
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
 
+use itertools::Itertools;
 use p3_field::Field;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
@@ -7,15 +8,16 @@ use serde::{Deserialize, Serialize};
 use super::SymbolicConstraints;
 use crate::{
     air_builders::symbolic::{
-        symbolic_expression::SymbolicExpression, symbolic_variable::SymbolicVariable,
+        symbolic_expression::SymbolicExpression,
+        symbolic_variable::{Entry, SymbolicVariable},
     },
-    interaction::{Interaction, SymbolicInteraction},
+    interaction::{Interaction, PermutationInteractionType, SymbolicInteraction},
 };
 
 /// A node in symbolic expression DAG.
 /// Basically replace `Arc`s in `SymbolicExpression` with node IDs.
 /// Intended to be serializable and deserializable.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(bound(serialize = "F: Serialize", deserialize = "F: Deserialize<'de>"))]
 #[repr(C)]
 pub enum SymbolicExpressionNode<F> {
@@ -23,6 +25,7 @@ pub enum SymbolicExpressionNode<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         left_idx: usize,
@@ -45,7 +48,7 @@ pub enum SymbolicExpressionNode<F> {
     },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(bound(serialize = "F: Serialize", deserialize = "F: Deserialize<'de>"))]
 #[repr(C)]
 pub struct SymbolicExpressionDag<F> {
@@ -66,12 +69,200 @@ impl<F> SymbolicExpressionDag<F> {
         rotation
     }
 
+    /// Returns the distinct sizes of the transition windows selected by
+    /// [IsTransitionWindow](SymbolicExpressionNode::IsTransitionWindow) nodes, in increasing order.
+    pub fn transition_window_sizes(&self) -> Vec<usize> {
+        let sizes: BTreeSet<usize> = self
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                SymbolicExpressionNode::IsTransitionWindow(size) => Some(*size),
+                _ => None,
+            })
+            .collect();
+        sizes.into_iter().collect()
+    }
+
     pub fn num_constraints(&self) -> usize {
         self.constraint_idx.len()
     }
+
+    /// Returns the maximum degree multiple of the constraints, from the degree multiples stored
+    /// in the nodes, or 0 if there are no constraints.
+    pub fn max_constraint_degree(&self) -> usize {
+        self.constraint_idx
+            .iter()
+            .map(|&idx| match &self.nodes[idx] {
+                SymbolicExpressionNode::Variable(var) => var.entry.degree_multiple(),
+                SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransitionWindow(_) => 1,
+                SymbolicExpressionNode::IsTransition | SymbolicExpressionNode::Constant(_) => 0,
+                SymbolicExpressionNode::Add {
+                    degree_multiple, ..
+                }
+                | SymbolicExpressionNode::Sub {
+                    degree_multiple, ..
+                }
+                | SymbolicExpressionNode::Neg {
+                    degree_multiple, ..
+                }
+                | SymbolicExpressionNode::Mul {
+                    degree_multiple, ..
+                } => *degree_multiple,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns, for each constraint in order, the trace columns that the constraint reads.
+    pub fn constraint_column_deps(&self) -> Vec<ConstraintDeps> {
+        // Nodes are topologically sorted, so the columns read by the children of a node are known
+        // when the node is reached, and each node is visited once.
+        let mut deps_per_node: Vec<ConstraintDeps> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let deps = match node {
+                SymbolicExpressionNode::Variable(var) => {
+                    let mut deps = ConstraintDeps::default();
+                    match var.entry {
+                        Entry::Preprocessed { .. } => deps.preprocessed.push(var.index),
+                        Entry::Main { part_index, .. } => deps.main.push((part_index, var.index)),
+                        _ => {}
+                    }
+                    deps.reads_next = var.entry.offset().unwrap_or(0) > 0;
+                    deps
+                }
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    ..
+                }
+                | SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    ..
+                }
+                | SymbolicExpressionNode::Mul {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => deps_per_node[*left_idx].union(&deps_per_node[*right_idx]),
+                SymbolicExpressionNode::Neg { idx, .. } => deps_per_node[*idx].clone(),
+                SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransition
+                | SymbolicExpressionNode::IsTransitionWindow(_)
+                | SymbolicExpressionNode::Constant(_) => ConstraintDeps::default(),
+            };
+            deps_per_node.push(deps);
+        }
+        self.constraint_idx
+            .iter()
+            .map(|&idx| deps_per_node[idx].clone())
+            .collect()
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl<F: Field> SymbolicExpressionDag<F> {
+    /// Folds constant subexpressions and returns, for each constraint in order, its value if the
+    /// constraint folds to a constant. A constraint folding to zero is always satisfied, while
+    /// one folding to a nonzero constant can never be satisfied.
+    ///
+    /// Only constants are folded, together with multiplication by zero. A constraint which is
+    /// constant only after algebraic simplification, such as `x - x`, is not detected.
+    pub fn fold_constant_constraints(&self) -> Vec<Option<F>> {
+        let mut values: Vec<Option<F>> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match *node {
+                SymbolicExpressionNode::Constant(c) => Some(c),
+                SymbolicExpressionNode::Variable(_)
+                | SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransition
+                | SymbolicExpressionNode::IsTransitionWindow(_) => None,
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => values[left_idx].zip(values[right_idx]).map(|(l, r)| l + r),
+                SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => values[left_idx].zip(values[right_idx]).map(|(l, r)| l - r),
+                SymbolicExpressionNode::Neg { idx, .. } => values[idx].map(|v| -v),
+                SymbolicExpressionNode::Mul {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => match (values[left_idx], values[right_idx]) {
+                    (Some(l), Some(r)) => Some(l * r),
+                    (Some(c), None) | (None, Some(c)) if c.is_zero() => Some(F::ZERO),
+                    _ => None,
+                },
+            };
+            values.push(value);
+        }
+        self.constraint_idx.iter().map(|&idx| values[idx]).collect()
+    }
+
+    /// Whether the interaction with count node `count_idx` sends or receives. As for
+    /// [InteractionSpec](crate::interaction::InteractionSpec), an interaction is a receive if its
+    /// count is negated or multiplied by `-1`.
+    pub fn interaction_type(&self, count_idx: usize) -> PermutationInteractionType {
+        let is_neg_one = |idx: usize| {
+            matches!(self.nodes[idx], SymbolicExpressionNode::Constant(c) if c == F::NEG_ONE)
+        };
+        match self.nodes[count_idx] {
+            SymbolicExpressionNode::Neg { .. } => PermutationInteractionType::Receive,
+            SymbolicExpressionNode::Mul {
+                left_idx,
+                right_idx,
+                ..
+            } if is_neg_one(left_idx) || is_neg_one(right_idx) => {
+                PermutationInteractionType::Receive
+            }
+            _ => PermutationInteractionType::Send,
+        }
+    }
+}
+
+/// The trace columns read by a single constraint, as returned by
+/// [SymbolicExpressionDag::constraint_column_deps].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConstraintDeps {
+    /// Sorted `(part_index, column)` pairs of the partitioned main trace columns read.
+    pub main: Vec<(usize, usize)>,
+    /// Sorted indices of the preprocessed trace columns read.
+    pub preprocessed: Vec<usize>,
+    /// Whether the constraint reads any trace column (including after-challenge columns) on the
+    /// next row.
+    pub reads_next: bool,
+}
+
+impl ConstraintDeps {
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            main: self
+                .main
+                .iter()
+                .merge(&other.main)
+                .dedup()
+                .copied()
+                .collect(),
+            preprocessed: self
+                .preprocessed
+                .iter()
+                .merge(&other.preprocessed)
+                .dedup()
+                .copied()
+                .collect(),
+            reads_next: self.reads_next || other.reads_next,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Hash)]
 #[serde(bound(serialize = "F: Serialize", deserialize = "F: Deserialize<'de>"))]
 #[repr(C)]
 pub struct SymbolicConstraintsDag<F> {
@@ -91,15 +282,30 @@ pub struct SymbolicConstraintsDag<F> {
     pub interactions: Vec<Interaction<usize>>,
 }
 
+/// Builds the constraint DAG from symbolic constraints and interactions.
+///
+/// The construction is deterministic: nodes are appended in the order of a depth-first traversal
+/// of `constraints` followed by `interactions`, and the expression caches are only used for
+/// lookups.
+///
+/// Structurally identical subexpressions share a node, even if they are built from distinct
+/// `Arc`s: leaves are identified by value, and compound expressions by their operation and the
+/// nodes of their operands.
+/// In particular, keygen and any later rebuild from the same AIR produce identical DAGs, so the
+/// DAG the prover evaluates from the proving key is the same as the one the verifier evaluates from
+/// the verifying key.
 pub(crate) fn build_symbolic_constraints_dag<F: Field>(
     constraints: &[SymbolicExpression<F>],
     interactions: &[SymbolicInteraction<F>],
 ) -> SymbolicConstraintsDag<F> {
     let mut expr_to_idx = FxHashMap::default();
+    let mut node_to_idx = FxHashMap::default();
     let mut nodes = Vec::new();
     let mut constraint_idx: Vec<usize> = constraints
         .iter()
-        .map(|expr| topological_sort_symbolic_expr(expr, &mut expr_to_idx, &mut nodes))
+        .map(|expr| {
+            topological_sort_symbolic_expr(expr, &mut expr_to_idx, &mut node_to_idx, &mut nodes)
+        })
         .collect();
     constraint_idx.sort();
     let interactions: Vec<Interaction<usize>> = interactions
@@ -109,16 +315,26 @@ pub(crate) fn build_symbolic_constraints_dag<F: Field>(
                 .message
                 .iter()
                 .map(|field_expr| {
-                    topological_sort_symbolic_expr(field_expr, &mut expr_to_idx, &mut nodes)
+                    topological_sort_symbolic_expr(
+                        field_expr,
+                        &mut expr_to_idx,
+                        &mut node_to_idx,
+                        &mut nodes,
+                    )
                 })
                 .collect();
-            let count =
-                topological_sort_symbolic_expr(&interaction.count, &mut expr_to_idx, &mut nodes);
+            let count = topological_sort_symbolic_expr(
+                &interaction.count,
+                &mut expr_to_idx,
+                &mut node_to_idx,
+                &mut nodes,
+            );
             Interaction {
                 message: fields,
                 count,
                 bus_index: interaction.bus_index,
                 count_weight: interaction.count_weight,
+                kind: interaction.kind,
             }
         })
         .collect();
@@ -136,10 +352,12 @@ pub(crate) fn build_symbolic_constraints_dag<F: Field>(
 }
 
 /// `expr_to_idx` is a cache so that the `Arc<_>` references within symbolic expressions get
-/// mapped to the same node ID if their underlying references are the same.
+/// mapped to the same node ID if their underlying references are the same. `node_to_idx` maps
+/// each node to its ID, so that distinct references to identical expressions also share a node.
 fn topological_sort_symbolic_expr<'a, F: Field>(
     expr: &'a SymbolicExpression<F>,
     expr_to_idx: &mut FxHashMap<&'a SymbolicExpression<F>, usize>,
+    node_to_idx: &mut FxHashMap<SymbolicExpressionNode<F>, usize>,
     nodes: &mut Vec<SymbolicExpressionNode<F>>,
 ) -> usize {
     if let Some(&idx) = expr_to_idx.get(expr) {
@@ -150,14 +368,19 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
         SymbolicExpression::IsFirstRow => SymbolicExpressionNode::IsFirstRow,
         SymbolicExpression::IsLastRow => SymbolicExpressionNode::IsLastRow,
         SymbolicExpression::IsTransition => SymbolicExpressionNode::IsTransition,
+        SymbolicExpression::IsTransitionWindow(size) => {
+            SymbolicExpressionNode::IsTransitionWindow(*size)
+        }
         SymbolicExpression::Constant(cons) => SymbolicExpressionNode::Constant(*cons),
         SymbolicExpression::Add {
             x,
             y,
             degree_multiple,
         } => {
-            let left_idx = topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, nodes);
-            let right_idx = topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, nodes);
+            let left_idx =
+                topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, node_to_idx, nodes);
+            let right_idx =
+                topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, node_to_idx, nodes);
             SymbolicExpressionNode::Add {
                 left_idx,
                 right_idx,
@@ -169,8 +392,10 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
             y,
             degree_multiple,
         } => {
-            let left_idx = topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, nodes);
-            let right_idx = topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, nodes);
+            let left_idx =
+                topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, node_to_idx, nodes);
+            let right_idx =
+                topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, node_to_idx, nodes);
             SymbolicExpressionNode::Sub {
                 left_idx,
                 right_idx,
@@ -178,7 +403,8 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
             }
         }
         SymbolicExpression::Neg { x, degree_multiple } => {
-            let idx = topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, nodes);
+            let idx =
+                topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, node_to_idx, nodes);
             SymbolicExpressionNode::Neg {
                 idx,
                 degree_multiple: *degree_multiple,
@@ -192,8 +418,10 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
             // An important case to remember: square will have Arc::as_ptr(&x) == Arc::as_ptr(&y)
             // The `expr_to_id` will ensure only one topological sort is done to prevent exponential
             // behavior.
-            let left_idx = topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, nodes);
-            let right_idx = topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, nodes);
+            let left_idx =
+                topological_sort_symbolic_expr(x.as_ref(), expr_to_idx, node_to_idx, nodes);
+            let right_idx =
+                topological_sort_symbolic_expr(y.as_ref(), expr_to_idx, node_to_idx, nodes);
             SymbolicExpressionNode::Mul {
                 left_idx,
                 right_idx,
@@ -202,12 +430,47 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
         }
     };
 
-    let idx = nodes.len();
-    nodes.push(node);
+    let idx = match node_to_idx.get(&node) {
+        Some(&idx) => idx,
+        None => {
+            let idx = nodes.len();
+            nodes.push(node.clone());
+            node_to_idx.insert(node, idx);
+            idx
+        }
+    };
     expr_to_idx.insert(expr, idx);
     idx
 }
 
+/// Asserts that the constraint DAG used by the prover and the one used by the verifier are
+/// identical node-for-node. Panics with the index of the first differing node otherwise.
+///
+/// This is intended for tests, and only available with the `test-utils` feature.
+#[cfg(any(test, feature = "test-utils"))]
+pub fn assert_dags_equal<F: std::fmt::Debug + PartialEq>(
+    prover_dag: &SymbolicExpressionDag<F>,
+    verifier_dag: &SymbolicExpressionDag<F>,
+) {
+    assert_eq!(
+        prover_dag.nodes.len(),
+        verifier_dag.nodes.len(),
+        "prover and verifier DAGs have different number of nodes"
+    );
+    for (idx, (prover_node, verifier_node)) in
+        std::iter::zip(&prover_dag.nodes, &verifier_dag.nodes).enumerate()
+    {
+        assert_eq!(
+            prover_node, verifier_node,
+            "prover and verifier DAGs differ at node {idx}"
+        );
+    }
+    assert_eq!(
+        prover_dag.constraint_idx, verifier_dag.constraint_idx,
+        "prover and verifier DAGs have different constraint indices"
+    );
+}
+
 impl<F: Field> SymbolicExpressionDag<F> {
     /// Convert each node to a [`SymbolicExpression<F>`] reference and return
     /// the full list.
@@ -219,6 +482,9 @@ impl<F: Field> SymbolicExpressionDag<F> {
                 SymbolicExpressionNode::IsFirstRow => SymbolicExpression::IsFirstRow,
                 SymbolicExpressionNode::IsLastRow => SymbolicExpression::IsLastRow,
                 SymbolicExpressionNode::IsTransition => SymbolicExpression::IsTransition,
+                SymbolicExpressionNode::IsTransitionWindow(size) => {
+                    SymbolicExpression::IsTransitionWindow(size)
+                }
                 SymbolicExpressionNode::Constant(f) => SymbolicExpression::Constant(f),
                 SymbolicExpressionNode::Add {
                     left_idx,
@@ -286,6 +552,7 @@ impl<'a, F: Field> From<&'a SymbolicConstraintsDag<F>> for SymbolicConstraints<F
                     count,
                     bus_index: interaction.bus_index,
                     count_weight: interaction.count_weight,
+                    kind: interaction.kind,
                 }
             })
             .collect::<Vec<_>>();
@@ -346,6 +613,7 @@ mod tests {
             message: vec![expr.clone(), SymbolicExpression::Constant(F::TWO)],
             count: SymbolicExpression::Constant(F::ONE),
             count_weight: 1,
+            kind: None,
         }];
         let dag = build_symbolic_constraints_dag(&constraints, &interactions);
         assert_eq!(
@@ -365,15 +633,11 @@ mod tests {
                         right_idx: 3,
                         degree_multiple: 2
                     },
-                    // Currently topological sort does not detect all subgraph isomorphisms. For example each IsFirstRow and IsLastRow is a new reference so ptr::hash is distinct.
-                    SymbolicExpressionNode::Mul {
-                        left_idx: 0,
-                        right_idx: 1,
-                        degree_multiple: 2
-                    },
+                    // The second `IsFirstRow * IsLastRow` is a new reference, but is identical to
+                    // node 2, so it is not added again.
                     SymbolicExpressionNode::Add {
                         left_idx: 4,
-                        right_idx: 5,
+                        right_idx: 2,
                         degree_multiple: 2
                     },
                     SymbolicExpressionNode::Variable(SymbolicVariable::new(
@@ -385,32 +649,43 @@ mod tests {
                     )),
                     SymbolicExpressionNode::Mul {
                         left_idx: 3,
-                        right_idx: 7,
+                        right_idx: 6,
                         degree_multiple: 1
                     },
                     SymbolicExpressionNode::Add {
-                        left_idx: 6,
-                        right_idx: 8,
+                        left_idx: 5,
+                        right_idx: 7,
                         degree_multiple: 2
                     },
                     SymbolicExpressionNode::Mul {
-                        left_idx: 8,
-                        right_idx: 8,
+                        left_idx: 7,
+                        right_idx: 7,
                         degree_multiple: 2
                     },
                     SymbolicExpressionNode::Constant(F::TWO),
                 ],
-                constraint_idx: vec![9, 10],
+                constraint_idx: vec![8, 9],
             }
         );
         assert_eq!(
             dag.interactions,
             vec![Interaction {
                 bus_index: 0,
-                message: vec![8, 11],
+                message: vec![7, 10],
                 count: 3,
                 count_weight: 1,
+                kind: None,
             }]
         );
     }
+
+    #[test]
+    fn test_identical_subexpressions_share_nodes() {
+        let product = || SymbolicExpression::<F>::IsFirstRow * SymbolicExpression::IsLastRow;
+        let constraints = vec![product() - product(), product() + product()];
+        let dag = build_symbolic_constraints_dag(&constraints, &[]);
+        // `IsFirstRow`, `IsLastRow`, their product, and the difference and sum of two products
+        assert_eq!(dag.constraints.nodes.len(), 5);
+        assert_eq!(dag.constraints.constraint_idx, vec![3, 4]);
+    }
 }
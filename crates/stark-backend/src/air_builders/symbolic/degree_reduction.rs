@@ -0,0 +1,347 @@
+//! Degree reduction of [SymbolicAir] constraints by witness columns.
+//!
+//! The quotient degree of an AIR is determined by its constraint of highest degree. A product
+//! whose degree exceeds the target is lowered by replacing one of its factors with a new witness
+//! column of the common main trace, constrained to equal the factor on every row.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use p3_field::Field;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+use super::{
+    symbolic_expression::SymbolicEvaluator,
+    symbolic_variable::{Entry, SymbolicVariable},
+    SymbolicConstraintsDag, SymbolicExpressionDag, SymbolicExpressionNode,
+};
+use crate::{interaction::Interaction, rap::SymbolicAir};
+
+impl<F: Field> SymbolicExpressionDag<F> {
+    /// Returns the degree of each node, as a multiple of the trace degree.
+    pub fn node_degrees(&self) -> Vec<usize> {
+        let mut degrees = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            degrees.push(node_degree(node));
+        }
+        degrees
+    }
+
+    /// Returns the degree of each constraint, in order.
+    pub fn constraint_degrees(&self) -> Vec<usize> {
+        let degrees = self.node_degrees();
+        self.constraint_idx.iter().map(|&idx| degrees[idx]).collect()
+    }
+}
+
+fn node_degree<F: Field>(node: &SymbolicExpressionNode<F>) -> usize {
+    match node {
+        SymbolicExpressionNode::Variable(var) => var.degree_multiple(),
+        SymbolicExpressionNode::IsFirstRow
+        | SymbolicExpressionNode::IsLastRow
+        | SymbolicExpressionNode::IsTransitionWindow(_) => 1,
+        SymbolicExpressionNode::IsTransition | SymbolicExpressionNode::Constant(_) => 0,
+        SymbolicExpressionNode::Add {
+            degree_multiple, ..
+        }
+        | SymbolicExpressionNode::Sub {
+            degree_multiple, ..
+        }
+        | SymbolicExpressionNode::Neg {
+            degree_multiple, ..
+        }
+        | SymbolicExpressionNode::Mul {
+            degree_multiple, ..
+        } => *degree_multiple,
+    }
+}
+
+/// A [SymbolicAir] whose constraint degree was lowered by [SymbolicAir::reduce_degree], together
+/// with what is needed to generate its witness columns.
+#[derive(Clone, Debug)]
+pub struct DegreeReducedAir<F> {
+    /// The AIR with the witness columns appended to its common main trace.
+    pub air: SymbolicAir<F>,
+    /// Width of the common main trace of the original AIR.
+    original_width: usize,
+    /// Constraint DAG of the original AIR.
+    original_nodes: Vec<SymbolicExpressionNode<F>>,
+    /// For each witness column, in order, the node of `original_nodes` whose value it holds.
+    witness_nodes: Vec<usize>,
+}
+
+impl<F: Field> SymbolicAir<F> {
+    /// Lowers the degree of all constraints and interaction expressions to at most `max_degree`
+    /// by adding witness columns to the common main trace. Use
+    /// [DegreeReducedAir::generate_trace] to extend the traces of this AIR.
+    ///
+    /// Only subexpressions without selectors are replaced by witness columns, since a witness
+    /// column must equal its subexpression on every row.
+    ///
+    /// # Panics
+    /// If the AIR has cached main traces, if `max_degree < 2`, or if a product of degree above
+    /// `max_degree` cannot be lowered because its factors involve selectors.
+    pub fn reduce_degree(&self, max_degree: usize) -> DegreeReducedAir<F> {
+        assert!(
+            self.cached_main_widths.is_empty(),
+            "cached main traces are not supported"
+        );
+        assert!(max_degree >= 2, "constraints of degree 2 cannot be lowered");
+        let original = &self.constraints.constraints;
+        let mut reduction = Reduction {
+            nodes: Vec::with_capacity(original.nodes.len()),
+            degrees: Vec::with_capacity(original.nodes.len()),
+            has_selector: Vec::with_capacity(original.nodes.len()),
+            witness_cols: HashMap::new(),
+            witness_nodes: vec![],
+            witness_constraint_idx: vec![],
+            original_width: self.common_main_width,
+        };
+        // Index in `reduction.nodes` of each original node
+        let mut new_idx: Vec<usize> = Vec::with_capacity(original.nodes.len());
+        for node in &original.nodes {
+            let (node, has_selector) = match *node {
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => {
+                    let (left_idx, right_idx) = (new_idx[left_idx], new_idx[right_idx]);
+                    let node = SymbolicExpressionNode::Add {
+                        left_idx,
+                        right_idx,
+                        degree_multiple: reduction.degrees[left_idx]
+                            .max(reduction.degrees[right_idx]),
+                    };
+                    (node, reduction.has_selector(left_idx, right_idx))
+                }
+                SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => {
+                    let (left_idx, right_idx) = (new_idx[left_idx], new_idx[right_idx]);
+                    let node = SymbolicExpressionNode::Sub {
+                        left_idx,
+                        right_idx,
+                        degree_multiple: reduction.degrees[left_idx]
+                            .max(reduction.degrees[right_idx]),
+                    };
+                    (node, reduction.has_selector(left_idx, right_idx))
+                }
+                SymbolicExpressionNode::Neg { idx, .. } => {
+                    let node = SymbolicExpressionNode::Neg {
+                        idx: new_idx[idx],
+                        degree_multiple: reduction.degrees[new_idx[idx]],
+                    };
+                    (node, reduction.has_selector[new_idx[idx]])
+                }
+                SymbolicExpressionNode::Mul {
+                    left_idx: left,
+                    right_idx: right,
+                    ..
+                } => {
+                    let (mut left_idx, mut right_idx) = (new_idx[left], new_idx[right]);
+                    while reduction.degrees[left_idx] + reduction.degrees[right_idx] > max_degree
+                    {
+                        // Witness the factor of higher degree which can be witnessed
+                        let witness_left = reduction.can_witness(left_idx)
+                            && (!reduction.can_witness(right_idx)
+                                || reduction.degrees[left_idx] >= reduction.degrees[right_idx]);
+                        if witness_left {
+                            left_idx = reduction.witness(left, left_idx);
+                        } else if reduction.can_witness(right_idx) {
+                            right_idx = reduction.witness(right, right_idx);
+                        } else {
+                            panic!(
+                                "product of degree above {max_degree} has selectors in both factors"
+                            );
+                        }
+                    }
+                    let node = SymbolicExpressionNode::Mul {
+                        left_idx,
+                        right_idx,
+                        degree_multiple: reduction.degrees[left_idx]
+                            + reduction.degrees[right_idx],
+                    };
+                    (node, reduction.has_selector(left_idx, right_idx))
+                }
+                ref node @ (SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransition
+                | SymbolicExpressionNode::IsTransitionWindow(_)) => (node.clone(), true),
+                ref node => (node.clone(), false),
+            };
+            new_idx.push(reduction.push(node, has_selector));
+        }
+
+        let constraint_idx = original
+            .constraint_idx
+            .iter()
+            .map(|&idx| new_idx[idx])
+            .chain(reduction.witness_constraint_idx)
+            .collect();
+        let interactions = self
+            .constraints
+            .interactions
+            .iter()
+            .map(|interaction| Interaction {
+                message: interaction.message.iter().map(|&idx| new_idx[idx]).collect(),
+                count: new_idx[interaction.count],
+                bus_index: interaction.bus_index,
+                count_weight: interaction.count_weight,
+                kind: interaction.kind,
+            })
+            .collect();
+        let air = SymbolicAir {
+            cached_main_widths: vec![],
+            common_main_width: self.common_main_width + reduction.witness_nodes.len(),
+            num_public_values: self.num_public_values,
+            has_variable_public_values: self.has_variable_public_values,
+            constraints: SymbolicConstraintsDag {
+                constraints: SymbolicExpressionDag {
+                    nodes: reduction.nodes,
+                    constraint_idx,
+                },
+                interactions,
+            },
+        };
+        DegreeReducedAir {
+            air,
+            original_width: self.common_main_width,
+            original_nodes: original.nodes.clone(),
+            witness_nodes: reduction.witness_nodes,
+        }
+    }
+}
+
+/// State of [SymbolicAir::reduce_degree] while the DAG is rebuilt.
+struct Reduction<F> {
+    nodes: Vec<SymbolicExpressionNode<F>>,
+    degrees: Vec<usize>,
+    /// Whether each node depends on a selector
+    has_selector: Vec<bool>,
+    /// Column of the witness of each witnessed original node
+    witness_cols: HashMap<usize, usize>,
+    witness_nodes: Vec<usize>,
+    witness_constraint_idx: Vec<usize>,
+    original_width: usize,
+}
+
+impl<F: Field> Reduction<F> {
+    fn push(&mut self, node: SymbolicExpressionNode<F>, has_selector: bool) -> usize {
+        self.degrees.push(node_degree(&node));
+        self.has_selector.push(has_selector);
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn has_selector(&self, left_idx: usize, right_idx: usize) -> bool {
+        self.has_selector[left_idx] || self.has_selector[right_idx]
+    }
+
+    fn can_witness(&self, idx: usize) -> bool {
+        self.degrees[idx] >= 2 && !self.has_selector[idx]
+    }
+
+    /// Returns the node of a witness column holding the value of the original node
+    /// `original_idx`, which was rebuilt as `idx`, adding the column on first use.
+    fn witness(&mut self, original_idx: usize, idx: usize) -> usize {
+        let entry = Entry::Main {
+            part_index: 0,
+            offset: 0,
+        };
+        if let Some(&col) = self.witness_cols.get(&original_idx) {
+            let var = SymbolicVariable::new(entry, col);
+            return self.push(SymbolicExpressionNode::Variable(var), false);
+        }
+        let col = self.original_width + self.witness_nodes.len();
+        self.witness_cols.insert(original_idx, col);
+        self.witness_nodes.push(original_idx);
+        let var = SymbolicVariable::new(entry, col);
+        let var_idx = self.push(SymbolicExpressionNode::Variable(var), false);
+        // The witness column equals its subexpression on every row
+        let constraint = SymbolicExpressionNode::Sub {
+            left_idx: var_idx,
+            right_idx: idx,
+            degree_multiple: self.degrees[idx],
+        };
+        let constraint_idx = self.push(constraint, false);
+        self.witness_constraint_idx.push(constraint_idx);
+        var_idx
+    }
+}
+
+impl<F: Field> DegreeReducedAir<F> {
+    /// Number of witness columns appended to the common main trace.
+    pub fn num_witness_columns(&self) -> usize {
+        self.witness_nodes.len()
+    }
+
+    /// Appends the witness columns to `trace`, the common main trace of the original AIR with
+    /// public values `public_values`. Rows wrap around, as the constraints do on the last row.
+    pub fn generate_trace(
+        &self,
+        trace: &RowMajorMatrix<F>,
+        public_values: &[F],
+    ) -> RowMajorMatrix<F> {
+        assert_eq!(trace.width(), self.original_width);
+        let height = trace.height();
+        let num_rows = self
+            .original_nodes
+            .iter()
+            .filter_map(|node| match node {
+                SymbolicExpressionNode::Variable(var) => var.entry.offset(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut values = Vec::with_capacity(height * self.air.common_main_width);
+        for row_idx in 0..height {
+            let rows = (0..num_rows)
+                .map(|offset| trace.row_slice((row_idx + offset) % height).to_vec())
+                .collect_vec();
+            let evaluator = RowEvaluator {
+                rows: &rows,
+                public_values,
+            };
+            let node_values = evaluator.eval_nodes(&self.original_nodes);
+            values.extend_from_slice(&rows[0]);
+            values.extend(self.witness_nodes.iter().map(|&idx| node_values[idx]));
+        }
+        RowMajorMatrix::new(values, self.air.common_main_width)
+    }
+}
+
+/// Evaluates the nodes of the original AIR on consecutive rows of its trace.
+struct RowEvaluator<'a, F> {
+    rows: &'a [Vec<F>],
+    public_values: &'a [F],
+}
+
+impl<F: Field> SymbolicEvaluator<F, F> for RowEvaluator<'_, F> {
+    fn eval_const(&self, c: F) -> F {
+        c
+    }
+    fn eval_var(&self, symbolic_var: SymbolicVariable<F>) -> F {
+        match symbolic_var.entry {
+            Entry::Main { offset, .. } => self.rows[offset][symbolic_var.index],
+            Entry::Public => self.public_values[symbolic_var.index],
+            entry => panic!("{entry:?} is not supported by SymbolicAir"),
+        }
+    }
+    // Witness columns never depend on selectors, so their values are not used
+    fn eval_is_first_row(&self) -> F {
+        F::ZERO
+    }
+    fn eval_is_last_row(&self) -> F {
+        F::ZERO
+    }
+    fn eval_is_transition(&self) -> F {
+        F::ZERO
+    }
+    fn eval_is_transition_window(&self, _size: usize) -> F {
+        F::ZERO
+    }
+}
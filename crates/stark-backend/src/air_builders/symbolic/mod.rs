@@ -13,21 +13,27 @@ use self::{
     symbolic_expression::SymbolicExpression,
     symbolic_variable::{Entry, SymbolicVariable},
 };
-use super::PartitionedAirBuilder;
+use super::{ConditionalAirBuilder, PartitionedAirBuilder};
 use crate::{
     interaction::{
-        fri_log_up::find_interaction_chunks, rap::InteractionPhaseAirBuilder, Interaction,
-        InteractionBuilder, RapPhaseSeqKind, SymbolicInteraction,
+        fri_log_up::{find_interaction_chunks, FingerprintScheme},
+        rap::InteractionPhaseAirBuilder,
+        BusSchema, Interaction, InteractionBuilder, PermutationInteractionType, RapPhaseSeqKind,
+        SymbolicInteraction,
     },
     keygen::types::{StarkVerifyingParams, TraceWidth},
     rap::{BaseAirWithPublicValues, PermutationAirBuilderWithExposedValues, Rap},
 };
 
 mod dag;
+mod degree_reduction;
 pub mod symbolic_expression;
 pub mod symbolic_variable;
+mod text_ir;
 
 pub use dag::*;
+pub use degree_reduction::*;
+pub use text_ir::*;
 
 use crate::interaction::BusIndex;
 
@@ -102,14 +108,46 @@ where
     F: Field,
     R: Rap<SymbolicRapBuilder<F>> + BaseAirWithPublicValues<F> + ?Sized,
 {
+    get_symbolic_builder_with_fingerprint_scheme(
+        rap,
+        width,
+        num_challenges_to_sample,
+        num_exposed_values_after_challenge,
+        rap_phase_seq_kind,
+        max_constraint_degree,
+        FingerprintScheme::default(),
+    )
+}
+
+/// Same as [get_symbolic_builder], with the LogUp constraints of the interactions using
+/// `fingerprint_scheme` instead of the default [FingerprintScheme].
+#[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
+pub fn get_symbolic_builder_with_fingerprint_scheme<F, R>(
+    rap: &R,
+    width: &TraceWidth,
+    num_challenges_to_sample: &[usize],
+    num_exposed_values_after_challenge: &[usize],
+    rap_phase_seq_kind: RapPhaseSeqKind,
+    max_constraint_degree: usize,
+    fingerprint_scheme: FingerprintScheme,
+) -> SymbolicRapBuilder<F>
+where
+    F: Field,
+    R: Rap<SymbolicRapBuilder<F>> + BaseAirWithPublicValues<F> + ?Sized,
+{
+    let variable_public_values = rap.has_variable_public_values();
+    // AIRs with variable public values have an extra public value for the active count
+    let num_public_values = rap.num_public_values() + usize::from(variable_public_values);
     let mut builder = SymbolicRapBuilder::new(
         width,
-        rap.num_public_values(),
+        num_public_values,
+        variable_public_values,
         num_challenges_to_sample,
         num_exposed_values_after_challenge,
         rap_phase_seq_kind,
         max_constraint_degree,
     );
+    builder.fingerprint_scheme = fingerprint_scheme;
     Rap::eval(rap, &mut builder);
     builder
 }
@@ -121,12 +159,15 @@ pub struct SymbolicRapBuilder<F> {
     partitioned_main: Vec<RowMajorMatrix<SymbolicVariable<F>>>,
     after_challenge: Vec<RowMajorMatrix<SymbolicVariable<F>>>,
     public_values: Vec<SymbolicVariable<F>>,
+    variable_public_values: bool,
     challenges: Vec<Vec<SymbolicVariable<F>>>,
     exposed_values_after_challenge: Vec<Vec<SymbolicVariable<F>>>,
     constraints: Vec<SymbolicExpression<F>>,
     interactions: Vec<SymbolicInteraction<F>>,
+    bus_schemas: Vec<BusSchema>,
     max_constraint_degree: usize,
     rap_phase_seq_kind: RapPhaseSeqKind,
+    fingerprint_scheme: FingerprintScheme,
     trace_width: TraceWidth,
 
     /// Caching for FRI logup to avoid recomputation during keygen
@@ -134,11 +175,13 @@ pub struct SymbolicRapBuilder<F> {
 }
 
 impl<F: Field> SymbolicRapBuilder<F> {
+    /// - `variable_public_values`: whether the last public value is the number of active public values
     /// - `num_challenges_to_sample`: for each challenge phase, how many challenges to sample
     /// - `num_exposed_values_after_challenge`: in each challenge phase, how many values to expose to verifier
     pub(crate) fn new(
         width: &TraceWidth,
         num_public_values: usize,
+        variable_public_values: bool,
         num_challenges_to_sample: &[usize],
         num_exposed_values_after_challenge: &[usize],
         rap_phase_seq_kind: RapPhaseSeqKind,
@@ -179,12 +222,15 @@ impl<F: Field> SymbolicRapBuilder<F> {
             partitioned_main,
             after_challenge,
             public_values,
+            variable_public_values,
             challenges,
             exposed_values_after_challenge,
             constraints: vec![],
             interactions: vec![],
+            bus_schemas: vec![],
             max_constraint_degree,
             rap_phase_seq_kind,
+            fingerprint_scheme: FingerprintScheme::default(),
             trace_width: width.clone(),
             interaction_partitions: None,
         }
@@ -197,6 +243,11 @@ impl<F: Field> SymbolicRapBuilder<F> {
         }
     }
 
+    /// The distinct bus schemas registered by typed interactions, in order of first use.
+    pub fn bus_schemas(&self) -> &[BusSchema] {
+        &self.bus_schemas
+    }
+
     pub fn params(&self) -> StarkVerifyingParams {
         let width = self.width();
         let num_exposed_values_after_challenge = self.num_exposed_values_after_challenge();
@@ -204,6 +255,7 @@ impl<F: Field> SymbolicRapBuilder<F> {
         StarkVerifyingParams {
             width,
             num_public_values: self.public_values.len(),
+            variable_public_values: self.variable_public_values,
             num_exposed_values_after_challenge,
             num_challenges_to_sample,
         }
@@ -294,10 +346,10 @@ impl<F: Field> AirBuilder for SymbolicRapBuilder<F> {
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
+        match size {
+            2 => SymbolicExpression::IsTransition,
+            3.. => SymbolicExpression::IsTransitionWindow(size),
+            _ => panic!("transition window size must be at least 2"),
         }
     }
 
@@ -362,12 +414,13 @@ impl<F: Field> PermutationAirBuilderWithExposedValues for SymbolicRapBuilder<F>
 }
 
 impl<F: Field> InteractionBuilder for SymbolicRapBuilder<F> {
-    fn push_interaction<E: Into<Self::Expr>>(
+    fn push_interaction_with_kind<E: Into<Self::Expr>>(
         &mut self,
         bus_index: BusIndex,
         fields: impl IntoIterator<Item = E>,
         count: impl Into<Self::Expr>,
         count_weight: u32,
+        kind: Option<PermutationInteractionType>,
     ) {
         let fields = fields.into_iter().map(|f| f.into()).collect();
         let count = count.into();
@@ -376,6 +429,7 @@ impl<F: Field> InteractionBuilder for SymbolicRapBuilder<F> {
             message: fields,
             count,
             count_weight,
+            kind,
         });
     }
 
@@ -386,6 +440,12 @@ impl<F: Field> InteractionBuilder for SymbolicRapBuilder<F> {
     fn all_interactions(&self) -> &[Interaction<Self::Expr>] {
         &self.interactions
     }
+
+    fn register_bus_schema(&mut self, schema: &BusSchema) {
+        if !self.bus_schemas.contains(schema) {
+            self.bus_schemas.push(schema.clone());
+        }
+    }
 }
 
 impl<F: Field> InteractionPhaseAirBuilder for SymbolicRapBuilder<F> {
@@ -427,6 +487,10 @@ impl<F: Field> InteractionPhaseAirBuilder for SymbolicRapBuilder<F> {
         self.rap_phase_seq_kind
     }
 
+    fn fingerprint_scheme(&self) -> FingerprintScheme {
+        self.fingerprint_scheme
+    }
+
     fn symbolic_interactions(&self) -> Vec<SymbolicInteraction<F>> {
         self.interactions.clone()
     }
@@ -445,6 +509,8 @@ impl<F: Field> PartitionedAirBuilder for SymbolicRapBuilder<F> {
     }
 }
 
+impl<F: Field> ConditionalAirBuilder for SymbolicRapBuilder<F> {}
+
 #[allow(dead_code)]
 struct LocalOnlyChecker;
 
@@ -467,6 +533,7 @@ impl LocalOnlyChecker {
             SymbolicExpression::IsFirstRow => false,
             SymbolicExpression::IsLastRow => false,
             SymbolicExpression::IsTransition => false,
+            SymbolicExpression::IsTransitionWindow(_) => false,
             SymbolicExpression::Constant(_) => true,
             SymbolicExpression::Add { x, y, .. } => Self::check_expr(x) && Self::check_expr(y),
             SymbolicExpression::Sub { x, y, .. } => Self::check_expr(x) && Self::check_expr(y),
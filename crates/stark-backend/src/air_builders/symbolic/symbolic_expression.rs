@@ -25,6 +25,9 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// Selector which is nonzero on the rows with a full window of `size` rows, i.e., zero on
+    /// the last `size - 1` rows. For `size = 2` this is [IsTransition](Self::IsTransition).
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         x: Arc<Self>,
@@ -57,6 +60,7 @@ impl<F: Field> Hash for SymbolicExpression<F> {
             Self::IsFirstRow => {}   // discriminant is enough
             Self::IsLastRow => {}    // discriminant is enough
             Self::IsTransition => {} // discriminant is enough
+            Self::IsTransitionWindow(size) => size.hash(state),
             Self::Constant(f) => f.hash(state),
             Self::Add { x, y, .. } => {
                 ptr::hash(&**x, state);
@@ -94,6 +98,7 @@ impl<F: Field> PartialEq for SymbolicExpression<F> {
             (Self::IsFirstRow, Self::IsFirstRow) => true,
             (Self::IsLastRow, Self::IsLastRow) => true,
             (Self::IsTransition, Self::IsTransition) => true,
+            (Self::IsTransitionWindow(s1), Self::IsTransitionWindow(s2)) => s1 == s2,
             (Self::Constant(c1), Self::Constant(c2)) => c1 == c2,
             // For compound expressions, compare pointers to match how Hash is implemented
             (Self::Add { x: x1, y: y1, .. }, Self::Add { x: x2, y: y2, .. }) => {
@@ -122,6 +127,9 @@ impl<F: Field> SymbolicExpression<F> {
             SymbolicExpression::IsFirstRow => 1,
             SymbolicExpression::IsLastRow => 1,
             SymbolicExpression::IsTransition => 0,
+            // The selector has degree `size - 1`, which is not absorbed by the vanishing
+            // polynomial for constraints of low degree, so it is counted like `IsLastRow`.
+            SymbolicExpression::IsTransitionWindow(_) => 1,
             SymbolicExpression::Constant(_) => 0,
             SymbolicExpression::Add {
                 degree_multiple, ..
@@ -343,6 +351,7 @@ where
     fn eval_is_first_row(&self) -> E;
     fn eval_is_last_row(&self) -> E;
     fn eval_is_transition(&self) -> E;
+    fn eval_is_transition_window(&self, size: usize) -> E;
 
     fn eval_expr(&self, symbolic_expr: &SymbolicExpression<F>) -> E {
         match symbolic_expr {
@@ -355,6 +364,7 @@ where
             SymbolicExpression::IsFirstRow => self.eval_is_first_row(),
             SymbolicExpression::IsLastRow => self.eval_is_last_row(),
             SymbolicExpression::IsTransition => self.eval_is_transition(),
+            SymbolicExpression::IsTransitionWindow(size) => self.eval_is_transition_window(*size),
         }
     }
 
@@ -388,6 +398,9 @@ where
                 SymbolicExpressionNode::IsFirstRow => self.eval_is_first_row(),
                 SymbolicExpressionNode::IsLastRow => self.eval_is_last_row(),
                 SymbolicExpressionNode::IsTransition => self.eval_is_transition(),
+                SymbolicExpressionNode::IsTransitionWindow(size) => {
+                    self.eval_is_transition_window(size)
+                }
             };
             exprs.push(expr);
         }
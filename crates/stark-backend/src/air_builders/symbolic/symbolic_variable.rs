@@ -10,6 +10,13 @@ use serde::{Deserialize, Serialize};
 
 use super::symbolic_expression::SymbolicExpression;
 
+/// The kind of values a [SymbolicVariable] refers to.
+///
+/// The entry kinds are a closed set, since they are serialized in the verifying key and the values
+/// of each kind must be committed and opened by the proof. A new kind of column needs a variant
+/// here, handled by the prover in `ProverConstraintEvaluator` and the quotient bounds checks, by
+/// the verifier in [GenericVerifierConstraintFolder](crate::verifier::GenericVerifierConstraintFolder),
+/// and in the text IR.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(C)]
 pub enum Entry {
@@ -64,6 +71,14 @@ impl Entry {
     pub fn next(self) -> Self {
         self.rotate(1)
     }
+
+    /// Degree of a variable of this entry as a polynomial in the trace columns.
+    pub const fn degree_multiple(&self) -> usize {
+        match self {
+            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
+            Entry::Public | Entry::Challenge | Entry::Exposed => 0,
+        }
+    }
 }
 
 /// A variable within the evaluation window, i.e. a column in either the local or next row.
@@ -85,10 +100,7 @@ impl<F: Field> SymbolicVariable<F> {
     }
 
     pub const fn degree_multiple(&self) -> usize {
-        match self.entry {
-            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
-            Entry::Public | Entry::Challenge | Entry::Exposed => 0,
-        }
+        self.entry.degree_multiple()
     }
 
     pub fn rotate(self, offset: usize) -> Self {
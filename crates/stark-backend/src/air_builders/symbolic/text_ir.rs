@@ -0,0 +1,206 @@
+//! Textual IR of a [SymbolicExpressionDag], listing one node per line in SSA form, e.g.
+//! ```text
+//! %0 = main part=0 col=1 rot=0
+//! %1 = main part=0 col=0 rot=1
+//! %2 = sub %0 %1 deg=1
+//! %3 = is_transition
+//! %4 = mul %3 %2 deg=2
+//! assert_zero %4
+//! ```
+//! Constants are written as canonical integers. The listing is stable, so it can be diffed to
+//! review changes of constraints.
+
+use std::fmt::Write;
+
+use p3_field::PrimeField64;
+use thiserror::Error;
+
+use super::{
+    symbolic_variable::{Entry, SymbolicVariable},
+    SymbolicExpressionDag, SymbolicExpressionNode,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TextIrError {
+    #[error("line {line}: cannot parse `{text}`")]
+    InvalidLine { line: usize, text: String },
+    #[error("line {line}: node %{idx} is used before it is defined")]
+    UndefinedNode { line: usize, idx: usize },
+}
+
+impl<F: PrimeField64> SymbolicExpressionDag<F> {
+    /// Writes the DAG as textual IR: each node in order as `%i = <op> <args>`, followed by one
+    /// `assert_zero %i` line per constraint.
+    pub fn to_text_ir(&self) -> String {
+        let mut text = String::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            writeln!(text, "%{idx} = {}", node_to_text(node)).unwrap();
+        }
+        for idx in &self.constraint_idx {
+            writeln!(text, "assert_zero %{idx}").unwrap();
+        }
+        text
+    }
+
+    /// Parses textual IR written by [to_text_ir](Self::to_text_ir). Empty lines are ignored.
+    pub fn from_text_ir(text: &str) -> Result<Self, TextIrError> {
+        let mut nodes = Vec::new();
+        let mut constraint_idx = Vec::new();
+        for (line_idx, line_text) in text.lines().enumerate() {
+            let line = line_idx + 1;
+            let invalid = || TextIrError::InvalidLine {
+                line,
+                text: line_text.to_string(),
+            };
+            let tokens = line_text.split_whitespace().collect::<Vec<_>>();
+            match tokens[..] {
+                [] => {}
+                ["assert_zero", idx] => {
+                    let idx = parse_node_ref(idx).ok_or_else(invalid)?;
+                    if idx >= nodes.len() {
+                        return Err(TextIrError::UndefinedNode { line, idx });
+                    }
+                    constraint_idx.push(idx);
+                }
+                [idx, "=", op, ref args @ ..] if idx == format!("%{}", nodes.len()) => {
+                    let node = parse_node(op, args).ok_or_else(invalid)?;
+                    let operands = match node {
+                        SymbolicExpressionNode::Add {
+                            left_idx,
+                            right_idx,
+                            ..
+                        }
+                        | SymbolicExpressionNode::Sub {
+                            left_idx,
+                            right_idx,
+                            ..
+                        }
+                        | SymbolicExpressionNode::Mul {
+                            left_idx,
+                            right_idx,
+                            ..
+                        } => vec![left_idx, right_idx],
+                        SymbolicExpressionNode::Neg { idx, .. } => vec![idx],
+                        _ => vec![],
+                    };
+                    if let Some(idx) = operands.into_iter().find(|&idx| idx >= nodes.len()) {
+                        return Err(TextIrError::UndefinedNode { line, idx });
+                    }
+                    nodes.push(node);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            nodes,
+            constraint_idx,
+        })
+    }
+}
+
+fn node_to_text<F: PrimeField64>(node: &SymbolicExpressionNode<F>) -> String {
+    match node {
+        SymbolicExpressionNode::Variable(var) => match var.entry {
+            Entry::Preprocessed { offset } => {
+                format!("preprocessed col={} rot={offset}", var.index)
+            }
+            Entry::Main { part_index, offset } => {
+                format!("main part={part_index} col={} rot={offset}", var.index)
+            }
+            Entry::Permutation { offset } => format!("permutation col={} rot={offset}", var.index),
+            Entry::Public => format!("public idx={}", var.index),
+            Entry::Challenge => format!("challenge idx={}", var.index),
+            Entry::Exposed => format!("exposed idx={}", var.index),
+        },
+        SymbolicExpressionNode::IsFirstRow => "is_first_row".to_string(),
+        SymbolicExpressionNode::IsLastRow => "is_last_row".to_string(),
+        SymbolicExpressionNode::IsTransition => "is_transition".to_string(),
+        SymbolicExpressionNode::IsTransitionWindow(size) => {
+            format!("is_transition_window size={size}")
+        }
+        SymbolicExpressionNode::Constant(c) => format!("const {}", c.as_canonical_u64()),
+        SymbolicExpressionNode::Add {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => format!("add %{left_idx} %{right_idx} deg={degree_multiple}"),
+        SymbolicExpressionNode::Sub {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => format!("sub %{left_idx} %{right_idx} deg={degree_multiple}"),
+        SymbolicExpressionNode::Neg {
+            idx,
+            degree_multiple,
+        } => format!("neg %{idx} deg={degree_multiple}"),
+        SymbolicExpressionNode::Mul {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => format!("mul %{left_idx} %{right_idx} deg={degree_multiple}"),
+    }
+}
+
+fn parse_node_ref(token: &str) -> Option<usize> {
+    token.strip_prefix('%')?.parse().ok()
+}
+
+/// Parses the node with operation `op` and arguments `args`, returning `None` if they are
+/// malformed.
+fn parse_node<F: PrimeField64>(op: &str, args: &[&str]) -> Option<SymbolicExpressionNode<F>> {
+    /// Parses `token` of the form `key=value`.
+    fn field(token: &str, key: &str) -> Option<usize> {
+        token.strip_prefix(key)?.strip_prefix('=')?.parse().ok()
+    }
+    let var = |entry: Entry, index: usize| {
+        SymbolicExpressionNode::Variable(SymbolicVariable::new(entry, index))
+    };
+    let node = match (op, args) {
+        ("preprocessed", &[col, rot]) => {
+            let offset = field(rot, "rot")?;
+            var(Entry::Preprocessed { offset }, field(col, "col")?)
+        }
+        ("main", &[part, col, rot]) => {
+            let (part_index, offset) = (field(part, "part")?, field(rot, "rot")?);
+            var(Entry::Main { part_index, offset }, field(col, "col")?)
+        }
+        ("permutation", &[col, rot]) => {
+            let offset = field(rot, "rot")?;
+            var(Entry::Permutation { offset }, field(col, "col")?)
+        }
+        ("public", &[idx]) => var(Entry::Public, field(idx, "idx")?),
+        ("challenge", &[idx]) => var(Entry::Challenge, field(idx, "idx")?),
+        ("exposed", &[idx]) => var(Entry::Exposed, field(idx, "idx")?),
+        ("is_first_row", &[]) => SymbolicExpressionNode::IsFirstRow,
+        ("is_last_row", &[]) => SymbolicExpressionNode::IsLastRow,
+        ("is_transition", &[]) => SymbolicExpressionNode::IsTransition,
+        ("is_transition_window", &[size]) => {
+            SymbolicExpressionNode::IsTransitionWindow(field(size, "size")?)
+        }
+        ("const", &[c]) => {
+            let c = c.parse::<u64>().ok().filter(|&c| c < F::ORDER_U64)?;
+            SymbolicExpressionNode::Constant(F::from_canonical_u64(c))
+        }
+        ("add", &[left, right, deg]) => SymbolicExpressionNode::Add {
+            left_idx: parse_node_ref(left)?,
+            right_idx: parse_node_ref(right)?,
+            degree_multiple: field(deg, "deg")?,
+        },
+        ("sub", &[left, right, deg]) => SymbolicExpressionNode::Sub {
+            left_idx: parse_node_ref(left)?,
+            right_idx: parse_node_ref(right)?,
+            degree_multiple: field(deg, "deg")?,
+        },
+        ("neg", &[x, deg]) => SymbolicExpressionNode::Neg {
+            idx: parse_node_ref(x)?,
+            degree_multiple: field(deg, "deg")?,
+        },
+        ("mul", &[left, right, deg]) => SymbolicExpressionNode::Mul {
+            left_idx: parse_node_ref(left)?,
+            right_idx: parse_node_ref(right)?,
+            degree_multiple: field(deg, "deg")?,
+        },
+        _ => return None,
+    };
+    Some(node)
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use itertools::zip_eq;
+use p3_challenger::{CanObserve, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger};
+use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra, PrimeField64};
+use serde::{Deserialize, Serialize};
+
+/// Hook invoked by the prover and the verifier right after observing each main trace
+/// commitment, with the index of the commitment in
+/// [Commitments::main_trace](crate::proof::Commitments::main_trace). It may observe further
+/// data into the transcript or sample from it, which binds all challenges sampled after the main
+/// trace commitments to that data.
+///
+/// The hook only adds data to the transcript. All main trace commitments are computed before the
+/// first one is observed, so no main trace can depend on what the hook observes or samples.
+///
+/// The prover and the verifier must be given the same hook, or the transcripts diverge.
+pub type MainCommitHook<C, Com> = Arc<dyn Fn(&mut C, usize, &Com) + Send + Sync>;
+
+/// Observes the main trace commitments `commits` in order, invoking `hook` after each.
+pub(crate) fn observe_main_commitments<C, Com>(
+    challenger: &mut C,
+    commits: &[Com],
+    hook: Option<&MainCommitHook<C, Com>>,
+) where
+    C: CanObserve<Com>,
+    Com: Clone,
+{
+    for (commit_idx, commit) in commits.iter().enumerate() {
+        challenger.observe(commit.clone());
+        if let Some(hook) = hook {
+            hook(challenger, commit_idx, commit);
+        }
+    }
+}
+
+/// Observes the number of quotient chunks of each AIR, in AIR order, if the quotient polynomial
+/// of some AIR is split into fewer chunks than its quotient degree. With the default chunking
+/// nothing is observed, so the transcript is the same as without configurable chunking.
+pub(crate) fn observe_quotient_chunks<F, C>(
+    challenger: &mut C,
+    num_quotient_chunks_per_air: &[u8],
+    quotient_degree_per_air: &[u8],
+) where
+    C: CanObserve<F>,
+    F: FieldAlgebra,
+{
+    if zip_eq(num_quotient_chunks_per_air, quotient_degree_per_air)
+        .any(|(num_chunks, quotient_degree)| num_chunks != quotient_degree)
+    {
+        for &num_chunks in num_quotient_chunks_per_air {
+            challenger.observe(F::from_canonical_u8(num_chunks));
+        }
+    }
+}
+
+/// Every base field element sampled from a challenger, in order. Extension field challenges are
+/// sampled as consecutive base field elements, and sampled bits are taken from a base field element.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChallengeLog<F> {
+    pub samples: Vec<F>,
+}
+
+/// Challenger which records every sample drawn from the inner challenger into a [ChallengeLog],
+/// or replays the samples of a log in place of those of the inner challenger.
+///
+/// The inner challenger observes and samples as usual in both modes, so the transcript is not
+/// affected by replaying. Proof of work witnesses are ground and checked by the inner
+/// challenger and not logged.
+#[derive(Clone, Debug)]
+pub struct ChallengeLogger<F, C> {
+    inner: C,
+    log: ChallengeLog<F>,
+    /// Index in `log` of the next sample to replay, or `None` when recording.
+    replay_idx: Option<usize>,
+}
+
+impl<F, C> ChallengeLogger<F, C> {
+    /// Records the samples of `inner`.
+    pub fn record(inner: C) -> Self {
+        Self {
+            inner,
+            log: ChallengeLog {
+                samples: Vec::new(),
+            },
+            replay_idx: None,
+        }
+    }
+
+    /// Replays the samples of `log`. Panics if more samples are drawn than the log contains.
+    pub fn replay(inner: C, log: ChallengeLog<F>) -> Self {
+        Self {
+            inner,
+            log,
+            replay_idx: Some(0),
+        }
+    }
+
+    /// The recorded samples, or the replayed log.
+    pub fn log(&self) -> &ChallengeLog<F> {
+        &self.log
+    }
+
+    pub fn into_log(self) -> ChallengeLog<F> {
+        self.log
+    }
+
+    fn sample_base(&mut self) -> F
+    where
+        F: Copy,
+        C: CanSample<F>,
+    {
+        let sample = self.inner.sample();
+        match &mut self.replay_idx {
+            None => {
+                self.log.samples.push(sample);
+                sample
+            }
+            Some(idx) => {
+                let sample = *self
+                    .log
+                    .samples
+                    .get(*idx)
+                    .expect("challenge log is exhausted");
+                *idx += 1;
+                sample
+            }
+        }
+    }
+}
+
+impl<F, C, T> CanObserve<T> for ChallengeLogger<F, C>
+where
+    C: CanObserve<T>,
+{
+    fn observe(&mut self, value: T) {
+        self.inner.observe(value);
+    }
+}
+
+impl<F, EF, C> CanSample<EF> for ChallengeLogger<F, C>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    C: CanSample<F>,
+{
+    fn sample(&mut self) -> EF {
+        EF::from_base_fn(|_| self.sample_base())
+    }
+}
+
+impl<F, C> CanSampleBits<usize> for ChallengeLogger<F, C>
+where
+    F: PrimeField64,
+    C: CanSample<F>,
+{
+    fn sample_bits(&mut self, bits: usize) -> usize {
+        debug_assert!(bits < (usize::BITS as usize));
+        debug_assert!((1 << bits) < F::ORDER_U64);
+        let sample = self.sample_base().as_canonical_u64() as usize;
+        sample & ((1 << bits) - 1)
+    }
+}
+
+impl<F, C> FieldChallenger<F> for ChallengeLogger<F, C>
+where
+    F: PrimeField64,
+    C: FieldChallenger<F>,
+{
+}
+
+impl<F, C> GrindingChallenger for ChallengeLogger<F, C>
+where
+    F: PrimeField64,
+    C: FieldChallenger<F> + GrindingChallenger + Clone,
+{
+    type Witness = C::Witness;
+
+    fn grind(&mut self, bits: usize) -> Self::Witness {
+        self.inner.grind(bits)
+    }
+
+    fn check_witness(&mut self, bits: usize, witness: Self::Witness) -> bool {
+        self.inner.check_witness(bits, witness)
+    }
+}
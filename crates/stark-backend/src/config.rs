@@ -4,7 +4,8 @@ use std::marker::PhantomData;
 
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{ExtensionField, Field};
+use p3_field::{ExtensionField, Field, FieldExtensionAlgebra};
+use serde::{Deserialize, Serialize};
 
 use crate::interaction::RapPhaseSeq;
 
@@ -19,6 +20,12 @@ where
     RapPartialProvingKey<Self>: Send + Sync,
 {
     /// The PCS used to commit to trace polynomials.
+    ///
+    /// Trace domains are always [Pcs::natural_domain_for_degree], so the PCS alone determines
+    /// the subgroup generator seen by the prover and the verifier. The generator cannot be
+    /// chosen by the config: for two-adic FRI it is the field's `two_adic_generator`, which
+    /// the LDE and FRI folding also assume. A verifier expecting another generator needs a
+    /// PCS built around it.
     type Pcs: Pcs<Self::Challenge, Self::Challenger>;
 
     /// The RAP challenge phases used to establish, e.g., that interactions are balanced.
@@ -35,6 +42,62 @@ where
     fn pcs(&self) -> &Self::Pcs;
 
     fn rap_phase_seq(&self) -> &Self::RapPhaseSeq;
+
+    /// How the `alpha` challenges folding the constraints of each AIR are sampled. The prover and
+    /// the verifier both follow the config, so the proof does not record it.
+    fn alpha_sampling(&self) -> AlphaSampling {
+        AlphaSampling::Global
+    }
+
+    /// Number of bits of the challenge field, which bounds the conjectured security of a proof.
+    /// For an extension of degree `D` over a base field whose order has `b` bits, this is `D * b`.
+    fn challenge_field_bits() -> usize {
+        let base_field_bits = Val::<Self>::order().bits() as usize;
+        <Self::Challenge as FieldExtensionAlgebra<Val<Self>>>::D * base_field_bits
+    }
+
+    /// Observes the quotient commitments, in order.
+    ///
+    /// The prover and the verifier call this once all trace commitments and RAP phases are
+    /// observed, and immediately before sampling the out-of-domain point `zeta`, which is the
+    /// next challenge drawn. Configurations targeting an external verifier with a different
+    /// transcript may override it.
+    fn observe_quotient_commitments(
+        challenger: &mut Self::Challenger,
+        quotient_commits: &[Com<Self>],
+    ) {
+        for commit in quotient_commits {
+            challenger.observe(commit.clone());
+        }
+    }
+}
+
+/// Specifies how the `alpha` challenges used to fold the constraints of each AIR into its quotient
+/// polynomial are sampled from the transcript.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlphaSampling {
+    /// A single `alpha` shared by all AIRs.
+    #[default]
+    Global,
+    /// A separate `alpha` for each AIR, sampled in the order of the AIRs in the proof.
+    PerAir,
+}
+
+impl AlphaSampling {
+    /// Samples the `alpha` challenge of each of `num_airs` AIRs.
+    pub fn sample<F, EF, Challenger>(self, challenger: &mut Challenger, num_airs: usize) -> Vec<EF>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+        Challenger: FieldChallenger<F>,
+    {
+        match self {
+            Self::Global => vec![challenger.sample_ext_element(); num_airs],
+            Self::PerAir => (0..num_airs)
+                .map(|_| challenger.sample_ext_element())
+                .collect(),
+        }
+    }
 }
 
 pub type Val<SC> = <<<SC as StarkGenericConfig>::Pcs as Pcs<
@@ -94,6 +157,7 @@ pub type PackedChallenge<SC> =
 pub struct StarkConfig<Pcs, RapPhaseSeq, Challenge, Challenger> {
     pcs: Pcs,
     rap_phase: RapPhaseSeq,
+    alpha_sampling: AlphaSampling,
     _phantom: PhantomData<(Challenge, Challenger)>,
 }
 
@@ -102,9 +166,23 @@ impl<Pcs, RapPhaseSeq, Challenge, Challenger> StarkConfig<Pcs, RapPhaseSeq, Chal
         Self {
             pcs,
             rap_phase,
+            alpha_sampling: AlphaSampling::Global,
             _phantom: PhantomData,
         }
     }
+
+    /// Replaces the RAP phase sequence, e.g. to change its parameters.
+    pub fn with_rap_phase(mut self, rap_phase: RapPhaseSeq) -> Self {
+        self.rap_phase = rap_phase;
+        self
+    }
+
+    /// Sets whether all AIRs share one `alpha` challenge for folding their constraints, or each
+    /// AIR gets its own. Proofs only verify with the sampling they were proven with.
+    pub fn with_alpha_sampling(mut self, alpha_sampling: AlphaSampling) -> Self {
+        self.alpha_sampling = alpha_sampling;
+        self
+    }
 }
 
 impl<Pcs, Rps, Challenge, Challenger> StarkGenericConfig
@@ -134,6 +212,9 @@ where
     fn rap_phase_seq(&self) -> &Self::RapPhaseSeq {
         &self.rap_phase
     }
+    fn alpha_sampling(&self) -> AlphaSampling {
+        self.alpha_sampling
+    }
 }
 
 pub struct UniStarkConfig<SC>(pub SC);
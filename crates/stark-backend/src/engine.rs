@@ -13,8 +13,9 @@ use crate::{
     },
     proof::Proof,
     prover::{
-        cpu::PcsData,
-        hal::{DeviceDataTransporter, TraceCommitter},
+        coordinator::Coordinator,
+        cpu::{CpuBackend, PcsData},
+        hal::{DeviceDataTransporter, ProverDevice, TraceCommitter},
         types::{
             AirProofInput, AirProvingContext, ProofInput, ProvingContext, SingleCommitPreimage,
         },
@@ -85,6 +86,17 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
 
     fn prove(&self, mpk: &MultiStarkProvingKey<SC>, proof_input: ProofInput<SC>) -> Proof<SC> {
         let mut prover = self.prover();
+        self.prove_with(&mut prover, mpk, proof_input)
+    }
+
+    /// Same as [prove](Self::prove), but proves with `prover` instead of [prover](Self::prover),
+    /// such as a coordinator whose device wraps the CPU device of the engine.
+    fn prove_with<PD: ProverDevice<CpuBackend<SC>>>(
+        &self,
+        prover: &mut Coordinator<SC, CpuBackend<SC>, PD>,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Proof<SC> {
         let backend = prover.backend;
         let air_ids = proof_input.per_air.iter().map(|(id, _)| *id).collect();
         // Commit cached traces if they are not provided
@@ -156,7 +168,7 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
             per_air: ctx_per_air,
         };
         let mpk_view = backend.transport_pk_to_device(mpk, air_ids);
-        let proof = Prover::prove(&mut prover, mpk_view, ctx);
+        let proof = Prover::prove(prover, mpk_view, ctx);
         proof.into()
     }
 
@@ -170,6 +182,19 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
         verifier.verify(&mut challenger, vk, proof)
     }
 
+    /// Verifies each of `proofs` against `vk`, see [MultiTraceStarkVerifier::verify_batch].
+    fn verify_batch(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        proofs: &[Proof<SC>],
+    ) -> Vec<Result<(), VerificationError>>
+    where
+        SC::Challenger: Clone,
+    {
+        self.verifier()
+            .verify_batch(&self.new_challenger(), vk, proofs)
+    }
+
     // mpk can be removed if we use BaseAir trait to regenerate preprocessed traces
     fn debug(
         &self,
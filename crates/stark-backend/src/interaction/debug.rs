@@ -16,6 +16,40 @@ pub struct LogicalInteractions<F: Field> {
     pub at_bus: BTreeMap<BusIndex, HashMap<Vec<F>, Vec<(usize, F)>>>,
 }
 
+/// A message on a bus whose multiplicities, summed over all sends and receives, are not zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Imbalance<F> {
+    pub bus_index: BusIndex,
+    pub fields: Vec<F>,
+    /// (air_idx, count) for every send or receive of `fields` on the bus
+    pub connections: Vec<(usize, F)>,
+}
+
+impl<F: Field> LogicalInteractions<F> {
+    /// Returns every message whose multiplicities do not sum to zero, in order of bus index.
+    pub fn imbalances(&self) -> Vec<Imbalance<F>> {
+        self.at_bus
+            .iter()
+            .flat_map(|(&bus_index, bus_interactions)| {
+                bus_interactions
+                    .iter()
+                    .filter(|(_, connections)| {
+                        !connections
+                            .iter()
+                            .map(|(_, count)| *count)
+                            .sum::<F>()
+                            .is_zero()
+                    })
+                    .map(move |(fields, connections)| Imbalance {
+                        bus_index,
+                        fields: fields.clone(),
+                        connections: connections.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
 pub fn generate_logical_interactions<F: Field>(
     air_idx: usize,
     all_interactions: &[SymbolicInteraction<F>],
@@ -28,7 +62,11 @@ pub fn generate_logical_interactions<F: Field>(
         return;
     }
 
-    let height = partitioned_main[0].height();
+    let height = partitioned_main
+        .first()
+        .or(preprocessed.as_ref())
+        .expect("no main or preprocessed trace")
+        .height();
 
     for n in 0..height {
         let evaluator = Evaluator {
@@ -26,6 +26,10 @@ pub struct FriLogUpPhase<F, Challenge, Challenger> {
     /// When the perm trace is created, the matrix will be allocated with `capacity = trace_length << extra_capacity_bits`.
     /// This is to avoid resizing for the coset LDE.
     extra_capacity_bits: usize,
+    fingerprint_scheme: FingerprintScheme,
+    /// First nonce of a sequential proof of work search, if set. See
+    /// [with_grinding_seed](Self::with_grinding_seed).
+    grinding_seed: Option<u64>,
     _marker: PhantomData<(F, Challenge, Challenger)>,
 }
 
@@ -34,9 +38,54 @@ impl<F, Challenge, Challenger> FriLogUpPhase<F, Challenge, Challenger> {
         Self {
             log_up_params,
             extra_capacity_bits,
+            fingerprint_scheme: FingerprintScheme::default(),
+            grinding_seed: None,
             _marker: PhantomData,
         }
     }
+
+    /// Sets how interaction messages are combined into LogUp fingerprints. The scheme is used by
+    /// the prover and in the constraints generated by keygen, so the verifying key has to be
+    /// generated with the same config.
+    pub fn with_fingerprint_scheme(mut self, fingerprint_scheme: FingerprintScheme) -> Self {
+        self.fingerprint_scheme = fingerprint_scheme;
+        self
+    }
+
+    /// Makes the logUp proof of work search sequential, trying the nonces `seed, seed + 1, ...`
+    /// in order, so that proving the same inputs twice gives the same witness. By default, the
+    /// search is parallel and returns any valid witness. The verifier does not depend on how the
+    /// witness was found.
+    pub fn with_grinding_seed(mut self, seed: u64) -> Self {
+        self.grinding_seed = Some(seed);
+        self
+    }
+}
+
+/// How the fields `f_0, ..., f_{n-1}` of an interaction message and its bus index `b` are
+/// combined with powers of the challenge `beta` into the LogUp fingerprint
+/// `alpha + combination`. In all schemes, `b + 1` is scaled by `beta^n`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FingerprintScheme {
+    /// `f_0 + beta * f_1 + ... + beta^(n-1) * f_{n-1} + beta^n * (b + 1)`, with explicit powers
+    /// of `beta` in message order.
+    #[default]
+    Powers,
+    /// Horner's rule over `b + 1, f_0, ..., f_{n-1}`, which is
+    /// `beta^n * (b + 1) + beta^(n-1) * f_0 + ... + f_{n-1}`: the powers of `beta` are in
+    /// reverse message order.
+    Horner,
+}
+
+impl FingerprintScheme {
+    /// The power of `beta` scaling field `idx` of a message with `len` fields.
+    pub fn field_exponent(&self, idx: usize, len: usize) -> usize {
+        debug_assert!(idx < len);
+        match self {
+            FingerprintScheme::Powers => idx,
+            FingerprintScheme::Horner => len - 1 - idx,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -84,6 +133,10 @@ where
         &self.log_up_params
     }
 
+    fn fingerprint_scheme(&self) -> FingerprintScheme {
+        self.fingerprint_scheme
+    }
+
     fn generate_pk_per_air(
         &self,
         symbolic_constraints_per_air: &[SymbolicConstraints<F>],
@@ -114,7 +167,10 @@ where
         }
 
         // Proof of work phase to boost logup security.
-        let logup_pow_witness = challenger.grind(self.log_up_params.log_up_pow_bits);
+        let logup_pow_witness = match self.grinding_seed {
+            Some(seed) => grind_from_seed(challenger, self.log_up_params.log_up_pow_bits, seed),
+            None => challenger.grind(self.log_up_params.log_up_pow_bits),
+        };
         let challenges: [Challenge; STARK_LU_NUM_CHALLENGES] =
             array::from_fn(|_| challenger.sample_ext_element::<Challenge>());
 
@@ -125,6 +181,7 @@ where
                 params_per_air,
                 trace_view_per_air,
                 self.extra_capacity_bits,
+                self.fingerprint_scheme,
             )
         });
         let cumulative_sum_per_air = Self::extract_cumulative_sums(&after_challenge_trace_per_air);
@@ -154,7 +211,7 @@ where
         challenger: &mut Challenger,
         partial_proof: Option<&Self::PartialProof>,
         exposed_values_per_phase_per_air: &[Vec<Vec<Challenge>>],
-        commitment_per_phase: &[Commitment],
+        commitments_after_challenge: &[Commitment],
         _permutation_opened_values: &[Vec<Vec<Vec<Challenge>>>],
     ) -> (RapPhaseVerifierData<Challenge>, Result<(), Self::Error>)
     where
@@ -198,7 +255,9 @@ where
             }
         }
 
-        challenger.observe(commitment_per_phase[0].clone());
+        for commitment in commitments_after_challenge {
+            challenger.observe(commitment.clone());
+        }
 
         let cumulative_sums = exposed_values_per_phase_per_air
             .iter()
@@ -218,7 +277,9 @@ where
             })
             .collect_vec();
 
-        // Check cumulative sum
+        // Check cumulative sum. Only the AIRs present in the proof contribute, so when proving a
+        // subset of AIRs every bus must balance within the subset: omitting both sides of a bus
+        // is fine, but omitting only one side leaves the bus unbalanced and fails this check.
         let sum: Challenge = cumulative_sums
             .into_iter()
             .map(|c| c.unwrap_or(Challenge::ZERO))
@@ -236,6 +297,21 @@ where
     }
 }
 
+/// Same as [GrindingChallenger::grind], but returns the first valid witness among the nonces
+/// `seed, seed + 1, ...`.
+fn grind_from_seed<F, Challenger>(challenger: &mut Challenger, bits: usize, seed: u64) -> F
+where
+    F: Field,
+    Challenger: GrindingChallenger<Witness = F>,
+{
+    let witness = (seed..)
+        .map(F::from_wrapped_u64)
+        .find(|&witness| challenger.clone().check_witness(bits, witness))
+        .expect("failed to find witness");
+    assert!(challenger.check_witness(bits, witness));
+    witness
+}
+
 pub const STARK_LU_NUM_CHALLENGES: usize = 2;
 pub const STARK_LU_NUM_EXPOSED_VALUES: usize = 1;
 
@@ -254,6 +330,7 @@ where
         params_per_air: &[&FriLogUpProvingKey],
         trace_view_per_air: Vec<PairTraceView<F>>,
         extra_capacity_bits: usize,
+        fingerprint_scheme: FingerprintScheme,
     ) -> Vec<Option<RowMajorMatrix<Challenge>>> {
         parizip!(constraints_per_air, trace_view_per_air, params_per_air)
             .map(|(constraints, trace_view, params)| {
@@ -263,6 +340,7 @@ where
                     challenges,
                     &params.interaction_partitions,
                     extra_capacity_bits,
+                    fingerprint_scheme,
                 )
             })
             .collect::<Vec<_>>()
@@ -289,18 +367,18 @@ where
     /// The permutation randomness is only available after the main trace from all chips
     /// involved in interactions have been committed.
     ///
-    /// - `partitioned_main` is the main trace, partitioned into several matrices of the same height
+    /// - `partitioned_main` is the main trace, partitioned into several matrices of the same height.
+    ///   It is empty for AIRs with only a preprocessed trace.
+    /// - `fingerprint_scheme` must be the scheme of the constraints, see [FingerprintScheme].
     ///
     /// Returns the permutation trace as a matrix of extension field elements.
-    ///
-    /// ## Panics
-    /// - If `partitioned_main` is empty.
     pub fn generate_after_challenge_trace(
         all_interactions: &[SymbolicInteraction<F>],
         trace_view: PairTraceView<F>,
         permutation_randomness: &[Challenge; STARK_LU_NUM_CHALLENGES],
         interaction_partitions: &[Vec<usize>],
         extra_capacity_bits: usize,
+        fingerprint_scheme: FingerprintScheme,
     ) -> Option<RowMajorMatrix<Challenge>>
     where
         F: Field,
@@ -318,7 +396,8 @@ where
         // For every row we do the following
         // We first compute the reciprocals: r_1, r_2, ..., r_n, where
         // r_i = \frac{1}{\alpha^i + \sum_j \beta^j * f_{i, j}}, where
-        // f_{i, j} is the jth main trace column for the ith interaction
+        // f_{i, j} is the jth main trace column for the ith interaction, in the order of the
+        // fingerprint scheme
         //
         // We then bundle every interaction_chunk_size interactions together
         // to get the value perm_i = \sum_{i \in bundle} r_i * m_i, where m_i
@@ -330,7 +409,7 @@ where
         // Row: | perm_1 | perm_2 | perm_3 | ... | perm_s | phi |, where s
         // is the number of bundles
         let num_interactions = all_interactions.len();
-        let height = trace_view.partitioned_main[0].height();
+        let height = 1usize << trace_view.log_trace_height;
 
         // Note: we could precompute this and include in the proving key, but this should be
         // a fast scan and only done once per AIR and not per row, so it is more ergonomic to compute
@@ -377,13 +456,14 @@ where
                 for (denom, interaction) in denom_row.iter_mut().zip(all_interactions.iter()) {
                     debug_assert!(interaction.message.len() <= betas.len());
                     let b = F::from_canonical_u32(interaction.bus_index as u32 + 1);
-                    let mut fields = interaction.message.iter();
-                    *denom = alpha
-                        + evaluator.eval_expr(fields.next().expect("fields should not be empty"));
-                    for (expr, &beta) in fields.zip(betas.iter().skip(1)) {
+                    let len = interaction.message.len();
+                    assert!(len != 0, "fields should not be empty");
+                    *denom = alpha;
+                    for (idx, expr) in interaction.message.iter().enumerate() {
+                        let beta = betas[fingerprint_scheme.field_exponent(idx, len)];
                         *denom += beta * evaluator.eval_expr(expr);
                     }
-                    *denom += betas[interaction.message.len()] * b;
+                    *denom += betas[len] * b;
                 }
             }
 
@@ -441,10 +521,14 @@ where
 /// and one column for the partial sum of log derivative. These columns are trace columns
 /// "after challenge" phase 0, and they are valued in the extension field.
 /// For more details, see the comment in the trace.rs file
+///
+/// Interaction messages are combined into fingerprints by `fingerprint_scheme`, which must be
+/// the scheme of the prover, see [FriLogUpPhase::with_fingerprint_scheme].
 pub fn eval_fri_log_up_phase<AB>(
     builder: &mut AB,
     symbolic_interactions: &[SymbolicInteraction<AB::F>],
     max_constraint_degree: usize,
+    fingerprint_scheme: FingerprintScheme,
 ) where
     AB: InteractionBuilder + PermutationAirBuilderWithExposedValues,
 {
@@ -492,8 +576,12 @@ pub fn eval_fri_log_up_phase<AB>(
                 );
                 let mut field_hash = AB::ExprEF::ZERO;
                 let b = AB::Expr::from_canonical_u32(interaction.bus_index as u32 + 1);
-                for (field, beta) in interaction.message.iter().chain([&b]).zip(&betas) {
-                    field_hash += beta.clone() * field.clone();
+                let len = interaction.message.len();
+                let exponents = (0..len)
+                    .map(|idx| fingerprint_scheme.field_exponent(idx, len))
+                    .chain([len]);
+                for (field, exponent) in interaction.message.iter().chain([&b]).zip(exponents) {
+                    field_hash += betas[exponent].clone() * field.clone();
                 }
                 field_hash + alpha.into()
             })
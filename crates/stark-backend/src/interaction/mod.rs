@@ -1,15 +1,18 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, iter::zip, sync::Arc};
 
+use itertools::Itertools;
 use p3_air::AirBuilder;
 use p3_challenger::CanObserve;
-use p3_field::Field;
+use p3_field::{Field, FieldAlgebra};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     air_builders::symbolic::{symbolic_expression::SymbolicExpression, SymbolicConstraints},
-    interaction::fri_log_up::{STARK_LU_NUM_CHALLENGES, STARK_LU_NUM_EXPOSED_VALUES},
+    interaction::fri_log_up::{
+        FingerprintScheme, STARK_LU_NUM_CHALLENGES, STARK_LU_NUM_EXPOSED_VALUES,
+    },
     prover::types::PairView,
 };
 
@@ -23,9 +26,16 @@ mod utils;
 // Must be a type smaller than u32 to make BusIndex p - 1 unrepresentable.
 pub type BusIndex = u16;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Interaction<Expr> {
     pub message: Vec<Expr>,
+    /// The multiplicity of `message`, as a single field element.
+    ///
+    /// LogUp only proves that the multiplicities of each message balance modulo the field
+    /// characteristic \( p \), so multiplicities of \( p \) or more cannot be supported by
+    /// spreading the count across several limbs: the count `c0 + c1 * 2^k` still enters the
+    /// LogUp sum reduced modulo \( p \). The verifier instead bounds the total count with
+    /// `count_weight`. Larger multiplicities need a field of larger characteristic.
     pub count: Expr,
     /// The bus index specifying the bus to send the message over. All valid instantiations of
     /// `BusIndex` are safe.
@@ -40,10 +50,63 @@ pub struct Interaction<Expr> {
     /// This is used to impose sufficient conditions for bus constraint soundness and setting a
     /// proper value depends on the bus and the constraint it imposes.
     pub count_weight: u32,
+    /// Whether the interaction sends or receives `message`, as recorded by
+    /// [InteractionBuilder::push_send] and [InteractionBuilder::push_receive]. `None` if the
+    /// direction is only given by the sign of `count`, as for [PermutationCheckBus::interact].
+    #[serde(default)]
+    pub kind: Option<PermutationInteractionType>,
 }
 
 pub type SymbolicInteraction<F> = Interaction<SymbolicExpression<F>>;
 
+/// Declaration of a single interaction of an AIR, see
+/// [InteractionsAir](crate::rap::InteractionsAir).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InteractionSpec {
+    pub bus_index: BusIndex,
+    /// See [Interaction::kind].
+    pub kind: Option<PermutationInteractionType>,
+    /// Number of fields of the message.
+    pub num_fields: usize,
+}
+
+impl InteractionSpec {
+    pub fn send(bus_index: BusIndex, num_fields: usize) -> Self {
+        Self {
+            bus_index,
+            kind: Some(PermutationInteractionType::Send),
+            num_fields,
+        }
+    }
+
+    pub fn receive(bus_index: BusIndex, num_fields: usize) -> Self {
+        Self {
+            bus_index,
+            kind: Some(PermutationInteractionType::Receive),
+            num_fields,
+        }
+    }
+
+    /// An interaction whose direction is only given by the sign of its count.
+    pub fn undirected(bus_index: BusIndex, num_fields: usize) -> Self {
+        Self {
+            bus_index,
+            kind: None,
+            num_fields,
+        }
+    }
+}
+
+impl<F: Field> From<&SymbolicInteraction<F>> for InteractionSpec {
+    fn from(interaction: &SymbolicInteraction<F>) -> Self {
+        Self {
+            bus_index: interaction.bus_index,
+            kind: interaction.kind,
+            num_fields: interaction.message.len(),
+        }
+    }
+}
+
 /// An [AirBuilder] with additional functionality to build special logUp arguments for
 /// communication between AIRs across buses. These arguments use randomness to
 /// add additional trace columns (in the extension field) and constraints to the AIR.
@@ -52,7 +115,20 @@ pub type SymbolicInteraction<F> = Interaction<SymbolicExpression<F>>;
 /// to other AIRs. The original AIR is augmented by virtual columns determined by
 /// the interactions to define a [RAP](crate::rap::Rap).
 pub trait InteractionBuilder: AirBuilder {
-    /// Stores a new interaction in the builder.
+    /// Stores a new interaction in the builder, with direction `kind`.
+    ///
+    /// See [Interaction] for more details on `count_weight` and `kind`.
+    fn push_interaction_with_kind<E: Into<Self::Expr>>(
+        &mut self,
+        bus_index: BusIndex,
+        fields: impl IntoIterator<Item = E>,
+        count: impl Into<Self::Expr>,
+        count_weight: u32,
+        kind: Option<PermutationInteractionType>,
+    );
+
+    /// Stores a new interaction in the builder, whose direction is only given by the sign of
+    /// `count`. Prefer [Self::push_send] and [Self::push_receive], which record the direction.
     ///
     /// See [Interaction] for more details on `count_weight`.
     fn push_interaction<E: Into<Self::Expr>>(
@@ -61,13 +137,98 @@ pub trait InteractionBuilder: AirBuilder {
         fields: impl IntoIterator<Item = E>,
         count: impl Into<Self::Expr>,
         count_weight: u32,
-    );
+    ) {
+        self.push_interaction_with_kind(bus_index, fields, count, count_weight, None);
+    }
+
+    /// Sends `fields` with multiplicity `count`.
+    ///
+    /// See [Interaction] for more details on `count_weight`.
+    fn push_send<E: Into<Self::Expr>>(
+        &mut self,
+        bus_index: BusIndex,
+        fields: impl IntoIterator<Item = E>,
+        count: impl Into<Self::Expr>,
+        count_weight: u32,
+    ) {
+        let kind = Some(PermutationInteractionType::Send);
+        self.push_interaction_with_kind(bus_index, fields, count, count_weight, kind);
+    }
+
+    /// Receives `fields` with multiplicity `count`, which is stored negated.
+    ///
+    /// See [Interaction] for more details on `count_weight`.
+    fn push_receive<E: Into<Self::Expr>>(
+        &mut self,
+        bus_index: BusIndex,
+        fields: impl IntoIterator<Item = E>,
+        count: impl Into<Self::Expr>,
+        count_weight: u32,
+    ) {
+        let kind = Some(PermutationInteractionType::Receive);
+        self.push_interaction_with_kind(bus_index, fields, -count.into(), count_weight, kind);
+    }
+
+    /// Stores a new interaction on a stateful bus, such as a memory bus, whose message is
+    /// `fields` followed by the ordering field `timestamp`. Alongside, the timestamp is
+    /// constrained to increase by one from each row to the next, where `next_timestamp` is the
+    /// timestamp on the next row, so that the messages of an AIR are ordered by row.
+    ///
+    /// See [Interaction] for more details on `count_weight`.
+    fn push_timed_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        bus_index: BusIndex,
+        fields: impl IntoIterator<Item = E>,
+        timestamp: impl Into<Self::Expr>,
+        next_timestamp: impl Into<Self::Expr>,
+        count: impl Into<Self::Expr>,
+        count_weight: u32,
+    ) {
+        let timestamp = timestamp.into();
+        self.when_transition()
+            .assert_eq(next_timestamp, timestamp.clone() + Self::Expr::ONE);
+        let message = fields.into_iter().map(Into::into).chain([timestamp]);
+        self.push_interaction(bus_index, message, count, count_weight);
+    }
 
     /// Returns the current number of interactions.
     fn num_interactions(&self) -> usize;
 
     /// Returns all interactions stored.
     fn all_interactions(&self) -> &[Interaction<Self::Expr>];
+
+    /// Records that messages on `schema.bus_index` follow `schema`, so that keygen can check that
+    /// all AIRs on the bus agree on the message layout. Builders that do not perform keygen may
+    /// ignore this.
+    fn register_bus_schema(&mut self, _schema: &BusSchema) {}
+
+    /// Sends a message with fields bound by name according to `schema`.
+    ///
+    /// Caller must constrain `enabled` to be boolean.
+    fn push_send_typed<E: Into<Self::Expr>>(
+        &mut self,
+        schema: &BusSchema,
+        binding: impl IntoIterator<Item = (&'static str, E)>,
+        enabled: impl Into<Self::Expr>,
+    ) {
+        let message = schema.bind(binding);
+        self.register_bus_schema(schema);
+        self.push_send(schema.bus_index, message, enabled, 1);
+    }
+
+    /// Receives a message with fields bound by name according to `schema`.
+    ///
+    /// Caller must constrain `enabled` to be boolean.
+    fn push_receive_typed<E: Into<Self::Expr>>(
+        &mut self,
+        schema: &BusSchema,
+        binding: impl IntoIterator<Item = (&'static str, E)>,
+        enabled: impl Into<Self::Expr>,
+    ) {
+        let message = schema.bind(binding);
+        self.register_bus_schema(schema);
+        self.push_receive(schema.bus_index, message, enabled, 1);
+    }
 }
 
 /// A `Lookup` bus is used to establish that one multiset of values (the queries) are subset of
@@ -105,7 +266,7 @@ impl LookupBus {
         // multiplicity to be {0, -1, ..., -p + 1}. Setting `count_weight = 1` will ensure that the
         // total number of lookups is at most p, which is sufficient to establish lookup multiset is
         // a subset of the key multiset. See Corollary 3.6 in [docs/Soundess_of_Interactions_via_LogUp.pdf].
-        builder.push_interaction(self.index, query, enabled, 1);
+        builder.push_send(self.index, query, enabled, 1);
     }
 
     /// Adds a key to the lookup table.
@@ -122,7 +283,7 @@ impl LookupBus {
     {
         // Since we only want a subset constraint, `count_weight` can be zero here. See the comment
         // in `LookupBus::lookup_key`.
-        builder.push_interaction(self.index, key, -num_lookups.into(), 0);
+        builder.push_receive(self.index, key, num_lookups, 0);
     }
 }
 
@@ -135,7 +296,7 @@ pub struct PermutationCheckBus {
     pub index: BusIndex,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PermutationInteractionType {
     Send,
     Receive,
@@ -159,7 +320,7 @@ impl PermutationCheckBus {
         E: Into<AB::Expr>,
     {
         // We embed the multiplicity `enabled` as an integer {0, 1}.
-        builder.push_interaction(self.index, message, enabled, 1);
+        builder.push_send(self.index, message, enabled, 1);
     }
 
     /// Receive a message.
@@ -175,7 +336,7 @@ impl PermutationCheckBus {
         E: Into<AB::Expr>,
     {
         // We embed the multiplicity `enabled` as an integer {0, -1}.
-        builder.push_interaction(self.index, message, -enabled.into(), 1);
+        builder.push_receive(self.index, message, enabled, 1);
     }
 
     /// Send or receive determined by `interaction_type`.
@@ -216,6 +377,83 @@ impl PermutationCheckBus {
     }
 }
 
+/// Named fields of the messages sent over a [PermutationCheckBus]-style bus.
+///
+/// Messages are bound by field name with [InteractionBuilder::push_send_typed] and
+/// [InteractionBuilder::push_receive_typed], and laid out in the order of `fields`. Keygen checks
+/// that all AIRs using a schema on the same bus use the same one, which catches senders and
+/// receivers disagreeing on the field order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BusSchema {
+    pub bus_index: BusIndex,
+    pub fields: Vec<&'static str>,
+}
+
+impl BusSchema {
+    pub fn new(bus_index: BusIndex, fields: impl IntoIterator<Item = &'static str>) -> Self {
+        let fields: Vec<_> = fields.into_iter().collect();
+        assert!(
+            fields.iter().all_unique(),
+            "bus {bus_index} schema has duplicate fields: {fields:?}"
+        );
+        Self { bus_index, fields }
+    }
+
+    /// Orders the named values of `binding` by the fields of the schema.
+    ///
+    /// Panics unless `binding` assigns exactly one value to every field.
+    pub fn bind<E>(&self, binding: impl IntoIterator<Item = (&'static str, E)>) -> Vec<E> {
+        let mut values: Vec<Option<E>> = self.fields.iter().map(|_| None).collect();
+        for (name, value) in binding {
+            let idx = self
+                .fields
+                .iter()
+                .position(|&field| field == name)
+                .unwrap_or_else(|| panic!("bus {} schema has no field {name}", self.bus_index));
+            assert!(
+                values[idx].replace(value).is_none(),
+                "field {name} bound twice on bus {}",
+                self.bus_index
+            );
+        }
+        zip(&self.fields, values)
+            .map(|(name, value)| {
+                value.unwrap_or_else(|| panic!("field {name} unbound on bus {}", self.bus_index))
+            })
+            .collect()
+    }
+}
+
+/// Packs extension field elements into a bus message over the common base field, so that AIRs
+/// working with different extension degrees of the same base field can share a bus.
+///
+/// Each element is given by its `D_i` base field coefficients, where `D_i <= target_degree`, and
+/// is zero-padded to `target_degree` coefficients. All AIRs on the bus must use the same
+/// `target_degree`, which should be the largest extension degree in use.
+///
+/// This is an injective packing of coefficient vectors, used only for message equality on the
+/// bus. It agrees with the field embedding `F_{p^D_i} -> F_{p^target_degree}` only when the
+/// extensions use compatible bases, so callers should not rely on it for arithmetic.
+pub fn embed_ext_message<Expr: FieldAlgebra>(
+    elements: impl IntoIterator<Item = Vec<Expr>>,
+    target_degree: usize,
+) -> Vec<Expr> {
+    elements
+        .into_iter()
+        .flat_map(|coeffs| {
+            assert!(
+                coeffs.len() <= target_degree,
+                "extension degree {} exceeds target degree {target_degree}",
+                coeffs.len()
+            );
+            let padding = target_degree - coeffs.len();
+            coeffs
+                .into_iter()
+                .chain(std::iter::repeat(Expr::ZERO).take(padding))
+        })
+        .collect()
+}
+
 pub struct RapPhaseProverData<Challenge> {
     /// Challenges from the challenger in this phase that determine RAP constraints and exposed values.
     pub challenges: Vec<Challenge>,
@@ -283,6 +521,12 @@ pub trait RapPhaseSeq<F, Challenge, Challenger> {
 
     fn log_up_security_params(&self) -> &LogUpSecurityParameters;
 
+    /// How interaction messages are combined into fingerprints, which keygen uses for the
+    /// constraints of the challenge phases.
+    fn fingerprint_scheme(&self) -> FingerprintScheme {
+        FingerprintScheme::default()
+    }
+
     /// The protocol parameters for the challenge phases may depend on the AIR constraints.
     fn generate_pk_per_air(
         &self,
@@ -311,13 +555,14 @@ pub trait RapPhaseSeq<F, Challenge, Challenger> {
     /// Assumes the shape of `exposed_values_per_air_per_phase` is verified externally.
     ///
     /// An implementation of this function must sample challenges for the challenge phases and then
-    /// observe the exposed values and commitment.
+    /// observe the exposed values and commitments. The commitments of all challenge phases are in
+    /// `commitments_after_challenge`, in phase order, and each phase may have multiple commitments.
     fn partially_verify<Commitment: Clone>(
         &self,
         challenger: &mut Challenger,
         partial_proof: Option<&Self::PartialProof>,
         exposed_values_per_air_per_phase: &[Vec<Vec<Challenge>>],
-        commitments_per_phase: &[Commitment],
+        commitments_after_challenge: &[Commitment],
         // per commitment, per matrix, per rotation, per column
         after_challenge_opened_values: &[Vec<Vec<Vec<Challenge>>>],
     ) -> (RapPhaseVerifierData<Challenge>, Result<(), Self::Error>)
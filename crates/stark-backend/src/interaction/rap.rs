@@ -5,7 +5,7 @@ use p3_air::{Air, AirBuilder};
 
 use super::{InteractionBuilder, RapPhaseSeqKind, SymbolicInteraction};
 use crate::{
-    interaction::fri_log_up::eval_fri_log_up_phase,
+    interaction::fri_log_up::{eval_fri_log_up_phase, FingerprintScheme},
     rap::{PermutationAirBuilderWithExposedValues, Rap},
 };
 
@@ -17,6 +17,7 @@ pub(crate) trait InteractionPhaseAirBuilder: InteractionBuilder {
     /// The maximum constraint degree allowed in a RAP.
     fn max_constraint_degree(&self) -> usize;
     fn rap_phase_seq_kind(&self) -> RapPhaseSeqKind;
+    fn fingerprint_scheme(&self) -> FingerprintScheme;
 }
 
 impl<AB, A> Rap<AB> for A
@@ -36,6 +37,7 @@ where
                         builder,
                         &symbolic_interactions,
                         builder.max_constraint_degree(),
+                        builder.fingerprint_scheme(),
                     );
                 }
                 RapPhaseSeqKind::None => {}
@@ -42,4 +42,7 @@ impl<F: Field> SymbolicEvaluator<F, F> for Evaluator<'_, F> {
     fn eval_is_transition(&self) -> F {
         unreachable!()
     }
+    fn eval_is_transition_window(&self, _size: usize) -> F {
+        unreachable!()
+    }
 }
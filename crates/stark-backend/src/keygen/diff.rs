@@ -0,0 +1,106 @@
+//! Differences between two verifying keys, to review how a change of the AIRs affects the
+//! verifying key.
+
+use std::iter::zip;
+
+use super::types::{MultiStarkVerifyingKey, StarkVerifyingKey, TraceWidth};
+use crate::config::{Com, StarkGenericConfig};
+
+/// Differences between two [MultiStarkVerifyingKey]s, from the first to the second. AIRs are
+/// matched by `air_id`. See [MultiStarkVerifyingKey::diff].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VkDiff {
+    /// Ids of the AIRs only in the second verifying key.
+    pub added_airs: Vec<usize>,
+    /// Ids of the AIRs only in the first verifying key.
+    pub removed_airs: Vec<usize>,
+    /// Changes of the AIRs in both verifying keys, by `air_id`. AIRs without changes are omitted.
+    pub changed_airs: Vec<(usize, AirVkDiff)>,
+    pub trace_height_constraints_changed: bool,
+    pub log_up_pow_bits_changed: bool,
+    /// Whether the hash of the verifying keys differs. This is the case whenever anything else
+    /// changed, but also for changes not reported by the other fields, such as the
+    /// preprocessed trace of an AIR.
+    pub pre_hash_changed: bool,
+}
+
+/// Changes of a single AIR in a [VkDiff]. Each changed value is reported as `(old, new)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AirVkDiff {
+    pub width: Option<(TraceWidth, TraceWidth)>,
+    pub num_public_values: Option<(usize, usize)>,
+    pub num_constraints: Option<(usize, usize)>,
+    pub num_interactions: Option<(usize, usize)>,
+    pub quotient_degree: Option<(u8, u8)>,
+    /// Whether the preprocessed trace commitment changed, including whether there is one.
+    pub preprocessed_changed: bool,
+    /// Whether the constraint DAG or the interactions changed, including changes which keep
+    /// their number the same.
+    pub constraints_changed: bool,
+}
+
+impl VkDiff {
+    /// Whether the verifying keys are the same.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AirVkDiff {
+    /// Compares the verifying keys `old` and `new` of an AIR.
+    pub fn new<Val: PartialEq, Com: PartialEq>(
+        old: &StarkVerifyingKey<Val, Com>,
+        new: &StarkVerifyingKey<Val, Com>,
+    ) -> Self {
+        fn changed<T: PartialEq>(old: T, new: T) -> Option<(T, T)> {
+            (old != new).then_some((old, new))
+        }
+        let preprocessed_commit = |vk: &StarkVerifyingKey<Val, Com>| {
+            vk.preprocessed_data.as_ref().map(|data| &data.commit)
+        };
+        Self {
+            width: changed(old.params.width.clone(), new.params.width.clone()),
+            num_public_values: changed(old.params.num_public_values, new.params.num_public_values),
+            num_constraints: changed(old.num_constraints(), new.num_constraints()),
+            num_interactions: changed(
+                old.symbolic_constraints.interactions.len(),
+                new.symbolic_constraints.interactions.len(),
+            ),
+            quotient_degree: changed(old.quotient_degree, new.quotient_degree),
+            preprocessed_changed: preprocessed_commit(old) != preprocessed_commit(new),
+            constraints_changed: old.symbolic_constraints.constraints
+                != new.symbolic_constraints.constraints
+                || old.symbolic_constraints.interactions != new.symbolic_constraints.interactions,
+        }
+    }
+
+    /// Whether the verifying keys of the AIR are the same.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC>
+where
+    Com<SC>: PartialEq,
+{
+    /// Reports how `other` differs from this verifying key: added and removed AIRs, changes of
+    /// the AIRs in both, and changes of the parameters shared by all AIRs.
+    pub fn diff(&self, other: &Self) -> VkDiff {
+        let (old, new) = (&self.inner.per_air, &other.inner.per_air);
+        let changed_airs = zip(old, new)
+            .enumerate()
+            .map(|(air_id, (old, new))| (air_id, AirVkDiff::new(old, new)))
+            .filter(|(_, diff)| !diff.is_empty())
+            .collect();
+        VkDiff {
+            added_airs: (old.len()..new.len()).collect(),
+            removed_airs: (new.len()..old.len()).collect(),
+            changed_airs,
+            trace_height_constraints_changed: self.inner.trace_height_constraints
+                != other.inner.trace_height_constraints,
+            log_up_pow_bits_changed: self.inner.log_up_pow_bits != other.inner.log_up_pow_bits,
+            pre_hash_changed: self.pre_hash != other.pre_hash,
+        }
+    }
+}
@@ -8,22 +8,33 @@ use tracing::instrument;
 use types::MultiStarkVerifyingKey0;
 
 use crate::{
-    air_builders::symbolic::{get_symbolic_builder, SymbolicRapBuilder},
+    air_builders::symbolic::{
+        get_symbolic_builder_with_fingerprint_scheme, SymbolicConstraintsDag,
+        SymbolicExpressionDag, SymbolicRapBuilder,
+    },
     config::{Com, RapPartialProvingKey, StarkGenericConfig, Val},
-    interaction::{RapPhaseSeq, RapPhaseSeqKind},
+    interaction::{
+        fri_log_up::FingerprintScheme, InteractionBuilder, InteractionSpec, RapPhaseSeq,
+        RapPhaseSeqKind,
+    },
     keygen::types::{
         LinearConstraint, MultiStarkProvingKey, ProverOnlySinglePreprocessedData, StarkProvingKey,
         StarkVerifyingKey, TraceWidth, VerifierSinglePreprocessedData,
     },
-    rap::AnyRap,
+    rap::{AnyRap, InteractionsAir},
 };
 
+pub mod diff;
 pub mod types;
 pub(crate) mod view;
 
 struct AirKeygenBuilder<SC: StarkGenericConfig> {
     air: Arc<dyn AnyRap<SC>>,
+    name_override: Option<String>,
+    /// See [InteractionsAir].
+    declared_interactions: Option<Vec<InteractionSpec>>,
     rap_phase_seq_kind: RapPhaseSeqKind,
+    fingerprint_scheme: FingerprintScheme,
     prep_keygen_data: PrepKeygenData<SC>,
 }
 
@@ -55,16 +66,47 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
 
     /// Default way to add a single Interactive AIR.
     /// Returns `air_id`
+    ///
+    /// # Panics
+    /// If the AIR has no columns at all: a main trace of width 0 and no preprocessed trace. Such
+    /// an AIR has no trace to commit and no height to prove it at.
     #[instrument(level = "debug", skip_all)]
     pub fn add_air(&mut self, air: Arc<dyn AnyRap<SC>>) -> usize {
         self.partitioned_airs.push(AirKeygenBuilder::new(
             self.config.pcs(),
             SC::RapPhaseSeq::ID,
+            self.config.rap_phase_seq().fingerprint_scheme(),
             air,
         ));
         self.partitioned_airs.len() - 1
     }
 
+    /// Adds a single Interactive AIR under the given instance `name`, which is used in place of
+    /// the AIR type name in metrics and error messages. This distinguishes multiple AIRs of the
+    /// same type, e.g. `FibChip[main]` and `FibChip[aux]`.
+    /// Returns `air_id`
+    pub fn add_air_with_name(
+        &mut self,
+        air: Arc<dyn AnyRap<SC>>,
+        name: impl Into<String>,
+    ) -> usize {
+        let air_id = self.add_air(air);
+        self.partitioned_airs[air_id].name_override = Some(name.into());
+        air_id
+    }
+
+    /// Adds a single Interactive AIR which declares its interactions. [Self::generate_pk] panics
+    /// if the interactions pushed by the AIR do not match the declaration.
+    pub fn add_air_with_declared_interactions<A>(&mut self, air: Arc<A>) -> usize
+    where
+        A: AnyRap<SC> + InteractionsAir<Val<SC>> + 'static,
+    {
+        let declared_interactions = air.declare_interactions();
+        let air_id = self.add_air(air);
+        self.partitioned_airs[air_id].declared_interactions = Some(declared_interactions);
+        air_id
+    }
+
     /// Consume the builder and generate proving key.
     /// The verifying key can be obtained from the proving key.
     pub fn generate_pk(mut self) -> MultiStarkProvingKey<SC> {
@@ -97,10 +139,16 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
             self.max_constraint_degree = air_max_constraint_degree;
         }
         // First pass: get symbolic constraints and interactions but RAP phase constraints are not final
-        let symbolic_constraints_per_air = self
+        let symbolic_builder_per_air = self
             .partitioned_airs
             .iter()
-            .map(|keygen_builder| keygen_builder.get_symbolic_builder(None).constraints())
+            .map(|keygen_builder| keygen_builder.get_symbolic_builder(None))
+            .collect_vec();
+        check_bus_schemas(&self.partitioned_airs, &symbolic_builder_per_air);
+        check_declared_interactions(&self.partitioned_airs, &symbolic_builder_per_air);
+        let symbolic_constraints_per_air = symbolic_builder_per_air
+            .into_iter()
+            .map(|symbolic_builder| symbolic_builder.constraints())
             .collect_vec();
         // Note: due to the need to go through a trait, there is some duplicate computation
         // (e.g., FRI logup will calculate the interaction chunking both here and in the second pass below)
@@ -118,7 +166,7 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
         for pk in pk_per_air.iter() {
             let width = &pk.vk.params.width;
             tracing::info!("{:<20} | Quotient Deg = {:<2} | Prep Cols = {:<2} | Main Cols = {:<8} | Perm Cols = {:<4} | {:4} Constraints | {:3} Interactions On Buses {:?}",
-                pk.air_name,
+                pk.name(),
                 pk.vk.quotient_degree,
                 width.preprocessed.unwrap_or(0),
                 format!("{:?}",width.main_widths()),
@@ -134,7 +182,7 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
             );
             #[cfg(feature = "bench-metrics")]
             {
-                let labels = [("air_name", pk.air_name.clone())];
+                let labels = [("air_name", pk.name().to_owned())];
                 metrics::counter!("quotient_deg", &labels).absolute(pk.vk.quotient_degree as u64);
                 // column info will be logged by prover later
                 metrics::counter!("constraints", &labels)
@@ -228,11 +276,28 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
 }
 
 impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
-    fn new(pcs: &SC::Pcs, rap_phase_seq_kind: RapPhaseSeqKind, air: Arc<dyn AnyRap<SC>>) -> Self {
+    fn new(
+        pcs: &SC::Pcs,
+        rap_phase_seq_kind: RapPhaseSeqKind,
+        fingerprint_scheme: FingerprintScheme,
+        air: Arc<dyn AnyRap<SC>>,
+    ) -> Self {
         let prep_keygen_data = compute_prep_data_for_air(pcs, air.as_ref());
+        // AIRs without a main trace, such as fixed lookup tables, take their height from the
+        // preprocessed trace.
+        assert!(
+            air.common_main_width() != 0
+                || !air.cached_main_widths().is_empty()
+                || prep_keygen_data.width().is_some(),
+            "AIR {} has width 0 and no preprocessed trace, so its trace height is undefined",
+            air.name()
+        );
         AirKeygenBuilder {
             air,
+            name_override: None,
+            declared_interactions: None,
             rap_phase_seq_kind,
+            fingerprint_scheme,
             prep_keygen_data,
         }
     }
@@ -249,14 +314,18 @@ impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
         max_constraint_degree: usize,
     ) -> StarkProvingKey<SC> {
         let air_name = self.air.name();
+        let display_name = self.name_override.as_deref().unwrap_or(&air_name);
 
         let symbolic_builder = self.get_symbolic_builder(Some(max_constraint_degree));
         let params = symbolic_builder.params();
         let symbolic_constraints = symbolic_builder.constraints();
         let log_quotient_degree = symbolic_constraints.get_log_quotient_degree();
         let quotient_degree = 1 << log_quotient_degree;
+        let symbolic_constraints: SymbolicConstraintsDag<Val<SC>> = symbolic_constraints.into();
+        check_constant_constraints(display_name, &symbolic_constraints.constraints);
 
         let Self {
+            name_override,
             prep_keygen_data:
                 PrepKeygenData {
                     verifier_data: prep_verifier_data,
@@ -268,12 +337,13 @@ impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
         let vk: StarkVerifyingKey<Val<SC>, Com<SC>> = StarkVerifyingKey {
             preprocessed_data: prep_verifier_data,
             params,
-            symbolic_constraints: symbolic_constraints.into(),
+            symbolic_constraints,
             quotient_degree,
             rap_phase_seq_kind: self.rap_phase_seq_kind,
         };
         StarkProvingKey {
             air_name,
+            air_name_override: name_override,
             vk,
             preprocessed_data: prep_prover_data,
             rap_partial_pk,
@@ -290,13 +360,14 @@ impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
             common_main: self.air.common_main_width(),
             after_challenge: vec![],
         };
-        get_symbolic_builder(
+        get_symbolic_builder_with_fingerprint_scheme(
             self.air.as_ref(),
             &width,
             &[],
             &[],
             SC::RapPhaseSeq::ID,
             max_constraint_degree.unwrap_or(0),
+            self.fingerprint_scheme,
         )
     }
 }
@@ -312,6 +383,85 @@ impl<SC: StarkGenericConfig> PrepKeygenData<SC> {
     }
 }
 
+/// Warns about constraints which are always satisfied because they fold to zero, and panics on
+/// constraints which can never be satisfied because they fold to a nonzero constant.
+fn check_constant_constraints<F: Field>(air_name: &str, constraints: &SymbolicExpressionDag<F>) {
+    for (i, value) in constraints.fold_constant_constraints().into_iter().enumerate() {
+        match value {
+            Some(value) if value.is_zero() => {
+                tracing::warn!("constraint {i} of {air_name} is always zero and has no effect");
+            }
+            Some(value) => {
+                panic!(
+                    "constraint {i} of {air_name} is unsatisfiable: it is the nonzero constant {value}"
+                );
+            }
+            None => {}
+        }
+    }
+}
+
+/// Panics if two AIRs register different schemas for the same bus, or if an interaction on a bus
+/// with a schema has a message of the wrong length.
+fn check_bus_schemas<SC: StarkGenericConfig>(
+    airs: &[AirKeygenBuilder<SC>],
+    symbolic_builders: &[SymbolicRapBuilder<Val<SC>>],
+) {
+    let mut schemas = HashMap::new();
+    for (air, symbolic_builder) in zip(airs, symbolic_builders) {
+        for schema in symbolic_builder.bus_schemas() {
+            let (first_air, first_schema) = schemas
+                .entry(schema.bus_index)
+                .or_insert_with(|| (air.air.name(), schema));
+            assert_eq!(
+                *first_schema,
+                schema,
+                "bus {} schema of {} does not match the schema of {first_air}",
+                schema.bus_index,
+                air.air.name()
+            );
+        }
+    }
+    for (air, symbolic_builder) in zip(airs, symbolic_builders) {
+        for interaction in symbolic_builder.all_interactions() {
+            if let Some((_, schema)) = schemas.get(&interaction.bus_index) {
+                assert_eq!(
+                    interaction.message.len(),
+                    schema.fields.len(),
+                    "{} sends a message on bus {} that does not match its schema {:?}",
+                    air.air.name(),
+                    schema.bus_index,
+                    schema.fields
+                );
+            }
+        }
+    }
+}
+
+/// Panics if the interactions pushed by an AIR do not match its
+/// [declaration](InteractionsAir::declare_interactions).
+fn check_declared_interactions<SC: StarkGenericConfig>(
+    airs: &[AirKeygenBuilder<SC>],
+    symbolic_builders: &[SymbolicRapBuilder<Val<SC>>],
+) {
+    for (air, symbolic_builder) in zip(airs, symbolic_builders) {
+        let Some(declared) = &air.declared_interactions else {
+            continue;
+        };
+        let pushed = symbolic_builder
+            .all_interactions()
+            .iter()
+            .map(InteractionSpec::from)
+            .collect_vec();
+        assert_eq!(
+            *declared,
+            pushed,
+            "interactions of {} do not match its declaration",
+            air.air.name()
+        );
+    }
+}
+
 fn compute_prep_data_for_air<SC: StarkGenericConfig>(
     pcs: &SC::Pcs,
     air: &dyn AnyRap<SC>,
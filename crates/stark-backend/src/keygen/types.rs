@@ -1,7 +1,7 @@
 // Keygen API for STARK backend
 // Changes:
 // - All AIRs can be optional
-use std::sync::Arc;
+use std::{collections::BTreeMap, fmt::Write, sync::Arc};
 
 use derivative::Derivative;
 use p3_matrix::dense::RowMajorMatrix;
@@ -10,11 +10,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
     air_builders::symbolic::SymbolicConstraintsDag,
     config::{Com, PcsProverData, RapPartialProvingKey, StarkGenericConfig, Val},
-    interaction::RapPhaseSeqKind,
+    interaction::{BusIndex, PermutationInteractionType, RapPhaseSeqKind},
+    verifier::OpeningRound,
 };
 
 /// Widths of different parts of trace matrix
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TraceWidth {
     pub preprocessed: Option<usize>,
     pub cached_mains: Vec<usize>,
@@ -41,6 +42,9 @@ pub struct StarkVerifyingParams {
     pub width: TraceWidth,
     /// Number of public values for this STARK only
     pub num_public_values: usize,
+    /// Whether the number of active public values varies per proof. If so, the last public value
+    /// is the number of active public values and the inactive ones are zero.
+    pub variable_public_values: bool,
     /// Number of values to expose to verifier in each trace challenge phase
     pub num_exposed_values_after_challenge: Vec<usize>,
     /// For only this RAP, how many challenges are needed in each trace challenge phase
@@ -49,7 +53,9 @@ pub struct StarkVerifyingParams {
 
 /// Verifier data for preprocessed trace for a single AIR.
 ///
-/// Currently assumes each AIR has it's own preprocessed commitment
+/// Currently assumes each AIR has it's own preprocessed commitment. The commitment cannot be
+/// bundled with the main trace, since it is fixed by the verifying key while the main trace
+/// commitments are chosen by the prover for each proof.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct VerifierSinglePreprocessedData<Com> {
     /// Commitment to the preprocessed trace.
@@ -66,9 +72,12 @@ pub struct StarkVerifyingKey<Val, Com> {
     pub params: StarkVerifyingParams,
     /// Symbolic constraints of the AIR in all challenge phases. This is
     /// a serialization of the constraints in the AIR.
+    ///
+    /// The DAG is built once at keygen, and the verifier evaluates it directly for every proof.
     pub symbolic_constraints: SymbolicConstraintsDag<Val>,
     /// The factor to multiple the trace degree by to get the degree of the quotient polynomial. Determined from the max constraint degree of the AIR constraints.
-    /// This is equivalently the number of chunks the quotient polynomial is split into.
+    /// This is the maximum number of chunks the quotient polynomial is split into; the number of
+    /// chunks of a proof is recorded in [AirProofData::num_quotient_chunks](crate::proof::AirProofData::num_quotient_chunks).
     pub quotient_degree: u8,
     pub rap_phase_seq_kind: RapPhaseSeqKind,
 }
@@ -121,6 +130,9 @@ pub struct LinearConstraint {
 pub struct StarkProvingKey<SC: StarkGenericConfig> {
     /// Type name of the AIR, for display purposes only
     pub air_name: String,
+    /// Per-instance name set at keygen, distinguishing AIRs of the same type. Display purposes
+    /// only, see [StarkProvingKey::name]
+    pub air_name_override: Option<String>,
     /// Verifying key
     pub vk: StarkVerifyingKey<Val<SC>, Com<SC>>,
     /// Prover only data for preprocessed trace
@@ -129,6 +141,14 @@ pub struct StarkProvingKey<SC: StarkGenericConfig> {
     pub rap_partial_pk: RapPartialProvingKey<SC>,
 }
 
+impl<SC: StarkGenericConfig> StarkProvingKey<SC> {
+    /// Name of the AIR for metrics and error messages: the name override if one was set at
+    /// keygen, otherwise the type name.
+    pub fn name(&self) -> &str {
+        self.air_name_override.as_deref().unwrap_or(&self.air_name)
+    }
+}
+
 /// Common proving key for multiple AIRs.
 ///
 /// This struct contains the necessary data for the prover to generate proofs for multiple AIRs
@@ -158,9 +178,32 @@ impl<Val, Com> StarkVerifyingKey<Val, Com> {
         self.params.width.common_main != 0
     }
 
+    /// Whether the AIR has any main trace. AIRs without one are preprocessed-only, and their
+    /// trace height is the height of the preprocessed trace.
+    pub fn has_main(&self) -> bool {
+        self.has_common_main() || self.num_cached_mains() != 0
+    }
+
     pub fn has_interaction(&self) -> bool {
         !self.symbolic_constraints.interactions.is_empty()
     }
+
+    /// Number of constraints of the AIR, including those added for interactions.
+    pub fn num_constraints(&self) -> usize {
+        self.symbolic_constraints.constraints.constraint_idx.len()
+    }
+
+    /// Maximum degree of the constraints of the AIR, including those added for interactions.
+    pub fn max_constraint_degree(&self) -> usize {
+        self.symbolic_constraints.constraints.max_constraint_degree()
+    }
+
+    /// Number of adjacent rows the constraints of the AIR are evaluated on, which is the number
+    /// of points its trace matrices are opened at. Trace matrices are always opened at the
+    /// out-of-domain point and its next point, so this is at least 2.
+    pub fn num_rotations(&self) -> usize {
+        self.symbolic_constraints.constraints.max_rotation().max(1) + 1
+    }
 }
 
 impl<SC: StarkGenericConfig> MultiStarkProvingKey<SC> {
@@ -178,6 +221,70 @@ impl<SC: StarkGenericConfig> MultiStarkProvingKey<SC> {
             log_up_pow_bits: self.log_up_pow_bits,
         }
     }
+
+    /// Graphviz DOT graph of the data flow between AIRs. There is a node per AIR, and an edge from
+    /// each AIR sending on a bus to each AIR receiving on it, labeled with the bus index and the
+    /// total count weights of the sends and of the receives of the two AIRs on the bus.
+    /// Interactions without a [kind](crate::interaction::Interaction::kind) count as both.
+    pub fn bus_graph_dot(&self) -> String {
+        // Total count weight per (bus index, AIR index)
+        let mut send_weights = BTreeMap::<(BusIndex, usize), u64>::new();
+        let mut receive_weights = BTreeMap::<(BusIndex, usize), u64>::new();
+        for (air_idx, pk) in self.per_air.iter().enumerate() {
+            for interaction in &pk.vk.symbolic_constraints.interactions {
+                let key = (interaction.bus_index, air_idx);
+                let count_weight = u64::from(interaction.count_weight);
+                if interaction.kind != Some(PermutationInteractionType::Receive) {
+                    *send_weights.entry(key).or_default() += count_weight;
+                }
+                if interaction.kind != Some(PermutationInteractionType::Send) {
+                    *receive_weights.entry(key).or_default() += count_weight;
+                }
+            }
+        }
+
+        let mut dot = String::from("digraph buses {\n");
+        for (air_idx, pk) in self.per_air.iter().enumerate() {
+            writeln!(dot, "    air{air_idx} [label={:?}];", pk.name()).unwrap();
+        }
+        for (&(bus_index, sender), send_weight) in &send_weights {
+            let receivers = receive_weights.range((bus_index, 0)..=(bus_index, usize::MAX));
+            for (&(_, receiver), receive_weight) in receivers {
+                writeln!(
+                    dot,
+                    "    air{sender} -> air{receiver} [label=\"bus {bus_index}, weights {send_weight}/{receive_weight}\"];"
+                )
+                .unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The buses on which all AIRs only send or only receive, with the kind of their
+    /// interactions, sorted by bus index. Such a bus can only balance if all counts are zero,
+    /// which usually means that the AIR on the other end of the bus is missing.
+    /// Buses with an interaction without a [kind](crate::interaction::Interaction::kind) are
+    /// never reported.
+    pub fn dangling_buses(&self) -> Vec<(BusIndex, PermutationInteractionType)> {
+        let mut kinds_per_bus =
+            BTreeMap::<BusIndex, Vec<Option<PermutationInteractionType>>>::new();
+        for pk in &self.per_air {
+            for interaction in &pk.vk.symbolic_constraints.interactions {
+                let kinds = kinds_per_bus.entry(interaction.bus_index).or_default();
+                if !kinds.contains(&interaction.kind) {
+                    kinds.push(interaction.kind);
+                }
+            }
+        }
+        kinds_per_bus
+            .into_iter()
+            .filter_map(|(bus_index, kinds)| match kinds[..] {
+                [Some(kind)] => Some((bus_index, kind)),
+                _ => None,
+            })
+            .collect()
+    }
 }
 impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
     pub fn num_challenges_per_phase(&self) -> Vec<usize> {
@@ -191,6 +298,90 @@ impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
     pub fn num_interactions(&self) -> Vec<usize> {
         self.full_view().num_interactions()
     }
+
+    pub fn num_constraints_per_air(&self) -> Vec<usize> {
+        self.full_view().num_constraints_per_air()
+    }
+
+    /// Returns the maximum constraint degree of each AIR, to check before proving that it is
+    /// supported by the FRI parameters, see `FriParameters::max_constraint_degree`.
+    pub fn max_constraint_degrees(&self) -> Vec<usize> {
+        self.full_view().max_constraint_degrees()
+    }
+
+    /// Returns the layout of the values opened by the PCS in proofs with all AIRs, so that a
+    /// recursion verifier can be generated from it. The quotient polynomial of the `i`-th AIR is
+    /// split into `num_quotient_chunks_per_air[i]` chunks, which is its `quotient_degree` unless
+    /// the prover caps the number of chunks.
+    pub fn opened_values_layout(&self, num_quotient_chunks_per_air: &[u8]) -> OpenedValuesLayout {
+        self.full_view()
+            .opened_values_layout::<SC::Challenge>(num_quotient_chunks_per_air)
+    }
+
+    /// Estimates the work of a verifier of proofs with all AIRs, to size a recursion circuit.
+    /// The FRI parameters are `log_final_poly_len` and `num_queries`, and the largest trace has
+    /// height `2^max_log_trace_height`.
+    pub fn recursion_estimate(
+        &self,
+        log_final_poly_len: usize,
+        num_queries: usize,
+        max_log_trace_height: usize,
+    ) -> RecursionEstimate {
+        self.full_view().recursion_estimate::<SC::Challenge>(
+            log_final_poly_len,
+            num_queries,
+            max_log_trace_height,
+        )
+    }
+}
+
+/// Read-only estimate of the work a verifier performs, derived from the verifying key and the FRI
+/// parameters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecursionEstimate {
+    /// Number of nodes in the constraint DAGs. The verifier evaluates each node once, in the
+    /// challenge field, at the out-of-domain point.
+    pub num_constraint_nodes: usize,
+    /// Number of constraints, each folded into the quotient identity with a power of `alpha`.
+    pub num_constraints: usize,
+    /// Number of PCS commitments.
+    pub num_commitments: usize,
+    /// Number of matrices opened by the PCS, including quotient chunks.
+    pub num_opened_matrices: usize,
+    /// Number of challenge field values opened by the PCS.
+    pub num_opened_values: usize,
+    /// Number of FRI commit phase rounds for the largest trace.
+    pub num_fri_rounds: usize,
+    /// Number of FRI queries. Each query checks a Merkle path for every commitment and every FRI
+    /// round.
+    pub num_fri_queries: usize,
+}
+
+/// The values opened by the PCS, grouped by commitment in the order the verifier opens them:
+/// preprocessed traces, cached main traces, common main traces, after challenge traces and
+/// quotient chunks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenedValuesLayout {
+    pub commitments: Vec<CommitmentLayout>,
+}
+
+/// The matrices opened for a single commitment, in the order they are opened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentLayout {
+    pub round: OpeningRound,
+    pub matrices: Vec<OpenedMatrixLayout>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenedMatrixLayout {
+    /// Index of the AIR the matrix belongs to, which is its `air_id` for the layout of a full
+    /// verifying key.
+    pub air_idx: usize,
+    /// Number of challenge field values opened at each point.
+    pub width: usize,
+    /// Number of points the matrix is opened at: the out-of-domain point, and for trace matrices
+    /// also the following points of the trace domain.
+    pub num_rotations: usize,
 }
 
 /// Prover only data for preprocessed trace for a single AIR.
@@ -1,9 +1,15 @@
+use std::iter::zip;
+
 use itertools::Itertools;
 use p3_field::{ExtensionField, Field};
 
 use crate::{
     config::{Com, StarkGenericConfig, Val},
-    keygen::types::{LinearConstraint, MultiStarkVerifyingKey, StarkVerifyingKey},
+    keygen::types::{
+        CommitmentLayout, LinearConstraint, MultiStarkVerifyingKey, OpenedMatrixLayout,
+        OpenedValuesLayout, RecursionEstimate, StarkVerifyingKey,
+    },
+    verifier::OpeningRound,
 };
 
 #[derive(Clone, derive_new::new)]
@@ -101,4 +107,145 @@ impl<Val, Com: Clone> MultiStarkVerifyingKeyView<'_, Val, Com> {
             .map(|vk| vk.symbolic_constraints.interactions.len())
             .collect()
     }
+
+    /// Returns the number of constraints for each AIR.
+    pub fn num_constraints_per_air(&self) -> Vec<usize> {
+        self.per_air.iter().map(|vk| vk.num_constraints()).collect()
+    }
+
+    /// Returns the maximum constraint degree of each AIR.
+    pub fn max_constraint_degrees(&self) -> Vec<usize> {
+        self.per_air.iter().map(|vk| vk.max_constraint_degree()).collect()
+    }
+
+    /// Estimates the work of a verifier for these AIRs. See [RecursionEstimate].
+    ///
+    /// Assumes the default grouping of one commitment for all permutation traces and one for all
+    /// quotient polynomials.
+    pub fn recursion_estimate<E>(
+        &self,
+        log_final_poly_len: usize,
+        num_queries: usize,
+        max_log_trace_height: usize,
+    ) -> RecursionEstimate
+    where
+        Val: Field,
+        E: ExtensionField<Val>,
+    {
+        let has_interaction = self.per_air.iter().any(|vk| vk.has_interaction());
+        let num_cached_mains: usize = self.per_air.iter().map(|vk| vk.num_cached_mains()).sum();
+        // One commitment each for all common main traces, all permutation traces (if any), and
+        // all quotient polynomials
+        let num_commitments = self.flattened_preprocessed_commits().len()
+            + num_cached_mains
+            + 1
+            + usize::from(has_interaction)
+            + 1;
+        let num_opened_matrices = self
+            .per_air
+            .iter()
+            .map(|vk| {
+                usize::from(vk.preprocessed_data.is_some())
+                    + vk.params.width.main_widths().len()
+                    + vk.params.width.after_challenge.len()
+                    + vk.quotient_degree as usize
+            })
+            .sum();
+        // Trace matrices are opened at the out-of-domain point and the following points, quotient
+        // chunks only at the out-of-domain point
+        let num_opened_values = zip(&self.per_air, self.total_widths::<E>())
+            .map(|(vk, width)| vk.num_rotations() * width + vk.quotient_degree as usize * E::D)
+            .sum();
+        RecursionEstimate {
+            num_constraint_nodes: self
+                .per_air
+                .iter()
+                .map(|vk| vk.symbolic_constraints.constraints.nodes.len())
+                .sum(),
+            num_constraints: self.num_constraints_per_air().into_iter().sum(),
+            num_commitments,
+            num_opened_matrices,
+            num_opened_values,
+            num_fri_rounds: max_log_trace_height.saturating_sub(log_final_poly_len),
+            num_fri_queries: num_queries,
+        }
+    }
+
+    /// Returns the layout of the values opened by the PCS for these AIRs, in the order the
+    /// verifier opens them. See [OpenedValuesLayout].
+    ///
+    /// Assumes the default grouping of one commitment for all permutation traces and one for all
+    /// quotient polynomials. The quotient polynomial of the `i`-th AIR is split into
+    /// `num_quotient_chunks_per_air[i]` chunks.
+    pub fn opened_values_layout<E>(&self, num_quotient_chunks_per_air: &[u8]) -> OpenedValuesLayout
+    where
+        Val: Field,
+        E: ExtensionField<Val>,
+    {
+        assert_eq!(num_quotient_chunks_per_air.len(), self.per_air.len());
+        // Trace matrices are opened at the out-of-domain point and the following points, one per
+        // row the constraints of the AIR read
+        let trace_matrix = |air_idx: usize, width| OpenedMatrixLayout {
+            air_idx,
+            width,
+            num_rotations: self.per_air[air_idx].num_rotations(),
+        };
+        let mut commitments = vec![];
+        for (air_idx, vk) in self.per_air.iter().enumerate() {
+            if let Some(width) = vk.params.width.preprocessed {
+                commitments.push(CommitmentLayout {
+                    round: OpeningRound::Preprocessed,
+                    matrices: vec![trace_matrix(air_idx, width)],
+                });
+            }
+        }
+        for (air_idx, vk) in self.per_air.iter().enumerate() {
+            for &width in &vk.params.width.cached_mains {
+                commitments.push(CommitmentLayout {
+                    round: OpeningRound::Main,
+                    matrices: vec![trace_matrix(air_idx, width)],
+                });
+            }
+        }
+        commitments.push(CommitmentLayout {
+            round: OpeningRound::Main,
+            matrices: self
+                .per_air
+                .iter()
+                .enumerate()
+                .filter(|(_, vk)| vk.has_common_main())
+                .map(|(air_idx, vk)| trace_matrix(air_idx, vk.params.width.common_main))
+                .collect(),
+        });
+        if self.per_air.iter().any(|vk| vk.has_interaction()) {
+            commitments.push(CommitmentLayout {
+                round: OpeningRound::AfterChallenge,
+                matrices: self
+                    .per_air
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, vk)| vk.has_interaction())
+                    .map(|(air_idx, vk)| {
+                        trace_matrix(air_idx, vk.params.width.after_challenge[0] * E::D)
+                    })
+                    .collect(),
+            });
+        }
+        // Quotient chunks are only opened at the out-of-domain point
+        commitments.push(CommitmentLayout {
+            round: OpeningRound::Quotient,
+            matrices: num_quotient_chunks_per_air
+                .iter()
+                .enumerate()
+                .flat_map(|(air_idx, &num_chunks)| {
+                    (0..num_chunks).map(move |_| OpenedMatrixLayout {
+                        air_idx,
+                        width: E::D,
+                        num_rotations: 1,
+                    })
+                })
+                .collect(),
+        });
+        OpenedValuesLayout { commitments }
+    }
 }
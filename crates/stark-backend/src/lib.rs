@@ -11,8 +11,12 @@ pub use p3_matrix;
 pub use p3_maybe_rayon;
 pub use p3_util;
 
+/// Strategies folding the constraints of an AIR with its `alpha` challenge.
+pub mod accumulation;
 /// AIR builders for prover and verifier, including support for cross-matrix permutation arguments.
 pub mod air_builders;
+/// Challenger which records or replays the sampled challenges.
+pub mod challenger;
 /// Trait for stateful chip that owns trace generation
 mod chip;
 /// Helper types associated to generic STARK config.
@@ -0,0 +1,22 @@
+//! Streaming of proofs to and from I/O in the [SerdeFormat::Bincode] encoding.
+
+use std::io::{Read, Write};
+
+use super::{Proof, ProofSerdeError, SerdeFormat};
+use crate::config::StarkGenericConfig;
+
+/// Writes `proof` to `writer`, encoding each value as it is written. See [Proof::serialize_to].
+pub fn write_proof<SC: StarkGenericConfig, W: Write>(
+    proof: &Proof<SC>,
+    writer: W,
+) -> Result<(), ProofSerdeError> {
+    proof.serialize_to(SerdeFormat::Bincode, writer)
+}
+
+/// Reads a proof written by [write_proof] from `reader`, decoding it while reading. See
+/// [Proof::deserialize_from].
+pub fn read_proof<SC: StarkGenericConfig, R: Read>(
+    reader: R,
+) -> Result<Proof<SC>, ProofSerdeError> {
+    Proof::deserialize_from(SerdeFormat::Bincode, reader)
+}
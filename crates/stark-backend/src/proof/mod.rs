@@ -0,0 +1,663 @@
+#[cfg(feature = "proof-serde")]
+use std::io::{Read, Write};
+use std::iter::zip;
+
+use derivative::Derivative;
+use itertools::Itertools;
+use p3_field::FieldExtensionAlgebra;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    verifier::{OpeningRound, ProofShapeError},
+};
+
+#[cfg(feature = "proof-serde")]
+pub mod io;
+
+/// The full proof for multiple RAPs where trace matrices are committed into
+/// multiple commitments, where each commitment is multi-matrix.
+///
+/// Includes the quotient commitments and FRI opening proofs for the constraints as well.
+#[derive(Serialize, Deserialize, Derivative)]
+#[serde(bound = "")]
+#[derivative(Clone(bound = "Com<SC>: Clone"))]
+pub struct Proof<SC: StarkGenericConfig> {
+    /// The PCS commitments
+    pub commitments: Commitments<Com<SC>>,
+    /// Opening proofs separated by partition, but this may change
+    pub opening: OpeningProof<PcsProof<SC>, SC::Challenge>,
+    /// Proof data for each AIR
+    pub per_air: Vec<AirProofData<Val<SC>, SC::Challenge>>,
+    /// Partial proof for rap phase if it exists
+    pub rap_phase_seq_proof: Option<RapPhaseSeqPartialProof<SC>>,
+}
+
+/// Encoding of a serialized [Proof].
+#[cfg(feature = "proof-serde")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerdeFormat {
+    /// Compact binary encoding with bincode.
+    Bincode,
+    /// Binary encoding with postcard, which also supports `no_std` targets.
+    Postcard,
+    /// JSON, which is larger but human-readable for debugging.
+    Json,
+}
+
+/// Error deserializing a [Proof] in a [SerdeFormat], or streaming it from or to I/O.
+#[cfg(feature = "proof-serde")]
+#[derive(Debug, Error)]
+pub enum ProofSerdeError {
+    #[error("I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("bincode: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("postcard: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "proof-serde")]
+impl<SC: StarkGenericConfig> Proof<SC> {
+    /// Serializes the proof in `format`.
+    pub fn to_bytes(&self, format: SerdeFormat) -> Vec<u8> {
+        let bytes = match format {
+            SerdeFormat::Bincode => bincode::serialize(self).map_err(ProofSerdeError::from),
+            SerdeFormat::Postcard => postcard::to_allocvec(self).map_err(ProofSerdeError::from),
+            SerdeFormat::Json => serde_json::to_vec(self).map_err(ProofSerdeError::from),
+        };
+        bytes.expect("proof serialization failed")
+    }
+
+    /// Serializes the proof in `format` to `writer`, without buffering the whole encoding in
+    /// memory. The bincode and JSON encoders write each value as it is encoded, so large opened
+    /// values and PCS proofs are streamed. The encoding is the same as [to_bytes](Self::to_bytes),
+    /// and is read back by [deserialize_from](Self::deserialize_from) or
+    /// [from_bytes](Self::from_bytes).
+    pub fn serialize_to<W: Write>(
+        &self,
+        format: SerdeFormat,
+        writer: W,
+    ) -> Result<(), ProofSerdeError> {
+        match format {
+            SerdeFormat::Bincode => bincode::serialize_into(writer, self)?,
+            SerdeFormat::Postcard => {
+                postcard::to_io(self, writer)?;
+            }
+            SerdeFormat::Json => serde_json::to_writer(writer, self)?,
+        }
+        Ok(())
+    }
+
+    /// Deserializes a proof serialized in `format` from `reader`. Bincode and JSON are decoded
+    /// while reading, while postcard has no streaming decoder and reads the whole input first.
+    pub fn deserialize_from<R: Read>(
+        format: SerdeFormat,
+        mut reader: R,
+    ) -> Result<Self, ProofSerdeError> {
+        Ok(match format {
+            SerdeFormat::Bincode => bincode::deserialize_from(reader)?,
+            SerdeFormat::Postcard => {
+                let mut bytes = vec![];
+                reader.read_to_end(&mut bytes)?;
+                postcard::from_bytes(&bytes)?
+            }
+            SerdeFormat::Json => serde_json::from_reader(reader)?,
+        })
+    }
+
+    /// Deserializes a proof serialized by [to_bytes](Self::to_bytes) in `format`.
+    pub fn from_bytes(format: SerdeFormat, bytes: &[u8]) -> Result<Self, ProofSerdeError> {
+        Ok(match format {
+            SerdeFormat::Bincode => bincode::deserialize(bytes)?,
+            SerdeFormat::Postcard => postcard::from_bytes(bytes)?,
+            SerdeFormat::Json => serde_json::from_slice(bytes)?,
+        })
+    }
+}
+
+impl<SC: StarkGenericConfig> Proof<SC> {
+    /// Whether `other` has the same cryptographic content: commitments, opened values, PCS
+    /// opening proof, per-AIR data and RAP phase proof. The PCS opening proof and the RAP phase
+    /// proof are compared by their encoding, since they are not required to implement
+    /// `PartialEq`.
+    ///
+    /// Metrics and timings are not part of the proof, so proofs of the same inputs by provers
+    /// with and without metrics are semantically equal.
+    pub fn semantically_eq(&self, other: &Self) -> bool
+    where
+        Com<SC>: PartialEq,
+    {
+        fn encoding_eq<T: Serialize>(a: &T, b: &T) -> bool {
+            let encode = |value: &T| bitcode::serialize(value).expect("proof serialization failed");
+            encode(a) == encode(b)
+        }
+        self.commitments == other.commitments
+            && self.opening.values == other.opening.values
+            && self.per_air == other.per_air
+            && encoding_eq(&self.opening.proof, &other.opening.proof)
+            && encoding_eq(&self.rap_phase_seq_proof, &other.rap_phase_seq_proof)
+    }
+
+    pub fn get_air_ids(&self) -> Vec<usize> {
+        self.per_air.iter().map(|p| p.air_id).collect()
+    }
+    pub fn get_public_values(&self) -> Vec<Vec<Val<SC>>> {
+        self.per_air
+            .iter()
+            .map(|p| p.public_values.clone())
+            .collect()
+    }
+    pub fn get_quotient_degrees(&self) -> Vec<u8> {
+        self.per_air.iter().map(|p| p.quotient_degree).collect()
+    }
+
+    /// The log2 heights of the committed main trace matrices, per main trace commitment in the
+    /// order of [Commitments::main_trace], and per matrix within each commitment. These are the
+    /// commitments of the cached main traces of each AIR, followed by the commitment of all
+    /// common main traces.
+    ///
+    /// The heights are the trace degrees of the AIRs, laid out according to their verifying keys
+    /// in `vk`. Panics if the proof contains an AIR not in `vk` or a trace degree which is not a
+    /// power of two, which [Self::validate_shape] rejects.
+    pub fn trace_log_heights(&self, vk: &MultiStarkVerifyingKey<SC>) -> Vec<Vec<u8>> {
+        let vk_and_log_height_per_air = self
+            .per_air
+            .iter()
+            .map(|ap| {
+                let log_height = log2_strict_usize(ap.degree) as u8;
+                (&vk.inner.per_air[ap.air_id], log_height)
+            })
+            .collect_vec();
+        let mut log_heights = vk_and_log_height_per_air
+            .iter()
+            .flat_map(|&(vk, log_height)| vec![vec![log_height]; vk.num_cached_mains()])
+            .collect_vec();
+        log_heights.push(
+            vk_and_log_height_per_air
+                .iter()
+                .filter(|(vk, _)| vk.has_common_main())
+                .map(|&(_, log_height)| log_height)
+                .collect(),
+        );
+        log_heights
+    }
+
+    /// The number of bytes of the proof attributed to each AIR, in the order of `per_air`: its
+    /// proof data, its opened values, and a share of each commitment it is opened from,
+    /// proportional to the bytes of its values opened from the commitment.
+    ///
+    /// Sizes are of the bitcode encoding of each part of the proof. Preprocessed commitments are
+    /// part of the verifying key and not counted, and the PCS opening proof and the RAP phase
+    /// proof are shared by all AIRs and not attributed. Panics if the proof contains an AIR not in
+    /// `vk`.
+    pub fn size_per_air(&self, vk: &MultiStarkVerifyingKey<SC>) -> Vec<usize> {
+        fn encoded_len<T: Serialize>(value: &T) -> usize {
+            bitcode::serialize(value)
+                .expect("proof serialization failed")
+                .len()
+        }
+        let mut sizes = self.per_air.iter().map(encoded_len).collect_vec();
+
+        // The AIR of each opened trace matrix, in the order the matrices of a round are opened
+        let num_quotient_chunks_per_air = self
+            .per_air
+            .iter()
+            .map(|ap| ap.num_quotient_chunks)
+            .collect_vec();
+        let layout = vk
+            .view(&self.get_air_ids())
+            .opened_values_layout::<SC::Challenge>(&num_quotient_chunks_per_air);
+        let air_idxs = |round: OpeningRound| {
+            layout
+                .commitments
+                .iter()
+                .filter(|c| c.round == round)
+                .flat_map(|c| c.matrices.iter().map(|m| m.air_idx))
+                .collect_vec()
+        };
+        for (air_idx, values) in zip(
+            air_idxs(OpeningRound::Preprocessed),
+            &self.opening.values.preprocessed,
+        ) {
+            sizes[air_idx] += encoded_len(values);
+        }
+        let mut attribute = |round, commits: &[Com<SC>], values_per_commit: &[Vec<_>]| {
+            let mut air_idxs = air_idxs(round).into_iter();
+            for (commit, values) in zip(commits, values_per_commit) {
+                let lens = values
+                    .iter()
+                    .map(|values: &AdjacentOpenedValues<SC::Challenge>| {
+                        let air_idx = air_idxs.next().expect("matrix not in the layout");
+                        (air_idx, encoded_len(values))
+                    })
+                    .collect_vec();
+                let total_len = lens.iter().map(|(_, len)| len).sum::<usize>().max(1);
+                for (air_idx, len) in lens {
+                    sizes[air_idx] += len + encoded_len(commit) * len / total_len;
+                }
+            }
+        };
+        attribute(
+            OpeningRound::Main,
+            &self.commitments.main_trace,
+            &self.opening.values.main,
+        );
+        attribute(
+            OpeningRound::AfterChallenge,
+            &self.commitments.after_challenge,
+            &self.opening.values.after_challenge,
+        );
+
+        // Quotient chunks, with each commitment shared by the AIRs committed in it
+        let quotient_lens = self
+            .opening
+            .values
+            .quotient
+            .iter()
+            .map(encoded_len)
+            .collect_vec();
+        let mut total_len_per_commit = vec![0; self.commitments.quotient.len()];
+        for (ap, len) in zip(&self.per_air, &quotient_lens) {
+            total_len_per_commit[ap.quotient_commit_idx] += len;
+        }
+        for (air_idx, (ap, &len)) in zip(&self.per_air, &quotient_lens).enumerate() {
+            let commit_len = encoded_len(&self.commitments.quotient[ap.quotient_commit_idx]);
+            let total_len = total_len_per_commit[ap.quotient_commit_idx].max(1);
+            sizes[air_idx] += len + commit_len * len / total_len;
+        }
+        sizes
+    }
+
+    /// Checks that the proof has the structure expected by `vk`: the AIRs, the number of
+    /// commitments, and the nesting and widths of the opened values.
+    ///
+    /// This is cheap compared to verification and is meant to reject malformed proofs, such as
+    /// corrupted deserialized ones, up front. It does not check the commitments or the PCS opening
+    /// proof, so a proof passing this check may still fail verification.
+    pub fn validate_shape(&self, vk: &MultiStarkVerifyingKey<SC>) -> Result<(), ProofShapeError> {
+        let num_airs = vk.inner.per_air.len();
+        let mut seen = vec![false; num_airs];
+        for ap in &self.per_air {
+            if ap.air_id >= num_airs {
+                return Err(ProofShapeError::AirIdOutOfRange {
+                    air_id: ap.air_id,
+                    num_airs,
+                });
+            }
+            if std::mem::replace(&mut seen[ap.air_id], true) {
+                return Err(ProofShapeError::DuplicateAirId(ap.air_id));
+            }
+            if !ap.degree.is_power_of_two() {
+                return Err(ProofShapeError::TraceHeight {
+                    air_id: ap.air_id,
+                    degree: ap.degree,
+                });
+            }
+        }
+        let vks = self
+            .per_air
+            .iter()
+            .map(|ap| &vk.inner.per_air[ap.air_id])
+            .collect_vec();
+
+        let num_quotient_commits = self.commitments.quotient.len();
+        if num_quotient_commits == 0 {
+            return Err(ProofShapeError::NumCommitments {
+                round: OpeningRound::Quotient,
+                expected: 1,
+                actual: 0,
+            });
+        }
+        for (ap, vk) in zip(&self.per_air, &vks) {
+            let air_id = ap.air_id;
+            if ap.public_values.len() != vk.params.num_public_values {
+                return Err(ProofShapeError::NumPublicValues {
+                    air_id,
+                    expected: vk.params.num_public_values,
+                    actual: ap.public_values.len(),
+                });
+            }
+            let expected_exposed = &vk.params.num_exposed_values_after_challenge;
+            if ap.exposed_values_after_challenge.len() != expected_exposed.len()
+                || zip(&ap.exposed_values_after_challenge, expected_exposed)
+                    .any(|(values, &n)| values.len() != n)
+            {
+                return Err(ProofShapeError::ExposedValues { air_id });
+            }
+            if ap.quotient_degree != vk.quotient_degree {
+                return Err(ProofShapeError::QuotientDegree {
+                    air_id,
+                    expected: vk.quotient_degree,
+                    actual: ap.quotient_degree,
+                });
+            }
+            if !ap.has_valid_quotient_chunks() {
+                return Err(ProofShapeError::NumQuotientChunks {
+                    air_id,
+                    num_chunks: ap.num_quotient_chunks,
+                    quotient_degree: ap.quotient_degree,
+                });
+            }
+            if ap.quotient_commit_idx >= num_quotient_commits {
+                return Err(ProofShapeError::QuotientCommitIdx {
+                    air_id,
+                    idx: ap.quotient_commit_idx,
+                    num_commits: num_quotient_commits,
+                });
+            }
+        }
+        if let Some(idx) = (0..num_quotient_commits)
+            .find(|&idx| self.per_air.iter().all(|ap| ap.quotient_commit_idx != idx))
+        {
+            return Err(ProofShapeError::EmptyQuotientCommitment(idx));
+        }
+
+        let values = &self.opening.values;
+        let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+
+        // The shape of an opened trace matrix is its width and its number of rotations, which is
+        // the same for all trace matrices of an AIR.
+
+        // Preprocessed: one commitment with a single matrix per AIR with a preprocessed trace
+        let preprocessed_shapes = vks
+            .iter()
+            .filter_map(|vk| Some((vk.params.width.preprocessed?, vk.num_rotations())))
+            .collect_vec();
+        check_num_opened(
+            OpeningRound::Preprocessed,
+            preprocessed_shapes.len(),
+            values.preprocessed.len(),
+        )?;
+        for (commit_idx, (&shape, values)) in
+            zip(&preprocessed_shapes, &values.preprocessed).enumerate()
+        {
+            check_opened_shape(OpeningRound::Preprocessed, commit_idx, 0, shape, values)?;
+        }
+
+        // Main: one commitment per cached main trace, then one for all common main traces
+        let mut main_shapes = vks
+            .iter()
+            .flat_map(|vk| {
+                let num_rotations = vk.num_rotations();
+                vk.params
+                    .width
+                    .cached_mains
+                    .iter()
+                    .map(move |&width| vec![(width, num_rotations)])
+            })
+            .collect_vec();
+        main_shapes.push(
+            vks.iter()
+                .filter(|vk| vk.has_common_main())
+                .map(|vk| (vk.params.width.common_main, vk.num_rotations()))
+                .collect(),
+        );
+        if self.commitments.main_trace.len() != main_shapes.len() {
+            return Err(ProofShapeError::NumCommitments {
+                round: OpeningRound::Main,
+                expected: main_shapes.len(),
+                actual: self.commitments.main_trace.len(),
+            });
+        }
+        check_num_opened(OpeningRound::Main, main_shapes.len(), values.main.len())?;
+        for (commit_idx, (shapes, values_per_mat)) in zip(&main_shapes, &values.main).enumerate() {
+            if shapes.len() != values_per_mat.len() {
+                return Err(ProofShapeError::NumOpenedMatrices {
+                    round: OpeningRound::Main,
+                    commit_idx,
+                    expected: shapes.len(),
+                    actual: values_per_mat.len(),
+                });
+            }
+            for (matrix_idx, (&shape, values)) in zip(shapes, values_per_mat).enumerate() {
+                check_opened_shape(OpeningRound::Main, commit_idx, matrix_idx, shape, values)?;
+            }
+        }
+
+        // After challenge: the traces of the AIRs with interactions, split into commitments of
+        // non-empty runs of consecutive AIRs
+        let after_challenge_shapes = vks
+            .iter()
+            .filter(|vk| vk.has_interaction())
+            .map(|vk| {
+                (
+                    vk.params.width.after_challenge[0] * ext_degree,
+                    vk.num_rotations(),
+                )
+            })
+            .collect_vec();
+        let num_after_challenge_commits = self.commitments.after_challenge.len();
+        if after_challenge_shapes.is_empty() != (num_after_challenge_commits == 0) {
+            return Err(ProofShapeError::NumCommitments {
+                round: OpeningRound::AfterChallenge,
+                expected: after_challenge_shapes.len().min(1),
+                actual: num_after_challenge_commits,
+            });
+        }
+        check_num_opened(
+            OpeningRound::AfterChallenge,
+            num_after_challenge_commits,
+            values.after_challenge.len(),
+        )?;
+        let num_opened = values.after_challenge.iter().map(Vec::len).sum::<usize>();
+        if let Some(commit_idx) = values.after_challenge.iter().position(Vec::is_empty) {
+            return Err(ProofShapeError::NumOpenedMatrices {
+                round: OpeningRound::AfterChallenge,
+                commit_idx,
+                expected: 1,
+                actual: 0,
+            });
+        }
+        if num_opened != after_challenge_shapes.len() {
+            let commit_idx = num_after_challenge_commits - 1;
+            let actual = values.after_challenge[commit_idx].len();
+            return Err(ProofShapeError::NumOpenedMatrices {
+                round: OpeningRound::AfterChallenge,
+                commit_idx,
+                expected: (actual + after_challenge_shapes.len()).saturating_sub(num_opened),
+                actual,
+            });
+        }
+        let after_challenge_values = values
+            .after_challenge
+            .iter()
+            .enumerate()
+            .flat_map(|(commit_idx, values_per_mat)| {
+                values_per_mat
+                    .iter()
+                    .enumerate()
+                    .map(move |(matrix_idx, values)| (commit_idx, matrix_idx, values))
+            });
+        for (&shape, (commit_idx, matrix_idx, values)) in
+            zip(&after_challenge_shapes, after_challenge_values)
+        {
+            check_opened_shape(
+                OpeningRound::AfterChallenge,
+                commit_idx,
+                matrix_idx,
+                shape,
+                values,
+            )?;
+        }
+
+        // Quotient: for each AIR, one opened value per quotient chunk
+        check_num_opened(OpeningRound::Quotient, vks.len(), values.quotient.len())?;
+        for (air_idx, (ap, chunks)) in zip(&self.per_air, &values.quotient).enumerate() {
+            if chunks.len() != ap.num_quotient_chunks as usize {
+                return Err(ProofShapeError::NumOpenedMatrices {
+                    round: OpeningRound::Quotient,
+                    commit_idx: air_idx,
+                    expected: ap.num_quotient_chunks as usize,
+                    actual: chunks.len(),
+                });
+            }
+            for (chunk_idx, chunk) in chunks.iter().enumerate() {
+                if chunk.len() != ext_degree {
+                    return Err(ProofShapeError::OpenedWidth {
+                        round: OpeningRound::Quotient,
+                        commit_idx: air_idx,
+                        matrix_idx: chunk_idx,
+                        expected: ext_degree,
+                        actual: chunk.len(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_num_opened(
+    round: OpeningRound,
+    expected: usize,
+    actual: usize,
+) -> Result<(), ProofShapeError> {
+    if expected != actual {
+        return Err(ProofShapeError::NumOpenedCommitments {
+            round,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `values` are opened at `num_rotations` points with `width` values each.
+fn check_opened_shape<Challenge>(
+    round: OpeningRound,
+    commit_idx: usize,
+    matrix_idx: usize,
+    (width, num_rotations): (usize, usize),
+    values: &AdjacentOpenedValues<Challenge>,
+) -> Result<(), ProofShapeError> {
+    if values.num_rotations() != num_rotations {
+        return Err(ProofShapeError::NumOpenedRotations {
+            round,
+            commit_idx,
+            matrix_idx,
+            expected: num_rotations,
+            actual: values.num_rotations(),
+        });
+    }
+    for actual in values.rows().map(Vec::len) {
+        if actual != width {
+            return Err(ProofShapeError::OpenedWidth {
+                round,
+                commit_idx,
+                matrix_idx,
+                expected: width,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// All commitments to a multi-matrix STARK that are not preprocessed.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commitments<Com> {
+    /// Multiple commitments for the main trace.
+    /// For each RAP, each part of a partitioned matrix trace matrix
+    /// must belong to one of these commitments.
+    pub main_trace: Vec<Com>,
+    /// Commitments for the trace matrices of all RAPs in the challenge phases after the main
+    /// phase, in phase order. The commitments of a challenge phase are made after observing the
+    /// commits to `preprocessed`, `main_trace`, and the commitments of earlier phases.
+    ///
+    /// There is currently at most one such phase. Its trace matrices are committed either in one
+    /// shared commitment or in one commitment per RAP; within a commitment, matrices are ordered
+    /// by RAP.
+    pub after_challenge: Vec<Com>,
+    /// Commitments for quotient polynomial evaluations. By default there is one shared commitment
+    /// for all AIRs, but AIRs may be split into multiple groups with one commitment per group.
+    pub quotient: Vec<Com>,
+}
+
+/// PCS opening proof with opened values for multi-matrix AIR.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OpeningProof<PcsProof, Challenge> {
+    pub proof: PcsProof,
+    pub values: OpenedValues<Challenge>,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedValues<Challenge> {
+    /// For each preprocessed trace commitment, the opened values
+    pub preprocessed: Vec<AdjacentOpenedValues<Challenge>>,
+    /// For each main trace commitment, for each matrix in commitment, the
+    /// opened values
+    pub main: Vec<Vec<AdjacentOpenedValues<Challenge>>>,
+    /// For each after challenge commitment, for each matrix in the commitment, the opened values
+    pub after_challenge: Vec<Vec<AdjacentOpenedValues<Challenge>>>,
+    /// For each RAP, for each quotient chunk in quotient poly, the opened values
+    pub quotient: Vec<Vec<Vec<Challenge>>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdjacentOpenedValues<Challenge> {
+    pub local: Vec<Challenge>,
+    pub next: Vec<Challenge>,
+    /// Values at the rows after `next`, in order, for AIRs whose constraints read more than two
+    /// adjacent rows. Empty otherwise.
+    #[serde(default)]
+    pub after_next: Vec<Vec<Challenge>>,
+}
+
+impl<Challenge> AdjacentOpenedValues<Challenge> {
+    /// Creates the opened values from the values at each rotation, starting with `local`.
+    /// Panics if there are fewer than 2 rotations.
+    pub fn from_rows(rows: Vec<Vec<Challenge>>) -> Self {
+        let mut rows = rows.into_iter();
+        let (Some(local), Some(next)) = (rows.next(), rows.next()) else {
+            panic!("opened values should have at least 2 rotations");
+        };
+        Self {
+            local,
+            next,
+            after_next: rows.collect(),
+        }
+    }
+
+    /// The values at each rotation, starting with `local`.
+    pub fn rows(&self) -> impl Iterator<Item = &Vec<Challenge>> {
+        [&self.local, &self.next].into_iter().chain(&self.after_next)
+    }
+
+    pub fn num_rotations(&self) -> usize {
+        2 + self.after_next.len()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AirProofData<Val, Challenge> {
+    pub air_id: usize,
+    /// height of trace matrix.
+    pub degree: usize,
+    /// Factor to multiply `degree` by to get the degree of the quotient polynomial. Must equal
+    /// `quotient_degree` in the verifying key of the AIR.
+    pub quotient_degree: u8,
+    /// Number of chunks the quotient polynomial is split into for its commitment. It is a power
+    /// of two dividing `quotient_degree`, and each chunk is committed on a domain of
+    /// `quotient_degree / num_quotient_chunks` times the trace height.
+    pub num_quotient_chunks: u8,
+    /// Index in `commitments.quotient` of the commitment containing the quotient chunks of this AIR.
+    pub quotient_commit_idx: usize,
+    /// For each challenge phase with trace, the values to expose to the verifier in that phase
+    pub exposed_values_after_challenge: Vec<Vec<Challenge>>,
+    // The public values to expose to the verifier
+    pub public_values: Vec<Val>,
+}
+
+impl<Val, Challenge> AirProofData<Val, Challenge> {
+    /// Whether `num_quotient_chunks` is a power of two dividing `quotient_degree`.
+    pub fn has_valid_quotient_chunks(&self) -> bool {
+        self.num_quotient_chunks.is_power_of_two()
+            && self.num_quotient_chunks <= self.quotient_degree
+    }
+}
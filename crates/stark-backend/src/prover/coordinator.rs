@@ -1,4 +1,9 @@
-use std::{iter, marker::PhantomData};
+use std::{
+    iter::{self, zip},
+    marker::PhantomData,
+    sync::{mpsc::sync_channel, Arc},
+    thread,
+};
 
 use itertools::{izip, Itertools};
 use p3_challenger::CanObserve;
@@ -7,21 +12,24 @@ use p3_util::log2_strict_usize;
 use tracing::{info, instrument};
 
 use super::{
-    hal::{ProverBackend, ProverDevice},
-    types::{DeviceMultiStarkProvingKey, HalProof, ProvingContext},
-    Prover,
+    hal::{DeviceDataTransporter, ProverBackend, ProverDevice},
+    types::{
+        AirProvingContext, DeviceMultiStarkProvingKey, HalProof, LazyAirProvingContext,
+        ProvingContext,
+    },
+    Prover, ProverError,
 };
-#[cfg(feature = "bench-metrics")]
-use crate::prover::metrics::trace_metrics;
 use crate::{
+    challenger::{observe_main_commitments, observe_quotient_chunks, MainCommitHook},
     config::{Com, StarkGenericConfig, Val},
-    keygen::view::MultiStarkVerifyingKeyView,
+    keygen::{types::MultiStarkProvingKey, view::MultiStarkVerifyingKeyView},
     proof::{AirProofData, Commitments},
     prover::{
         hal::MatrixDimensions,
+        metrics::{compute_trace_metrics, ProofStats, TraceMetrics},
         types::{AirView, SingleCommitPreimage},
     },
-    utils::metrics_span,
+    utils::{current_num_threads, metrics_span},
 };
 
 /// Host-to-device coordinator for full prover implementation.
@@ -30,10 +38,20 @@ use crate::{
 /// - `SC`: Stark configuration for proving key (from host)
 /// - `PB`: Prover backend types
 /// - `PD`: Prover device methods
+///
+/// # Checkpointing
+/// Proving runs to completion in [prove](Prover::prove); there is no intermediate state after
+/// the main trace commitments that could be saved and resumed. Such a state would need to
+/// serialize the committed [PcsData](super::cpu::PcsData), i.e. the prover data of the PCS, and
+/// the challenger, but neither the `Pcs` nor the `Challenger` of [StarkGenericConfig] is
+/// required to be serializable. To resume a crashed prover, prove again from the proving key and
+/// traces: the proof is deterministic, see [prove](Prover::prove).
 pub struct Coordinator<SC: StarkGenericConfig, PB, PD> {
     pub backend: PB,
     pub device: PD,
     challenger: SC::Challenger,
+    main_commit_hook: Option<MainCommitHook<SC::Challenger, Com<SC>>>,
+    proof_stats: Option<ProofStats>,
     phantom: PhantomData<(SC, PB)>,
 }
 
@@ -43,9 +61,117 @@ impl<SC: StarkGenericConfig, PB, PD> Coordinator<SC, PB, PD> {
             backend,
             device,
             challenger,
+            main_commit_hook: None,
+            proof_stats: None,
             phantom: PhantomData,
         }
     }
+
+    /// Invokes `hook` after each main trace commitment is observed. The verifier must be given
+    /// the same hook, see [MainCommitHook].
+    pub fn with_main_commit_hook(
+        mut self,
+        hook: MainCommitHook<SC::Challenger, Com<SC>>,
+    ) -> Self {
+        self.main_commit_hook = Some(hook);
+        self
+    }
+
+    /// The current challenger state. After [prove](Prover::prove), this is the final state of
+    /// the proof transcript, which the verifier reaches after verifying the proof. It can be used
+    /// to seed the transcript of a subsequent proof.
+    pub fn challenger(&self) -> &SC::Challenger {
+        &self.challenger
+    }
+
+    /// Consumes the coordinator and returns its challenger. See [Self::challenger].
+    pub fn into_challenger(self) -> SC::Challenger {
+        self.challenger
+    }
+
+    /// The trace metrics of the last [prove](Prover::prove), if any. Use
+    /// [TraceMetrics::max_cost_cells_air] to find the AIR with the most cells to commit to or
+    /// evaluate.
+    pub fn trace_metrics(&self) -> Option<&TraceMetrics> {
+        self.proof_stats.as_ref().map(|stats| &stats.trace_metrics)
+    }
+
+    /// The statistics of the last [prove](Prover::prove), if any, including the number of
+    /// threads it ran on.
+    pub fn proof_stats(&self) -> Option<&ProofStats> {
+        self.proof_stats.as_ref()
+    }
+}
+
+impl<SC, PB, PD> Coordinator<SC, PB, PD>
+where
+    SC: StarkGenericConfig,
+    PB: ProverBackend<
+            Val = Val<SC>,
+            Challenge = SC::Challenge,
+            Commitment = Com<SC>,
+            Challenger = SC::Challenger,
+        > + DeviceDataTransporter<SC, PB>,
+    PD: ProverDevice<PB>,
+{
+    /// Proves the single AIR `air_id` of `mpk`, transporting only its proving key to the device.
+    ///
+    /// The proof is identical to the one from [prove](Prover::prove) with a proving context
+    /// containing only `air_id`, so it is accepted by the standard verifier.
+    pub fn prove_single<'a>(
+        &'a mut self,
+        mpk: &'a MultiStarkProvingKey<SC>,
+        air_id: usize,
+        ctx: AirProvingContext<'a, PB>,
+    ) -> HalProof<PB> {
+        let mpk_view = self.backend.transport_pk_to_device(mpk, vec![air_id]);
+        self.prove(mpk_view, ProvingContext::new(vec![(air_id, ctx)]))
+    }
+
+    /// Same as [prove](Prover::prove), but the common main trace of each AIR is generated while
+    /// the prover runs. The producers in `ctx` are invoked one at a time in the order of the
+    /// AIRs on a separate thread, and the prover transports each trace to the device as it
+    /// reaches the common main trace commitment. At most one produced trace waits to be
+    /// transported, so the generation of a trace overlaps with the transfer of the previous one
+    /// instead of all traces being held on the host before proving starts.
+    ///
+    /// `ctx` is given as (AIR id, AIR context) pairs ordered by AIR id, like [ProvingContext].
+    pub fn prove_lazy<'a>(
+        &'a mut self,
+        mpk: DeviceMultiStarkProvingKey<'a, PB>,
+        ctx: Vec<(usize, LazyAirProvingContext<'a, Val<SC>>)>,
+    ) -> HalProof<PB> {
+        let (per_air, producers): (Vec<_>, Vec<_>) = ctx
+            .into_iter()
+            .map(|(air_id, ctx)| {
+                let air_ctx = AirProvingContext {
+                    cached_mains: vec![],
+                    common_main: None,
+                    public_values: ctx.public_values,
+                    cached_lifetime: PhantomData,
+                };
+                ((air_id, air_ctx), ctx.common_main)
+            })
+            .unzip();
+        let (sender, receiver) = sync_channel(1);
+        thread::scope(move |scope| {
+            scope.spawn(move || {
+                for producer in producers {
+                    // The prover stopped receiving traces because it failed
+                    if sender.send(producer()).is_err() {
+                        break;
+                    }
+                }
+            });
+            self.try_prove_with_common_mains(mpk, ProvingContext::new(per_air), |backend, _| {
+                receiver
+                    .into_iter()
+                    .map(|trace| backend.transport_matrix_to_device(&Arc::new(trace)))
+                    .collect()
+            })
+        })
+        .unwrap_or_else(|err| panic!("{err}"))
+    }
 }
 
 impl<SC, PB, PD> Prover for Coordinator<SC, PB, PD>
@@ -75,15 +201,65 @@ where
     /// Assumes the main traces have been generated and committed already.
     ///
     /// The [DeviceMultiStarkProvingKey] should already be filtered to only include the relevant AIR's proving keys.
-    #[instrument(name = "Coordinator::prove", level = "info", skip_all)]
+    ///
+    /// # Determinism
+    /// The proof is a deterministic function of the proving key, the proving context, and the
+    /// initial challenger state, provided proving is run inside [force_serial](crate::utils::force_serial)
+    /// or without the `"parallel"` feature. In particular, the proof does not depend on hash map
+    /// iteration order or on the platform. Only the emitted timing metrics vary between runs.
+    ///
+    /// # Degenerate traces
+    /// Traces may be entirely zero, including interactions whose counts are all zero, as long as
+    /// they satisfy the constraints. The prover never divides by trace values: the LogUp
+    /// denominators are nonzero with overwhelming probability over the challenges, and all
+    /// domains and selectors depend only on the trace heights.
     fn prove<'a>(
         &'a mut self,
         mpk: Self::ProvingKeyView<'a>,
         ctx: Self::ProvingContext<'a>,
     ) -> Self::Proof {
+        self.try_prove(mpk, ctx).unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+impl<SC, PB, PD> Coordinator<SC, PB, PD>
+where
+    SC: StarkGenericConfig,
+    PB: ProverBackend<
+        Val = Val<SC>,
+        Challenge = SC::Challenge,
+        Commitment = Com<SC>,
+        Challenger = SC::Challenger,
+    >,
+    PD: ProverDevice<PB>,
+{
+    /// Same as [prove](Prover::prove), but returns an error instead of panicking if the number of
+    /// public values of an AIR in `ctx` differs from its `num_public_values`, or if the device
+    /// finds that the quotient identity of an AIR does not hold, see
+    /// [with_quotient_identity_check](super::cpu::CpuDevice::with_quotient_identity_check).
+    pub fn try_prove<'a>(
+        &'a mut self,
+        mpk: DeviceMultiStarkProvingKey<'a, PB>,
+        ctx: ProvingContext<'a, PB>,
+    ) -> Result<HalProof<PB>, ProverError> {
+        self.try_prove_with_common_mains(mpk, ctx, |_, common_main_per_air| {
+            common_main_per_air.into_iter().flatten().collect()
+        })
+    }
+
+    /// Proves with the common main traces returned by `common_mains`, which is called with the
+    /// common main traces of `ctx` when the prover reaches the common main trace commitment.
+    #[instrument(name = "Coordinator::prove", level = "info", skip_all)]
+    fn try_prove_with_common_mains<'a>(
+        &'a mut self,
+        mpk: DeviceMultiStarkProvingKey<'a, PB>,
+        ctx: ProvingContext<'a, PB>,
+        common_mains: impl FnOnce(&PB, Vec<Option<PB::Matrix>>) -> Vec<PB::Matrix>,
+    ) -> Result<HalProof<PB>, ProverError> {
         #[cfg(feature = "bench-metrics")]
         let start = std::time::Instant::now();
         assert!(mpk.validate(&ctx), "Invalid proof input");
+        mpk.validate_public_values(&ctx)?;
         self.challenger.observe(mpk.vk_pre_hash.clone());
 
         let num_air = ctx.per_air.len();
@@ -115,7 +291,7 @@ where
         // Commit all common main traces in a commitment. Traces inside are ordered by AIR id.
         let (common_main_traces, (common_main_commit, common_main_pcs_data)) =
             metrics_span("main_trace_commit_time_ms", || {
-                let traces = common_main_per_air.into_iter().flatten().collect_vec();
+                let traces = common_mains(&self.backend, common_main_per_air);
                 let prover_data = self.device.commit(&traces);
                 (traces, prover_data)
             });
@@ -150,7 +326,12 @@ where
             if pk.vk.has_common_main() {
                 main_trace_views.push(common_main_traces_it.next().expect("expected common main"));
             }
-            let trace_height = main_trace_views.first().expect("no main trace").height();
+            // Preprocessed-only AIRs have no main trace, so take the preprocessed trace height
+            let trace_height = main_trace_views
+                .first()
+                .or(pk.preprocessed_data.as_ref().map(|data| &data.trace))
+                .expect("no main or preprocessed trace")
+                .height();
             let log_trace_height: u8 = log2_strict_usize(trace_height).try_into().unwrap();
             let air_trace_view = AirView {
                 partitioned_main: main_trace_views,
@@ -159,8 +340,17 @@ where
             log_trace_height_per_air.push(log_trace_height);
             air_trace_views_per_air.push(air_trace_view);
         }
+        let proof_stats = ProofStats {
+            num_threads: current_num_threads(),
+            trace_metrics: compute_trace_metrics(&mpk, &log_trace_height_per_air),
+        };
         #[cfg(feature = "bench-metrics")]
-        trace_metrics(&mpk, &log_trace_height_per_air).emit();
+        {
+            info!(num_threads = proof_stats.num_threads);
+            info!("{}", proof_stats.trace_metrics);
+            proof_stats.emit();
+        }
+        self.proof_stats = Some(proof_stats);
 
         // ============ Challenger observations before additional RAP phases =============
         // Observe public values:
@@ -172,7 +362,11 @@ where
         let mvk = mpk.vk_view();
         let preprocessed_commits = mvk.flattened_preprocessed_commits();
         self.challenger.observe_slice(&preprocessed_commits);
-        self.challenger.observe_slice(&main_trace_commitments);
+        observe_main_commitments(
+            &mut self.challenger,
+            &main_trace_commitments,
+            self.main_commit_hook.as_ref(),
+        );
         // Observe trace domain size per air:
         self.challenger.observe_slice(
             &log_trace_height_per_air
@@ -189,7 +383,7 @@ where
         // At this point, main trace should be dropped
 
         // Challenger observes additional commitments if any exist:
-        for (commit, _) in &prover_data_after.committed_pcs_data_per_phase {
+        for (commit, _) in &prover_data_after.committed_pcs_data_after_challenge {
             self.challenger.observe(commit.clone());
         }
 
@@ -224,7 +418,7 @@ where
         // ==================== Quotient polynomial computation and commitment, if any ====================
         // Note[jpw]: Currently we always call this step, we could add a flag to skip it for protocols that
         // do not require quotient poly.
-        let (quotient_commit, quotient_data) = self.device.eval_and_commit_quotient(
+        let quotient_data = self.device.eval_and_commit_quotient(
             &mut self.challenger,
             &mpk.per_air,
             &pvs_per_air,
@@ -232,37 +426,85 @@ where
             &common_main_pcs_data,
             &prover_data_after,
         );
-        // Observe quotient commitment
-        self.challenger.observe(quotient_commit.clone());
+        if let Some((air_idx, source)) = quotient_data.failed_quotient_identity {
+            return Err(ProverError::QuotientIdentity {
+                air_name: mpk.per_air[air_idx].air_name.to_owned(),
+                source,
+            });
+        }
+        let (quotient_commits, quotient_pcs_data): (Vec<_>, Vec<_>) = quotient_data
+            .committed_pcs_data_per_group
+            .into_iter()
+            .unzip();
+        let quotient_group_idx_per_air = quotient_data.group_idx_per_air;
+        let num_quotient_chunks_per_air = quotient_data.num_chunks_per_air;
+        let quotient_degrees = mpk
+            .per_air
+            .iter()
+            .map(|pk| pk.vk.quotient_degree)
+            .collect_vec();
+        // Observe the number of quotient chunks of each AIR if the chunking is not the default,
+        // as it determines how the quotient polynomial is recomposed
+        observe_quotient_chunks::<Val<SC>, _>(
+            &mut self.challenger,
+            &num_quotient_chunks_per_air,
+            &quotient_degrees,
+        );
+        // Observe the quotient commitments. The device samples `zeta` right after, when opening.
+        SC::observe_quotient_commitments(&mut self.challenger, &quotient_commits);
 
         let (commitments_after, pcs_data_after): (Vec<_>, Vec<_>) = prover_data_after
-            .committed_pcs_data_per_phase
+            .committed_pcs_data_after_challenge
             .into_iter()
             .unzip();
-        // ==================== Polynomial Opening Proofs ====================
-        let opening = metrics_span("pcs_opening_time_ms", || {
-            let mut quotient_degrees = Vec::with_capacity(mpk.per_air.len());
-            let mut preprocessed = Vec::new();
-
-            for pk in mpk.per_air {
-                quotient_degrees.push(pk.vk.quotient_degree);
-                if let Some(preprocessed_data) = pk.preprocessed_data {
-                    preprocessed.push(preprocessed_data.data);
+        // All trace matrices of an AIR are opened at the same number of rotations
+        let num_rotations_per_air = mpk
+            .per_air
+            .iter()
+            .map(|pk| pk.vk.num_rotations())
+            .collect_vec();
+        let common_main_num_rotations = zip(&mpk.per_air, &num_rotations_per_air)
+            .filter(|(pk, _)| pk.vk.has_common_main())
+            .map(|(_, &num_rotations)| num_rotations)
+            .collect_vec();
+        let mut after_phase_num_rotations = vec![vec![]; pcs_data_after.len()];
+        for views in &prover_data_after.rap_views_per_phase {
+            for (view, &num_rotations) in zip(views, &num_rotations_per_air) {
+                if let Some((commit_idx, matrix_idx)) = view.inner {
+                    debug_assert_eq!(after_phase_num_rotations[commit_idx].len(), matrix_idx);
+                    after_phase_num_rotations[commit_idx].push(num_rotations);
                 }
             }
+        }
+        // ==================== Polynomial Opening Proofs ====================
+        let opening = metrics_span("pcs_opening_time_ms", || {
+            let preprocessed = zip(mpk.per_air, &num_rotations_per_air)
+                .filter_map(|(pk, &num_rotations)| {
+                    pk.preprocessed_data
+                        .map(|data| (data.data, vec![num_rotations]))
+                })
+                .collect();
 
-            let main = cached_pcs_datas_per_air
-                .into_iter()
-                .flatten()
-                .chain(iter::once(common_main_pcs_data))
+            let main = zip(cached_pcs_datas_per_air, &num_rotations_per_air)
+                .flat_map(|(pcs_datas, &num_rotations)| {
+                    pcs_datas
+                        .into_iter()
+                        .map(move |data| (data, vec![num_rotations]))
+                })
+                .chain(iter::once((
+                    common_main_pcs_data,
+                    common_main_num_rotations,
+                )))
                 .collect();
+            let after_phase = zip(pcs_data_after, after_phase_num_rotations).collect();
             self.device.open(
                 &mut self.challenger,
                 preprocessed,
                 main,
-                pcs_data_after,
-                quotient_data,
-                &quotient_degrees,
+                after_phase,
+                quotient_pcs_data,
+                &num_quotient_chunks_per_air,
+                &quotient_group_idx_per_air,
             )
         });
 
@@ -271,7 +513,7 @@ where
         let commitments = Commitments {
             main_trace: main_trace_commitments,
             after_challenge: commitments_after,
-            quotient: quotient_commit,
+            quotient: quotient_commits,
         };
         let proof = HalProof {
             commitments,
@@ -279,15 +521,31 @@ where
             per_air: izip!(
                 &mpk.air_ids,
                 log_trace_height_per_air,
+                quotient_degrees,
+                num_quotient_chunks_per_air,
+                quotient_group_idx_per_air,
                 exposed_values_per_air,
                 pvs_per_air
             )
             .map(
-                |(&air_id, log_height, exposed_values, public_values)| AirProofData {
-                    air_id,
-                    degree: 1 << log_height,
+                |(
+                    &air_id,
+                    log_height,
+                    quotient_degree,
+                    num_quotient_chunks,
+                    quotient_commit_idx,
+                    exposed_values,
                     public_values,
-                    exposed_values_after_challenge: exposed_values,
+                )| {
+                    AirProofData {
+                        air_id,
+                        degree: 1 << log_height,
+                        quotient_degree,
+                        num_quotient_chunks,
+                        quotient_commit_idx,
+                        public_values,
+                        exposed_values_after_challenge: exposed_values,
+                    }
                 },
             )
             .collect(),
@@ -298,7 +556,7 @@ where
         ::metrics::gauge!("stark_prove_excluding_trace_time_ms")
             .set(start.elapsed().as_millis() as f64);
 
-        proof
+        Ok(proof)
     }
 }
 
@@ -313,6 +571,22 @@ impl<'a, PB: ProverBackend> DeviceMultiStarkProvingKey<'a, PB> {
             && ctx.per_air.iter().tuple_windows().all(|(a, b)| a.0 < b.0)
     }
 
+    /// Checks that each AIR in `ctx` has exactly `num_public_values` public values.
+    pub fn validate_public_values(&self, ctx: &ProvingContext<PB>) -> Result<(), ProverError> {
+        for ((air_id, air_ctx), pk) in ctx.per_air.iter().zip(&self.per_air) {
+            let expected = pk.vk.params.num_public_values;
+            let actual = air_ctx.public_values.len();
+            if actual != expected {
+                return Err(ProverError::PublicValueCountMismatch {
+                    air_id: *air_id,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn vk_view(&'a self) -> MultiStarkVerifyingKeyView<'a, PB::Val, PB::Commitment> {
         MultiStarkVerifyingKeyView::new(
             self.per_air.iter().map(|pk| pk.vk).collect(),
@@ -4,23 +4,29 @@ use derivative::Derivative;
 use itertools::{izip, zip_eq, Itertools};
 use opener::OpeningProver;
 use p3_challenger::FieldChallenger;
-use p3_commit::{Pcs, PolynomialSpace};
+use p3_commit::{Mmcs, Pcs};
 use p3_field::{ExtensionField, Field, FieldExtensionAlgebra};
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_maybe_rayon::prelude::*;
 use p3_util::log2_strict_usize;
-use quotient::QuotientCommitter;
+use quotient::{QuotientCommitter, QuotientEvalStats, DEFAULT_DIRECT_EVAL_MAX_CONSTRAINTS};
+use thiserror::Error;
 
 use super::{
-    hal::{self, DeviceDataTransporter, MatrixDimensions, ProverBackend, ProverDevice},
+    hal::{
+        self, mmcs_layout, DeviceDataTransporter, MatrixDimensions, ProverBackend, ProverDevice,
+    },
     types::{
-        AirView, DeviceMultiStarkProvingKey, DeviceStarkProvingKey, ProverDataAfterRapPhases,
-        RapView, SingleCommitPreimage,
+        split_commit_groups, AirView, DeviceMultiStarkProvingKey, DeviceStarkProvingKey,
+        PermutationCommitGrouping, ProverDataAfterRapPhases, ProverQuotientData,
+        QuotientCommitGrouping, RapView, SingleCommitPreimage,
     },
 };
 use crate::{
+    accumulation::{AccumulationStrategy, AlphaPowers},
     air_builders::symbolic::SymbolicConstraints,
     config::{
-        Com, PcsProof, PcsProverData, RapPartialProvingKey, RapPhaseSeqPartialProof,
+        Com, Domain, PcsProof, PcsProverData, RapPartialProvingKey, RapPhaseSeqPartialProof,
         StarkGenericConfig, Val,
     },
     interaction::RapPhaseSeq,
@@ -29,8 +35,9 @@ use crate::{
     prover::{
         hal::TraceCommitter,
         types::{PairView, RapSinglePhaseView},
+        ProverError,
     },
-    utils::metrics_span,
+    utils::{metrics_span, quotient_domain},
 };
 
 /// Polynomial opening proofs
@@ -57,12 +64,53 @@ pub struct CpuBackend<SC> {
 /// # Safety
 /// See [`CpuBackend`].
 #[derive(Derivative, derive_new::new)]
-#[derivative(Clone(bound = ""), Copy(bound = ""))]
-pub struct CpuDevice<'a, SC> {
+#[derivative(Clone(bound = ""))]
+pub struct CpuDevice<'a, SC: StarkGenericConfig> {
     config: &'a SC,
     /// When committing a matrix, the matrix is cloned into newly allocated memory.
     /// The size of the newly allocated memory will be `matrix.size() << log_blowup_factor`.
+    ///
+    /// This should be the log blowup of the PCS. The `Pcs` trait does not expose its blowup, so
+    /// [try_new](CpuDevice::try_new) checks it against the log blowup the PCS was built with. A
+    /// mismatch never affects the proof: a smaller value makes the PCS reallocate for the
+    /// low-degree extension, a larger one over-allocates.
     log_blowup_factor: usize,
+    /// How quotient chunks of different AIRs are grouped into commitments.
+    #[new(default)]
+    quotient_grouping: QuotientCommitGrouping,
+    /// How permutation traces of different AIRs are grouped into commitments.
+    #[new(default)]
+    perm_grouping: PermutationCommitGrouping,
+    /// Whether to check the quotient identity of each AIR at an out-of-domain point before
+    /// committing to the quotient polynomials.
+    #[new(default)]
+    check_quotient_identity: bool,
+    /// Number of SIMD-packed rows per parallel batch of the quotient polynomial evaluation.
+    #[new(value = "1")]
+    quotient_batch_multiplier: usize,
+    /// Maximum number of matrices in a shared permutation or quotient commitment, for MMCS
+    /// implementations which cap it. Commitments exceeding it are split.
+    #[new(default)]
+    max_matrices_per_commit: Option<usize>,
+    /// AIRs with at most this many constraints evaluate their quotient polynomial directly
+    /// instead of interpreting the constraint DAG.
+    #[new(value = "DEFAULT_DIRECT_EVAL_MAX_CONSTRAINTS")]
+    direct_eval_max_constraints: usize,
+    /// If set, independent commitments, such as the permutation or quotient commitment of each
+    /// group of AIRs, are computed in parallel with it.
+    #[new(default)]
+    parallel_commit: Option<CommitBatchesFn<SC>>,
+    /// Maximum number of chunks the quotient polynomial of each AIR is split into. Defaults to
+    /// the quotient degree of the AIR.
+    #[new(default)]
+    max_quotient_chunks: Option<usize>,
+    /// Counts the quotient evaluation strategy used for each AIR with the `test-utils` feature.
+    /// Shared by clones of the device.
+    #[new(default)]
+    quotient_eval_stats: Arc<QuotientEvalStats>,
+    /// How the constraints of each AIR are folded with its `alpha` challenge.
+    #[new(value = "Arc::new(AlphaPowers)")]
+    accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
 }
 
 impl<SC: StarkGenericConfig> ProverBackend for CpuBackend<SC> {
@@ -89,6 +137,50 @@ pub struct PcsData<SC: StarkGenericConfig> {
     pub log_trace_heights: Vec<u8>,
 }
 
+impl<SC: StarkGenericConfig> PcsData<SC>
+where
+    Com<SC>: PartialEq,
+{
+    /// Wraps prover data computed outside of the CPU prover, such as a Merkle tree built on a
+    /// GPU, checking that it is the preimage of the claimed `commitment`.
+    ///
+    /// Only the committed matrices of `data` are trusted: the digest layers are recomputed from
+    /// them with `mmcs`, which must be the MMCS used by the PCS, and the recomputed root must
+    /// equal `commitment`. `log_trace_heights` are the log2 heights of the committed matrices,
+    /// in commitment order.
+    pub fn from_precomputed<M>(
+        mmcs: &M,
+        commitment: &Com<SC>,
+        data: &PcsProverData<SC>,
+        log_trace_heights: Vec<u8>,
+    ) -> Result<Self, PcsDataError>
+    where
+        M: Mmcs<
+            Val<SC>,
+            Commitment = Com<SC>,
+            ProverData<RowMajorMatrix<Val<SC>>> = PcsProverData<SC>,
+        >,
+    {
+        let matrices = mmcs.get_matrices(data).into_iter().cloned().collect_vec();
+        if matrices.len() != log_trace_heights.len() {
+            return Err(PcsDataError::HeightsMismatch);
+        }
+        let (root, data) = mmcs.commit(matrices);
+        if root != *commitment {
+            return Err(PcsDataError::CommitmentMismatch);
+        }
+        Ok(Self::new(Arc::new(data), log_trace_heights))
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PcsDataError {
+    #[error("prover data does not match the claimed commitment")]
+    CommitmentMismatch,
+    #[error("number of trace heights does not match the number of committed matrices")]
+    HeightsMismatch,
+}
+
 impl<T: Send + Sync + Clone> MatrixDimensions for Arc<RowMajorMatrix<T>> {
     fn height(&self) -> usize {
         self.deref().height()
@@ -98,10 +190,117 @@ impl<T: Send + Sync + Clone> MatrixDimensions for Arc<RowMajorMatrix<T>> {
     }
 }
 
-impl<SC> CpuDevice<'_, SC> {
+impl<'a, SC: StarkGenericConfig> CpuDevice<'a, SC> {
+    /// Same as [new](Self::new), but returns [ProverError::LogBlowupMismatch] if
+    /// `log_blowup_factor` differs from `pcs_log_blowup`, the log blowup the PCS of `config` was
+    /// built with, e.g. the `log_blowup` of its FRI parameters.
+    pub fn try_new(
+        config: &'a SC,
+        log_blowup_factor: usize,
+        pcs_log_blowup: usize,
+    ) -> Result<Self, ProverError> {
+        if log_blowup_factor != pcs_log_blowup {
+            return Err(ProverError::LogBlowupMismatch {
+                device: log_blowup_factor,
+                pcs: pcs_log_blowup,
+            });
+        }
+        Ok(Self::new(config, log_blowup_factor))
+    }
+}
+
+impl<SC: StarkGenericConfig> CpuDevice<'_, SC> {
     pub fn config(&self) -> &SC {
         self.config
     }
+
+    /// Sets how quotient chunks of different AIRs are grouped into commitments.
+    pub fn with_quotient_grouping(mut self, quotient_grouping: QuotientCommitGrouping) -> Self {
+        self.quotient_grouping = quotient_grouping;
+        self
+    }
+
+    /// Sets how permutation traces of different AIRs are grouped into commitments.
+    pub fn with_perm_grouping(mut self, perm_grouping: PermutationCommitGrouping) -> Self {
+        self.perm_grouping = perm_grouping;
+        self
+    }
+
+    /// Enables a debugging check that `constraints(x) = quotient(x) * Z_H(x)` holds for each AIR
+    /// at an out-of-domain point `x`, after computing the quotient polynomials. Proving stops
+    /// before the opening proof at the first AIR that fails the check:
+    /// [try_prove](crate::prover::coordinator::Coordinator::try_prove) returns
+    /// [ProverError::QuotientIdentity](crate::prover::ProverError::QuotientIdentity) with the
+    /// name of the AIR, and [prove](crate::prover::Prover::prove) panics with it.
+    ///
+    /// This catches traces which do not satisfy the constraints before the verifier does.
+    pub fn with_quotient_identity_check(mut self, check_quotient_identity: bool) -> Self {
+        self.check_quotient_identity = check_quotient_identity;
+        self
+    }
+
+    /// Sets the number of rows per parallel batch when evaluating quotient polynomials, in units
+    /// of the SIMD packing width. See [QuotientCommitter::with_batch_multiplier].
+    pub fn with_quotient_batch_multiplier(mut self, quotient_batch_multiplier: usize) -> Self {
+        self.quotient_batch_multiplier = quotient_batch_multiplier;
+        self
+    }
+
+    /// Sets how the constraints of each AIR are folded with its `alpha` challenge. The verifier
+    /// must be given the same strategy, see
+    /// [MultiTraceStarkVerifier::with_accumulation](crate::verifier::MultiTraceStarkVerifier::with_accumulation).
+    pub fn with_accumulation(
+        mut self,
+        accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
+    ) -> Self {
+        self.accumulation = accumulation;
+        self
+    }
+
+    /// Caps the number of matrices in each permutation and quotient commitment. Shared
+    /// commitments with more matrices are split into multiple commitments, each with a run of
+    /// consecutive AIRs. The common main trace commitment is never split.
+    pub fn with_max_matrices_per_commit(mut self, max_matrices_per_commit: usize) -> Self {
+        assert!(max_matrices_per_commit > 0);
+        self.max_matrices_per_commit = Some(max_matrices_per_commit);
+        self
+    }
+
+    /// Sets the maximum number of constraints of an AIR for its quotient polynomial to be
+    /// evaluated directly. See [QuotientCommitter::with_direct_eval_max_constraints].
+    pub fn with_direct_eval_max_constraints(mut self, direct_eval_max_constraints: usize) -> Self {
+        self.direct_eval_max_constraints = direct_eval_max_constraints;
+        self
+    }
+
+    /// Computes independent commitments in parallel instead of one after another. Each
+    /// commitment is the same either way, so this only affects proving time: it pays off when
+    /// there are many commitments whose matrices are too small for the PCS to parallelize well
+    /// on its own, e.g. with [PermutationCommitGrouping::PerAir].
+    ///
+    /// The PCS is shared across threads, so it must be `Sync`.
+    pub fn with_parallel_commits(mut self, parallel_commits: bool) -> Self
+    where
+        SC::Pcs: Sync,
+    {
+        let commit_batches: CommitBatchesFn<SC> = commit_batches_in_parallel::<SC>;
+        self.parallel_commit = parallel_commits.then_some(commit_batches);
+        self
+    }
+
+    /// Splits the quotient polynomial of each AIR into at most `max_quotient_chunks` chunks. See
+    /// [QuotientCommitter::with_max_chunks].
+    pub fn with_max_quotient_chunks(mut self, max_quotient_chunks: usize) -> Self {
+        assert!(max_quotient_chunks.is_power_of_two());
+        self.max_quotient_chunks = Some(max_quotient_chunks);
+        self
+    }
+
+    /// Number of AIRs whose quotient polynomial was evaluated with each strategy, over all
+    /// proofs by this device. Only counted with the `test-utils` feature.
+    pub fn quotient_eval_stats(&self) -> &QuotientEvalStats {
+        &self.quotient_eval_stats
+    }
 }
 
 impl<SC: StarkGenericConfig> CpuDevice<'_, SC> {
@@ -110,59 +309,108 @@ impl<SC: StarkGenericConfig> CpuDevice<'_, SC> {
     }
 }
 
+/// Commits to each batch of matrices, evaluated over their domains, in its own commitment.
+pub(crate) type CommitBatchesFn<SC> = fn(
+    &<SC as StarkGenericConfig>::Pcs,
+    Vec<Vec<(Domain<SC>, RowMajorMatrix<Val<SC>>)>>,
+) -> Vec<(Com<SC>, PcsProverData<SC>)>;
+
+/// A [CommitBatchesFn] computing the commitments in parallel. It is only instantiated for a
+/// `Sync` PCS, so that the PCS of a [StarkGenericConfig] is not required to be `Sync`.
+fn commit_batches_in_parallel<SC: StarkGenericConfig>(
+    pcs: &SC::Pcs,
+    batches: Vec<Vec<(Domain<SC>, RowMajorMatrix<Val<SC>>)>>,
+) -> Vec<(Com<SC>, PcsProverData<SC>)>
+where
+    SC::Pcs: Sync,
+{
+    batches
+        .into_par_iter()
+        .map(|batch| pcs.commit(batch))
+        .collect()
+}
+
 impl<SC: StarkGenericConfig> ProverDevice<CpuBackend<SC>> for CpuDevice<'_, SC> {}
 
 impl<SC: StarkGenericConfig> TraceCommitter<CpuBackend<SC>> for CpuDevice<'_, SC> {
     fn commit(&self, traces: &[Arc<RowMajorMatrix<Val<SC>>>]) -> (Com<SC>, PcsData<SC>) {
-        let log_blowup_factor = self.log_blowup_factor;
-        let pcs = self.pcs();
-        let (log_trace_heights, traces_with_domains): (Vec<_>, Vec<_>) = traces
-            .iter()
-            .map(|matrix| {
-                let height = matrix.height();
-                let log_height: u8 = log2_strict_usize(height).try_into().unwrap();
-                // Recomputing the domain is lightweight
-                let domain = pcs.natural_domain_for_degree(height);
-                // pcs.commit takes the trace matrix and in the case of FRI, does in-place cosetDFT
-                // which requires resizing to a larger buffer size. Since we are cloning anyways,
-                // we should just allocate the larger size to avoid memory-reallocation
-                // ref: https://github.com/Plonky3/Plonky3/blob/8c8bbb4c17bd2b7ef2404338ab8f9036d5f08337/dft/src/traits.rs#L116
-                let trace_slice = &matrix.as_ref().values;
-                let new_buffer_size = trace_slice
-                    .len()
-                    .checked_shl(log_blowup_factor.try_into().unwrap())
-                    .unwrap();
-                let mut new_buffer = Vec::with_capacity(new_buffer_size);
-                // SAFETY:
-                // - `trace_slice` is allocated for `trace_slice.len() * size_of::<F>` bytes, obviously
-                // - we just allocated `new_buffer` for at least `trace_slice.len() * size_of::<F>` bytes above (more if there's blowup)
-                // - both are slices of &[F] so alignment is guaranteed
-                // - `new_buffer` is newly allocated so non-overlapping with `trace_slice`
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        trace_slice.as_ptr(),
-                        new_buffer.as_mut_ptr(),
-                        trace_slice.len(),
-                    );
-                    new_buffer.set_len(trace_slice.len());
-                }
-                (
-                    log_height,
-                    (domain, RowMajorMatrix::new(new_buffer, matrix.width)),
-                )
-            })
-            .unzip();
-        let (commit, data) = pcs.commit(traces_with_domains);
-        (
-            commit,
-            PcsData {
-                data: Arc::new(data),
-                log_trace_heights,
+        commit_traces::<SC>(self.pcs(), self.log_blowup_factor, traces)
+    }
+
+    /// Returns the [mmcs_layout] of `traces`. In debug builds, the layout is checked against the
+    /// MMCS of the PCS: committing to the matrices already in that layout must give the same
+    /// commitment, which fails if the MMCS keeps matrices in the order they are given.
+    fn commit_ordered(
+        &self,
+        traces: &[Arc<RowMajorMatrix<Val<SC>>>],
+    ) -> (Com<SC>, PcsData<SC>, Vec<usize>) {
+        let permutation = mmcs_layout(traces);
+        let (commit, data) = self.commit(traces);
+        debug_assert_eq!(
+            {
+                let laid_out = permutation.iter().map(|&i| traces[i].clone()).collect_vec();
+                bitcode::serialize(&self.commit(&laid_out).0).unwrap()
             },
-        )
+            bitcode::serialize(&commit).unwrap(),
+            "the MMCS does not lay out matrices by decreasing height"
+        );
+        (commit, data, permutation)
     }
 }
 
+/// Commits to `traces` in a single commitment, cloning each trace into a buffer with capacity for
+/// the low-degree extension by `2^log_blowup_factor`.
+fn commit_traces<SC: StarkGenericConfig>(
+    pcs: &SC::Pcs,
+    log_blowup_factor: usize,
+    traces: &[Arc<RowMajorMatrix<Val<SC>>>],
+) -> (Com<SC>, PcsData<SC>) {
+    let (log_trace_heights, traces_with_domains): (Vec<_>, Vec<_>) = traces
+        .iter()
+        .map(|matrix| {
+            let height = matrix.height();
+            let log_height: u8 = log2_strict_usize(height).try_into().unwrap();
+            // Recomputing the domain is lightweight
+            let domain = pcs.natural_domain_for_degree(height);
+            // pcs.commit takes the trace matrix and in the case of FRI, does in-place cosetDFT
+            // which requires resizing to a larger buffer size. Since we are cloning anyways,
+            // we should just allocate the larger size to avoid memory-reallocation
+            // ref: https://github.com/Plonky3/Plonky3/blob/8c8bbb4c17bd2b7ef2404338ab8f9036d5f08337/dft/src/traits.rs#L116
+            let trace_slice = &matrix.as_ref().values;
+            let new_buffer_size = trace_slice
+                .len()
+                .checked_shl(log_blowup_factor.try_into().unwrap())
+                .unwrap();
+            let mut new_buffer = Vec::with_capacity(new_buffer_size);
+            // SAFETY:
+            // - `trace_slice` is allocated for `trace_slice.len() * size_of::<F>` bytes, obviously
+            // - we just allocated `new_buffer` for at least `trace_slice.len() * size_of::<F>` bytes above (more if there's blowup)
+            // - both are slices of &[F] so alignment is guaranteed
+            // - `new_buffer` is newly allocated so non-overlapping with `trace_slice`
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    trace_slice.as_ptr(),
+                    new_buffer.as_mut_ptr(),
+                    trace_slice.len(),
+                );
+                new_buffer.set_len(trace_slice.len());
+            }
+            (
+                log_height,
+                (domain, RowMajorMatrix::new(new_buffer, matrix.width)),
+            )
+        })
+        .unzip();
+    let (commit, data) = pcs.commit(traces_with_domains);
+    (
+        commit,
+        PcsData {
+            data: Arc::new(data),
+            log_trace_heights,
+        },
+    )
+}
+
 impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice<'_, SC> {
     fn partially_prove(
         &self,
@@ -188,12 +436,21 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
             .unzip();
 
         let trace_views = zip(&mpk.per_air, trace_views)
-            .map(|(pk, v)| PairView {
-                log_trace_height: log2_strict_usize(v.partitioned_main.first().unwrap().height())
-                    as u8,
-                preprocessed: pk.preprocessed_data.as_ref().map(|p| p.trace.clone()), // Arc::clone for now
-                partitioned_main: v.partitioned_main,
-                public_values: v.public_values,
+            .map(|(pk, v)| {
+                // Arc::clone for now
+                let preprocessed = pk.preprocessed_data.as_ref().map(|p| p.trace.clone());
+                let height = v
+                    .partitioned_main
+                    .first()
+                    .or(preprocessed.as_ref())
+                    .expect("no main or preprocessed trace")
+                    .height();
+                PairView {
+                    log_trace_height: log2_strict_usize(height) as u8,
+                    preprocessed,
+                    partitioned_main: v.partitioned_main,
+                    public_values: v.public_values,
+                }
             })
             .collect_vec();
         let (rap_phase_seq_proof, rap_phase_seq_data) = self
@@ -209,7 +466,21 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
 
         let mvk_view = mpk.vk_view();
 
-        let mut perm_matrix_idx = 0usize;
+        // For each AIR with a permutation trace, the commitment index and the matrix index within
+        // that commitment.
+        let max_perm_traces_per_commit = match self.perm_grouping {
+            PermutationCommitGrouping::Single => self.max_matrices_per_commit.unwrap_or(usize::MAX),
+            PermutationCommitGrouping::PerAir => 1,
+        };
+        let mut perm_trace_idx = 0usize;
+        let mut next_perm_view = || {
+            let view = (
+                perm_trace_idx / max_perm_traces_per_commit,
+                perm_trace_idx % max_perm_traces_per_commit,
+            );
+            perm_trace_idx += 1;
+            view
+        };
         let rap_views_per_phase;
         let perm_trace_per_air = if let Some(phase_data) = rap_phase_seq_data {
             assert_eq!(mvk_view.num_phases(), 1);
@@ -221,17 +492,10 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
                 &phase_data.after_challenge_trace_per_air,
                 phase_data.exposed_values_per_air,
             )
-            .map(|(perm_trace, exposed_values)| {
-                let mut matrix_idx = None;
-                if perm_trace.is_some() {
-                    matrix_idx = Some(perm_matrix_idx);
-                    perm_matrix_idx += 1;
-                }
-                RapSinglePhaseView {
-                    inner: matrix_idx,
-                    challenges: phase_data.challenges.clone(),
-                    exposed_values: exposed_values.unwrap_or_default(),
-                }
+            .map(|(perm_trace, exposed_values)| RapSinglePhaseView {
+                inner: perm_trace.as_ref().map(|_| next_perm_view()),
+                challenges: phase_data.challenges.clone(),
+                exposed_values: exposed_values.unwrap_or_default(),
             })
             .collect_vec();
             rap_views_per_phase = vec![perm_views]; // 1 challenge phase
@@ -243,8 +507,9 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
         };
 
         // Commit to permutation traces: this means only 1 challenge round right now
-        // One shared commit for all permutation traces
-        let committed_pcs_data_per_phase: Vec<(Com<SC>, PcsData<SC>)> =
+        // Either shared commits for runs of at most `max_perm_traces_per_commit` permutation
+        // traces, or one commit per permutation trace
+        let committed_pcs_data_after_challenge: Vec<(Com<SC>, PcsData<SC>)> =
             metrics_span("perm_trace_commit_time_ms", || {
                 let (log_trace_heights, flattened_traces): (Vec<_>, Vec<_>) = perm_trace_per_air
                     .into_iter()
@@ -259,18 +524,36 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
                         (log_height, (domain, trace))
                     })
                     .collect();
-                // Only commit if there are permutation traces
-                if !flattened_traces.is_empty() {
-                    let (commit, data) = self.pcs().commit(flattened_traces);
-                    Some((commit, PcsData::new(Arc::new(data), log_trace_heights)))
-                } else {
-                    None
-                }
-            })
-            .into_iter()
-            .collect();
+                // Only commits if there are permutation traces
+                let mut flattened_traces = flattened_traces.into_iter();
+                let traces_per_commit = log_trace_heights
+                    .chunks(max_perm_traces_per_commit)
+                    .map(|log_trace_heights| {
+                        let traces = flattened_traces
+                            .by_ref()
+                            .take(log_trace_heights.len())
+                            .collect_vec();
+                        (traces, log_trace_heights.to_vec())
+                    })
+                    .collect_vec();
+                let (traces_per_commit, log_trace_heights_per_commit): (Vec<_>, Vec<_>) =
+                    traces_per_commit.into_iter().unzip();
+                let pcs = self.pcs();
+                let commits = match self.parallel_commit {
+                    Some(commit_batches) => commit_batches(pcs, traces_per_commit),
+                    None => traces_per_commit
+                        .into_iter()
+                        .map(|traces| pcs.commit(traces))
+                        .collect(),
+                };
+                zip(commits, log_trace_heights_per_commit)
+                    .map(|((commit, data), log_trace_heights)| {
+                        (commit, PcsData::new(Arc::new(data), log_trace_heights))
+                    })
+                    .collect()
+            });
         let prover_view = ProverDataAfterRapPhases {
-            committed_pcs_data_per_phase,
+            committed_pcs_data_after_challenge,
             rap_views_per_phase,
         };
         (rap_phase_seq_proof, prover_view)
@@ -286,11 +569,12 @@ impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>> for CpuDevic
         cached_pcs_datas_per_air: &[Vec<PcsData<SC>>],
         common_main_pcs_data: &PcsData<SC>,
         prover_data_after: &ProverDataAfterRapPhases<CpuBackend<SC>>,
-    ) -> (Com<SC>, PcsData<SC>) {
+    ) -> ProverQuotientData<CpuBackend<SC>> {
         let pcs = self.pcs();
-        // Generate `alpha` challenge
-        let alpha: SC::Challenge = challenger.sample_ext_element();
-        tracing::debug!("alpha: {alpha:?}");
+        // Generate `alpha` challenges
+        let alphas: Vec<SC::Challenge> =
+            self.config.alpha_sampling().sample(challenger, pk_views.len());
+        tracing::debug!("alphas: {alphas:?}");
         // Prepare extended views:
         let mut common_main_idx = 0;
         let extended_views = izip!(pk_views, cached_pcs_datas_per_air, public_values)
@@ -299,12 +583,17 @@ impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>> for CpuDevic
                 let quotient_degree = pk.vk.quotient_degree;
                 let log_trace_height = if pk.vk.has_common_main() {
                     common_main_pcs_data.log_trace_heights[common_main_idx]
+                } else if let Some(cached_pcs_data) = cached_pcs_datas.first() {
+                    cached_pcs_data.log_trace_heights[0]
                 } else {
-                    cached_pcs_datas[0].log_trace_heights[0]
+                    let preprocessed_data = pk
+                        .preprocessed_data
+                        .as_ref()
+                        .expect("no main or preprocessed trace");
+                    preprocessed_data.data.log_trace_heights[preprocessed_data.matrix_idx as usize]
                 };
                 let trace_domain = pcs.natural_domain_for_degree(1usize << log_trace_height);
-                let quotient_domain = trace_domain
-                    .create_disjoint_domain(trace_domain.size() * quotient_degree as usize);
+                let quotient_domain = quotient_domain(trace_domain, quotient_degree);
                 // **IMPORTANT**: the return type of `get_evaluations_on_domain` is a matrix view. DO NOT call to_row_major_matrix as this will allocate new memory
                 let preprocessed = pk.preprocessed_data.as_ref().map(|cv| {
                     pcs.get_evaluations_on_domain(
@@ -326,22 +615,26 @@ impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>> for CpuDevic
                     ));
                     common_main_idx += 1;
                 }
-                let mut per_phase = zip(
-                    &prover_data_after.committed_pcs_data_per_phase,
-                    &prover_data_after.rap_views_per_phase,
-                )
-                .map(|((_, pcs_data), rap_views)| -> Option<_> {
-                    let rap_view = rap_views.get(i)?;
-                    let matrix_idx = rap_view.inner?;
-                    let extended_matrix =
-                        pcs.get_evaluations_on_domain(&pcs_data.data, matrix_idx, quotient_domain);
-                    Some(RapSinglePhaseView {
-                        inner: Some(extended_matrix),
-                        challenges: rap_view.challenges.clone(),
-                        exposed_values: rap_view.exposed_values.clone(),
+                let mut per_phase = prover_data_after
+                    .rap_views_per_phase
+                    .iter()
+                    .map(|rap_views| -> Option<_> {
+                        let rap_view = rap_views.get(i)?;
+                        let (commit_idx, matrix_idx) = rap_view.inner?;
+                        let (_, pcs_data) =
+                            &prover_data_after.committed_pcs_data_after_challenge[commit_idx];
+                        let extended_matrix = pcs.get_evaluations_on_domain(
+                            &pcs_data.data,
+                            matrix_idx,
+                            quotient_domain,
+                        );
+                        Some(RapSinglePhaseView {
+                            inner: Some(extended_matrix),
+                            challenges: rap_view.challenges.clone(),
+                            exposed_values: rap_view.exposed_values.clone(),
+                        })
                     })
-                })
-                .collect_vec();
+                    .collect_vec();
                 while let Some(last) = per_phase.last() {
                     if last.is_none() {
                         per_phase.pop();
@@ -373,15 +666,68 @@ impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>> for CpuDevic
                 )
             })
             .unzip();
-        let qc = QuotientCommitter::new(self.pcs(), alpha, self.log_blowup_factor);
-        let quotient_values = metrics_span("quotient_poly_compute_time_ms", || {
-            qc.quotient_values(&constraints, extended_views, &quotient_degrees)
+        let air_labels = pk_views
+            .iter()
+            .map(|pk| {
+                vec![
+                    ("air_name", pk.air_name.to_owned()),
+                    ("air_id", pk.air_id.to_string()),
+                ]
+            })
+            .collect_vec();
+        let mut qc = QuotientCommitter::new(self.pcs(), alphas[0], self.log_blowup_factor)
+            .with_alpha_per_air(alphas.clone())
+            .with_accumulation(self.accumulation.clone())
+            .with_batch_multiplier(self.quotient_batch_multiplier)
+            .with_direct_eval_max_constraints(self.direct_eval_max_constraints)
+            .with_eval_stats(self.quotient_eval_stats.clone())
+            .with_parallel_commit(self.parallel_commit);
+        if let Some(max_chunks) = self.max_quotient_chunks {
+            qc = qc.with_max_chunks(max_chunks);
+        }
+        let num_chunks_per_air = quotient_degrees
+            .iter()
+            .map(|&quotient_degree| qc.num_chunks(quotient_degree))
+            .collect_vec();
+        // The out-of-domain point only needs to be independent of the traces, so it is derived
+        // from `alpha` to leave the challenger untouched.
+        let opened_views = self.check_quotient_identity.then(|| {
+            let point = alphas[0].square();
+            izip!(&extended_views, &quotient_degrees, pk_views)
+                .map(|(view, &quotient_degree, pk)| {
+                    qc.open_rap_view(view, quotient_degree, point, pk.vk.num_rotations())
+                })
+                .collect_vec()
+        });
+        let quotient_values =
+            qc.quotient_values(&constraints, extended_views, &quotient_degrees, &air_labels);
+        let failed_quotient_identity = opened_views.and_then(|opened_views| {
+            zip(&constraints, opened_views)
+                .enumerate()
+                .find_map(|(air_idx, (constraints, opened))| {
+                    qc.verify_quotient_identity(constraints, opened, &quotient_values, air_idx)
+                        .err()
+                        .map(|err| (air_idx, err))
+                })
         });
 
-        // Commit to quotient polynomials. One shared commit for all quotient polynomials
-        metrics_span("quotient_poly_commit_time_ms", || {
-            qc.commit(quotient_values)
-        })
+        // Commit to quotient polynomials, grouped by AIR according to `quotient_grouping`
+        let mut group_idx_per_air = self.quotient_grouping.group_idx_per_air(pk_views.len());
+        if let Some(max_matrices) = self.max_matrices_per_commit {
+            let num_matrices_per_air = num_chunks_per_air.iter().map(|&n| n as usize).collect_vec();
+            group_idx_per_air =
+                split_commit_groups(&group_idx_per_air, &num_matrices_per_air, max_matrices);
+        }
+        let committed_pcs_data_per_group =
+            qc.commit_grouped(quotient_values, &group_idx_per_air, &air_labels);
+        ProverQuotientData {
+            failed_quotient_identity,
+            ..ProverQuotientData::new(
+                committed_pcs_data_per_group,
+                group_idx_per_air,
+                num_chunks_per_air,
+            )
+        }
     }
 }
 
@@ -390,18 +736,21 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
         &self,
         challenger: &mut SC::Challenger,
         // For each preprocessed trace commitment, the prover data and
-        // the log height of the matrix, in order
-        preprocessed: Vec<PcsData<SC>>,
+        // the number of rotations of the matrix
+        preprocessed: Vec<(PcsData<SC>, Vec<usize>)>,
         // For each main trace commitment, the prover data and
-        // the log height of each matrix, in order
+        // the number of rotations of each matrix, in order
         // Note: this is all one challenge phase.
-        main: Vec<PcsData<SC>>,
-        // `after_phase[i]` has shared commitment prover data for all matrices in phase `i + 1`.
-        after_phase: Vec<PcsData<SC>>,
-        // Quotient poly commitment prover data
-        quotient_data: PcsData<SC>,
-        // Quotient degree for each RAP committed in quotient_data, in order
-        quotient_degrees: &[u8],
+        main: Vec<(PcsData<SC>, Vec<usize>)>,
+        // For each after_challenge commitment, in phase order, the prover data and
+        // the number of rotations of each matrix, in order
+        after_phase: Vec<(PcsData<SC>, Vec<usize>)>,
+        // Quotient poly commitment prover data, for each quotient commitment
+        quotient_data: Vec<PcsData<SC>>,
+        // Number of quotient chunks for each RAP, in order
+        num_quotient_chunks: &[u8],
+        // For each RAP, the index in `quotient_data` of the commitment with its quotient chunks
+        quotient_group_idx_per_air: &[usize],
     ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
         // Draw `zeta` challenge
         let zeta: SC::Challenge = challenger.sample_ext_element();
@@ -409,35 +758,38 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
 
         let pcs = self.pcs();
         let domain = |log_height| pcs.natural_domain_for_degree(1usize << log_height);
+        // The domain and number of rotations of each matrix in the commitment of `v`
+        let domains = |(v, num_rotations): &(PcsData<SC>, Vec<usize>)| {
+            assert_eq!(v.log_trace_heights.len(), num_rotations.len());
+            zip(&v.log_trace_heights, num_rotations)
+                .map(|(&log_height, &num_rotations)| (domain(log_height), num_rotations))
+                .collect_vec()
+        };
         let opener = OpeningProver::<SC>::new(pcs, zeta);
         let preprocessed = preprocessed
             .iter()
             .map(|v| {
-                assert_eq!(v.log_trace_heights.len(), 1);
-                (v.data.as_ref(), domain(v.log_trace_heights[0]))
+                let domains = domains(v);
+                assert_eq!(domains.len(), 1);
+                (v.0.data.as_ref(), domains[0])
             })
             .collect();
         let main = main
             .iter()
-            .map(|v| {
-                let domains = v.log_trace_heights.iter().copied().map(domain).collect();
-                (v.data.as_ref(), domains)
-            })
+            .map(|v| (v.0.data.as_ref(), domains(v)))
             .collect();
         let after_phase: Vec<_> = after_phase
             .iter()
-            .map(|v| {
-                let domains = v.log_trace_heights.iter().copied().map(domain).collect();
-                (v.data.as_ref(), domains)
-            })
+            .map(|v| (v.0.data.as_ref(), domains(v)))
             .collect();
         opener.open(
             challenger,
             preprocessed,
             main,
             after_phase,
-            &quotient_data.data,
-            quotient_degrees,
+            quotient_data.iter().map(|v| v.data.as_ref()).collect(),
+            num_quotient_chunks,
+            quotient_group_idx_per_air,
         )
     }
 }
@@ -474,7 +826,8 @@ where
                     }
                 });
                 DeviceStarkProvingKey {
-                    air_name: &pk.air_name,
+                    air_name: pk.name(),
+                    air_id: air_idx,
                     vk: &pk.vk,
                     preprocessed_data,
                     rap_partial_pk: pk.rap_partial_pk.clone(),
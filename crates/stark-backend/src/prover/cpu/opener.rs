@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::iter::{self, zip};
 
 use itertools::Itertools;
 use p3_commit::{Pcs, PolynomialSpace};
@@ -22,24 +22,29 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
     /// Opening proof for multiple RAP matrices, where
     /// - (for now) each preprocessed trace matrix has a separate commitment
     /// - main trace matrices can have multiple commitments
-    /// - for each after_challenge phase, all matrices in the phase share a commitment
-    /// - quotient poly chunks are all committed together
+    /// - after_challenge matrices can have multiple commitments, in phase order
+    /// - quotient poly chunks are committed in one or more groups of RAPs
+    ///
+    /// Each trace matrix is opened at `zeta` and the next `num_rotations - 1` points of its domain.
     #[instrument(name = "PCS opening proofs", skip_all)]
     pub fn open(
         &self,
         challenger: &mut SC::Challenger,
         // For each preprocessed trace commitment, the prover data and
-        // the domain of the matrix, in order
-        preprocessed: Vec<(&PcsProverData<SC>, Domain<SC>)>,
+        // the domain and number of rotations of the matrix
+        preprocessed: Vec<(&PcsProverData<SC>, (Domain<SC>, usize))>,
         // For each main trace commitment, the prover data and
-        // the domain of each matrix, in order
-        main: Vec<(&PcsProverData<SC>, Vec<Domain<SC>>)>,
-        // after_challenge[i] has shared commitment prover data for all matrices in that phase, and domains of those matrices, in order
-        after_challenge: Vec<(&PcsProverData<SC>, Vec<Domain<SC>>)>,
-        // Quotient poly commitment prover data
-        quotient_data: &PcsProverData<SC>,
-        // Quotient degree for each RAP committed in quotient_data, in order
-        quotient_degrees: &[u8],
+        // the domain and number of rotations of each matrix, in order
+        main: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, usize)>)>,
+        // For each after_challenge commitment, in phase order, the prover data and
+        // the domain and number of rotations of each matrix, in order
+        after_challenge: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, usize)>)>,
+        // Quotient poly commitment prover data, for each quotient commitment
+        quotient_data: Vec<&PcsProverData<SC>>,
+        // Number of quotient chunks for each RAP, in order
+        num_quotient_chunks: &[u8],
+        // For each RAP, the index in `quotient_data` of the commitment with its quotient chunks
+        quotient_group_idx_per_air: &[usize],
     ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
         let preprocessed: Vec<_> = preprocessed
             .into_iter()
@@ -54,21 +59,36 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
             .map(|(data, domains)| {
                 let points_per_mat = domains
                     .iter()
-                    .map(|domain| vec![zeta, domain.next_point(zeta).unwrap()])
+                    .map(|&(domain, num_rotations)| {
+                        iter::successors(Some(zeta), |&x| domain.next_point(x))
+                            .take(num_rotations)
+                            .collect_vec()
+                    })
                     .collect_vec();
                 (*data, points_per_mat)
             })
             .collect_vec();
 
         // open every quotient chunk at zeta
-        let num_chunks = quotient_degrees.iter().map(|x| *x as usize).sum();
-        let quotient_opening_points = vec![vec![zeta]; num_chunks];
-        rounds.push((quotient_data, quotient_opening_points));
+        assert_eq!(num_quotient_chunks.len(), quotient_group_idx_per_air.len());
+        let num_quotient_groups = quotient_data.len();
+        for (group_idx, data) in quotient_data.into_iter().enumerate() {
+            let num_chunks = zip(num_quotient_chunks, quotient_group_idx_per_air)
+                .filter(|&(_, &g)| g == group_idx)
+                .map(|(&n, _)| n as usize)
+                .sum();
+            let quotient_opening_points = vec![vec![zeta]; num_chunks];
+            rounds.push((data, quotient_opening_points));
+        }
 
         let (mut opening_values, opening_proof) = self.pcs.open(rounds, challenger);
 
         // Unflatten opening_values
-        let mut quotient_openings = opening_values.pop().expect("Should have quotient opening");
+        let mut quotient_openings_per_group = opening_values
+            .split_off(opening_values.len() - num_quotient_groups)
+            .into_iter()
+            .map(|openings| openings.into_iter())
+            .collect_vec();
 
         let num_after_challenge = after_challenge.len();
         let after_challenge_openings = opening_values
@@ -109,11 +129,11 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
         );
 
         // Unflatten quotient openings
-        let quotient_openings = quotient_degrees
-            .iter()
-            .map(|&chunk_size| {
-                quotient_openings
-                    .drain(..chunk_size as usize)
+        let quotient_openings = zip(num_quotient_chunks, quotient_group_idx_per_air)
+            .map(|(&num_chunks, &group_idx)| {
+                quotient_openings_per_group[group_idx]
+                    .by_ref()
+                    .take(num_chunks as usize)
                     .map(|mut op| {
                         op.pop()
                             .expect("quotient chunk should be opened at 1 point")
@@ -134,13 +154,8 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
     }
 }
 
-fn collect_trace_openings<Challenge: Debug>(
+fn collect_trace_openings<Challenge>(
     ops: Vec<Vec<Vec<Challenge>>>,
 ) -> Vec<AdjacentOpenedValues<Challenge>> {
-    ops.into_iter()
-        .map(|op| {
-            let [local, next] = op.try_into().expect("Should have 2 openings");
-            AdjacentOpenedValues { local, next }
-        })
-        .collect()
+    ops.into_iter().map(AdjacentOpenedValues::from_rows).collect()
 }
@@ -15,40 +15,52 @@ use crate::{
     config::{PackedChallenge, PackedVal, StarkGenericConfig, Val},
 };
 
-pub(super) struct ViewPair<T> {
-    pub(super) local: Vec<T>,
-    pub(super) next: Option<Vec<T>>,
+/// Buffers for the rows of a matrix at rotations `0, 1, ..., rows.len() - 1` from the current row.
+pub(super) struct RowWindow<T> {
+    pub(super) rows: Vec<Vec<T>>,
 }
 
-impl<T> ViewPair<T> {
-    pub fn new(local: Vec<T>, next: Option<Vec<T>>) -> Self {
-        Self { local, next }
+impl<T> RowWindow<T> {
+    /// Allocates `num_rows` row buffers of length `width` without initializing them.
+    ///
+    /// # Safety
+    /// Every element must be written before it is read.
+    #[allow(clippy::uninit_vec)]
+    pub unsafe fn new_uninit(width: usize, num_rows: usize) -> Self {
+        let rows = (0..num_rows)
+            .map(|_| {
+                let mut row = Vec::with_capacity(width);
+                row.set_len(width);
+                row
+            })
+            .collect();
+        Self { rows }
     }
 
     /// SAFETY: no matrix bounds checks are done.
     pub unsafe fn get(&self, row_offset: usize, column_idx: usize) -> &T {
-        match row_offset {
-            0 => self.local.get_unchecked(column_idx),
-            1 => self
-                .next
-                .as_ref()
-                .unwrap_unchecked()
-                .get_unchecked(column_idx),
-            _ => panic!("row offset {row_offset} not supported"),
-        }
+        self.rows
+            .get_unchecked(row_offset)
+            .get_unchecked(column_idx)
     }
 }
 
 /// A struct for quotient polynomial evaluation. This evaluates `WIDTH` rows of the quotient polynomial
 /// simultaneously using SIMD (if target arch allows it) via `PackedVal` and `PackedChallenge` types.
 pub(super) struct ProverConstraintEvaluator<'a, SC: StarkGenericConfig> {
-    pub preprocessed: &'a ViewPair<PackedVal<SC>>,
-    pub partitioned_main: &'a [ViewPair<PackedVal<SC>>],
-    pub after_challenge: &'a [ViewPair<PackedChallenge<SC>>],
+    pub preprocessed: &'a RowWindow<PackedVal<SC>>,
+    pub partitioned_main: &'a [RowWindow<PackedVal<SC>>],
+    /// The after challenge trace of the AIR in each challenge phase. An AIR has a single after
+    /// challenge matrix per phase, independently of how the matrices of the phase are grouped
+    /// into commitments, so [Entry::Permutation] of the only phase reads the first window.
+    pub after_challenge: &'a [RowWindow<PackedChallenge<SC>>],
     pub challenges: &'a [Vec<PackedChallenge<SC>>],
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
     pub is_transition: PackedVal<SC>,
+    /// Transition window selectors, indexed by window size. Only the sizes used by the
+    /// constraints are set.
+    pub is_transition_window: &'a [PackedVal<SC>],
     pub public_values: &'a [Val<SC>],
     pub exposed_values_after_challenge: &'a [Vec<PackedChallenge<SC>>],
 }
@@ -132,6 +144,9 @@ where
     fn eval_is_transition(&self) -> PackedExpr<SC> {
         PackedExpr::Val(self.is_transition)
     }
+    fn eval_is_transition_window(&self, size: usize) -> PackedExpr<SC> {
+        PackedExpr::Val(self.is_transition_window[size])
+    }
 
     /// SAFETY: we only use this trait implementation when we have already done
     /// a previous scan to ensure all matrix bounds are satisfied,
@@ -168,55 +183,137 @@ where
 }
 
 impl<SC: StarkGenericConfig> ProverConstraintEvaluator<'_, SC> {
+    /// Evaluates `node` from the values of its children in `exprs`.
+    ///
+    /// # Safety
+    /// The children of `node` must be set in `exprs`.
+    unsafe fn eval_node(
+        &self,
+        node: &SymbolicExpressionNode<Val<SC>>,
+        exprs: &[PackedExpr<SC>],
+    ) -> PackedExpr<SC> {
+        match *node {
+            SymbolicExpressionNode::Variable(var) => self.eval_var(var),
+            SymbolicExpressionNode::Constant(c) => self.eval_const(c),
+            SymbolicExpressionNode::Add {
+                left_idx,
+                right_idx,
+                ..
+            } => exprs.get_unchecked(left_idx).clone() + exprs.get_unchecked(right_idx).clone(),
+            SymbolicExpressionNode::Sub {
+                left_idx,
+                right_idx,
+                ..
+            } => exprs.get_unchecked(left_idx).clone() - exprs.get_unchecked(right_idx).clone(),
+            SymbolicExpressionNode::Neg { idx, .. } => -exprs.get_unchecked(idx).clone(),
+            SymbolicExpressionNode::Mul {
+                left_idx,
+                right_idx,
+                ..
+            } => exprs.get_unchecked(left_idx).clone() * exprs.get_unchecked(right_idx).clone(),
+            SymbolicExpressionNode::IsFirstRow => self.eval_is_first_row(),
+            SymbolicExpressionNode::IsLastRow => self.eval_is_last_row(),
+            SymbolicExpressionNode::IsTransition => self.eval_is_transition(),
+            SymbolicExpressionNode::IsTransitionWindow(size) => {
+                self.eval_is_transition_window(size)
+            }
+        }
+    }
+
+    /// Evaluates all `nodes` in order, storing the value of each node at its index in `exprs`.
+    ///
     /// # Safety
     /// - The `nodes` must already be topologically sorted, so they only reference previous nodes.
-    /// - `exprs` should have capacity at least `constraints.nodes.len()`.
+    /// - `exprs` should have capacity at least `nodes.len()`.
     unsafe fn eval_nodes_mut(
         &self,
         nodes: &[SymbolicExpressionNode<Val<SC>>],
         exprs: &mut Vec<PackedExpr<SC>>,
-    ) where
-        PackedExpr<SC>: Clone,
-    {
+    ) {
         debug_assert!(exprs.capacity() >= nodes.len());
-        // SAFETY: we will set all `exprs` in the loop; this is to make debug assertions happy for `exprs.get_unchecked`.
+        // SAFETY: every node is set before it is read; this is to make debug assertions happy for
+        // `exprs.get_unchecked`.
         unsafe {
             exprs.set_len(nodes.len());
         }
-        let mut expr_ptr = exprs.as_mut_ptr();
-        for node in nodes.iter() {
-            // SAFETY: dereference raw pointer `expr_ptr` because we assume `exprs` has enough capacity.
-            *expr_ptr = match *node {
-                SymbolicExpressionNode::Variable(var) => self.eval_var(var),
-                SymbolicExpressionNode::Constant(c) => self.eval_const(c),
-                SymbolicExpressionNode::Add {
-                    left_idx,
-                    right_idx,
-                    ..
-                } => exprs.get_unchecked(left_idx).clone() + exprs.get_unchecked(right_idx).clone(),
-                SymbolicExpressionNode::Sub {
-                    left_idx,
-                    right_idx,
-                    ..
-                } => exprs.get_unchecked(left_idx).clone() - exprs.get_unchecked(right_idx).clone(),
-                SymbolicExpressionNode::Neg { idx, .. } => -exprs.get_unchecked(idx).clone(),
-                SymbolicExpressionNode::Mul {
-                    left_idx,
-                    right_idx,
-                    ..
-                } => exprs.get_unchecked(left_idx).clone() * exprs.get_unchecked(right_idx).clone(),
-                SymbolicExpressionNode::IsFirstRow => self.eval_is_first_row(),
-                SymbolicExpressionNode::IsLastRow => self.eval_is_last_row(),
-                SymbolicExpressionNode::IsTransition => self.eval_is_transition(),
-            };
-            expr_ptr = expr_ptr.add(1);
+        for (idx, node) in nodes.iter().enumerate() {
+            let value = self.eval_node(node, exprs);
+            exprs.as_mut_ptr().add(idx).write(value);
         }
     }
 
-    /// `alpha_powers` are in **increasing** order of powers, `alpha^0, alpha^1, ...`
+    /// Evaluates the node with index `idx` by recursively evaluating its children, without storing
+    /// the values of intermediate nodes. Nodes used by multiple parents are evaluated once per use.
+    ///
+    /// # Safety
+    /// - The `nodes` must already be topologically sorted, so they only reference previous nodes.
+    /// - `idx < nodes.len()`.
+    unsafe fn eval_node_recursive(
+        &self,
+        nodes: &[SymbolicExpressionNode<Val<SC>>],
+        idx: usize,
+    ) -> PackedExpr<SC> {
+        match *nodes.get_unchecked(idx) {
+            SymbolicExpressionNode::Variable(var) => self.eval_var(var),
+            SymbolicExpressionNode::Constant(c) => self.eval_const(c),
+            SymbolicExpressionNode::Add {
+                left_idx,
+                right_idx,
+                ..
+            } => {
+                self.eval_node_recursive(nodes, left_idx)
+                    + self.eval_node_recursive(nodes, right_idx)
+            }
+            SymbolicExpressionNode::Sub {
+                left_idx,
+                right_idx,
+                ..
+            } => {
+                self.eval_node_recursive(nodes, left_idx)
+                    - self.eval_node_recursive(nodes, right_idx)
+            }
+            SymbolicExpressionNode::Neg { idx, .. } => -self.eval_node_recursive(nodes, idx),
+            SymbolicExpressionNode::Mul {
+                left_idx,
+                right_idx,
+                ..
+            } => {
+                self.eval_node_recursive(nodes, left_idx)
+                    * self.eval_node_recursive(nodes, right_idx)
+            }
+            SymbolicExpressionNode::IsFirstRow => self.eval_is_first_row(),
+            SymbolicExpressionNode::IsLastRow => self.eval_is_last_row(),
+            SymbolicExpressionNode::IsTransition => self.eval_is_transition(),
+            SymbolicExpressionNode::IsTransitionWindow(size) => {
+                self.eval_is_transition_window(size)
+            }
+        }
+    }
+
+    /// Folds the values in `exprs` of the constraints with `coefficients`.
+    ///
+    /// # Safety
+    /// The values of the constraint nodes must be set in `exprs`.
+    unsafe fn fold_constraints(
+        constraints: &SymbolicExpressionDag<Val<SC>>,
+        coefficients: &[PackedChallenge<SC>],
+        exprs: &[PackedExpr<SC>],
+    ) -> PackedChallenge<SC> {
+        debug_assert!(coefficients.len() >= constraints.constraint_idx.len());
+        let mut accumulator = PackedChallenge::<SC>::ZERO;
+        for (&coefficient, &node_idx) in zip(coefficients, &constraints.constraint_idx) {
+            match *exprs.get_unchecked(node_idx) {
+                PackedExpr::Val(x) => accumulator += coefficient * x,
+                PackedExpr::Challenge(x) => accumulator += coefficient * x,
+            }
+        }
+        accumulator
+    }
+
+    /// Folds the constraints with `coefficients`, one per constraint in order.
     ///
     /// # Panics
-    /// If `alpha_powers.len() < constraints.constraint_idx.len()`.
+    /// If `coefficients.len() < constraints.constraint_idx.len()`.
     ///
     /// # Safety
     /// - The `nodes` must already be topologically sorted, so they only reference previous nodes.
@@ -225,18 +322,30 @@ impl<SC: StarkGenericConfig> ProverConstraintEvaluator<'_, SC> {
     pub unsafe fn accumulate(
         &self,
         constraints: &SymbolicExpressionDag<Val<SC>>,
-        alpha_powers: &[PackedChallenge<SC>],
+        coefficients: &[PackedChallenge<SC>],
         exprs: &mut Vec<PackedExpr<SC>>,
     ) -> PackedChallenge<SC> {
-        debug_assert!(alpha_powers.len() >= constraints.constraint_idx.len());
-        // We want alpha powers to have highest power first, because of how accumulator "folding" works
-        // So this will be alpha^{num_constraints - 1}, ..., alpha^0
         self.eval_nodes_mut(&constraints.nodes, exprs);
+        Self::fold_constraints(constraints, coefficients, exprs)
+    }
+
+    /// Same as [accumulate](Self::accumulate), but evaluates each constraint directly from its
+    /// root node instead of interpreting the whole DAG, so no buffer of node values is needed.
+    /// This is faster for AIRs with few constraints.
+    ///
+    /// # Safety
+    /// - The `nodes` must already be topologically sorted, so they only reference previous nodes.
+    pub unsafe fn accumulate_direct(
+        &self,
+        constraints: &SymbolicExpressionDag<Val<SC>>,
+        coefficients: &[PackedChallenge<SC>],
+    ) -> PackedChallenge<SC> {
+        debug_assert!(coefficients.len() >= constraints.constraint_idx.len());
         let mut accumulator = PackedChallenge::<SC>::ZERO;
-        for (&alpha_pow, &node_idx) in zip(alpha_powers, constraints.constraint_idx.iter().rev()) {
-            match *exprs.get_unchecked(node_idx) {
-                PackedExpr::Val(x) => accumulator += alpha_pow * x,
-                PackedExpr::Challenge(x) => accumulator += alpha_pow * x,
+        for (&coefficient, &node_idx) in zip(coefficients, &constraints.constraint_idx) {
+            match self.eval_node_recursive(&constraints.nodes, node_idx) {
+                PackedExpr::Val(x) => accumulator += coefficient * x,
+                PackedExpr::Challenge(x) => accumulator += coefficient * x,
             }
         }
         accumulator
@@ -0,0 +1,157 @@
+//! Prover-side sanity check that the quotient polynomial of each RAP is consistent with its
+//! constraints, i.e., that `constraints(x) = quotient(x) * Z_H(x)` at an out-of-domain point `x`.
+//!
+//! This is a debugging aid to catch trace-vs-constraint mismatches before producing a proof.
+//! It performs the same check as the verifier, with trace and quotient polynomials evaluated
+//! directly by interpolation instead of through PCS openings.
+
+use std::iter::{self, zip};
+
+use itertools::Itertools;
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{batch_multiplicative_inverse, Field, FieldAlgebra};
+use p3_matrix::Matrix;
+
+use super::{QuotientCommitter, QuotientData};
+use crate::{
+    air_builders::symbolic::SymbolicExpressionDag,
+    config::{Domain, StarkGenericConfig, Val},
+    proof::AdjacentOpenedValues,
+    prover::types::RapView,
+    utils::quotient_domain,
+    verifier::{constraints::verify_single_rap_constraints, VerificationError},
+};
+
+/// The trace polynomials of a single RAP evaluated at an out-of-domain point and at the next
+/// point of the trace domain, together with the other data needed to evaluate its constraints.
+pub struct OpenedRapView<SC: StarkGenericConfig> {
+    point: SC::Challenge,
+    trace_domain: Domain<SC>,
+    preprocessed: Option<AdjacentOpenedValues<SC::Challenge>>,
+    partitioned_main: Vec<AdjacentOpenedValues<SC::Challenge>>,
+    after_challenge: Vec<AdjacentOpenedValues<SC::Challenge>>,
+    challenges: Vec<Vec<SC::Challenge>>,
+    exposed_values_after_challenge: Vec<Vec<SC::Challenge>>,
+    public_values: Vec<Val<SC>>,
+}
+
+impl<SC: StarkGenericConfig> QuotientCommitter<'_, SC> {
+    /// Evaluates the trace polynomials in `view` at `point` and at the following points of the
+    /// trace domain, `num_rotations` points in total. The matrices in `view` must be evaluations
+    /// on the quotient domain of the RAP, in natural order.
+    pub fn open_rap_view<M: Matrix<Val<SC>>>(
+        &self,
+        view: &RapView<M, Val<SC>, SC::Challenge>,
+        quotient_degree: u8,
+        point: SC::Challenge,
+        num_rotations: usize,
+    ) -> OpenedRapView<SC> {
+        let trace_domain = self
+            .pcs
+            .natural_domain_for_degree(1usize << view.log_trace_height);
+        let quotient_domain = quotient_domain(trace_domain, quotient_degree);
+        let points = iter::successors(Some(point), |&x| trace_domain.next_point(x))
+            .take(num_rotations)
+            .collect_vec();
+        assert_eq!(points.len(), num_rotations, "trace domain should have next points");
+        let open = |matrix: &M| {
+            AdjacentOpenedValues::from_rows(
+                points
+                    .iter()
+                    .map(|&x| interpolate_at_point::<SC>(quotient_domain, matrix, x))
+                    .collect(),
+            )
+        };
+        OpenedRapView {
+            point,
+            trace_domain,
+            preprocessed: view.preprocessed.as_ref().map(open),
+            partitioned_main: view.partitioned_main.iter().map(open).collect(),
+            after_challenge: view
+                .per_phase
+                .iter()
+                .map(|phase| {
+                    open(
+                        phase
+                            .inner
+                            .as_ref()
+                            .expect("gap in challenge phase not supported yet"),
+                    )
+                })
+                .collect(),
+            challenges: view
+                .per_phase
+                .iter()
+                .map(|phase| phase.challenges.clone())
+                .collect(),
+            exposed_values_after_challenge: view
+                .per_phase
+                .iter()
+                .map(|phase| phase.exposed_values.clone())
+                .collect(),
+            public_values: view.public_values.clone(),
+        }
+    }
+
+    /// Checks that the folded constraints of the RAP with index `air_idx` in `data` equal its
+    /// quotient polynomial times the vanishing polynomial of the trace domain, at the point of
+    /// `opened`.
+    pub fn verify_quotient_identity(
+        &self,
+        constraints: &SymbolicExpressionDag<Val<SC>>,
+        opened: OpenedRapView<SC>,
+        data: &QuotientData<SC>,
+        air_idx: usize,
+    ) -> Result<(), VerificationError> {
+        let chunks = &data.chunks_per_air[air_idx];
+        let qc_domains = chunks.iter().map(|chunk| chunk.domain).collect_vec();
+        let quotient_chunks = chunks
+            .iter()
+            .map(|chunk| interpolate_at_point::<SC>(chunk.domain, &chunk.matrix, opened.point))
+            .collect_vec();
+        verify_single_rap_constraints::<SC>(
+            constraints,
+            opened.preprocessed.as_ref(),
+            opened.partitioned_main.iter().collect(),
+            opened.after_challenge.iter().collect(),
+            &quotient_chunks,
+            opened.trace_domain,
+            &qc_domains,
+            opened.point,
+            self.alpha(air_idx),
+            self.accumulation.as_ref(),
+            &opened.challenges,
+            &opened.public_values,
+            &opened.exposed_values_after_challenge,
+        )
+    }
+}
+
+/// Evaluates at `point` each column of `evals`, viewed as the evaluations on `domain` (in natural
+/// order) of a polynomial of degree less than `domain.size()`.
+///
+/// Assumes `domain` is a multiplicative coset `sH`, whose Lagrange basis is
+/// `L_i(z) = Z(z) * x_i / (|H| * (z - x_i))` with `Z(z) = (z / s)^|H| - 1`.
+fn interpolate_at_point<SC: StarkGenericConfig>(
+    domain: Domain<SC>,
+    evals: &impl Matrix<Val<SC>>,
+    point: SC::Challenge,
+) -> Vec<SC::Challenge> {
+    let size = domain.size();
+    assert_eq!(evals.height(), size);
+    let domain_points = iter::successors(Some(domain.first_point()), |&x| domain.next_point(x))
+        .take(size)
+        .collect_vec();
+    let inv_denoms =
+        batch_multiplicative_inverse(&domain_points.iter().map(|&x| point - x).collect_vec());
+    let scale = domain.zp_at_point(point) * Val::<SC>::from_canonical_usize(size).inverse();
+
+    let mut res = vec![SC::Challenge::ZERO; evals.width()];
+    for ((row, x), inv_denom) in zip(zip(evals.rows(), domain_points), inv_denoms) {
+        let weight = inv_denom * x;
+        for (acc, value) in zip(&mut res, row) {
+            *acc += weight * value;
+        }
+    }
+    res.into_iter().map(|v| v * scale).collect()
+}
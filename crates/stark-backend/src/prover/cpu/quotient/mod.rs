@@ -1,27 +1,122 @@
-use std::sync::Arc;
+#[cfg(any(test, feature = "test-utils"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    collections::HashMap,
+    iter::zip,
+    sync::{Arc, Mutex},
+};
 
 use itertools::{izip, multiunzip, Itertools};
-use p3_commit::{Pcs, PolynomialSpace};
+use p3_commit::{LagrangeSelectors, Pcs, PolynomialSpace};
 use p3_field::FieldAlgebra;
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use self::single::compute_single_rap_quotient_values;
-use super::PcsData;
+use super::{CommitBatchesFn, PcsData};
 use crate::{
+    accumulation::{AccumulationStrategy, AlphaPowers},
     air_builders::symbolic::SymbolicExpressionDag,
     config::{Com, Domain, PackedChallenge, StarkGenericConfig, Val},
     prover::types::RapView,
+    utils::{metrics_span_with_labels, quotient_domain, MetricLabels},
 };
 
 mod evaluator;
+mod identity;
 pub(crate) mod single;
 
+pub use identity::OpenedRapView;
+
+/// Default maximum number of constraints of an AIR for its quotient polynomial to be evaluated
+/// with [QuotientEvalStrategy::Direct].
+pub const DEFAULT_DIRECT_EVAL_MAX_CONSTRAINTS: usize = 8;
+
+/// How the constraints of a RAP are evaluated on each row of the quotient domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotientEvalStrategy {
+    /// Each constraint is evaluated recursively from its root node. Subexpressions shared by
+    /// multiple constraints are re-evaluated, but no node values are stored.
+    Direct,
+    /// The whole constraint DAG is interpreted in topological order, storing the value of every
+    /// node.
+    Dag,
+}
+
+/// Number of RAPs whose quotient polynomial was evaluated with each [QuotientEvalStrategy]. The
+/// counts are only kept with the `test-utils` feature, and recording is a no-op otherwise.
+#[derive(Debug, Default)]
+pub struct QuotientEvalStats {
+    #[cfg(any(test, feature = "test-utils"))]
+    direct: AtomicUsize,
+    #[cfg(any(test, feature = "test-utils"))]
+    dag: AtomicUsize,
+    #[cfg(any(test, feature = "test-utils"))]
+    selectors: AtomicUsize,
+}
+
+#[cfg(not(any(test, feature = "test-utils")))]
+impl QuotientEvalStats {
+    pub fn record(&self, _strategy: QuotientEvalStrategy) {}
+
+    pub fn record_selectors(&self) {}
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl QuotientEvalStats {
+    pub fn record(&self, strategy: QuotientEvalStrategy) {
+        let counter = match strategy {
+            QuotientEvalStrategy::Direct => &self.direct,
+            QuotientEvalStrategy::Dag => &self.dag,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self, strategy: QuotientEvalStrategy) -> usize {
+        match strategy {
+            QuotientEvalStrategy::Direct => self.direct.load(Ordering::Relaxed),
+            QuotientEvalStrategy::Dag => self.dag.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn record_selectors(&self) {
+        self.selectors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of times the selectors of a trace domain on its quotient domain were computed,
+    /// including the batch inverse of the zeroifier. RAPs with equal trace heights and quotient
+    /// degrees share their selectors.
+    pub fn num_selector_computations(&self) -> usize {
+        self.selectors.load(Ordering::Relaxed)
+    }
+}
+
+/// Computes and commits to the quotient polynomials of RAPs.
+///
+/// The quotient polynomials are committed with the same PCS, and hence the same MMCS, as the trace
+/// matrices. This is required because all commitments of a proof are opened together in a single
+/// batched PCS opening proof, which uses one MMCS for every commitment.
 pub struct QuotientCommitter<'pcs, SC: StarkGenericConfig> {
     pcs: &'pcs SC::Pcs,
     alpha: SC::Challenge,
+    /// If non-empty, the `alpha` of each RAP, overriding `alpha`.
+    alpha_per_air: Vec<SC::Challenge>,
+    /// Folds the constraints of each RAP with its `alpha`.
+    accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
     extra_capacity_bits: usize,
+    /// Number of SIMD-packed rows processed per parallel batch when evaluating the quotient
+    /// polynomial, in units of `PackedVal::<SC>::WIDTH` rows.
+    batch_multiplier: usize,
+    /// RAPs with at most this many constraints use [QuotientEvalStrategy::Direct].
+    direct_eval_max_constraints: usize,
+    eval_stats: Arc<QuotientEvalStats>,
+    /// If set, the maximum number of chunks the quotient polynomial of each RAP is split into.
+    max_chunks: Option<usize>,
+    /// If set, the commitments of different groups are computed in parallel with it.
+    parallel_commit: Option<CommitBatchesFn<SC>>,
+    /// Selectors on the quotient domain, by log trace height and quotient degree.
+    selectors: Mutex<HashMap<(u8, u8), Arc<LagrangeSelectors<Vec<Val<SC>>>>>>,
 }
 
 impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
@@ -29,10 +124,149 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
         Self {
             pcs,
             alpha,
+            alpha_per_air: vec![],
+            accumulation: Arc::new(AlphaPowers),
             extra_capacity_bits,
+            batch_multiplier: 1,
+            direct_eval_max_constraints: DEFAULT_DIRECT_EVAL_MAX_CONSTRAINTS,
+            eval_stats: Arc::default(),
+            max_chunks: None,
+            parallel_commit: None,
+            selectors: Mutex::default(),
+        }
+    }
+
+    /// Folds the constraints of the `i`-th RAP with `alpha_per_air[i]` instead of the shared
+    /// `alpha`. An empty `alpha_per_air` restores the shared `alpha`.
+    pub fn with_alpha_per_air(mut self, alpha_per_air: Vec<SC::Challenge>) -> Self {
+        self.alpha_per_air = alpha_per_air;
+        self
+    }
+
+    /// Folds the constraints of each RAP with `accumulation` instead of the default
+    /// [AlphaPowers]. The verifier must use the same strategy.
+    pub fn with_accumulation(
+        mut self,
+        accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
+    ) -> Self {
+        self.accumulation = accumulation;
+        self
+    }
+
+    /// The `alpha` used to fold the constraints of the RAP with index `air_idx`.
+    pub fn alpha(&self, air_idx: usize) -> SC::Challenge {
+        if self.alpha_per_air.is_empty() {
+            self.alpha
+        } else {
+            self.alpha_per_air[air_idx]
+        }
+    }
+
+    /// Sets the number of rows evaluated per parallel batch to `batch_multiplier *
+    /// PackedVal::<SC>::WIDTH`, capped by the trace height. Larger batches reduce scheduling
+    /// overhead at the cost of load balancing. The default is `1`.
+    ///
+    /// The quotient values do not depend on the batch size.
+    ///
+    /// # Panics
+    /// If `batch_multiplier` is not a power of two.
+    pub fn with_batch_multiplier(mut self, batch_multiplier: usize) -> Self {
+        assert!(
+            batch_multiplier.is_power_of_two(),
+            "batch multiplier must be a power of two"
+        );
+        self.batch_multiplier = batch_multiplier;
+        self
+    }
+
+    /// RAPs with at most `direct_eval_max_constraints` constraints are evaluated with
+    /// [QuotientEvalStrategy::Direct], and all others with [QuotientEvalStrategy::Dag]. The default
+    /// is [DEFAULT_DIRECT_EVAL_MAX_CONSTRAINTS].
+    ///
+    /// The quotient values do not depend on the strategy.
+    pub fn with_direct_eval_max_constraints(mut self, direct_eval_max_constraints: usize) -> Self {
+        self.direct_eval_max_constraints = direct_eval_max_constraints;
+        self
+    }
+
+    /// Records the strategy used for each RAP in `eval_stats`.
+    pub fn with_eval_stats(mut self, eval_stats: Arc<QuotientEvalStats>) -> Self {
+        self.eval_stats = eval_stats;
+        self
+    }
+
+    /// Splits the quotient polynomial of each RAP into at most `max_chunks` chunks, instead of
+    /// `quotient_degree` chunks of the size of the trace domain. Fewer, larger chunks reduce the
+    /// number of committed matrices, while each chunk is committed on a larger domain. By default
+    /// the number of chunks is not capped.
+    ///
+    /// # Transcript
+    /// If the quotient polynomial of some AIR is split into fewer than `quotient_degree` chunks,
+    /// the number of chunks of every AIR is observed right before the quotient commitments. This
+    /// changes the transcript, so such proofs are only accepted by verifiers aware of it. Proofs
+    /// with the default chunking have the same transcript as before.
+    ///
+    /// # Panics
+    /// If `max_chunks` is not a power of two.
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+        assert!(
+            max_chunks.is_power_of_two(),
+            "maximum number of quotient chunks must be a power of two"
+        );
+        self.max_chunks = Some(max_chunks);
+        self
+    }
+
+    /// The number of chunks the quotient polynomial of a RAP with `quotient_degree` is split into.
+    pub fn num_chunks(&self, quotient_degree: u8) -> u8 {
+        match self.max_chunks {
+            Some(max_chunks) if max_chunks < quotient_degree as usize => max_chunks as u8,
+            _ => quotient_degree,
         }
     }
 
+    /// Computes the commitments of different groups in [commit_grouped](Self::commit_grouped)
+    /// with `parallel_commit` if set. The commitments do not depend on this.
+    pub(crate) fn with_parallel_commit(
+        mut self,
+        parallel_commit: Option<CommitBatchesFn<SC>>,
+    ) -> Self {
+        self.parallel_commit = parallel_commit;
+        self
+    }
+
+    /// The strategy used to evaluate `constraints` on the quotient domain.
+    pub fn eval_strategy(
+        &self,
+        constraints: &SymbolicExpressionDag<Val<SC>>,
+    ) -> QuotientEvalStrategy {
+        if constraints.constraint_idx.len() <= self.direct_eval_max_constraints {
+            QuotientEvalStrategy::Direct
+        } else {
+            QuotientEvalStrategy::Dag
+        }
+    }
+
+    /// The selectors of the trace domain with `log_trace_height` on its quotient domain for
+    /// `quotient_degree`. These are computed once and shared by all RAPs with the same trace
+    /// height and quotient degree.
+    fn selectors(
+        &self,
+        log_trace_height: u8,
+        quotient_degree: u8,
+        trace_domain: Domain<SC>,
+        quotient_domain: Domain<SC>,
+    ) -> Arc<LagrangeSelectors<Vec<Val<SC>>>> {
+        let mut selectors = self.selectors.lock().unwrap();
+        let sels = selectors
+            .entry((log_trace_height, quotient_degree))
+            .or_insert_with(|| {
+                self.eval_stats.record_selectors();
+                Arc::new(trace_domain.selectors_on_coset(quotient_domain))
+            });
+        sels.clone()
+    }
+
     /// Constructs quotient domains and computes the evaluation of the quotient polynomials
     /// on the quotient domains of each RAP.
     ///
@@ -40,6 +274,7 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
     /// - `constraints`, `extended_views`, `quotient_degrees` have equal lengths and the length equals number of RAPs.
     /// - `quotient_degrees` is the factor to **multiply** the trace degree by to get the degree of the quotient polynomial. This should be determined from the constraint degree of the RAP.
     /// - `extended_views` is a view of the trace polynomials evaluated on the quotient domain, with rows bit reversed to account for the fact that the quotient domain is different for each RAP.
+    /// - `air_labels` has the metric labels of each RAP, attached to its quotient computation time.
     ///
     /// **Note**: This function assumes that the
     /// `quotient_domain.split_evals(quotient_degree, quotient_flat)` function from Plonky3 works
@@ -50,31 +285,34 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
         constraints: &[&SymbolicExpressionDag<Val<SC>>],
         extended_views: Vec<RapView<impl Matrix<Val<SC>>, Val<SC>, SC::Challenge>>,
         quotient_degrees: &[u8],
+        air_labels: &[MetricLabels],
     ) -> QuotientData<SC> {
-        let max_alpha_pow = constraints
-            .iter()
-            .map(|c| c.constraint_idx.len())
-            .max()
-            .unwrap_or(0);
-        let alpha_powers = self
-            .alpha
-            .powers()
-            .take(max_alpha_pow)
-            .map(PackedChallenge::<SC>::from_f)
-            .collect_vec();
         assert_eq!(constraints.len(), extended_views.len());
         assert_eq!(constraints.len(), quotient_degrees.len());
-        let chunks = izip!(constraints, extended_views, quotient_degrees)
-            .flat_map(|(constraints, extended_view, &quotient_degree)| {
-                self.single_rap_quotient_values(
-                    constraints,
-                    extended_view,
-                    quotient_degree,
-                    &alpha_powers,
-                )
+        assert_eq!(constraints.len(), air_labels.len());
+        let chunks_per_air = izip!(constraints, extended_views, quotient_degrees, air_labels)
+            .enumerate()
+            .map(|(air_idx, (constraints, extended_view, &quotient_degree, labels))| {
+                let coefficients = self
+                    .accumulation
+                    .coefficients(self.alpha(air_idx), constraints.constraint_idx.len())
+                    .into_iter()
+                    .map(PackedChallenge::<SC>::from_f)
+                    .collect_vec();
+                metrics_span_with_labels("quotient_poly_compute_time_ms", labels, || {
+                    self.single_rap_quotient_values(
+                        constraints,
+                        extended_view,
+                        quotient_degree,
+                        self.num_chunks(quotient_degree),
+                        &coefficients,
+                    )
+                    .into_iter()
+                    .collect_vec()
+                })
             })
             .collect();
-        QuotientData { chunks }
+        QuotientData { chunks_per_air }
     }
 
     pub(super) fn single_rap_quotient_values(
@@ -82,14 +320,22 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
         constraints: &SymbolicExpressionDag<Val<SC>>,
         view: RapView<impl Matrix<Val<SC>>, Val<SC>, SC::Challenge>,
         quotient_degree: u8,
-        alpha_powers: &[PackedChallenge<SC>],
+        num_chunks: u8,
+        coefficients: &[PackedChallenge<SC>],
     ) -> impl IntoIterator<Item = QuotientChunk<SC>> {
         let log_trace_height = view.log_trace_height;
         let trace_domain = self
             .pcs
             .natural_domain_for_degree(1usize << log_trace_height);
-        let quotient_domain =
-            trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree as usize);
+        let quotient_domain = quotient_domain(trace_domain, quotient_degree);
+        let sels = self.selectors(
+            log_trace_height,
+            quotient_degree,
+            trace_domain,
+            quotient_domain,
+        );
+        let eval_strategy = self.eval_strategy(constraints);
+        self.eval_stats.record(eval_strategy);
 
         let (after_challenge_lde_on_quotient_domain, challenges, exposed_values_after_challenge): (
             Vec<_>,
@@ -114,29 +360,81 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
             constraints,
             trace_domain,
             quotient_domain,
+            &sels,
+            num_chunks as usize,
             view.preprocessed,
             view.partitioned_main,
             after_challenge_lde_on_quotient_domain,
             &challenges,
-            alpha_powers,
+            coefficients,
             &view.public_values,
             &exposed_values_after_challenge,
             self.extra_capacity_bits,
+            self.batch_multiplier,
+            eval_strategy,
         )
     }
 
+    /// Commits to the quotient chunks of all RAPs in a single commitment.
     #[instrument(name = "commit to quotient poly chunks", skip_all)]
     pub fn commit(&self, data: QuotientData<SC>) -> (Com<SC>, PcsData<SC>) {
-        let (log_trace_heights, quotient_domains_and_chunks): (Vec<_>, Vec<_>) = data
-            .chunks
-            .into_iter()
-            .map(|q| {
-                (
-                    log2_strict_usize(q.domain.size()) as u8,
-                    (q.domain, q.matrix),
-                )
-            })
-            .unzip();
+        self.commit_chunks(data.chunks_per_air.into_iter().flatten().collect())
+    }
+
+    /// Commits to the quotient chunks in groups of RAPs, where the quotient chunks of the `i`-th
+    /// RAP are committed in the commitment with index `group_idx_per_air[i]`.
+    /// Within each commitment, the quotient chunks are ordered by RAP.
+    /// The commit time of each commitment is labeled with the `air_labels` of its RAPs.
+    #[instrument(name = "commit to quotient poly chunks", skip_all)]
+    pub fn commit_grouped(
+        &self,
+        data: QuotientData<SC>,
+        group_idx_per_air: &[usize],
+        air_labels: &[MetricLabels],
+    ) -> Vec<(Com<SC>, PcsData<SC>)> {
+        assert_eq!(data.chunks_per_air.len(), group_idx_per_air.len());
+        assert_eq!(air_labels.len(), group_idx_per_air.len());
+        let num_groups = group_idx_per_air.iter().max().map_or(0, |&g| g + 1);
+        let mut chunks_per_group: Vec<Vec<_>> = (0..num_groups).map(|_| Vec::new()).collect();
+        let mut labels_per_group: Vec<MetricLabels> = vec![vec![]; num_groups];
+        for (chunks, &group_idx, labels) in
+            izip!(data.chunks_per_air, group_idx_per_air, air_labels)
+        {
+            chunks_per_group[group_idx].extend(chunks);
+            // Label values of all AIRs in the group are joined by commas
+            let group_labels = &mut labels_per_group[group_idx];
+            for (key, value) in labels {
+                match group_labels.iter_mut().find(|(k, _)| k == key) {
+                    Some((_, v)) => *v = format!("{v},{value}"),
+                    None => group_labels.push((*key, value.clone())),
+                }
+            }
+        }
+        let Some(commit_batches) = self.parallel_commit else {
+            return zip(chunks_per_group, labels_per_group)
+                .map(|(chunks, labels)| {
+                    metrics_span_with_labels("quotient_poly_commit_time_ms", &labels, || {
+                        self.commit_chunks(chunks)
+                    })
+                })
+                .collect();
+        };
+        // The commitments run concurrently, so their total time is reported with the labels of
+        // all groups
+        let labels = labels_per_group.concat();
+        metrics_span_with_labels("quotient_poly_commit_time_ms", &labels, || {
+            let (log_trace_heights_per_group, chunks_per_group): (Vec<_>, Vec<_>) =
+                chunks_per_group.into_iter().map(split_chunks).unzip();
+            zip(commit_batches(self.pcs, chunks_per_group), log_trace_heights_per_group)
+                .map(|((commit, data), log_trace_heights)| {
+                    (commit, PcsData::new(Arc::new(data), log_trace_heights))
+                })
+                .collect()
+        })
+    }
+
+    fn commit_chunks(&self, chunks: Vec<QuotientChunk<SC>>) -> (Com<SC>, PcsData<SC>) {
+        let (log_trace_heights, quotient_domains_and_chunks) = split_chunks(chunks);
         let (commit, data) = self.pcs.commit(quotient_domains_and_chunks);
         (
             commit,
@@ -148,22 +446,39 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
     }
 }
 
+/// Splits quotient chunks into their log domain sizes and the evaluations to commit.
+#[allow(clippy::type_complexity)]
+fn split_chunks<SC: StarkGenericConfig>(
+    chunks: Vec<QuotientChunk<SC>>,
+) -> (Vec<u8>, Vec<(Domain<SC>, RowMajorMatrix<Val<SC>>)>) {
+    chunks
+        .into_iter()
+        .map(|q| {
+            (
+                log2_strict_usize(q.domain.size()) as u8,
+                (q.domain, q.matrix),
+            )
+        })
+        .unzip()
+}
+
 /// The quotient polynomials from multiple RAP matrices.
 pub struct QuotientData<SC: StarkGenericConfig> {
-    /// Length equals `quotient_ degree`.
-    chunks: Vec<QuotientChunk<SC>>,
+    /// For each RAP, the quotient chunks. The number of chunks divides the `quotient_degree` of
+    /// the RAP, see [QuotientCommitter::num_chunks].
+    chunks_per_air: Vec<Vec<QuotientChunk<SC>>>,
 }
 
 /// The vector of evaluations of the quotient polynomial on the quotient domain,
-/// split into chunks of size equal to the trace domain size (quotient domain size
-/// divided by `quotient_degree`).
+/// split into chunks of equal size (quotient domain size divided by the number of chunks).
+/// By default, there are `quotient_degree` chunks of the size of the trace domain.
 ///
 /// This represents a single chunk, where the vector of extension field elements is
 /// further flattened to a matrix of base field elements.
 pub struct QuotientChunk<SC: StarkGenericConfig> {
-    /// Chunk of quotient domain, which is a coset of the trace domain
+    /// Chunk of quotient domain, which is a coset of a subgroup containing the trace domain
     pub domain: Domain<SC>,
-    /// Matrix with number of rows equal to trace domain size,
+    /// Matrix with number of rows equal to the size of `domain`,
     /// and number of columns equal to extension field degree.
     pub matrix: RowMajorMatrix<Val<SC>>,
 }
@@ -1,17 +1,17 @@
 use std::{
     cmp::{max, min},
-    iter::zip,
+    iter::{self, zip},
 };
 
-use p3_commit::PolynomialSpace;
+use p3_commit::{LagrangeSelectors, PolynomialSpace};
 use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PackedValue};
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use super::{
-    evaluator::{ProverConstraintEvaluator, ViewPair},
-    QuotientChunk,
+    evaluator::{ProverConstraintEvaluator, RowWindow},
+    QuotientChunk, QuotientEvalStrategy,
 };
 use crate::{
     air_builders::symbolic::{
@@ -19,7 +19,7 @@ use crate::{
     },
     config::{Domain, PackedChallenge, PackedVal, StarkGenericConfig, Val},
     prover::cpu::transmute_to_base,
-    utils::parallelize_chunks,
+    utils::{parallelize_chunks, transition_window_selector_at_point},
 };
 
 // Starting reference: p3_uni_stark::prover::quotient_values
@@ -50,6 +50,9 @@ use crate::{
 /// [q_{0,trace_height - 1}]   [q_{1,trace_height - 1}]  ...  [q_{quotient_degree - 1,trace_height - 1}]
 /// ```
 /// where `q_{0,*}` and `q_{1,*}` are separate matrices. Each matrix is called a "chunk".
+///
+/// With `num_chunks < quotient_degree`, the evaluations are instead regrouped with stride
+/// `num_chunks` into `num_chunks` chunks of `quotient_size / num_chunks` rows each.
 #[allow(clippy::too_many_arguments)]
 #[instrument(
     name = "compute single RAP quotient polynomial",
@@ -60,16 +63,24 @@ pub fn compute_single_rap_quotient_values<'a, SC, M>(
     constraints: &SymbolicExpressionDag<Val<SC>>,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
+    // Selectors of the trace domain on the quotient domain
+    sels: &LagrangeSelectors<Vec<Val<SC>>>,
+    // Number of chunks the quotient polynomial is split into, dividing the quotient degree
+    num_chunks: usize,
     preprocessed_trace_on_quotient_domain: Option<M>,
     partitioned_main_lde_on_quotient_domain: Vec<M>,
     after_challenge_lde_on_quotient_domain: Vec<M>,
     // For each challenge round, the challenges drawn
     challenges: &'a [Vec<PackedChallenge<SC>>],
-    alpha_powers: &[PackedChallenge<SC>],
+    // Coefficient of each constraint in the folded constraint
+    coefficients: &[PackedChallenge<SC>],
     public_values: &'a [Val<SC>],
     // Values exposed to verifier after challenge round i
     exposed_values_after_challenge: &'a [Vec<PackedChallenge<SC>>],
     extra_capacity_bits: usize,
+    // Number of fat rows per parallel batch
+    batch_multiplier: usize,
+    eval_strategy: QuotientEvalStrategy,
 ) -> Vec<QuotientChunk<SC>>
 where
     SC: StarkGenericConfig,
@@ -87,15 +98,16 @@ where
         .as_ref()
         .map(|m| m.width())
         .unwrap_or(0);
-    let sels = trace_domain.selectors_on_coset(quotient_domain);
 
     let qdb = log2_strict_usize(quotient_size) - log2_strict_usize(trace_height);
     let quotient_degree = 1 << qdb;
     debug_assert_eq!(quotient_size, trace_height * quotient_degree);
+    assert!(num_chunks.is_power_of_two() && num_chunks <= quotient_degree);
+    let chunk_height = quotient_size / num_chunks;
 
     let ext_degree = SC::Challenge::D;
 
-    // Scan constraints to see if we need `next` row and also check index bounds
+    // Scan constraints for the rows after the current one that are needed, and check index bounds
     // so we don't need to check them per row.
     let mut rotation = 0;
     for node in &constraints.nodes {
@@ -151,82 +163,86 @@ where
             }
         }
     }
-    let needs_next = rotation > 0;
+    // Number of rows each constraint is evaluated on: the current row and the next `rotation` rows
+    let num_rows = rotation + 1;
 
-    let qc_domains = quotient_domain.split_domains(quotient_degree);
+    // Transition window selectors on the quotient domain, indexed by window size
+    let window_sizes = constraints.transition_window_sizes();
+    let mut window_sels = vec![vec![]; window_sizes.last().map_or(0, |&size| size + 1)];
+    if !window_sizes.is_empty() {
+        let points = iter::successors(Some(quotient_domain.first_point()), |&x| {
+            quotient_domain.next_point(x)
+        })
+        .take(quotient_size)
+        .collect::<Vec<_>>();
+        for &size in &window_sizes {
+            window_sels[size] = points
+                .iter()
+                .map(|&x| transition_window_selector_at_point(trace_domain, size, x))
+                .collect();
+        }
+    }
+
+    let qc_domains = quotient_domain.split_domains(num_chunks);
     qc_domains
         .into_iter()
         .enumerate()
         .map(|(chunk_idx, chunk_domain)| {
-            // This will be evaluations of the quotient poly on the `chunk_domain`, where `chunk_domain.size() = trace_height`. We reserve extra capacity for the coset lde in the pcs.commit of this chunk.
-            let mut chunk = SC::Challenge::zero_vec(trace_height << extra_capacity_bits);
-            chunk.truncate(trace_height);
-            // We parallel iterate over "fat" rows, which are consecutive rows packed for SIMD.
-            // If trace_height is smaller than PackedVal::<SC>::WIDTH, we just don't parallelize
-            let simd_width = min(trace_height, PackedVal::<SC>::WIDTH);
-            parallelize_chunks(&mut chunk, simd_width, |chunk, start_row_idx| {
+            // This will be evaluations of the quotient poly on the `chunk_domain`, where `chunk_domain.size() = chunk_height`. We reserve extra capacity for the coset lde in the pcs.commit of this chunk.
+            let mut chunk = SC::Challenge::zero_vec(chunk_height << extra_capacity_bits);
+            chunk.truncate(chunk_height);
+            // We parallel iterate over batches of `batch_multiplier` "fat" rows, where fat rows are
+            // consecutive rows packed for SIMD.
+            // If chunk_height is smaller than the batch size, we just don't parallelize
+            let batch_size = min(chunk_height, batch_multiplier * PackedVal::<SC>::WIDTH);
+            parallelize_chunks(&mut chunk, batch_size, |chunk, start_row_idx| {
                 debug_assert_eq!(start_row_idx % PackedVal::<SC>::WIDTH, 0);
 
-                // Pre-allocate vectors
-                let mut row_idx_local = Vec::with_capacity(PackedVal::<SC>::WIDTH);
-                let mut row_idx_next = Vec::with_capacity(PackedVal::<SC>::WIDTH);
-                // SAFETY: we will set these vectors to exactly this length in each inner loop per fat row
-                #[allow(clippy::uninit_vec)]
-                unsafe {
-                    row_idx_local.set_len(PackedVal::<SC>::WIDTH);
-                    row_idx_next.set_len(PackedVal::<SC>::WIDTH);
-                }
+                // Pre-allocate vectors: for each rotation, the indices of the packed rows
+                let mut row_idx_per_rotation = vec![vec![0; PackedVal::<SC>::WIDTH]; num_rows];
 
-                fn new_view_pair<T>(width: usize, needs_next: bool) -> ViewPair<T> {
-                    let mut local = Vec::with_capacity(width);
-                    // SAFETY: these vectors will always have a known width (the matrix width), and we
-                    // populate them with the appropriate values in each inner loop per fat row
-                    #[allow(clippy::uninit_vec)]
-                    unsafe {
-                        local.set_len(width);
-                    }
-                    let next = needs_next.then(|| {
-                        let mut next = Vec::with_capacity(width);
-                        #[allow(clippy::uninit_vec)]
-                        unsafe {
-                            next.set_len(width);
-                        }
-                        next
-                    });
-                    ViewPair::new(local, next)
-                }
-
-                let mut preprocessed_pair: ViewPair<PackedVal<SC>> =
-                    new_view_pair(preprocessed_width, needs_next);
-                let mut partitioned_main_pairs: Vec<ViewPair<PackedVal<SC>>> =
+                // SAFETY: these vectors will always have a known width (the matrix width), and we
+                // populate them with the appropriate values in each inner loop per fat row
+                let mut preprocessed_window: RowWindow<PackedVal<SC>> =
+                    unsafe { RowWindow::new_uninit(preprocessed_width, num_rows) };
+                let mut partitioned_main_windows: Vec<RowWindow<PackedVal<SC>>> =
                     partitioned_main_lde_on_quotient_domain
                         .iter()
-                        .map(|lde| new_view_pair(lde.width(), needs_next))
+                        .map(|lde| unsafe { RowWindow::new_uninit(lde.width(), num_rows) })
                         .collect();
-                let mut after_challenge_pairs: Vec<ViewPair<PackedChallenge<SC>>> =
+                let mut after_challenge_windows: Vec<RowWindow<PackedChallenge<SC>>> =
                     after_challenge_lde_on_quotient_domain
                         .iter()
-                        .map(|lde| new_view_pair(lde.width() / ext_degree, needs_next))
+                        .map(|lde| unsafe {
+                            RowWindow::new_uninit(lde.width() / ext_degree, num_rows)
+                        })
                         .collect();
-                let mut node_exprs = Vec::with_capacity(constraints.nodes.len());
+                let mut is_transition_window = vec![PackedVal::<SC>::ZERO; window_sels.len()];
+                // Direct evaluation does not store node values
+                let num_node_exprs = match eval_strategy {
+                    QuotientEvalStrategy::Direct => 0,
+                    QuotientEvalStrategy::Dag => constraints.nodes.len(),
+                };
+                let mut node_exprs = Vec::with_capacity(num_node_exprs);
 
-                // Use chunks instead of chunks_exact in case trace_height is not a multiple of PackedVal::WIDTH
+                // Use chunks instead of chunks_exact in case chunk_height is not a multiple of PackedVal::WIDTH
                 for (local_fat_row_idx, packed_ef_mut) in
                     chunk.chunks_mut(PackedVal::<SC>::WIDTH).enumerate()
                 {
                     let row_idx = start_row_idx + local_fat_row_idx * PackedVal::<SC>::WIDTH;
                     // `packed_ef_mut` is a vertical sub-column, index `offset` of `packed_ef_mut`
                     // is supposed to be the `chunk_row_idx = row_idx + offset` row of the chunk matrix
-                    // which is the `chunk_idx + chunk_row_idx * quotient_degree`th row of the evaluation of quotient polynomial on the quotient domain
+                    // which is the `chunk_idx + chunk_row_idx * num_chunks`th row of the evaluation of quotient polynomial on the quotient domain
                     // PERF[jpw]: This may not be cache friendly - would it be better to generate the quotient values in order first and then do some in-place permutation?
                     let quot_row_idx =
-                        |offset| (chunk_idx + (row_idx + offset) * quotient_degree) % quotient_size;
+                        |offset| (chunk_idx + (row_idx + offset) * num_chunks) % quotient_size;
 
-                    for (offset, (local, next)) in
-                        zip(&mut row_idx_local, &mut row_idx_next).enumerate()
-                    {
-                        *local = quot_row_idx(offset);
-                        *next = quot_row_idx(offset + 1);
+                    // The next row of the trace domain is `quotient_degree` rows further in the
+                    // quotient domain
+                    for (rot, wrapped_idx) in row_idx_per_rotation.iter_mut().enumerate() {
+                        for (offset, idx) in wrapped_idx.iter_mut().enumerate() {
+                            *idx = (quot_row_idx(offset) + rot * quotient_degree) % quotient_size;
+                        }
                     }
 
                     let is_first_row =
@@ -237,81 +253,82 @@ where
                         PackedVal::<SC>::from_fn(|offset| sels.is_transition[quot_row_idx(offset)]);
                     let inv_zeroifier =
                         PackedVal::<SC>::from_fn(|offset| sels.inv_zeroifier[quot_row_idx(offset)]);
+                    for &size in &window_sizes {
+                        let window_sel = &window_sels[size];
+                        is_transition_window[size] =
+                            PackedVal::<SC>::from_fn(|offset| window_sel[quot_row_idx(offset)]);
+                    }
 
-                    // Vertically pack rows of each matrix,
-                    // skipping `next` if above scan showed no constraints need it:
-                    for (wrapped_idx, row_buf) in [
-                        (&row_idx_local, Some(&mut preprocessed_pair.local)),
-                        (&row_idx_next, Option::as_mut(&mut preprocessed_pair.next)),
-                    ] {
-                        if let Some(row_buf) = row_buf {
-                            for (col, row_elt) in row_buf.iter_mut().enumerate() {
-                                *row_elt = PackedVal::<SC>::from_fn(|offset| unsafe {
-                                    preprocessed_trace_on_quotient_domain
-                                        .as_ref()
-                                        .unwrap_unchecked()
-                                        .get(*wrapped_idx.get_unchecked(offset), col)
-                                });
-                            }
+                    // Vertically pack rows of each matrix, only for the rotations which the above
+                    // scan showed the constraints need:
+                    for (wrapped_idx, row_buf) in
+                        zip(&row_idx_per_rotation, &mut preprocessed_window.rows)
+                    {
+                        for (col, row_elt) in row_buf.iter_mut().enumerate() {
+                            *row_elt = PackedVal::<SC>::from_fn(|offset| unsafe {
+                                preprocessed_trace_on_quotient_domain
+                                    .as_ref()
+                                    .unwrap_unchecked()
+                                    .get(*wrapped_idx.get_unchecked(offset), col)
+                            });
                         }
                     }
 
-                    for (lde, view_pair) in partitioned_main_lde_on_quotient_domain
+                    for (lde, window) in partitioned_main_lde_on_quotient_domain
                         .iter()
-                        .zip(partitioned_main_pairs.iter_mut())
+                        .zip(partitioned_main_windows.iter_mut())
                     {
-                        for (wrapped_idx, row_buf) in [
-                            (&row_idx_local, Some(&mut view_pair.local)),
-                            (&row_idx_next, Option::as_mut(&mut view_pair.next)),
-                        ] {
-                            if let Some(row_buf) = row_buf {
-                                for (col, row_elt) in row_buf.iter_mut().enumerate() {
-                                    *row_elt = PackedVal::<SC>::from_fn(|offset| {
-                                        lde.get(unsafe { *wrapped_idx.get_unchecked(offset) }, col)
-                                    });
-                                }
+                        for (wrapped_idx, row_buf) in zip(&row_idx_per_rotation, &mut window.rows) {
+                            for (col, row_elt) in row_buf.iter_mut().enumerate() {
+                                *row_elt = PackedVal::<SC>::from_fn(|offset| {
+                                    lde.get(unsafe { *wrapped_idx.get_unchecked(offset) }, col)
+                                });
                             }
                         }
                     }
 
-                    for (lde, view_pair) in after_challenge_lde_on_quotient_domain
+                    for (lde, window) in after_challenge_lde_on_quotient_domain
                         .iter()
-                        .zip(after_challenge_pairs.iter_mut())
+                        .zip(after_challenge_windows.iter_mut())
                     {
                         // Width in base field with extension field elements flattened
-                        for (wrapped_idx, row_buf) in [
-                            (&row_idx_local, Some(&mut view_pair.local)),
-                            (&row_idx_next, Option::as_mut(&mut view_pair.next)),
-                        ] {
-                            if let Some(row_buf) = row_buf {
-                                for (col, row_elt) in row_buf.iter_mut().enumerate() {
-                                    *row_elt = PackedChallenge::<SC>::from_base_fn(|i| {
-                                        PackedVal::<SC>::from_fn(|offset| {
-                                            lde.get(
-                                                unsafe { *wrapped_idx.get_unchecked(offset) },
-                                                col * ext_degree + i,
-                                            )
-                                        })
-                                    });
-                                }
+                        for (wrapped_idx, row_buf) in zip(&row_idx_per_rotation, &mut window.rows) {
+                            for (col, row_elt) in row_buf.iter_mut().enumerate() {
+                                *row_elt = PackedChallenge::<SC>::from_base_fn(|i| {
+                                    PackedVal::<SC>::from_fn(|offset| {
+                                        lde.get(
+                                            unsafe { *wrapped_idx.get_unchecked(offset) },
+                                            col * ext_degree + i,
+                                        )
+                                    })
+                                });
                             }
                         }
                     }
 
                     let evaluator: ProverConstraintEvaluator<SC> = ProverConstraintEvaluator {
-                        preprocessed: &preprocessed_pair,
-                        partitioned_main: &partitioned_main_pairs,
-                        after_challenge: &after_challenge_pairs,
+                        preprocessed: &preprocessed_window,
+                        partitioned_main: &partitioned_main_windows,
+                        after_challenge: &after_challenge_windows,
                         challenges,
                         is_first_row,
                         is_last_row,
                         is_transition,
+                        is_transition_window: &is_transition_window,
                         public_values,
                         exposed_values_after_challenge,
                     };
                     // SAFETY: `constraints.nodes` should be in topological order
-                    let accumulator =
-                        unsafe { evaluator.accumulate(constraints, alpha_powers, &mut node_exprs) };
+                    let accumulator = unsafe {
+                        match eval_strategy {
+                            QuotientEvalStrategy::Direct => {
+                                evaluator.accumulate_direct(constraints, coefficients)
+                            }
+                            QuotientEvalStrategy::Dag => {
+                                evaluator.accumulate(constraints, coefficients, &mut node_exprs)
+                            }
+                        }
+                    };
                     // quotient(x) = constraints(x) / Z_H(x)
                     let quotient: PackedChallenge<SC> = accumulator * inv_zeroifier;
 
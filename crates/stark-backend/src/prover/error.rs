@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+use crate::verifier::VerificationError;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProverError {
+    #[error("AIR {air_id} has {actual} public values, expected {expected}")]
+    PublicValueCountMismatch {
+        air_id: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The constraints of an AIR do not match its quotient polynomial times the vanishing
+    /// polynomial, i.e., its trace does not satisfy the constraints.
+    #[error("quotient identity check failed for AIR {air_name}: {source}")]
+    QuotientIdentity {
+        air_name: String,
+        source: VerificationError,
+    },
+    /// The log blowup factor of a [CpuDevice](super::cpu::CpuDevice) differs from the log
+    /// blowup of its PCS.
+    #[error("device log blowup factor {device} differs from the PCS log blowup {pcs}")]
+    LogBlowupMismatch { device: usize, pcs: usize },
+}
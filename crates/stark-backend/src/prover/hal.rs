@@ -3,14 +3,16 @@
 //! Not all hardware implementations need to implement this.
 //! A pure external device implementation can just implement the [Prover](super::Prover) trait directly.
 
-use std::sync::Arc;
+use std::{cmp::Reverse, sync::Arc};
 
+use itertools::Itertools;
 use p3_challenger::CanObserve;
 use p3_matrix::dense::RowMajorMatrix;
 use serde::{de::DeserializeOwned, Serialize};
 
 use super::types::{
     AirView, DeviceMultiStarkProvingKey, DeviceStarkProvingKey, ProverDataAfterRapPhases,
+    ProverQuotientData,
 };
 use crate::{
     config::{Com, StarkGenericConfig, Val},
@@ -66,6 +68,29 @@ pub trait ProverDevice<PB: ProverBackend>:
 /// Provides functionality for committing to a batch of trace matrices, possibly of different heights.
 pub trait TraceCommitter<PB: ProverBackend> {
     fn commit(&self, traces: &[PB::Matrix]) -> (PB::Commitment, PB::PcsData);
+
+    /// Commits to `traces` as in [commit](Self::commit), and also returns the order in which the
+    /// matrices are laid out inside the commitment: the `i`-th matrix of the layout is
+    /// `traces[permutation[i]]`.
+    ///
+    /// Openings are always reported in the order of `traces`, but the mixed matrix commitment
+    /// scheme may lay out matrices in a different order, which external verifiers that recompute
+    /// the commitment need to know. The default implementation returns the layout of the Merkle
+    /// tree MMCS from Plonky3, see [mmcs_layout]. Devices committing with another MMCS must
+    /// override it.
+    fn commit_ordered(&self, traces: &[PB::Matrix]) -> (PB::Commitment, PB::PcsData, Vec<usize>) {
+        let (commit, data) = self.commit(traces);
+        (commit, data, mmcs_layout(traces))
+    }
+}
+
+/// Layout of `matrices` inside a commitment of the Merkle tree MMCS from Plonky3, which stably
+/// sorts matrices by decreasing height: the `i`-th matrix of the layout is
+/// `matrices[layout[i]]`.
+pub fn mmcs_layout(matrices: &[impl MatrixDimensions]) -> Vec<usize> {
+    (0..matrices.len())
+        .sorted_by_key(|&i| Reverse(matrices[i].height()))
+        .collect()
 }
 
 /// This trait is responsible for all partial proving of after challenge rounds (a.k.a layers) in a
@@ -104,7 +129,8 @@ pub trait QuotientCommitter<PB: ProverBackend> {
     ///
     /// must be equal, and all equal to the number of AIRs.
     ///
-    /// Quotient polynomials for multiple RAP matrices are committed together into a single commitment.
+    /// Quotient polynomials for multiple RAP matrices are committed into one or more commitments,
+    /// where the grouping of AIRs into commitments is implementation specific.
     /// The quotient polynomials can be committed together even if the corresponding trace matrices
     /// are committed separately.
     fn eval_and_commit_quotient(
@@ -115,7 +141,7 @@ pub trait QuotientCommitter<PB: ProverBackend> {
         cached_pcs_datas_per_air: &[Vec<PB::PcsData>],
         common_main_pcs_data: &PB::PcsData,
         prover_data_after: &ProverDataAfterRapPhases<PB>,
-    ) -> (PB::Commitment, PB::PcsData);
+    ) -> ProverQuotientData<PB>;
 }
 
 /// Polynomial commitment scheme (PCS) opening proof generator.
@@ -123,24 +149,35 @@ pub trait OpeningProver<PB: ProverBackend> {
     /// Opening proof for multiple RAP matrices, where
     /// - (for now) each preprocessed trace matrix has a separate commitment
     /// - main trace matrices can have multiple commitments
-    /// - for each after_challenge phase, all matrices in the phase share a commitment
-    /// - quotient poly chunks are all committed together
+    /// - after_challenge matrices can have multiple commitments, in phase order
+    /// - quotient poly chunks are committed in one or more groups of AIRs
+    ///
+    /// Each trace matrix is opened at `num_rotations` points: the out-of-domain point `zeta` and
+    /// its next `num_rotations - 1` points in the trace domain.
+    ///
+    /// All openings are batched into a single FRI proof. Matrices of the same height in a
+    /// commitment share their Merkle leaves, so each query opens them with a single path.
+    /// Grouping AIRs by height would not shrink the proof any further, since the verifier needs
+    /// the opened values of every matrix individually.
     fn open(
         &self,
         challenger: &mut PB::Challenger,
         // For each preprocessed trace commitment, the prover data and
-        // the log height of the matrix, in order
-        preprocessed: Vec<PB::PcsData>,
+        // the number of rotations of the matrix
+        preprocessed: Vec<(PB::PcsData, Vec<usize>)>,
         // For each main trace commitment, the prover data and
-        // the log height of each matrix, in order
+        // the number of rotations of each matrix, in order
         // Note: this is all one challenge phase.
-        main: Vec<PB::PcsData>,
-        // `after_phase[i]` has shared commitment prover data for all matrices in phase `i + 1`.
-        after_phase: Vec<PB::PcsData>,
-        // Quotient poly commitment prover data
-        quotient_data: PB::PcsData,
-        // Quotient degree for each RAP committed in quotient_data, in order
-        quotient_degrees: &[u8],
+        main: Vec<(PB::PcsData, Vec<usize>)>,
+        // For each after_challenge commitment, in phase order, the prover data and
+        // the number of rotations of each matrix, in order
+        after_phase: Vec<(PB::PcsData, Vec<usize>)>,
+        // Quotient poly commitment prover data, for each quotient commitment
+        quotient_data: Vec<PB::PcsData>,
+        // Number of quotient chunks for each RAP, in order
+        num_quotient_chunks: &[u8],
+        // For each RAP, the index in `quotient_data` of the commitment with its quotient chunks
+        quotient_group_idx_per_air: &[usize],
     ) -> PB::OpeningProof;
 }
 
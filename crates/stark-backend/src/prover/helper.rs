@@ -63,13 +63,18 @@ impl<SC: StarkGenericConfig> AirProofInput<SC> {
             .map(AirProofInput::simple_no_pis)
             .collect()
     }
-    /// Return the height of the main trace.
-    pub fn main_trace_height(&self) -> usize {
-        if self.raw.cached_mains.is_empty() {
-            // An AIR must have a main trace.
-            self.raw.common_main.as_ref().unwrap().height()
+    /// Return the height of the main trace. Preprocessed-only AIRs have no main trace, so the
+    /// height of their `preprocessed_trace` is returned instead.
+    pub fn main_trace_height(&self, preprocessed_trace: Option<&RowMajorMatrix<Val<SC>>>) -> usize {
+        if let Some(cached_main) = self.raw.cached_mains.first() {
+            cached_main.height()
         } else {
-            self.raw.cached_mains[0].height()
+            self.raw
+                .common_main
+                .as_ref()
+                .or(preprocessed_trace)
+                .expect("no main or preprocessed trace")
+                .height()
         }
     }
 }
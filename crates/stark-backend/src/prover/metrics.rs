@@ -32,6 +32,32 @@ pub struct SingleTraceMetrics {
     pub quotient_poly_cells: usize,
 }
 
+/// Statistics of a proof, to put its timing metrics in context.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofStats {
+    /// Number of threads the proof was generated with, see
+    /// [current_num_threads](crate::utils::current_num_threads).
+    pub num_threads: usize,
+    pub trace_metrics: TraceMetrics,
+}
+
+impl TraceMetrics {
+    /// The AIR with the most [cost cells](SingleTraceMetrics::cost_cells). Ties are broken in
+    /// favor of the earliest AIR. This is computed from the trace shapes, not measured.
+    pub fn max_cost_cells_air(&self) -> Option<&SingleTraceMetrics> {
+        self.per_air.iter().rev().max_by_key(|m| m.cost_cells())
+    }
+}
+
+impl SingleTraceMetrics {
+    /// Base field cells the prover commits to or evaluates for this AIR: the main and after
+    /// challenge traces, and the quotient polynomial on the quotient domain. The work of each
+    /// proving phase scales with it, but it is not a measured time.
+    pub fn cost_cells(&self) -> usize {
+        self.total_cells + self.quotient_poly_cells
+    }
+}
+
 /// Trace cells, counted in terms of number of **base field** elements.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TraceCells {
@@ -120,6 +146,16 @@ impl Display for SingleTraceMetrics {
 pub fn trace_metrics<PB: ProverBackend>(
     mpk: &DeviceMultiStarkProvingKey<PB>,
     log_trace_heights: &[u8],
+) -> TraceMetrics {
+    let metrics = compute_trace_metrics(mpk, log_trace_heights);
+    info!("{}", metrics);
+    metrics
+}
+
+/// Same as [trace_metrics], without logging the metrics.
+pub fn compute_trace_metrics<PB: ProverBackend>(
+    mpk: &DeviceMultiStarkProvingKey<PB>,
+    log_trace_heights: &[u8],
 ) -> TraceMetrics {
     let heights = log_trace_heights
         .iter()
@@ -173,13 +209,11 @@ pub fn trace_metrics<PB: ProverBackend>(
         })
         .collect();
     let total_cells = per_air.iter().map(|m| m.total_cells).sum();
-    let metrics = TraceMetrics {
+    TraceMetrics {
         per_air,
         total_cells,
         trace_height_inequalities,
-    };
-    info!("{}", metrics);
-    metrics
+    }
 }
 
 pub fn format_number_with_underscores(n: usize) -> String {
@@ -202,7 +236,14 @@ pub fn format_number_with_underscores(n: usize) -> String {
 mod emit {
     use metrics::counter;
 
-    use super::{SingleTraceMetrics, TraceMetrics};
+    use super::{ProofStats, SingleTraceMetrics, TraceMetrics};
+
+    impl ProofStats {
+        pub fn emit(&self) {
+            counter!("num_threads").absolute(self.num_threads as u64);
+            self.trace_metrics.emit();
+        }
+    }
 
     impl TraceMetrics {
         pub fn emit(&self) {
@@ -215,6 +256,9 @@ mod emit {
             for trace_metrics in &self.per_air {
                 trace_metrics.emit();
             }
+            if let Some(max_cost_air) = self.max_cost_cells_air() {
+                counter!("max_cost_cells_air_id").absolute(max_cost_air.air_id as u64);
+            }
             counter!("total_cells").absolute(self.total_cells as u64);
         }
     }
@@ -224,6 +268,7 @@ mod emit {
             let labels = [("air_name", self.air_name.clone()), ("air_id", self.air_id.to_string())];
             counter!("rows", &labels).absolute(self.height as u64);
             counter!("cells", &labels).absolute(self.total_cells as u64);
+            counter!("cost_cells", &labels).absolute(self.cost_cells() as u64);
             counter!("prep_cols", &labels).absolute(self.width.preprocessed.unwrap_or(0) as u64);
             counter!("main_cols", &labels).absolute(
                 (self.width.cached_mains.iter().sum::<usize>() + self.width.common_main) as u64,
@@ -12,14 +12,19 @@ use cpu::{CpuBackend, CpuDevice};
 pub mod coordinator;
 /// CPU implementation of proving backend
 pub mod cpu;
+mod error;
 pub mod hal;
 /// Types used by the prover
 pub mod types;
 
+pub use error::*;
+
 /// Testing helper
 pub mod helper; // [jpw]: maybe this should be moved to sdk
 /// Metrics about trace and other statistics related to prover performance
 pub mod metrics;
+/// Committing traces on a remote service
+pub mod remote;
 
 /// Trait for STARK/SNARK proving at the highest abstraction level.
 pub trait Prover {
@@ -0,0 +1,130 @@
+//! Integration point for committing traces on a remote service, such as a dedicated machine for
+//! the commitments of very large proofs.
+//!
+//! A [RemoteCommitter] submits traces and returns a [CommitHandle] to the pending commitment.
+//! [RemoteCommitDevice] adapts it to the synchronous prover by waiting on the handle, so that
+//! a [Coordinator](super::coordinator::Coordinator) with this device commits its main traces
+//! remotely.
+
+use std::thread::JoinHandle;
+
+use super::{
+    hal::{
+        OpeningProver, ProverBackend, ProverDevice, QuotientCommitter, RapPartialProver,
+        TraceCommitter,
+    },
+    types::{
+        AirView, DeviceMultiStarkProvingKey, DeviceStarkProvingKey, ProverDataAfterRapPhases,
+        ProverQuotientData,
+    },
+};
+
+/// Commits to a batch of trace matrices on a remote service, like [TraceCommitter] but without
+/// waiting for the commitment.
+pub trait RemoteCommitter<PB: ProverBackend> {
+    type Handle: CommitHandle<PB>;
+
+    /// Submits `traces` for commitment. The traces must be sent before returning, since they are
+    /// only borrowed, while the commitment is received through the returned handle.
+    fn submit(&self, traces: &[PB::Matrix]) -> Self::Handle;
+}
+
+/// Handle to a commitment pending on a [RemoteCommitter].
+pub trait CommitHandle<PB: ProverBackend> {
+    /// Blocks until the commitment is available and returns it, as [TraceCommitter::commit].
+    fn wait(self) -> (PB::Commitment, PB::PcsData);
+}
+
+impl<PB: ProverBackend> CommitHandle<PB> for JoinHandle<(PB::Commitment, PB::PcsData)> {
+    fn wait(self) -> (PB::Commitment, PB::PcsData) {
+        self.join().expect("remote commitment panicked")
+    }
+}
+
+/// Prover device which commits traces with `committer`, blocking on each commitment, and
+/// delegates all other proving to `device`.
+///
+/// Only commitments made through [TraceCommitter] are remote. Commitments made inside the
+/// other proving steps, such as of the quotient polynomials, stay on `device`.
+#[derive(derive_new::new)]
+pub struct RemoteCommitDevice<D, C> {
+    pub device: D,
+    pub committer: C,
+}
+
+impl<PB, D, C> ProverDevice<PB> for RemoteCommitDevice<D, C>
+where
+    PB: ProverBackend,
+    D: ProverDevice<PB>,
+    C: RemoteCommitter<PB>,
+{
+}
+
+impl<PB, D, C> TraceCommitter<PB> for RemoteCommitDevice<D, C>
+where
+    PB: ProverBackend,
+    C: RemoteCommitter<PB>,
+{
+    fn commit(&self, traces: &[PB::Matrix]) -> (PB::Commitment, PB::PcsData) {
+        self.committer.submit(traces).wait()
+    }
+}
+
+impl<PB: ProverBackend, D: RapPartialProver<PB>, C> RapPartialProver<PB>
+    for RemoteCommitDevice<D, C>
+{
+    fn partially_prove(
+        &self,
+        challenger: &mut PB::Challenger,
+        mpk: &DeviceMultiStarkProvingKey<'_, PB>,
+        trace_views: Vec<AirView<PB::Matrix, PB::Val>>,
+    ) -> (PB::RapPartialProof, ProverDataAfterRapPhases<PB>) {
+        self.device.partially_prove(challenger, mpk, trace_views)
+    }
+}
+
+impl<PB: ProverBackend, D: QuotientCommitter<PB>, C> QuotientCommitter<PB>
+    for RemoteCommitDevice<D, C>
+{
+    fn eval_and_commit_quotient(
+        &self,
+        challenger: &mut PB::Challenger,
+        pk_views: &[DeviceStarkProvingKey<PB>],
+        public_values: &[Vec<PB::Val>],
+        cached_pcs_datas_per_air: &[Vec<PB::PcsData>],
+        common_main_pcs_data: &PB::PcsData,
+        prover_data_after: &ProverDataAfterRapPhases<PB>,
+    ) -> ProverQuotientData<PB> {
+        self.device.eval_and_commit_quotient(
+            challenger,
+            pk_views,
+            public_values,
+            cached_pcs_datas_per_air,
+            common_main_pcs_data,
+            prover_data_after,
+        )
+    }
+}
+
+impl<PB: ProverBackend, D: OpeningProver<PB>, C> OpeningProver<PB> for RemoteCommitDevice<D, C> {
+    fn open(
+        &self,
+        challenger: &mut PB::Challenger,
+        preprocessed: Vec<(PB::PcsData, Vec<usize>)>,
+        main: Vec<(PB::PcsData, Vec<usize>)>,
+        after_phase: Vec<(PB::PcsData, Vec<usize>)>,
+        quotient_data: Vec<PB::PcsData>,
+        num_quotient_chunks: &[u8],
+        quotient_group_idx_per_air: &[usize],
+    ) -> PB::OpeningProof {
+        self.device.open(
+            challenger,
+            preprocessed,
+            main,
+            after_phase,
+            quotient_data,
+            num_quotient_chunks,
+            quotient_group_idx_per_air,
+        )
+    }
+}
@@ -3,16 +3,26 @@ use std::{marker::PhantomData, sync::Arc};
 use derivative::Derivative;
 use p3_field::Field;
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_maybe_rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::hal::ProverBackend;
+use super::hal::{ProverBackend, TraceCommitter};
 use crate::{
     config::{Com, PcsProof, PcsProverData, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
     keygen::types::{LinearConstraint, StarkVerifyingKey},
     proof::{AirProofData, Commitments, OpeningProof, Proof},
+    verifier::VerificationError,
 };
 
 /// A view of the proving key after it has been transferred to device.
+///
+/// To prove multiple times with the same proving key, transport it once and clone this view for
+/// each proof. For backends whose device matrices and prover data are shared handles, such as
+/// [CpuBackend](super::cpu::CpuBackend), cloning does not copy the preprocessed data.
+#[derive(Derivative)]
+#[derivative(Clone(
+    bound = "PB::Matrix: Clone, PB::PcsData: Clone, PB::RapPartialProvingKey: Clone"
+))]
 pub struct DeviceMultiStarkProvingKey<'a, PB: ProverBackend> {
     pub(super) air_ids: Vec<usize>,
     pub per_air: Vec<DeviceStarkProvingKey<'a, PB>>,
@@ -39,9 +49,15 @@ impl<'a, PB: ProverBackend> DeviceMultiStarkProvingKey<'a, PB> {
     }
 }
 
+#[derive(Derivative)]
+#[derivative(Clone(
+    bound = "PB::Matrix: Clone, PB::PcsData: Clone, PB::RapPartialProvingKey: Clone"
+))]
 pub struct DeviceStarkProvingKey<'a, PB: ProverBackend> {
-    /// Type name of the AIR, for display purposes only
+    /// Name of the AIR, for display purposes only. See [crate::keygen::types::StarkProvingKey::name]
     pub air_name: &'a str,
+    /// Index of the AIR in the multi-stark proving key
+    pub air_id: usize,
     pub vk: &'a StarkVerifyingKey<PB::Val, PB::Commitment>,
     /// Prover only data for preprocessed trace
     pub preprocessed_data: Option<SingleCommitPreimage<PB::Matrix, PB::PcsData>>,
@@ -106,6 +122,51 @@ pub struct AirProvingContext<'a, PB: ProverBackend> {
     pub cached_lifetime: PhantomData<&'a PB::PcsData>,
 }
 
+impl<PB: ProverBackend> AirProvingContext<'_, PB> {
+    /// Context of an AIR whose main trace is split across multiple commitments. Each matrix of
+    /// `separate_mains` is committed in its own commitment with `committer`, in parallel, and
+    /// `common_main` is committed together with the common main traces of the other AIRs.
+    ///
+    /// The separately committed matrices are the cached main traces of the AIR, so the AIR must be
+    /// partitioned with their widths as its
+    /// [cached_main_widths](crate::rap::PartitionedBaseAir::cached_main_widths).
+    pub fn from_split_main(
+        committer: &(impl TraceCommitter<PB> + Sync),
+        separate_mains: Vec<PB::Matrix>,
+        common_main: Option<PB::Matrix>,
+        public_values: Vec<PB::Val>,
+    ) -> Self {
+        let cached_mains = separate_mains
+            .into_par_iter()
+            .map(|trace| {
+                let (commitment, data) = committer.commit(std::slice::from_ref(&trace));
+                let preimage = SingleCommitPreimage {
+                    trace,
+                    data,
+                    matrix_idx: 0,
+                };
+                (commitment, preimage)
+            })
+            .collect();
+        Self {
+            cached_mains,
+            common_main,
+            public_values,
+            cached_lifetime: PhantomData,
+        }
+    }
+}
+
+/// Proving context of an AIR without cached main traces, whose common main trace is generated on
+/// demand. See [Coordinator::prove_lazy](super::coordinator::Coordinator::prove_lazy).
+#[derive(derive_new::new)]
+pub struct LazyAirProvingContext<'a, F> {
+    /// Produces the common main trace. It is invoked on a separate thread while proving.
+    pub common_main: Box<dyn FnOnce() -> RowMajorMatrix<F> + Send + 'a>,
+    /// Public values
+    pub public_values: Vec<F>,
+}
+
 /// A view of just the AIR, without any preprocessed or after challenge columns.
 /// The AIR's main trace is horizontally partitioned into multiple matrices,
 /// where each matrix can belong to a separate matrix commitment.
@@ -179,14 +240,122 @@ impl<T, Challenge> Default for RapSinglePhaseView<T, Challenge> {
 
 #[derive(derive_new::new)]
 pub struct ProverDataAfterRapPhases<PB: ProverBackend> {
-    /// For each challenge phase **after** the main phase,
-    /// the commitment and preimage (there should never be a reason to have more than one).
+    /// The commitments and preimages of the trace matrices of all challenge phases **after** the
+    /// main phase, in phase order. The trace matrices of a single phase may be split into multiple
+    /// commitments, see [PermutationCommitGrouping].
     /// This may be empty if challenge phases do not require additional trace commitments.
-    pub committed_pcs_data_per_phase: Vec<(PB::Commitment, PB::PcsData)>,
+    pub committed_pcs_data_after_challenge: Vec<(PB::Commitment, PB::PcsData)>,
     /// For each challenge phase, for each RAP,
     /// the challenge, and exposed values for the RAP.
     /// The indexing is `rap_views_per_phase[phase_idx][rap_idx]`.
-    pub rap_views_per_phase: Vec<Vec<RapSinglePhaseView<usize, PB::Challenge>>>,
+    ///
+    /// The view of a RAP trace matrix is `(commit_idx, matrix_idx)`, where `commit_idx` is the
+    /// index in `committed_pcs_data_after_challenge` of the commitment containing the matrix and
+    /// `matrix_idx` is the index of the matrix within that commitment.
+    pub rap_views_per_phase: Vec<Vec<RapSinglePhaseView<(usize, usize), PB::Challenge>>>,
+}
+
+/// Specifies how the permutation trace matrices of different AIRs are grouped into commitments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PermutationCommitGrouping {
+    /// One shared commitment for the permutation traces of all AIRs.
+    #[default]
+    Single,
+    /// A separate commitment for the permutation trace of each AIR with interactions. This allows
+    /// the permutation traces of AIRs to be committed independently of each other.
+    PerAir,
+}
+
+/// Specifies how the quotient polynomial chunks of different AIRs are grouped into commitments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum QuotientCommitGrouping {
+    /// One shared commitment for the quotient chunks of all AIRs.
+    #[default]
+    Single,
+    /// A separate commitment for the quotient chunks of each AIR.
+    PerAir,
+    /// `Custom(group_idx_per_air)` assigns the AIR at position `i` (in proving order) to the
+    /// commitment with index `group_idx_per_air[i]`. Group indices must cover `0..num_groups`
+    /// and every group must contain at least one AIR.
+    Custom(Vec<usize>),
+}
+
+impl QuotientCommitGrouping {
+    /// Returns the commitment group index for each of `num_airs` AIRs.
+    pub fn group_idx_per_air(&self, num_airs: usize) -> Vec<usize> {
+        let group_idx_per_air = match self {
+            Self::Single => vec![0; num_airs],
+            Self::PerAir => (0..num_airs).collect(),
+            Self::Custom(group_idx_per_air) => {
+                assert_eq!(
+                    group_idx_per_air.len(),
+                    num_airs,
+                    "quotient grouping must assign a group to every AIR"
+                );
+                group_idx_per_air.clone()
+            }
+        };
+        let num_groups = group_idx_per_air.iter().max().map_or(0, |&g| g + 1);
+        assert!(
+            (0..num_groups).all(|g| group_idx_per_air.contains(&g)),
+            "quotient grouping has an empty group"
+        );
+        group_idx_per_air
+    }
+}
+
+/// Splits commitment groups so that none has more than `max_matrices` matrices, where the AIR at
+/// position `i` contributes `num_matrices_per_air[i]` matrices to its group
+/// `group_idx_per_air[i]`. Each group is split into runs of its AIRs in order, and the new group
+/// indices follow the order of the original groups.
+///
+/// Panics if a single AIR has more than `max_matrices` matrices.
+pub fn split_commit_groups(
+    group_idx_per_air: &[usize],
+    num_matrices_per_air: &[usize],
+    max_matrices: usize,
+) -> Vec<usize> {
+    assert_eq!(group_idx_per_air.len(), num_matrices_per_air.len());
+    let num_groups = group_idx_per_air.iter().max().map_or(0, |&g| g + 1);
+    let mut new_group_idx_per_air = vec![0; group_idx_per_air.len()];
+    let mut num_new_groups = 0;
+    for group_idx in 0..num_groups {
+        let mut num_matrices = 0;
+        for (air_idx, _) in group_idx_per_air
+            .iter()
+            .enumerate()
+            .filter(|(_, &g)| g == group_idx)
+        {
+            let n = num_matrices_per_air[air_idx];
+            assert!(
+                n <= max_matrices,
+                "AIR {air_idx} has {n} matrices, more than the {max_matrices} allowed per commitment"
+            );
+            if num_matrices == 0 || num_matrices + n > max_matrices {
+                num_new_groups += 1;
+                num_matrices = 0;
+            }
+            num_matrices += n;
+            new_group_idx_per_air[air_idx] = num_new_groups - 1;
+        }
+    }
+    new_group_idx_per_air
+}
+
+/// Quotient polynomial commitments, possibly split into multiple commitments.
+#[derive(derive_new::new)]
+pub struct ProverQuotientData<PB: ProverBackend> {
+    /// For each quotient commitment, the commitment and preimage.
+    pub committed_pcs_data_per_group: Vec<(PB::Commitment, PB::PcsData)>,
+    /// For each AIR, the index in `committed_pcs_data_per_group` of the commitment containing
+    /// the quotient chunks of the AIR. Within a commitment, chunks are ordered by AIR.
+    pub group_idx_per_air: Vec<usize>,
+    /// For each AIR, the number of chunks its quotient polynomial is split into.
+    pub num_chunks_per_air: Vec<u8>,
+    /// If the device checks the quotient identity, the index of the first AIR failing the check
+    /// and the error.
+    #[new(default)]
+    pub failed_quotient_identity: Option<(usize, VerificationError)>,
 }
 
 /// The full proof for multiple RAPs where trace matrices are committed into
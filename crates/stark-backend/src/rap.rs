@@ -6,11 +6,26 @@ use std::{
     sync::Arc,
 };
 
-use p3_air::{BaseAir, PermutationAirBuilder};
+use itertools::Itertools;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, PermutationAirBuilder};
+use p3_field::Field;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    air_builders::{debug::DebugConstraintBuilder, symbolic::SymbolicRapBuilder},
+    air_builders::{
+        debug::DebugConstraintBuilder,
+        symbolic::{
+            get_symbolic_builder,
+            symbolic_expression::SymbolicEvaluator,
+            symbolic_variable::{Entry, SymbolicVariable},
+            SymbolicConstraintsDag, SymbolicRapBuilder,
+        },
+        PartitionedAirBuilder,
+    },
     config::{StarkGenericConfig, Val},
+    interaction::{InteractionBuilder, InteractionSpec, RapPhaseSeqKind},
+    keygen::types::TraceWidth,
 };
 
 /// An AIR with 0 or more public values.
@@ -19,6 +34,37 @@ pub trait BaseAirWithPublicValues<F>: BaseAir<F> {
     fn num_public_values(&self) -> usize {
         0
     }
+
+    /// If `true`, the number of public values varies per proof and `num_public_values` is the
+    /// maximum. The AIR then has one additional public value, placed last, holding the number of
+    /// active public values. Inactive slots must be zero: see [pad_public_values].
+    fn has_variable_public_values(&self) -> bool {
+        false
+    }
+}
+
+/// Pads `active` public values of an AIR with [variable public values](BaseAirWithPublicValues::has_variable_public_values)
+/// to `max_num_public_values` with zeros, and appends the number of active public values.
+pub fn pad_public_values<F: Field>(mut active: Vec<F>, max_num_public_values: usize) -> Vec<F> {
+    let num_active = active.len();
+    assert!(
+        num_active <= max_num_public_values,
+        "too many public values: {num_active} > {max_num_public_values}"
+    );
+    active.resize(max_num_public_values, F::ZERO);
+    active.push(F::from_canonical_usize(num_active));
+    active
+}
+
+/// Returns the number of active public values if `public_values` is a valid padding of
+/// variable public values (see [pad_public_values]), and `None` otherwise.
+pub fn num_active_public_values<F: Field>(public_values: &[F]) -> Option<usize> {
+    let (&num_active, slots) = public_values.split_last()?;
+    let num_active = (0..=slots.len()).find(|&n| F::from_canonical_usize(n) == num_active)?;
+    slots[num_active..]
+        .iter()
+        .all(|v| v.is_zero())
+        .then_some(num_active)
 }
 
 /// An AIR with 1 or more main trace partitions.
@@ -89,6 +135,22 @@ pub trait ColumnsAir<F>: BaseAir<F> {
     fn columns(&self) -> Option<Vec<String>> {
         None
     }
+
+    /// The names of the columns: [columns](Self::columns) if available, otherwise `col_0`,
+    /// `col_1`, ... up to the width of the AIR.
+    fn column_names(&self) -> Vec<String> {
+        self.columns()
+            .unwrap_or_else(|| (0..self.width()).map(|i| format!("col_{i}")).collect())
+    }
+}
+
+/// Opt-in trait for AIRs that declare their interactions. Keygen checks the declaration of AIRs
+/// added with `add_air_with_declared_interactions` of the
+/// [keygen builder](crate::keygen::MultiStarkKeygenBuilder).
+pub trait InteractionsAir<F>: BaseAir<F> {
+    /// Returns the interactions pushed by `eval`, in order. Keygen panics if they do not match
+    /// the declaration.
+    fn declare_interactions(&self) -> Vec<InteractionSpec>;
 }
 
 impl<SC, T> AnyRap<SC> for T
@@ -112,6 +174,243 @@ where
     }
 }
 
+/// Adapter to use an AIR implementing only the Plonky3 [Air] and [BaseAir] traits as a RAP,
+/// without writing a newtype. The AIR has no public values, interactions, or partitioned main
+/// trace.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct P3AirAdapter<A>(pub A);
+
+impl<F, A: BaseAir<F>> BaseAir<F> for P3AirAdapter<A> {
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.0.preprocessed_trace()
+    }
+}
+impl<F, A: BaseAir<F>> BaseAirWithPublicValues<F> for P3AirAdapter<A> {}
+impl<F, A: BaseAir<F>> PartitionedBaseAir<F> for P3AirAdapter<A> {}
+impl<F, A: BaseAir<F>> ColumnsAir<F> for P3AirAdapter<A> {}
+
+impl<AB: AirBuilder, A: Air<AB>> Air<AB> for P3AirAdapter<A> {
+    fn eval(&self, builder: &mut AB) {
+        self.0.eval(builder);
+    }
+}
+
+/// AIR defined by the symbolic constraints and interactions of its main trace instead of an
+/// [Air] implementation, so that constraints can be shipped as data. Its `eval` interprets the
+/// constraint DAG on the trace of the builder.
+///
+/// The DAG must not contain the constraints of the RAP phases, which keygen adds as for any other
+/// AIR. Use [Self::from_air] to extract the DAG from an existing AIR. Preprocessed traces are not
+/// supported.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "F: Serialize", deserialize = "F: Deserialize<'de>"))]
+pub struct SymbolicAir<F> {
+    pub cached_main_widths: Vec<usize>,
+    pub common_main_width: usize,
+    pub num_public_values: usize,
+    pub has_variable_public_values: bool,
+    pub constraints: SymbolicConstraintsDag<F>,
+}
+
+impl<F: Field> SymbolicAir<F> {
+    /// Extracts the constraints and interactions of the main trace of `air`.
+    pub fn from_air<R>(air: &R) -> Self
+    where
+        R: Rap<SymbolicRapBuilder<F>>
+            + BaseAirWithPublicValues<F>
+            + PartitionedBaseAir<F>
+            + ?Sized,
+    {
+        assert!(
+            air.preprocessed_trace().is_none(),
+            "preprocessed traces are not supported"
+        );
+        let width = TraceWidth {
+            preprocessed: None,
+            cached_mains: air.cached_main_widths(),
+            common_main: air.common_main_width(),
+            after_challenge: vec![],
+        };
+        // Without a RAP phase, no constraints are added for the interactions
+        let constraints = get_symbolic_builder(air, &width, &[], &[], RapPhaseSeqKind::None, 0)
+            .constraints()
+            .into();
+        Self {
+            cached_main_widths: width.cached_mains,
+            common_main_width: width.common_main,
+            num_public_values: air.num_public_values(),
+            has_variable_public_values: air.has_variable_public_values(),
+            constraints,
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for SymbolicAir<F> {
+    fn width(&self) -> usize {
+        self.cached_main_widths.iter().sum::<usize>() + self.common_main_width
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for SymbolicAir<F> {
+    fn num_public_values(&self) -> usize {
+        self.num_public_values
+    }
+
+    fn has_variable_public_values(&self) -> bool {
+        self.has_variable_public_values
+    }
+}
+impl<F: Field> PartitionedBaseAir<F> for SymbolicAir<F> {
+    fn cached_main_widths(&self) -> Vec<usize> {
+        self.cached_main_widths.clone()
+    }
+
+    fn common_main_width(&self) -> usize {
+        self.common_main_width
+    }
+}
+impl<F: Field> ColumnsAir<F> for SymbolicAir<F> {}
+
+impl<AB> Air<AB> for SymbolicAir<AB::F>
+where
+    AB: InteractionBuilder + AirBuilderWithPublicValues + PartitionedAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let partitioned_main = builder
+            .cached_mains()
+            .iter()
+            .chain((self.common_main_width > 0).then(|| builder.common_main()))
+            .collect_vec();
+        let evaluator = AirBuilderEvaluator {
+            builder: &*builder,
+            partitioned_main,
+        };
+        let exprs = evaluator.eval_nodes(&self.constraints.constraints.nodes);
+
+        for &idx in &self.constraints.constraints.constraint_idx {
+            builder.assert_zero(exprs[idx].clone());
+        }
+        for interaction in &self.constraints.interactions {
+            builder.push_interaction_with_kind(
+                interaction.bus_index,
+                interaction.message.iter().map(|&idx| exprs[idx].clone()),
+                exprs[interaction.count].clone(),
+                interaction.count_weight,
+                interaction.kind,
+            );
+        }
+    }
+}
+
+/// AIR whose constraints and interactions are given by a closure on the [SymbolicRapBuilder], to
+/// prototype ad-hoc constraints without implementing the AIR traits. The closure is evaluated
+/// once in [Self::new], and the AIR is proven as the resulting [SymbolicAir].
+#[derive(Clone, Debug)]
+pub struct ClosureAir<F>(pub SymbolicAir<F>);
+
+impl<F: Field> ClosureAir<F> {
+    /// AIR with a common main trace of `width` columns and `num_public_values` public values,
+    /// constrained by `eval`.
+    pub fn new(
+        width: usize,
+        num_public_values: usize,
+        eval: impl Fn(&mut SymbolicRapBuilder<F>),
+    ) -> Self {
+        let air = ClosureEval {
+            width,
+            num_public_values,
+            eval: &eval,
+        };
+        Self(SymbolicAir::from_air(&air))
+    }
+}
+
+impl<F: Field> BaseAir<F> for ClosureAir<F> {
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for ClosureAir<F> {
+    fn num_public_values(&self) -> usize {
+        self.0.num_public_values
+    }
+}
+impl<F: Field> PartitionedBaseAir<F> for ClosureAir<F> {}
+impl<F: Field> ColumnsAir<F> for ClosureAir<F> {}
+
+impl<AB> Air<AB> for ClosureAir<AB::F>
+where
+    AB: InteractionBuilder + AirBuilderWithPublicValues + PartitionedAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        self.0.eval(builder);
+    }
+}
+
+/// The closure of a [ClosureAir] as an AIR, to extract its constraints.
+struct ClosureEval<'a, F> {
+    width: usize,
+    num_public_values: usize,
+    eval: &'a dyn Fn(&mut SymbolicRapBuilder<F>),
+}
+
+impl<F: Field> BaseAir<F> for ClosureEval<'_, F> {
+    fn width(&self) -> usize {
+        self.width
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for ClosureEval<'_, F> {
+    fn num_public_values(&self) -> usize {
+        self.num_public_values
+    }
+}
+impl<F: Field> PartitionedBaseAir<F> for ClosureEval<'_, F> {}
+
+impl<F: Field> Air<SymbolicRapBuilder<F>> for ClosureEval<'_, F> {
+    fn eval(&self, builder: &mut SymbolicRapBuilder<F>) {
+        (self.eval)(builder);
+    }
+}
+
+/// Evaluates symbolic expressions of the main trace on the trace of an [AirBuilder].
+struct AirBuilderEvaluator<'a, AB: AirBuilderWithPublicValues> {
+    builder: &'a AB,
+    partitioned_main: Vec<&'a AB::M>,
+}
+
+impl<AB: AirBuilderWithPublicValues> SymbolicEvaluator<AB::F, AB::Expr>
+    for AirBuilderEvaluator<'_, AB>
+{
+    fn eval_const(&self, c: AB::F) -> AB::Expr {
+        c.into()
+    }
+    fn eval_var(&self, symbolic_var: SymbolicVariable<AB::F>) -> AB::Expr {
+        let index = symbolic_var.index;
+        match symbolic_var.entry {
+            Entry::Main { part_index, offset } => {
+                self.partitioned_main[part_index].row_slice(offset)[index].into()
+            }
+            Entry::Public => self.builder.public_values()[index].into(),
+            entry => panic!("{entry:?} is not supported by SymbolicAir"),
+        }
+    }
+    fn eval_is_first_row(&self) -> AB::Expr {
+        self.builder.is_first_row()
+    }
+    fn eval_is_last_row(&self) -> AB::Expr {
+        self.builder.is_last_row()
+    }
+    fn eval_is_transition(&self) -> AB::Expr {
+        self.builder.is_transition()
+    }
+    fn eval_is_transition_window(&self, size: usize) -> AB::Expr {
+        self.builder.is_transition_window(size)
+    }
+}
+
 /// Automatically derives the AIR name from the type name for pretty display purposes.
 pub fn get_air_name<T>(_rap: &T) -> String {
     let full_name = type_name::<T>().to_string();
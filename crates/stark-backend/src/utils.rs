@@ -1,7 +1,8 @@
 use std::borrow::Cow;
 
 use cfg_if::cfg_if;
-use p3_field::Field;
+use p3_commit::PolynomialSpace;
+use p3_field::{ExtensionField, Field, FieldAlgebra};
 use tracing::instrument;
 
 use crate::air_builders::debug::USE_DEBUG_BUILDER;
@@ -105,6 +106,48 @@ where
     }
 }
 
+/// Returns the domain on which the quotient polynomial of an AIR with trace domain `trace_domain`
+/// and quotient degree `quotient_degree` is evaluated and committed. The prover and verifier must
+/// agree on this domain.
+///
+/// The coset shift of the quotient domain is not configurable: it is the shift chosen by
+/// [PolynomialSpace::create_disjoint_domain]. The prover reads the trace evaluations on the
+/// quotient domain from the low-degree extensions committed by the PCS, so the quotient domain
+/// must lie within the committed coset. For the two-adic FRI PCS, this fixes the shift to the
+/// generator of the field.
+///
+/// The quotient domain is the smallest domain determining the quotient polynomial. Evaluating on
+/// the whole committed coset instead would not save any work: the quotient commitment extends
+/// each chunk from its own domain regardless of how many evaluations are known.
+pub fn quotient_domain<D: PolynomialSpace>(trace_domain: D, quotient_degree: u8) -> D {
+    trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree as usize)
+}
+
+/// Evaluates at `point` the selector of the rows of `trace_domain` which start a full window of
+/// `size` consecutive rows, i.e., the polynomial vanishing exactly on the last `size - 1` rows.
+/// For `size = 2` this equals the `is_transition` selector of
+/// [PolynomialSpace::selectors_at_point], and like it, the selector is not normalized.
+pub fn transition_window_selector_at_point<D, Ext>(trace_domain: D, size: usize, point: Ext) -> Ext
+where
+    D: PolynomialSpace,
+    Ext: ExtensionField<D::Val>,
+{
+    let shift = trace_domain.first_point();
+    let generator_inv = shift
+        * trace_domain
+            .next_point(shift)
+            .expect("trace domain should have a next point")
+            .inverse();
+    let unshifted_point = point * shift.inverse();
+    let mut last_point = D::Val::ONE;
+    (1..size)
+        .map(|_| {
+            last_point *= generator_inv;
+            unshifted_point - last_point
+        })
+        .product()
+}
+
 /// Disables the debug builder so there are not debug assert panics.
 /// Commonly used in negative tests to prevent panics.
 pub fn disable_debug_builder() {
@@ -130,6 +173,61 @@ pub fn metrics_span<R, F: FnOnce() -> R>(name: impl Into<Cow<'static, str>>, f:
     }
 }
 
+/// Labels attached to an emitted metric, as `(key, value)` pairs.
+pub type MetricLabels = Vec<(&'static str, String)>;
+
+/// Same as [metrics_span], but the emitted gauge is labeled with `labels`.
+#[allow(unused_variables)]
+pub fn metrics_span_with_labels<R, F: FnOnce() -> R>(
+    name: impl Into<Cow<'static, str>>,
+    labels: &[(&'static str, String)],
+    f: F,
+) -> R {
+    cfg_if! {
+        if #[cfg(feature = "bench-metrics")] {
+            let start = std::time::Instant::now();
+            let res = f();
+            let labels = labels.to_vec();
+            metrics::gauge!(name.into(), &labels).set(start.elapsed().as_millis() as f64);
+            res
+        } else {
+            f()
+        }
+    }
+}
+
+/// Runs `f` on a single thread, so that any parallel iterators used in `f` are executed serially
+/// and in order. This is a no-op when the feature `"parallel"` is disabled.
+///
+/// Proving is deterministic except for the proof-of-work witness search in FRI, which returns
+/// whichever valid witness is found first when run in parallel. Proving inside `force_serial`
+/// makes the proof a deterministic function of the proving key, inputs, and challenger state.
+pub fn force_serial<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    cfg_if! {
+        if #[cfg(feature = "parallel")] {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .expect("failed to build single-threaded thread pool")
+                .install(f)
+        } else {
+            f()
+        }
+    }
+}
+
+/// The number of threads parallel iterators run on in the current context, which is the size of
+/// the current rayon thread pool, or 1 when the feature `"parallel"` is disabled.
+pub fn current_num_threads() -> usize {
+    cfg_if! {
+        if #[cfg(feature = "parallel")] {
+            rayon::current_num_threads()
+        } else {
+            1
+        }
+    }
+}
+
 #[macro_export]
 #[cfg(feature = "parallel")]
 macro_rules! parizip {
@@ -1,9 +1,9 @@
 use std::marker::PhantomData;
 
 use itertools::Itertools;
-use p3_commit::PolynomialSpace;
+use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
+use p3_matrix::dense::RowMajorMatrixView;
 use tracing::instrument;
 
 use super::{
@@ -11,9 +11,11 @@ use super::{
     folder::{GenericVerifierConstraintFolder, VerifierConstraintFolder},
 };
 use crate::{
+    accumulation::AccumulationStrategy,
     air_builders::symbolic::SymbolicExpressionDag,
     config::{Domain, StarkGenericConfig, Val},
-    proof::AdjacentOpenedValues,
+    proof::{AdjacentOpenedValues, Proof},
+    utils::transition_window_selector_at_point,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -28,6 +30,7 @@ pub fn verify_single_rap_constraints<SC>(
     qc_domains: &[Domain<SC>],
     zeta: SC::Challenge,
     alpha: SC::Challenge,
+    accumulation: &dyn AccumulationStrategy<SC::Challenge>,
     challenges: &[Vec<SC::Challenge>],
     public_values: &[Val<SC>],
     exposed_values_after_challenge: &[Vec<SC::Challenge>],
@@ -75,51 +78,46 @@ where
     };
 
     let sels = domain.selectors_at_point(zeta);
+    // Transition window selectors at `zeta`, indexed by window size
+    let window_sizes = constraints.transition_window_sizes();
+    let mut is_transition_window =
+        vec![SC::Challenge::ZERO; window_sizes.last().map_or(0, |&size| size + 1)];
+    for size in window_sizes {
+        is_transition_window[size] = transition_window_selector_at_point(domain, size, zeta);
+    }
 
-    let (preprocessed_local, preprocessed_next) = preprocessed_values
-        .as_ref()
-        .map(|values| (values.local.as_slice(), values.next.as_slice()))
-        .unwrap_or((&[], &[]));
-    let preprocessed = VerticalPair::new(
-        RowMajorMatrixView::new_row(preprocessed_local),
-        RowMajorMatrixView::new_row(preprocessed_next),
-    );
-
-    let partitioned_main: Vec<_> = partitioned_main_values
+    // The values of each trace matrix at all rotations, concatenated, and the matrix width
+    let concat_rows = |values: &AdjacentOpenedValues<SC::Challenge>| {
+        (values.rows().flatten().copied().collect_vec(), values.local.len())
+    };
+    let preprocessed_rows = preprocessed_values.map(concat_rows).unwrap_or_default();
+    let partitioned_main_rows = partitioned_main_values
         .into_iter()
-        .map(|values| {
-            VerticalPair::new(
-                RowMajorMatrixView::new_row(&values.local),
-                RowMajorMatrixView::new_row(&values.next),
-            )
-        })
-        .collect();
-
-    let after_challenge_ext_values: Vec<_> = after_challenge_values
+        .map(concat_rows)
+        .collect_vec();
+    let after_challenge_rows = after_challenge_values
         .into_iter()
         .map(|values| {
-            let [local, next] = [&values.local, &values.next]
-                .map(|flattened_ext_values| unflatten(flattened_ext_values));
-            (local, next)
-        })
-        .collect();
-    let after_challenge = after_challenge_ext_values
-        .iter()
-        .map(|(local, next)| {
-            VerticalPair::new(
-                RowMajorMatrixView::new_row(local),
-                RowMajorMatrixView::new_row(next),
-            )
+            let rows = values.rows().map(|row| unflatten(row)).collect_vec();
+            let width = rows[0].len();
+            (rows.concat(), width)
         })
-        .collect();
+        .collect_vec();
 
     let mut folder: VerifierConstraintFolder<'_, SC> = GenericVerifierConstraintFolder {
-        preprocessed,
-        partitioned_main,
-        after_challenge,
+        preprocessed: RowMajorMatrixView::new(&preprocessed_rows.0, preprocessed_rows.1),
+        partitioned_main: partitioned_main_rows
+            .iter()
+            .map(|(values, width)| RowMajorMatrixView::new(values, *width))
+            .collect(),
+        after_challenge: after_challenge_rows
+            .iter()
+            .map(|(values, width)| RowMajorMatrixView::new(values, *width))
+            .collect(),
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
         is_transition: sels.is_transition,
+        is_transition_window: &is_transition_window,
         alpha,
         accumulator: SC::Challenge::ZERO,
         challenges,
@@ -127,7 +125,8 @@ where
         exposed_values_after_challenge,
         _marker: PhantomData,
     };
-    folder.eval_constraints(constraints);
+    let coefficients = accumulation.coefficients(alpha, constraints.constraint_idx.len());
+    folder.eval_constraints_with_coefficients(constraints, &coefficients);
 
     let folded_constraints = folder.accumulator;
     // Finally, check that
@@ -138,3 +137,29 @@ where
 
     Ok(())
 }
+
+/// Evaluates the vanishing polynomial `Z_H` of the trace domain `domain` at `zeta`.
+///
+/// The verifier checks `folded_constraints(zeta) = quotient(zeta) * Z_H(zeta)` for each AIR.
+pub fn vanishing_eval_at<SC: StarkGenericConfig>(
+    domain: Domain<SC>,
+    zeta: SC::Challenge,
+) -> SC::Challenge {
+    domain.zp_at_point(zeta)
+}
+
+/// Evaluates at `zeta` the vanishing polynomial of the trace domain of each AIR in `proof`, in
+/// the order of `proof.per_air`.
+pub fn vanishing_evals_at<SC: StarkGenericConfig>(
+    pcs: &SC::Pcs,
+    proof: &Proof<SC>,
+    zeta: SC::Challenge,
+) -> Vec<SC::Challenge> {
+    proof
+        .per_air
+        .iter()
+        .map(|air_proof| {
+            vanishing_eval_at::<SC>(pcs.natural_domain_for_degree(air_proof.degree), zeta)
+        })
+        .collect()
+}
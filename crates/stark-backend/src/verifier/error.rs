@@ -15,4 +15,107 @@ pub enum VerificationError {
     OodEvaluationMismatch,
     #[error("challenge phase error")]
     ChallengePhaseError,
+    #[error("AIR {air_id} has {actual} public values, expected {expected}")]
+    PublicValueCountMismatch {
+        air_id: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// Errors of [verify_all](super::MultiTraceStarkVerifier::verify_all).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyAllError {
+    /// A failure which does not belong to the constraints of a single AIR.
+    #[error(transparent)]
+    Global(#[from] VerificationError),
+    /// The `air_id` and error of each AIR whose constraints fail, in the order of the proof.
+    #[error("constraints of {} AIRs failed", .0.len())]
+    Airs(Vec<(usize, VerificationError)>),
+}
+
+/// The group of opened values of a proof, in the order they are opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpeningRound {
+    Preprocessed,
+    Main,
+    AfterChallenge,
+    /// Opened values of the quotient chunks are grouped by AIR rather than by commitment, so
+    /// errors in this round index the AIRs of the proof in place of commitments.
+    Quotient,
+}
+
+/// A structural mismatch between a proof and the verifying key, found by
+/// [Proof::validate_shape](crate::proof::Proof::validate_shape).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProofShapeError {
+    #[error("air_id {air_id} is out of range for {num_airs} AIRs")]
+    AirIdOutOfRange { air_id: usize, num_airs: usize },
+    #[error("air_id {0} appears more than once")]
+    DuplicateAirId(usize),
+    #[error("trace height {degree} of AIR {air_id} is not a power of two")]
+    TraceHeight { air_id: usize, degree: usize },
+    #[error("AIR {air_id} has {actual} public values, expected {expected}")]
+    NumPublicValues {
+        air_id: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("AIR {air_id} has exposed values of the wrong shape")]
+    ExposedValues { air_id: usize },
+    #[error("AIR {air_id} has quotient degree {actual}, expected {expected}")]
+    QuotientDegree {
+        air_id: usize,
+        expected: u8,
+        actual: u8,
+    },
+    #[error("AIR {air_id} has {num_chunks} quotient chunks, expected a power of two dividing quotient degree {quotient_degree}")]
+    NumQuotientChunks {
+        air_id: usize,
+        num_chunks: u8,
+        quotient_degree: u8,
+    },
+    #[error("AIR {air_id} refers to quotient commitment {idx}, but there are {num_commits}")]
+    QuotientCommitIdx {
+        air_id: usize,
+        idx: usize,
+        num_commits: usize,
+    },
+    #[error("quotient commitment {0} contains no AIR")]
+    EmptyQuotientCommitment(usize),
+    #[error("{round:?} round has {actual} commitments, expected {expected}")]
+    NumCommitments {
+        round: OpeningRound,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{round:?} round has opened values for {actual} commitments, expected {expected}")]
+    NumOpenedCommitments {
+        round: OpeningRound,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{round:?} commitment {commit_idx} opens {actual} matrices, expected {expected}")]
+    NumOpenedMatrices {
+        round: OpeningRound,
+        commit_idx: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{round:?} matrix {matrix_idx} of commitment {commit_idx} has width {actual}, expected {expected}")]
+    OpenedWidth {
+        round: OpeningRound,
+        commit_idx: usize,
+        matrix_idx: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{round:?} matrix {matrix_idx} of commitment {commit_idx} is opened at {actual} rotations, expected {expected}")]
+    NumOpenedRotations {
+        round: OpeningRound,
+        commit_idx: usize,
+        matrix_idx: usize,
+        expected: usize,
+        actual: usize,
+    },
 }
@@ -1,19 +1,17 @@
 use std::{
+    iter::zip,
     marker::PhantomData,
     ops::{Add, AddAssign, MulAssign},
 };
 
 use p3_field::{ExtensionField, Field, FieldAlgebra};
-use p3_matrix::Matrix;
+use p3_matrix::{dense::RowMajorMatrixView, Matrix};
 
 use crate::{
-    air_builders::{
-        symbolic::{
-            symbolic_expression::SymbolicEvaluator,
-            symbolic_variable::{Entry, SymbolicVariable},
-            SymbolicExpressionDag,
-        },
-        ViewPair,
+    air_builders::symbolic::{
+        symbolic_expression::SymbolicEvaluator,
+        symbolic_variable::{Entry, SymbolicVariable},
+        SymbolicExpressionDag,
     },
     config::{StarkGenericConfig, Val},
 };
@@ -31,13 +29,18 @@ pub type VerifierConstraintFolder<'a, SC> = GenericVerifierConstraintFolder<
 ///
 /// `Var` is still a challenge type because this is a verifier.
 pub struct GenericVerifierConstraintFolder<'a, F, EF, PubVar, Var, Expr> {
-    pub preprocessed: ViewPair<'a, Var>,
-    pub partitioned_main: Vec<ViewPair<'a, Var>>,
-    pub after_challenge: Vec<ViewPair<'a, Var>>,
+    /// The values of each trace matrix at the rotations the constraints read, one row per
+    /// rotation.
+    pub preprocessed: RowMajorMatrixView<'a, Var>,
+    pub partitioned_main: Vec<RowMajorMatrixView<'a, Var>>,
+    pub after_challenge: Vec<RowMajorMatrixView<'a, Var>>,
     pub challenges: &'a [Vec<Var>],
     pub is_first_row: Var,
     pub is_last_row: Var,
     pub is_transition: Var,
+    /// Transition window selectors, indexed by window size. Only the sizes used by the
+    /// constraints are set.
+    pub is_transition_window: &'a [Var],
     pub alpha: Var,
     pub accumulator: Expr,
     pub public_values: &'a [PubVar],
@@ -72,6 +75,25 @@ where
             .collect();
         self.accumulator = balanced_sum_rec(&v);
     }
+
+    /// Same as [eval_constraints](Self::eval_constraints), but folds the constraints with
+    /// `coefficients`, one per constraint in order, instead of the powers of `alpha`.
+    pub fn eval_constraints_with_coefficients(
+        &mut self,
+        constraints: &SymbolicExpressionDag<F>,
+        coefficients: &[Var],
+    ) {
+        assert_eq!(coefficients.len(), constraints.constraint_idx.len());
+        let exprs = self.eval_nodes(&constraints.nodes);
+        let v: Vec<Expr> = zip(&constraints.constraint_idx, coefficients)
+            .map(|(&idx, &coefficient)| {
+                let mut expr = exprs[idx].clone();
+                expr *= coefficient;
+                expr
+            })
+            .collect();
+        self.accumulator = balanced_sum_rec(&v);
+    }
 }
 
 fn balanced_sum_rec<E: Clone + Add<Output = E>>(v: &[E]) -> E {
@@ -104,6 +126,9 @@ where
     fn eval_is_transition(&self) -> Expr {
         self.is_transition.into()
     }
+    fn eval_is_transition_window(&self, size: usize) -> Expr {
+        self.is_transition_window[size].into()
+    }
     fn eval_var(&self, symbolic_var: SymbolicVariable<F>) -> Expr {
         let index = symbolic_var.index;
         match symbolic_var.entry {
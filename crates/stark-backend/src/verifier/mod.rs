@@ -1,4 +1,8 @@
-use std::iter::zip;
+use std::{
+    collections::HashMap,
+    iter::{self, zip},
+    sync::Arc,
+};
 
 use itertools::{izip, zip_eq, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger};
@@ -8,10 +12,14 @@ use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use crate::{
+    accumulation::{AccumulationStrategy, AlphaPowers},
+    challenger::{observe_main_commitments, observe_quotient_chunks, MainCommitHook},
     config::{Com, Domain, StarkGenericConfig, Val},
     interaction::RapPhaseSeq,
     keygen::{types::MultiStarkVerifyingKey, view::MultiStarkVerifyingKeyView},
     proof::{AdjacentOpenedValues, Proof},
+    rap::num_active_public_values,
+    utils::quotient_domain,
     verifier::constraints::verify_single_rap_constraints,
 };
 
@@ -23,17 +31,63 @@ pub mod folder;
 pub use error::*;
 pub use folder::GenericVerifierConstraintFolder;
 
+/// The challenges sampled while proving, as recovered by
+/// [MultiTraceStarkVerifier::recover_challenges].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecoveredChallenges<Challenge> {
+    /// For each challenge phase, the challenges sampled in it, such as the LogUp challenges.
+    pub challenges_per_phase: Vec<Vec<Challenge>>,
+    /// For each AIR in the proof, the `alpha` challenge folding its constraints.
+    pub alphas: Vec<Challenge>,
+    /// The out-of-domain point at which the trace matrices are opened.
+    pub zeta: Challenge,
+}
+
+/// The trace domain and quotient chunk domains of an AIR, by trace height, quotient degree and
+/// number of quotient chunks. Shared by the proofs of a
+/// [verify_batch](MultiTraceStarkVerifier::verify_batch).
+type DomainCache<SC> = HashMap<(usize, u8, u8), (Domain<SC>, Vec<Domain<SC>>)>;
+
 /// Verifies a partitioned proof of multi-matrix AIRs.
 pub struct MultiTraceStarkVerifier<'c, SC: StarkGenericConfig> {
     config: &'c SC,
+    main_commit_hook: Option<MainCommitHook<SC::Challenger, Com<SC>>>,
+    accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
 }
 
 impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
     pub fn new(config: &'c SC) -> Self {
-        Self { config }
+        Self {
+            config,
+            main_commit_hook: None,
+            accumulation: Arc::new(AlphaPowers),
+        }
+    }
+
+    /// Folds the constraints of each AIR with `accumulation`, which must be the strategy of the
+    /// prover. The default is [AlphaPowers].
+    pub fn with_accumulation(
+        mut self,
+        accumulation: Arc<dyn AccumulationStrategy<SC::Challenge>>,
+    ) -> Self {
+        self.accumulation = accumulation;
+        self
+    }
+
+    /// Invokes `hook` after each main trace commitment is observed, as the prover did, see
+    /// [MainCommitHook].
+    pub fn with_main_commit_hook(
+        mut self,
+        hook: MainCommitHook<SC::Challenger, Com<SC>>,
+    ) -> Self {
+        self.main_commit_hook = Some(hook);
+        self
     }
     /// Verify collection of InteractiveAIRs and check the permutation
     /// cumulative sum is equal to zero across all AIRs.
+    ///
+    /// The proof may cover any subset of the AIRs in `mvk`. The cumulative sum is then taken over
+    /// the AIRs in the proof only, so every bus must balance among them.
     #[instrument(name = "MultiTraceStarkVerifier::verify", level = "debug", skip_all)]
     pub fn verify(
         &self,
@@ -47,6 +101,74 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         Ok(())
     }
 
+    /// Verifies each of `proofs` against `mvk` as in [verify](Self::verify), each starting from
+    /// the state of `challenger`. Returns the result of each proof, so a failing proof does not
+    /// stop the verification of the others.
+    ///
+    /// The work that only depends on `mvk` is shared by the proofs: the pre-hash of `mvk` is
+    /// observed once into a copy of `challenger`, which is cloned for each proof, and the domains
+    /// are computed once per trace height and quotient chunking. The symbolic constraints are
+    /// read from `mvk` as they are, without recomputing them per proof.
+    #[instrument(name = "MultiTraceStarkVerifier::verify_batch", level = "debug", skip_all)]
+    pub fn verify_batch(
+        &self,
+        challenger: &SC::Challenger,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proofs: &[Proof<SC>],
+    ) -> Vec<Result<(), VerificationError>>
+    where
+        SC::Challenger: Clone,
+    {
+        let mut key_challenger = challenger.clone();
+        key_challenger.observe(mvk.pre_hash.clone());
+        let mut domain_cache = DomainCache::<SC>::default();
+        proofs
+            .iter()
+            .map(|proof| {
+                let mvk = mvk.view(&proof.get_air_ids());
+                let failures = self.verify_raps_per_air(
+                    &mut key_challenger.clone(),
+                    &mvk,
+                    proof,
+                    &mut domain_cache,
+                    None,
+                )?;
+                match failures.into_iter().next() {
+                    Some((_, err)) => Err(err),
+                    None => Ok(()),
+                }
+            })
+            .collect()
+    }
+
+    /// Same as [verify](Self::verify), but checks the constraints of every AIR instead of
+    /// stopping at the first AIR whose constraints fail, and reports all of them by `air_id`.
+    ///
+    /// Failures which do not belong to a single AIR, such as an invalid proof shape or opening
+    /// argument, are still reported as a single [VerifyAllError::Global] error.
+    #[instrument(name = "MultiTraceStarkVerifier::verify_all", level = "debug", skip_all)]
+    pub fn verify_all(
+        &self,
+        challenger: &mut SC::Challenger,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+    ) -> Result<(), VerifyAllError> {
+        let mvk = mvk.view(&proof.get_air_ids());
+        challenger.observe(mvk.pre_hash.clone());
+        let failures = self.verify_raps_per_air(
+            challenger,
+            &mvk,
+            proof,
+            &mut DomainCache::<SC>::default(),
+            None,
+        )?;
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(VerifyAllError::Airs(failures))
+        }
+    }
+
     /// Verify general RAPs without checking any relations (e.g., cumulative sum) between exposed values of different RAPs.
     ///
     /// Public values is a global list shared across all AIRs.
@@ -61,6 +183,61 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         proof: &Proof<SC>,
     ) -> Result<(), VerificationError> {
         challenger.observe(mvk.pre_hash.clone());
+        let failures = self.verify_raps_per_air(
+            challenger,
+            mvk,
+            proof,
+            &mut DomainCache::<SC>::default(),
+            None,
+        )?;
+        match failures.into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Replays the transcript of `proof` from `challenger` to recover the challenges sampled by
+    /// the prover, for debugging.
+    ///
+    /// The proof is verified along the way, but only failures before `zeta` is sampled, such as
+    /// an invalid proof shape, are returned. The challenges of a proof failing later checks, such
+    /// as its constraints, are still recovered.
+    pub fn recover_challenges(
+        &self,
+        challenger: &mut SC::Challenger,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+    ) -> Result<RecoveredChallenges<SC::Challenge>, VerificationError> {
+        let mvk = mvk.view(&proof.get_air_ids());
+        challenger.observe(mvk.pre_hash.clone());
+        let mut recovered = None;
+        let result = self.verify_raps_per_air(
+            challenger,
+            &mvk,
+            proof,
+            &mut DomainCache::<SC>::default(),
+            Some(&mut recovered),
+        );
+        match recovered {
+            Some(recovered) => Ok(recovered),
+            None => Err(result.expect_err("challenges are recovered before verification ends")),
+        }
+    }
+
+    /// Verifies the RAPs as in [verify_raps](Self::verify_raps), returning the `air_id` and error
+    /// of each AIR whose constraints fail. Any other failure is returned as the error.
+    ///
+    /// The pre-hash of `mvk` must already be observed by `challenger`. The domains of the AIRs
+    /// are taken from `domain_cache`, and added to it if missing. If `recovered` is given, the
+    /// challenges are stored in it as soon as `zeta` is sampled.
+    fn verify_raps_per_air(
+        &self,
+        challenger: &mut SC::Challenger,
+        mvk: &MultiStarkVerifyingKeyView<Val<SC>, Com<SC>>,
+        proof: &Proof<SC>,
+        domain_cache: &mut DomainCache<SC>,
+        recovered: Option<&mut Option<RecoveredChallenges<SC::Challenge>>>,
+    ) -> Result<Vec<(usize, VerificationError)>, VerificationError> {
         let air_ids = proof.get_air_ids();
         let num_airs = air_ids.len();
         challenger.observe(Val::<SC>::from_canonical_usize(num_airs));
@@ -93,8 +270,18 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         let public_values = proof.get_public_values();
         // (T03a): verify shape of public values
         {
-            for (pvs_per_air, vk) in zip_eq(&public_values, &mvk.per_air) {
+            for (air_proof, vk) in zip_eq(&proof.per_air, &mvk.per_air) {
+                let pvs_per_air = &air_proof.public_values;
                 if pvs_per_air.len() != vk.params.num_public_values {
+                    return Err(VerificationError::PublicValueCountMismatch {
+                        air_id: air_proof.air_id,
+                        expected: vk.params.num_public_values,
+                        actual: pvs_per_air.len(),
+                    });
+                }
+                if vk.params.variable_public_values
+                    && num_active_public_values(pvs_per_air).is_none()
+                {
                     return Err(VerificationError::InvalidProofShape);
                 }
             }
@@ -121,7 +308,11 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
             }
         }
         // Observe main trace commitments
-        challenger.observe_slice(&proof.commitments.main_trace);
+        observe_main_commitments(
+            challenger,
+            &proof.commitments.main_trace,
+            self.main_commit_hook.as_ref(),
+        );
         challenger.observe_slice(
             &proof
                 .per_air
@@ -145,9 +336,7 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
             .map(|after_challenge_per_matrix| {
                 after_challenge_per_matrix
                     .iter()
-                    .map(|after_challenge| {
-                        vec![after_challenge.local.clone(), after_challenge.next.clone()]
-                    })
+                    .map(|after_challenge| after_challenge.rows().cloned().collect_vec())
                     .collect_vec()
             })
             .collect_vec();
@@ -155,7 +344,7 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         // (T01b): `num_phases < 2`.
         // Assumption: valid mvk has num_phases consistent between num_challenges_to_sample and exposed_values
         let num_phases = mvk.num_phases();
-        if num_phases != proof.commitments.after_challenge.len() || num_phases > 1 {
+        if num_phases > 1 || (num_phases == 0) != proof.commitments.after_challenge.is_empty() {
             return Err(VerificationError::InvalidProofShape);
         }
         // (T01c): validate shape of `exposed_values_after_challenge`
@@ -179,16 +368,60 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         let rap_phase_seq_result =
             rap_phase_seq_result.map_err(|_| VerificationError::ChallengePhaseError);
 
-        // Draw `alpha` challenge
-        let alpha: SC::Challenge = challenger.sample_ext_element();
-        tracing::debug!("alpha: {alpha:?}");
+        // Draw `alpha` challenges
+        let alphas: Vec<SC::Challenge> = self.config.alpha_sampling().sample(challenger, num_airs);
+        tracing::debug!("alphas: {alphas:?}");
 
-        // Observe quotient commitments
-        challenger.observe(proof.commitments.quotient.clone());
+        // (T06a): validate shape of quotient commitments: every AIR belongs to an existing
+        // quotient commitment, and every quotient commitment contains at least one AIR.
+        {
+            let num_quotient_commits = proof.commitments.quotient.len();
+            if num_quotient_commits == 0
+                || proof
+                    .per_air
+                    .iter()
+                    .any(|ap| ap.quotient_commit_idx >= num_quotient_commits)
+                || (0..num_quotient_commits).any(|idx| {
+                    proof
+                        .per_air
+                        .iter()
+                        .all(|ap| ap.quotient_commit_idx != idx)
+                })
+            {
+                return Err(VerificationError::InvalidProofShape);
+            }
+        }
+        // The quotient degree recorded for each AIR must match its verifying key, and the quotient
+        // polynomial must be split into a power of two number of chunks dividing it.
+        if zip_eq(&proof.per_air, &mvk.per_air).any(|(ap, vk)| {
+            ap.quotient_degree != vk.quotient_degree || !ap.has_valid_quotient_chunks()
+        }) {
+            return Err(VerificationError::InvalidProofShape);
+        }
+        // Observe the number of quotient chunks of each AIR if the chunking is not the default,
+        // and the quotient commitments
+        let (num_quotient_chunks_per_air, quotient_degrees): (Vec<_>, Vec<_>) = proof
+            .per_air
+            .iter()
+            .map(|ap| (ap.num_quotient_chunks, ap.quotient_degree))
+            .unzip();
+        observe_quotient_chunks::<Val<SC>, _>(
+            challenger,
+            &num_quotient_chunks_per_air,
+            &quotient_degrees,
+        );
+        SC::observe_quotient_commitments(challenger, &proof.commitments.quotient);
 
         // Draw `zeta` challenge
         let zeta: SC::Challenge = challenger.sample_ext_element();
         tracing::debug!("zeta: {zeta:?}");
+        if let Some(recovered) = recovered {
+            *recovered = Some(RecoveredChallenges {
+                challenges_per_phase: after_challenge_data.challenges_per_phase.clone(),
+                alphas: alphas.clone(),
+                zeta,
+            });
+        }
 
         let pcs = self.config.pcs();
         // Build domains
@@ -199,39 +432,46 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
             .map(|(vk, air_proof)| {
                 let degree = air_proof.degree;
                 let quotient_degree = vk.quotient_degree;
-                let domain = pcs.natural_domain_for_degree(degree);
-                let quotient_domain =
-                    domain.create_disjoint_domain(degree * quotient_degree as usize);
-                let qc_domains = quotient_domain.split_domains(quotient_degree as usize);
-                (domain, qc_domains)
+                let num_chunks = air_proof.num_quotient_chunks;
+                domain_cache
+                    .entry((degree, quotient_degree, num_chunks))
+                    .or_insert_with(|| {
+                        let domain = pcs.natural_domain_for_degree(degree);
+                        let quotient_domain = quotient_domain(domain, quotient_degree);
+                        let qc_domains = quotient_domain.split_domains(num_chunks as usize);
+                        (domain, qc_domains)
+                    })
+                    .clone()
             })
             .unzip();
         // Verify all opening proofs
         let opened_values = &proof.opening.values;
+        // Trace matrices are opened at `zeta` and the next points of the trace domain
         let trace_domain_and_openings =
             |domain: Domain<SC>,
              zeta: SC::Challenge,
              values: &AdjacentOpenedValues<SC::Challenge>| {
-                (
-                    domain,
-                    vec![
-                        (zeta, values.local.clone()),
-                        (domain.next_point(zeta).unwrap(), values.next.clone()),
-                    ],
-                )
+                let points = iter::successors(Some(zeta), |&x| domain.next_point(x));
+                (domain, zip(points, values.rows().cloned()).collect_vec())
             };
+        // All trace matrices of an AIR are opened at `vk.num_rotations()` points
+        let has_shape = |values: &AdjacentOpenedValues<SC::Challenge>,
+                         width: usize,
+                         num_rotations: usize| {
+            values.num_rotations() == num_rotations && values.rows().all(|row| row.len() == width)
+        };
         // Build the opening rounds
         // 1. First the preprocessed trace openings
         // Assumption: each AIR with preprocessed trace has its own commitment and opening values
         // T05a: validate `opened_values.preprocessed` shape
-        let preprocessed_widths: Vec<usize> = mvk
+        let preprocessed_shapes: Vec<(usize, usize)> = mvk
             .per_air
             .iter()
-            .filter_map(|vk| vk.params.width.preprocessed)
+            .filter_map(|vk| Some((vk.params.width.preprocessed?, vk.num_rotations())))
             .collect();
-        if preprocessed_widths.len() != opened_values.preprocessed.len()
-            || zip_eq(preprocessed_widths, &opened_values.preprocessed)
-                .any(|(w, ov)| w != ov.local.len() || w != ov.next.len())
+        if preprocessed_shapes.len() != opened_values.preprocessed.len()
+            || zip_eq(preprocessed_shapes, &opened_values.preprocessed)
+                .any(|((w, r), ov)| !has_shape(ov, w, r))
         {
             return Err(VerificationError::InvalidProofShape);
         }
@@ -262,7 +502,7 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
                     return Err(VerificationError::InvalidProofShape);
                 }
                 let value = &opened_values.main[main_commit_idx][0];
-                if cached_main_width != value.local.len() || cached_main_width != value.next.len() {
+                if !has_shape(value, cached_main_width, vk.num_rotations()) {
                     return Err(VerificationError::InvalidProofShape);
                 }
                 let domains_and_openings = vec![trace_domain_and_openings(*domain, zeta, value)];
@@ -279,7 +519,7 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
                 .zip(values_per_mat)
                 .map(|((vk, domain), values)| {
                     let width = vk.params.width.common_main;
-                    if width != values.local.len() || width != values.next.len() {
+                    if !has_shape(values, width, vk.num_rotations()) {
                         Err(VerificationError::InvalidProofShape)
                     } else {
                         Ok(trace_domain_and_openings(*domain, zeta, values))
@@ -294,7 +534,8 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
 
         let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
         // 3. Then after_challenge trace openings, at most 1 phase for now.
-        // All AIRs with interactions should an after challenge trace.
+        // All AIRs with interactions should an after challenge trace. The phase may have multiple
+        // commitments, each with the traces of a non-empty run of consecutive AIRs.
         let mut after_challenge_vk_domain_per_air = zip_eq(&mvk.per_air, &domains)
             .filter(|(vk, _)| vk.has_interaction())
             .peekable();
@@ -306,34 +547,45 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
             }
             assert_eq!(num_phases, 0);
         } else {
-            if num_phases != 1 || opened_values.after_challenge.len() != 1 {
+            if num_phases != 1
+                || opened_values.after_challenge.len() != proof.commitments.after_challenge.len()
+            {
                 return Err(VerificationError::InvalidProofShape);
             }
-            let after_challenge_commit = proof.commitments.after_challenge[0].clone();
-            let domains_and_openings = zip(
-                after_challenge_vk_domain_per_air,
-                &opened_values.after_challenge[0],
-            )
-            .map(|((vk, domain), values)| {
-                let width = vk.params.width.after_challenge[0] * ext_degree;
-                if width != values.local.len() || width != values.next.len() {
-                    Err(VerificationError::InvalidProofShape)
-                } else {
-                    Ok(trace_domain_and_openings(*domain, zeta, values))
+            for (commit, values_per_mat) in
+                zip(&proof.commitments.after_challenge, &opened_values.after_challenge)
+            {
+                // `values_per_mat` goes first so that no AIR is skipped once it is exhausted
+                let domains_and_openings = zip(
+                    values_per_mat,
+                    after_challenge_vk_domain_per_air.by_ref(),
+                )
+                .map(|(values, (vk, domain))| {
+                    let width = vk.params.width.after_challenge[0] * ext_degree;
+                    if !has_shape(values, width, vk.num_rotations()) {
+                        Err(VerificationError::InvalidProofShape)
+                    } else {
+                        Ok(trace_domain_and_openings(*domain, zeta, values))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+                if domains_and_openings.is_empty()
+                    || domains_and_openings.len() != values_per_mat.len()
+                {
+                    return Err(VerificationError::InvalidProofShape);
                 }
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-            if domains_and_openings.len() != opened_values.after_challenge[0].len() {
+                rounds.push((commit.clone(), domains_and_openings));
+            }
+            if after_challenge_vk_domain_per_air.next().is_some() {
                 return Err(VerificationError::InvalidProofShape);
             }
-            rounds.push((after_challenge_commit, domains_and_openings));
         }
         if opened_values.quotient.len() != num_airs {
             return Err(VerificationError::InvalidProofShape);
         }
         // All opened_values.quotient should have width D
-        if zip_eq(&opened_values.quotient, &mvk.per_air).any(|(per_air, vk)| {
-            per_air.len() != vk.quotient_degree as usize || {
+        if zip_eq(&opened_values.quotient, &proof.per_air).any(|(per_air, air_proof)| {
+            per_air.len() != air_proof.num_quotient_chunks as usize || {
                 per_air
                     .iter()
                     .any(|per_chunk| per_chunk.len() != ext_degree)
@@ -341,35 +593,42 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         }) {
             return Err(VerificationError::InvalidProofShape);
         }
-        let quotient_domains_and_openings =
-            zip_eq(&opened_values.quotient, &quotient_chunks_domains)
-                .flat_map(|(chunk, quotient_chunks_domains_per_air)| {
-                    chunk
-                        .iter()
-                        .zip_eq(quotient_chunks_domains_per_air)
-                        .map(|(values, &domain)| (domain, vec![(zeta, values.clone())]))
-                })
-                .collect_vec();
-        rounds.push((
-            proof.commitments.quotient.clone(),
-            quotient_domains_and_openings,
-        ));
+        // Within each quotient commitment, the quotient chunks are ordered by AIR.
+        for (commit_idx, commit) in proof.commitments.quotient.iter().enumerate() {
+            let quotient_domains_and_openings = izip!(
+                &opened_values.quotient,
+                &quotient_chunks_domains,
+                &proof.per_air
+            )
+            .filter(|(_, _, air_proof)| air_proof.quotient_commit_idx == commit_idx)
+            .flat_map(|(chunk, quotient_chunks_domains_per_air, _)| {
+                chunk
+                    .iter()
+                    .zip_eq(quotient_chunks_domains_per_air)
+                    .map(|(values, &domain)| (domain, vec![(zeta, values.clone())]))
+            })
+            .collect_vec();
+            rounds.push((commit.clone(), quotient_domains_and_openings));
+        }
 
         pcs.verify(rounds, &proof.opening.proof, challenger)
             .map_err(|e| VerificationError::InvalidOpeningArgument(format!("{:?}", e)))?;
 
         let mut preprocessed_idx = 0usize; // preprocessed commit idx
-        let mut after_challenge_idx = vec![0usize; num_phases];
+        // The after challenge traces of all commitments, in order of AIRs with interactions
+        let mut after_challenge_values = opened_values.after_challenge.iter().flatten();
         let mut cached_main_commit_idx = 0;
         let mut common_main_matrix_idx = 0;
 
         // Verify each RAP's constraints
-        for (domain, qc_domains, quotient_chunks, vk, air_proof) in izip!(
+        let mut failures = vec![];
+        for (domain, qc_domains, quotient_chunks, vk, air_proof, alpha) in izip!(
             domains,
             quotient_chunks_domains,
             &opened_values.quotient,
             &mvk.per_air,
-            &proof.per_air
+            &proof.per_air,
+            alphas
         ) {
             let preprocessed_values = vk.preprocessed_data.as_ref().map(|_| {
                 let values = &opened_values.preprocessed[preprocessed_idx];
@@ -387,34 +646,37 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
                 common_main_matrix_idx += 1;
             }
             // loop through challenge phases of this single RAP
-            let after_challenge_values = if vk.has_interaction() {
+            // At most 1 challenge phase, see (T01b)
+            let after_challenge_values_per_phase = if vk.has_interaction() {
                 (0..num_phases)
-                    .map(|phase_idx| {
-                        let matrix_idx = after_challenge_idx[phase_idx];
-                        after_challenge_idx[phase_idx] += 1;
-                        &opened_values.after_challenge[phase_idx][matrix_idx]
-                    })
+                    .map(|_| after_challenge_values.next().unwrap())
                     .collect_vec()
             } else {
                 vec![]
             };
-            verify_single_rap_constraints::<SC>(
+            if let Err(err) = verify_single_rap_constraints::<SC>(
                 &vk.symbolic_constraints.constraints,
                 preprocessed_values,
                 partitioned_main_values,
-                after_challenge_values,
+                after_challenge_values_per_phase,
                 quotient_chunks,
                 domain,
                 &qc_domains,
                 zeta,
                 alpha,
+                self.accumulation.as_ref(),
                 &after_challenge_data.challenges_per_phase,
                 &air_proof.public_values,
                 &air_proof.exposed_values_after_challenge,
-            )?;
+            ) {
+                failures.push((air_proof.air_id, err));
+            }
         }
 
-        // If we made it this far, use the `rap_phase_result` as the final result.
-        rap_phase_seq_result
+        if failures.is_empty() {
+            // If we made it this far, use the `rap_phase_result` as the final result.
+            rap_phase_seq_result?;
+        }
+        Ok(failures)
     }
 }
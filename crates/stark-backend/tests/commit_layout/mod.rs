@@ -0,0 +1,334 @@
+use std::{iter::zip, sync::Arc};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    p3_challenger::{CanObserve, FieldChallenger},
+    p3_commit::Pcs,
+    p3_field::FieldAlgebra,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    prover::{
+        cpu::{CpuDevice, PcsData, PcsDataError},
+        hal::TraceCommitter,
+        types::{PermutationCommitGrouping, QuotientCommitGrouping},
+    },
+    AirRef,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{val_mmcs_from_perm, BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air, fib_air::air::FibonacciAir,
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkFriEngine,
+    utils::{create_seeded_rng, prove_on_device},
+};
+use p3_baby_bear::BabyBear;
+use rand::Rng;
+
+use crate::{
+    fib_selector_air, fib_selector_air::air::FibonacciSelectorAir, get_conditional_fib_number,
+    get_fib_number,
+};
+
+type Challenge = <BabyBearPoseidon2Config as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_per_air_quotient_commitments() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let a = 0u32;
+    let b = 1u32;
+    let n1 = 1usize << 3;
+    let n2 = 1usize << 5;
+    let sels: Vec<bool> = (0..n2).map(|i| i % 2 == 0).collect();
+    let pis1 = [a, b, get_fib_number(n1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [a, b, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air2 = FibonacciSelectorAir::new(sels, false);
+    let trace1 = fib_air::trace::generate_trace_rows::<BabyBear>(a, b, n1);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<BabyBear>(a, b, air2.sels());
+
+    let airs = any_rap_arc_vec![FibonacciAir, air2];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_quotient_grouping(QuotientCommitGrouping::PerAir);
+    let inputs = vec![(air_ids[0], trace1, pis1), (air_ids[1], trace2, pis2)];
+    let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    assert_eq!(proof.commitments.quotient.len(), 2);
+    assert_eq!(
+        proof
+            .per_air
+            .iter()
+            .map(|ap| ap.quotient_commit_idx)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_per_air_perm_commitments() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let n = 1usize << 3;
+    let fib_pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let fib_trace = fib_air::trace::generate_trace_rows::<BabyBear>(0, 1, n);
+    // Rows of (count, field), sent by one AIR and received by the other
+    let interaction_trace = RowMajorMatrix::new(
+        [1, 4, 2, 5, 0, 6, 3, 7]
+            .map(BabyBear::from_canonical_u32)
+            .to_vec(),
+        2,
+    );
+
+    let airs = any_rap_arc_vec![
+        DummyInteractionAir::new(1, true, 0),
+        FibonacciAir,
+        DummyInteractionAir::new(1, false, 0)
+    ];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_perm_grouping(PermutationCommitGrouping::PerAir);
+    let inputs = vec![
+        (air_ids[0], interaction_trace.clone(), vec![]),
+        (air_ids[1], fib_trace, fib_pis),
+        (air_ids[2], interaction_trace, vec![]),
+    ];
+    let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    // One permutation commitment for each of the two AIRs with interactions
+    assert_eq!(proof.commitments.after_challenge.len(), 2);
+    assert!(proof
+        .opening
+        .values
+        .after_challenge
+        .iter()
+        .all(|values_per_mat| values_per_mat.len() == 1));
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_max_matrices_per_commit() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    // Rows of (count, field). Three AIRs send the same rows, and two AIRs receive them with
+    // counts 2 and 1.
+    let interaction_trace = |counts: [u32; 4]| {
+        let values = counts.into_iter().zip([4, 5, 6, 7]).flat_map(|(c, f)| [c, f]);
+        RowMajorMatrix::new(values.map(BabyBear::from_canonical_u32).collect(), 2)
+    };
+    let airs = any_rap_arc_vec![
+        DummyInteractionAir::new(1, true, 0),
+        DummyInteractionAir::new(1, true, 0),
+        DummyInteractionAir::new(1, true, 0),
+        DummyInteractionAir::new(1, false, 0),
+        DummyInteractionAir::new(1, false, 0)
+    ];
+    let traces = [
+        interaction_trace([1, 2, 0, 3]),
+        interaction_trace([1, 2, 0, 3]),
+        interaction_trace([1, 2, 0, 3]),
+        interaction_trace([2, 4, 0, 6]),
+        interaction_trace([1, 2, 0, 3]),
+    ];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_max_matrices_per_commit(2);
+    let inputs = zip(air_ids, traces)
+        .map(|(air_id, trace)| (air_id, trace, vec![]))
+        .collect();
+    let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    // The five permutation traces are split into commitments of 2, 2 and 1 matrices
+    assert_eq!(proof.commitments.after_challenge.len(), 3);
+    let perm_matrices = proof
+        .opening
+        .values
+        .after_challenge
+        .iter()
+        .map(|values_per_mat| values_per_mat.len())
+        .collect::<Vec<_>>();
+    assert_eq!(perm_matrices, vec![2, 2, 1]);
+    // Every quotient commitment has at most 2 quotient chunks
+    for commit_idx in 0..proof.commitments.quotient.len() {
+        let num_chunks: usize = proof
+            .per_air
+            .iter()
+            .filter(|ap| ap.quotient_commit_idx == commit_idx)
+            .map(|ap| ap.quotient_degree as usize)
+            .sum();
+        assert!((1..=2).contains(&num_chunks));
+    }
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_commit_ordered_ascending_heights() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let pcs = engine.config().pcs();
+    let mut rng = create_seeded_rng();
+    let mut random_matrix = |height: usize, width: usize| {
+        let values = (0..height * width)
+            .map(|_| BabyBear::from_canonical_u32(rng.gen_range(0..1 << 30)))
+            .collect();
+        Arc::new(RowMajorMatrix::new(values, width))
+    };
+    // Ascending heights, while the MMCS lays matrices out by decreasing height
+    let traces = [random_matrix(4, 2), random_matrix(16, 3)];
+
+    let (commit, data, permutation) = device.commit_ordered(&traces);
+    assert_eq!(permutation, vec![1, 0]);
+
+    let mut challenger = engine.new_challenger();
+    challenger.observe(commit);
+    let zeta = challenger.sample_ext_element::<Challenge>();
+    let (values, proof) = pcs.open(
+        vec![(data.data.as_ref(), vec![vec![zeta]; 2])],
+        &mut challenger,
+    );
+    // Openings follow the order of the committed traces, not the MMCS layout
+    let values = &values[0];
+    assert_eq!(values[0][0].len(), 2);
+    assert_eq!(values[1][0].len(), 3);
+
+    let mut challenger = engine.new_challenger();
+    challenger.observe(commit);
+    assert_eq!(challenger.sample_ext_element::<Challenge>(), zeta);
+    let rounds = vec![(
+        commit,
+        zip(&traces, values)
+            .map(|(trace, values)| {
+                let domain = pcs.natural_domain_for_degree(trace.height());
+                (domain, vec![(zeta, values[0].clone())])
+            })
+            .collect(),
+    )];
+    pcs.verify(rounds, &proof, &mut challenger)
+        .expect("Opening verification failed");
+}
+
+#[test]
+fn test_pcs_data_from_precomputed_tree() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let pcs = engine.config().pcs();
+    let mmcs = val_mmcs_from_perm(&engine.perm);
+    let trace = Arc::new(RowMajorMatrix::new(
+        (0..32).map(BabyBear::from_canonical_u32).collect(),
+        4,
+    ));
+
+    // The tree is computed on CPU here, but could be produced by any other device
+    let (commit, data) = device.commit(&[trace.clone()]);
+    let (tree, log_trace_heights) = (data.data, data.log_trace_heights);
+    let other_trace = Arc::new(RowMajorMatrix::new(vec![BabyBear::ONE; 32], 4));
+    let (other_commit, _) = device.commit(&[other_trace]);
+    assert_eq!(
+        PcsData::<BabyBearPoseidon2Config>::from_precomputed(
+            &mmcs,
+            &other_commit,
+            &tree,
+            log_trace_heights.clone()
+        )
+        .err(),
+        Some(PcsDataError::CommitmentMismatch)
+    );
+    assert_eq!(
+        PcsData::<BabyBearPoseidon2Config>::from_precomputed(&mmcs, &commit, &tree, vec![])
+            .err(),
+        Some(PcsDataError::HeightsMismatch)
+    );
+    let data = PcsData::<BabyBearPoseidon2Config>::from_precomputed(
+        &mmcs,
+        &commit,
+        &tree,
+        log_trace_heights,
+    )
+    .expect("root should match the commitment");
+
+    let mut challenger = engine.new_challenger();
+    challenger.observe(commit);
+    let zeta = challenger.sample_ext_element::<Challenge>();
+    let (values, proof) = pcs.open(vec![(data.data.as_ref(), vec![vec![zeta]])], &mut challenger);
+
+    let mut challenger = engine.new_challenger();
+    challenger.observe(commit);
+    assert_eq!(challenger.sample_ext_element::<Challenge>(), zeta);
+    let domain = pcs.natural_domain_for_degree(trace.height());
+    let rounds = vec![(commit, vec![(domain, vec![(zeta, values[0][0][0].clone())])])];
+    pcs.verify(rounds, &proof, &mut challenger)
+        .expect("Opening verification failed");
+}
+
+#[test]
+fn test_parallel_commits_match_serial() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let serial_device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_perm_grouping(PermutationCommitGrouping::PerAir)
+        .with_quotient_grouping(QuotientCommitGrouping::PerAir);
+    let parallel_device = serial_device.clone().with_parallel_commits(true);
+
+    // Many AIRs with their own permutation and quotient commitments, committed in parallel.
+    // Only the commitments are compared, since the proof-of-work witnesses may differ.
+    let num_pairs = 8;
+    let airs = (0..2 * num_pairs)
+        .map(|i| -> AirRef<BabyBearPoseidon2Config> {
+            Arc::new(DummyInteractionAir::new(1, i % 2 == 0, 0))
+        })
+        .collect::<Vec<_>>();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+    // The AIRs at positions `2 * pair` and `2 * pair + 1` send and receive the same messages
+    let interaction_trace = |pair: usize| {
+        let height = 1 << (pair % 4 + 2);
+        let values = (0..height).flat_map(|row| [row % 3, pair * 100 + row]);
+        RowMajorMatrix::new(values.map(BabyBear::from_canonical_usize).collect(), 2)
+    };
+    let commitments = [serial_device, parallel_device].map(|device| {
+        let inputs = air_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &air_id)| (air_id, interaction_trace(i / 2), vec![]))
+            .collect();
+        let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+            .expect("Proving failed");
+        assert_eq!(proof.commitments.after_challenge.len(), 2 * num_pairs);
+        assert_eq!(proof.commitments.quotient.len(), 2 * num_pairs);
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        (proof.commitments.after_challenge, proof.commitments.quotient)
+    });
+    assert_eq!(commitments[0], commitments[1]);
+}
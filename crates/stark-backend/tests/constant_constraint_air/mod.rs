@@ -0,0 +1,97 @@
+//! An AIR with constraints that fold to constants, which keygen should flag.
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::StarkFriEngine,
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_matrix::Matrix;
+
+/// Asserts that its single column is boolean, together with a dead constraint that is always
+/// zero and, if `unsatisfiable` is set, a constraint that is always one.
+pub struct ConstantConstraintAir {
+    pub unsatisfiable: bool,
+}
+
+impl<F> BaseAir<F> for ConstantConstraintAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F> BaseAirWithPublicValues<F> for ConstantConstraintAir {}
+impl<F> PartitionedBaseAir<F> for ConstantConstraintAir {}
+impl<F> ColumnsAir<F> for ConstantConstraintAir {}
+
+impl<AB: AirBuilder> Air<AB> for ConstantConstraintAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let x: AB::Expr = main.row_slice(0)[0].into();
+
+        builder.assert_bool(x.clone());
+        builder.when_transition().assert_zero(x * AB::Expr::ZERO);
+        if self.unsatisfiable {
+            builder.assert_one(AB::Expr::ZERO);
+        }
+    }
+}
+
+/// Collects the formatted log output.
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn keygen(air: ConstantConstraintAir) {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::new_for_testing(1));
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(air));
+    keygen_builder.generate_pk();
+}
+
+#[test]
+fn test_dead_constraint_warning() {
+    let logs = LogBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_max_level(tracing::Level::WARN)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    tracing::subscriber::with_default(subscriber, || {
+        keygen(ConstantConstraintAir {
+            unsatisfiable: false,
+        })
+    });
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("constraint 1 of ConstantConstraintAir is always zero"));
+}
+
+#[test]
+#[should_panic(expected = "constraint 2 of ConstantConstraintAir is unsatisfiable")]
+fn test_unsatisfiable_constraint_error() {
+    keygen(ConstantConstraintAir {
+        unsatisfiable: true,
+    });
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    air_builders::{
+        debug::{debug_constraints_on_row, FailedConstraint},
+        sub::MergedAir,
+        symbolic::{ConstraintDeps, SymbolicExpressionDag},
+    },
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    p3_air::{AirBuilder, AirBuilderWithPublicValues},
+    p3_field::FieldAlgebra,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    proof::Proof,
+    rap::{ClosureAir, SymbolicAir},
+    utils::disable_debug_builder,
+    AirRef,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    utils::{create_seeded_rng, fuzz_air_satisfiability},
+};
+use p3_baby_bear::BabyBear;
+use rand::Rng;
+
+use crate::{
+    fib_selector_air, fib_selector_air::air::FibonacciSelectorAir, fib_triples_air, get_fib_number,
+};
+
+#[test]
+fn test_merged_fib_airs() {
+    let n = 1usize << 3;
+    let pis1 = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [0, 1, get_fib_number(n + 1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace1 = generate_trace_rows::<BabyBear>(0, 1, n);
+    let trace2 = fib_triples_air::trace::generate_trace_rows::<BabyBear>(0, 1, n);
+
+    let separate = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir, fib_triples_air::air::FibonacciAir],
+        vec![trace1.clone(), trace2.clone()],
+        vec![pis1.clone(), pis2.clone()],
+    )
+    .expect("Verification failed");
+
+    let merged_trace = RowMajorMatrix::new(
+        trace1
+            .rows()
+            .zip(trace2.rows())
+            .flat_map(|(left, right)| left.chain(right))
+            .collect(),
+        trace1.width() + trace2.width(),
+    );
+    let merged_air = MergedAir::new(FibonacciAir, fib_triples_air::air::FibonacciAir);
+    let merged = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![merged_air],
+        vec![merged_trace],
+        vec![[pis1, pis2].concat()],
+    )
+    .expect("Verification failed");
+
+    let num_opened_matrices = |proof: &Proof<_>| {
+        let values = &proof.opening.values;
+        values.main.iter().map(Vec::len).sum::<usize>()
+            + values.quotient.iter().map(Vec::len).sum::<usize>()
+    };
+    let merged_proof = &merged.data.proof;
+    assert_eq!(merged_proof.per_air.len(), 1);
+    assert!(num_opened_matrices(merged_proof) < num_opened_matrices(&separate.data.proof));
+}
+
+#[test]
+fn test_fib_constraint_column_deps() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::new_for_testing(3));
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let deps = pk.per_air[0]
+        .vk
+        .symbolic_constraints
+        .constraints
+        .constraint_column_deps();
+
+    let reads = |main: &[usize], reads_next| ConstraintDeps {
+        main: main.iter().map(|&col| (0, col)).collect(),
+        preprocessed: vec![],
+        reads_next,
+    };
+    assert_eq!(
+        deps,
+        vec![
+            // first row: left == a, right == b
+            reads(&[0], false),
+            reads(&[1], false),
+            // transition: next.left == local.right, next.right == local.left + local.right
+            reads(&[0, 1], true),
+            reads(&[0, 1], true),
+            // last row: right == x
+            reads(&[1], false),
+        ]
+    );
+}
+
+#[test]
+fn test_fib_constraints_text_ir_round_trip() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::new_for_testing(3));
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let dag = &pk.per_air[0].vk.symbolic_constraints.constraints;
+
+    let text = dag.to_text_ir();
+    assert!(text.contains("mul"));
+    assert_eq!(text.lines().filter(|l| l.starts_with("assert_zero")).count(), 5);
+    let parsed = SymbolicExpressionDag::<BabyBear>::from_text_ir(&text).unwrap();
+    assert_eq!(&parsed, dag);
+    assert_eq!(parsed.to_text_ir(), text);
+}
+
+#[test]
+fn test_prove_with_serialized_constraints() {
+    let serialized =
+        serde_json::to_string(&SymbolicAir::<BabyBear>::from_air(&FibonacciAir)).unwrap();
+    let air: SymbolicAir<BabyBear> = serde_json::from_str(&serialized).unwrap();
+
+    // Keygen derives the same constraints from the data as from the original AIR
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let keygen = |air: AirRef<BabyBearPoseidon2Config>| {
+        let mut keygen_builder = engine.keygen_builder();
+        keygen_builder.add_air(air);
+        keygen_builder.generate_pk()
+    };
+    let pk = keygen(Arc::new(air.clone()));
+    let expected_pk = keygen(Arc::new(FibonacciAir));
+    assert_eq!(
+        pk.per_air[0].vk.symbolic_constraints.constraints,
+        expected_pk.per_air[0].vk.symbolic_constraints.constraints
+    );
+
+    let n = 1usize << 3;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+    BabyBearPoseidon2Engine::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_closure_air() {
+    // Starts at the public value and increments by one on each row
+    let air = ClosureAir::<BabyBear>::new(1, 1, |builder| {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0)[0], main.row_slice(1)[0]);
+        let start = builder.public_values()[0];
+        builder.when_first_row().assert_eq(local, start);
+        builder.when_transition().assert_one(next - local);
+    });
+
+    let values = (5..13).map(BabyBear::from_canonical_u32).collect();
+    let trace = RowMajorMatrix::new(values, 1);
+    let pis = vec![BabyBear::from_canonical_u32(5)];
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![air.clone()],
+        vec![trace.clone()],
+        vec![pis],
+    )
+    .expect("Verification failed");
+
+    disable_debug_builder();
+    let wrong_pis = vec![BabyBear::from_canonical_u32(6)];
+    assert!(BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![air],
+        vec![trace],
+        vec![wrong_pis],
+    )
+    .is_err());
+}
+
+#[test]
+fn test_debug_constraints_on_broken_row() {
+    type SC = BabyBearPoseidon2Config;
+    type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+    let n = 8;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let mut trace: RowMajorMatrix<BabyBear> = generate_trace_rows(0, 1, n);
+    // Row 4 is (3, 5); break its `right` column.
+    trace.values[4 * trace.width() + 1] = BabyBear::from_canonical_u32(6);
+
+    let main = [trace.as_view()];
+    let failed = (0..n)
+        .flat_map(|row| {
+            debug_constraints_on_row::<_, SC>(&FibonacciAir, &None, &main, &pis, row)
+        })
+        .collect::<Vec<_>>();
+    let failed_constraint = |row_index, constraint_idx, value: BabyBear| {
+        FailedConstraint::<Challenge> {
+            row_index,
+            constraint_idx,
+            value: value.into(),
+        }
+    };
+    assert_eq!(
+        failed,
+        vec![
+            // 2 + 3 != 6
+            failed_constraint(3, 3, BabyBear::NEG_ONE),
+            // 6 != 5
+            failed_constraint(4, 2, BabyBear::ONE),
+            // 3 + 6 != 8
+            failed_constraint(4, 3, BabyBear::ONE),
+        ]
+    );
+}
+
+#[test]
+fn test_fuzz_fib_air_satisfiability() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut rng = create_seeded_rng();
+    // Heights 4 to 2^11, starting from random initial values
+    fuzz_air_satisfiability(&engine, Arc::new(FibonacciAir), 10, 2, |case| {
+        let n = 1 << (case + 2);
+        let trace = generate_trace_rows::<BabyBear>(rng.gen(), rng.gen(), n);
+        let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(n - 1, 1)];
+        (trace, public_values)
+    });
+}
+
+#[test]
+fn test_fuzz_preprocessed_air_satisfiability() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut rng = create_seeded_rng();
+    // The selectors are the preprocessed trace, so all cases have its height
+    let sels: Vec<bool> = (0..16).map(|i| i % 3 != 0).collect();
+    let air = FibonacciSelectorAir::new(sels.clone(), false);
+    fuzz_air_satisfiability(&engine, Arc::new(air), 4, 2, |_| {
+        let (a, b) = (rng.gen_range(0..1 << 30), rng.gen_range(0..1 << 30));
+        let trace = fib_selector_air::trace::generate_trace_rows::<BabyBear>(a, b, &sels);
+        let n = trace.height();
+        let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(n - 1, 1)];
+        (trace, public_values)
+    });
+}
@@ -0,0 +1,68 @@
+//! AIR receiving one message per row on bus 0, with a configurable declaration of its
+//! interactions.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    interaction::{InteractionBuilder, InteractionSpec, PermutationCheckBus},
+    p3_field::{Field, FieldAlgebra},
+    rap::{BaseAirWithPublicValues, ColumnsAir, InteractionsAir, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::StarkFriEngine,
+};
+use p3_air::{Air, BaseAir};
+use p3_matrix::Matrix;
+
+struct ReceiverAir {
+    declared: Vec<InteractionSpec>,
+}
+
+impl<F: Field> BaseAir<F> for ReceiverAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for ReceiverAir {}
+impl<F: Field> PartitionedBaseAir<F> for ReceiverAir {}
+impl<F: Field> ColumnsAir<F> for ReceiverAir {}
+impl<F: Field> InteractionsAir<F> for ReceiverAir {
+    fn declare_interactions(&self) -> Vec<InteractionSpec> {
+        self.declared.clone()
+    }
+}
+
+impl<AB: InteractionBuilder> Air<AB> for ReceiverAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let x = main.row_slice(0)[0];
+        PermutationCheckBus::new(0).receive(builder, [x], AB::Expr::ONE);
+    }
+}
+
+fn keygen(declared: Vec<InteractionSpec>) {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air_with_declared_interactions(Arc::new(ReceiverAir { declared }));
+    keygen_builder.generate_pk();
+}
+
+#[test]
+fn test_declared_interactions_match() {
+    keygen(vec![InteractionSpec::receive(0, 1)]);
+}
+
+#[test]
+#[should_panic(expected = "do not match its declaration")]
+fn test_declared_interactions_mismatch() {
+    keygen(vec![InteractionSpec::send(0, 1)]);
+}
+
+#[test]
+#[should_panic(expected = "do not match its declaration")]
+fn test_declared_interactions_direction() {
+    // The receive records its direction, so it does not match an undirected declaration
+    keygen(vec![InteractionSpec::undirected(0, 1)]);
+}
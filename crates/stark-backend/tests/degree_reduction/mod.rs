@@ -0,0 +1,85 @@
+//! Degree reduction of a [SymbolicAir] with a constraint of degree 5 to an AIR of degree 3.
+
+use openvm_stark_backend::{
+    p3_field::{Field, FieldAlgebra},
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir, SymbolicAir},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::StarkFriEngine,
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+/// Columns `[x, y]` with `y = x^5` on every row and `x` increasing by one.
+struct QuinticAir;
+
+impl<F: Field> BaseAir<F> for QuinticAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for QuinticAir {}
+impl<F: Field> PartitionedBaseAir<F> for QuinticAir {}
+impl<F: Field> ColumnsAir<F> for QuinticAir {}
+
+impl<AB: AirBuilder> Air<AB> for QuinticAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (x, y) = (local[0], local[1]);
+
+        let x_pow_5 = AB::Expr::from(x) * x * x * x * x;
+        builder.assert_eq(x_pow_5, y);
+        builder
+            .when_transition()
+            .assert_eq(next[0], x + AB::Expr::ONE);
+    }
+}
+
+#[test]
+fn test_reduce_degree() {
+    let air = SymbolicAir::<BabyBear>::from_air(&QuinticAir);
+    assert_eq!(
+        air.constraints.constraints.constraint_degrees().into_iter().max(),
+        Some(5)
+    );
+    let values = (0..16u32)
+        .flat_map(|i| {
+            let x = BabyBear::from_canonical_u32(i);
+            [x, x.exp_u64(5)]
+        })
+        .collect();
+    let trace = RowMajorMatrix::new(values, 2);
+
+    let reduced = air.reduce_degree(3);
+    let degrees = reduced.air.constraints.constraints.constraint_degrees();
+    assert!(degrees.into_iter().all(|degree| degree <= 3));
+    assert!(reduced.num_witness_columns() > 0);
+    let reduced_trace = reduced.generate_trace(&trace, &[]);
+    assert_eq!(reduced_trace.width(), 2 + reduced.num_witness_columns());
+    for row_idx in 0..trace.height() {
+        assert_eq!(reduced_trace.row_slice(row_idx)[..2], trace.row_slice(row_idx)[..]);
+    }
+
+    // The original AIR needs a blowup of 4 for its quotient of degree 4
+    let engine =
+        BabyBearPoseidon2Engine::new(FriParameters::standard_with_100_bits_conjectured_security(2));
+    let original = StarkFriEngine::<_>::run_simple_test_impl(
+        &engine,
+        any_rap_arc_vec![air],
+        vec![trace],
+        vec![vec![]],
+    )
+    .expect("Verification failed");
+    assert_eq!(original.data.vk.inner.per_air[0].quotient_degree, 4);
+
+    let reduced = BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+        any_rap_arc_vec![reduced.air],
+        vec![reduced_trace],
+    )
+    .expect("Verification failed");
+    assert_eq!(reduced.data.vk.inner.per_air[0].quotient_degree, 2);
+}
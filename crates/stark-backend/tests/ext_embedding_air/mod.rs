@@ -0,0 +1,108 @@
+//! AIRs over the same base field whose rows are extension field elements of different degrees,
+//! interacting on a shared bus via [embed_ext_message].
+
+use openvm_stark_backend::{
+    interaction::{embed_ext_message, InteractionBuilder, PermutationCheckBus},
+    p3_field::FieldAlgebra,
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec, dummy_airs::interaction::verify_interactions, utils::to_field_vec,
+};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+/// Extension degree shared by all messages on the bus.
+const TARGET_DEGREE: usize = 4;
+
+/// Each row holds the coefficients of one element of a degree `ext_degree` extension, which is
+/// sent or received on `bus`.
+pub struct ExtElementBusAir {
+    pub bus: PermutationCheckBus,
+    pub ext_degree: usize,
+    pub is_send: bool,
+}
+
+impl<F> BaseAir<F> for ExtElementBusAir {
+    fn width(&self) -> usize {
+        self.ext_degree
+    }
+}
+impl<F> BaseAirWithPublicValues<F> for ExtElementBusAir {}
+impl<F> PartitionedBaseAir<F> for ExtElementBusAir {}
+impl<F> ColumnsAir<F> for ExtElementBusAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for ExtElementBusAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let coeffs: Vec<AB::Expr> = local.iter().map(|&x| x.into()).collect();
+        drop(local);
+
+        let message = embed_ext_message([coeffs], TARGET_DEGREE);
+        if self.is_send {
+            self.bus.send(builder, message, AB::Expr::ONE);
+        } else {
+            self.bus.receive(builder, message, AB::Expr::ONE);
+        }
+    }
+}
+
+fn airs_and_sender_trace() -> (ExtElementBusAir, ExtElementBusAir, RowMajorMatrix<BabyBear>) {
+    let bus = PermutationCheckBus::new(0);
+    let sender_air = ExtElementBusAir {
+        bus,
+        ext_degree: 2,
+        is_send: true,
+    };
+    let receiver_air = ExtElementBusAir {
+        bus,
+        ext_degree: TARGET_DEGREE,
+        is_send: false,
+    };
+    let sender_trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 4, 5, 6, 7, 8]), 2);
+    (sender_air, receiver_air, sender_trace)
+}
+
+#[test]
+fn test_embed_ext_message() {
+    let message = embed_ext_message(
+        [to_field_vec::<BabyBear>(vec![1, 2]), to_field_vec(vec![3, 4, 5, 6])],
+        TARGET_DEGREE,
+    );
+    assert_eq!(message, to_field_vec(vec![1, 2, 0, 0, 3, 4, 5, 6]));
+}
+
+#[test]
+fn test_ext_embedding_interactions_balance() {
+    let (sender_air, receiver_air, sender_trace) = airs_and_sender_trace();
+    // Same elements in a different order, embedded into the degree 4 extension.
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![5, 6, 0, 0, 1, 2, 0, 0, 7, 8, 0, 0, 3, 4, 0, 0]),
+        TARGET_DEGREE,
+    );
+    verify_interactions(
+        vec![sender_trace, receiver_trace],
+        any_rap_arc_vec![sender_air, receiver_air],
+        vec![vec![], vec![]],
+    )
+    .expect("Verification failed");
+}
+
+#[test]
+fn test_ext_embedding_interactions_unbalanced() {
+    let (sender_air, receiver_air, sender_trace) = airs_and_sender_trace();
+    // The last element has a nonzero coefficient outside the degree 2 subfield.
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![5, 6, 0, 0, 1, 2, 0, 0, 7, 8, 0, 0, 3, 4, 0, 1]),
+        TARGET_DEGREE,
+    );
+    let res = verify_interactions(
+        vec![sender_trace, receiver_trace],
+        any_rap_arc_vec![sender_air, receiver_air],
+        vec![vec![], vec![]],
+    );
+    assert_eq!(res, Err(VerificationError::ChallengePhaseError));
+}
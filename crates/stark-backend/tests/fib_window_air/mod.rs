@@ -0,0 +1,93 @@
+//! Fibonacci AIR with a single column, constraining each row by the two rows before it through
+//! a transition window of size 3.
+
+use openvm_stark_backend::{
+    p3_field::{Field, FieldAlgebra},
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec, config::baby_bear_poseidon2::BabyBearPoseidon2Engine, engine::StarkFriEngine,
+};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+struct FibWindowAir;
+
+impl<F: Field> BaseAir<F> for FibWindowAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for FibWindowAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+impl<F: Field> PartitionedBaseAir<F> for FibWindowAir {}
+impl<F: Field> ColumnsAir<F> for FibWindowAir {}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FibWindowAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, x) = (pis[0], pis[1], pis[2]);
+
+        let local = main.row_slice(0)[0];
+        let next = main.row_slice(1)[0];
+        let after_next = main.row_slice(2)[0];
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_eq(local, a);
+        when_first_row.assert_eq(next, b);
+
+        let is_transition_window = builder.is_transition_window(3);
+        builder
+            .when(is_transition_window)
+            .assert_eq(local + next, after_next);
+
+        builder.when_last_row().assert_eq(local, x);
+    }
+}
+
+fn prove_and_verify(values: Vec<u32>) -> Result<(), VerificationError> {
+    let pis = [values[0], values[1], values[values.len() - 1]]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace = RowMajorMatrix::new(
+        values.into_iter().map(BabyBear::from_canonical_u32).collect(),
+        1,
+    );
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibWindowAir],
+        vec![trace],
+        vec![pis],
+    )
+    .map(|_| ())
+}
+
+fn fib_values(n: usize) -> Vec<u32> {
+    let mut values = vec![0, 1];
+    while values.len() < n {
+        values.push(values[values.len() - 2] + values[values.len() - 1]);
+    }
+    values
+}
+
+#[test]
+fn test_fib_window_air() {
+    prove_and_verify(fib_values(16)).expect("Verification failed");
+}
+
+#[test]
+fn test_fib_window_air_neg() {
+    let mut values = fib_values(16);
+    values[10] += 1;
+    disable_debug_builder();
+    assert_eq!(
+        prove_and_verify(values),
+        Err(VerificationError::OodEvaluationMismatch)
+    );
+}
@@ -0,0 +1,139 @@
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    p3_matrix::dense::RowMajorMatrix,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        fri_params::SecurityParameters,
+        goldilocks_poseidon::{engine_from_perm, random_instrumented_perm, GoldilocksPoseidonConfig},
+        instrument::num_instrumented_calls,
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngineWithHashInstrumentation, StarkFriEngine},
+    p3_goldilocks::Goldilocks,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+#[test]
+fn test_optimal_final_poly_len() {
+    let max_log_height = 20;
+    let default_params = FriParameters::standard_fast();
+    let params = default_params.with_optimal_final_poly_len(max_log_height);
+
+    assert!(params.log_final_poly_len <= max_log_height);
+    assert!(params.get_conjectured_security_bits(100) >= 100);
+    assert!(
+        params.fri_proof_size_estimate(max_log_height)
+            < default_params.fri_proof_size_estimate(max_log_height)
+    );
+}
+
+#[test]
+fn test_challenge_field_bits() {
+    // Quartic extension of the 31-bit BabyBear field
+    assert_eq!(BabyBearPoseidon2Config::challenge_field_bits(), 124);
+    // Quadratic extension of the 64-bit Goldilocks field
+    assert_eq!(GoldilocksPoseidonConfig::challenge_field_bits(), 128);
+
+    // The challenge field bits bound the conjectured security
+    let params = FriParameters {
+        num_queries: 200,
+        ..FriParameters::standard_fast()
+    };
+    assert_eq!(
+        params.conjectured_security_bits::<BabyBearPoseidon2Config>(),
+        params.get_conjectured_security_bits(124)
+    );
+    assert_eq!(
+        params.conjectured_security_bits::<BabyBearPoseidon2Config>(),
+        124
+    );
+    assert_eq!(
+        params.conjectured_security_bits::<GoldilocksPoseidonConfig>(),
+        128
+    );
+    let params = FriParameters::standard_fast();
+    assert!(params.conjectured_security_bits::<BabyBearPoseidon2Config>() >= 100);
+}
+
+#[test]
+fn test_prove_with_hash_counts() {
+    let engine = engine_from_perm(
+        random_instrumented_perm(),
+        SecurityParameters::standard_fast(),
+    );
+    let airs = any_rap_arc_vec![
+        DummyInteractionAir::new(1, true, 0),
+        DummyInteractionAir::new(1, false, 0)
+    ];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    // Rows of (count, field), sent and received alike
+    let values = [1, 4, 2, 5, 0, 6, 3, 7].map(Goldilocks::from_canonical_u32);
+    let trace = RowMajorMatrix::new(values.to_vec(), 2);
+    let per_air = air_ids
+        .into_iter()
+        .map(|air_id| (air_id, AirProofInput::simple_no_pis(trace.clone())))
+        .collect();
+
+    let counter = engine.instrument_counter();
+    let start = num_instrumented_calls(&counter);
+    let (proof, counts) = engine.prove_with_hash_counts(&pk, ProofInput::new(per_air));
+    assert_eq!(counts.total(), num_instrumented_calls(&counter) - start);
+    for count in [
+        counts.main_trace_commit,
+        counts.rap_phases,
+        counts.quotient_commit,
+        counts.opening,
+        counts.transcript,
+    ] {
+        assert!(count > 0);
+    }
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_prove_with_log_final_poly_len() {
+    let n = 1usize << 5;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let proof_size = |fri_params: FriParameters| {
+        let engine = BabyBearPoseidon2Engine::new(fri_params);
+        let data = engine
+            .run_simple_test_impl(
+                any_rap_arc_vec![FibonacciAir],
+                vec![generate_trace_rows::<BabyBear>(0, 1, n)],
+                vec![pis.clone()],
+            )
+            .expect("Verification failed");
+        assert_eq!(data.fri_params, fri_params);
+        assert_eq!(
+            data.data.proof.opening.proof.commit_phase_commits.len(),
+            fri_params.num_commit_phase_rounds(5)
+        );
+        bitcode::serialize(&data.data.proof).unwrap().len()
+    };
+
+    let default_params = FriParameters::standard_fast();
+    assert_eq!(default_params.log_final_poly_len, 0);
+    assert_ne!(
+        proof_size(default_params),
+        proof_size(default_params.with_log_final_poly_len(3))
+    );
+}
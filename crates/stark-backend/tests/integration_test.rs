@@ -13,11 +13,30 @@ use openvm_stark_sdk::{
 };
 use p3_baby_bear::BabyBear;
 
+mod bus_schema;
 mod cached_lookup;
+mod commit_layout;
+mod constant_constraint_air;
+mod constraints;
+mod declared_interactions;
+mod degree_reduction;
+mod ext_embedding_air;
 mod fib_selector_air;
 mod fib_triples_air;
+mod fib_window_air;
+mod fri_params;
 pub mod interaction;
+mod keccak_air;
+mod keygen;
+mod lazy_filter_air;
 mod partitioned_sum_air;
+mod preprocessed_only_air;
+mod proof;
+mod prover;
+mod timed_memory_air;
+mod transcript;
+mod variable_public_values_air;
+mod verifier;
 
 #[test]
 fn test_single_fib_stark() {
@@ -42,6 +61,32 @@ fn test_single_fib_stark() {
         .expect("Verification failed");
 }
 
+#[test]
+fn test_all_zero_traces() {
+    use openvm_stark_backend::p3_matrix::dense::RowMajorMatrix;
+    use openvm_stark_sdk::dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    };
+
+    // The Fibonacci sequence starting from 0, 0 is all zeros
+    let fib_trace = generate_trace_rows::<BabyBear>(0, 0, 8);
+    assert!(fib_trace.values.iter().all(|v| *v == BabyBear::ZERO));
+    // Interactions with zero count and zero message, on both sides of the bus
+    let zero_trace = |height| RowMajorMatrix::new(vec![BabyBear::ZERO; 2 * height], 2);
+
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![
+            FibonacciAir,
+            DummyInteractionAir::new(1, true, 0),
+            DummyInteractionAir::new(1, false, 0)
+        ],
+        vec![fib_trace, zero_trace(4), zero_trace(16)],
+        vec![vec![BabyBear::ZERO; 3], vec![], vec![]],
+    )
+    .expect("Verification failed");
+}
+
 #[test]
 fn test_single_fib_triples_stark() {
     use fib_triples_air::{air::FibonacciAir, trace::generate_trace_rows};
@@ -4,13 +4,20 @@ use itertools::Itertools;
 use openvm_stark_backend::{
     config::StarkGenericConfig,
     interaction::RapPhaseSeq,
-    keygen::{types::LinearConstraint, MultiStarkKeygenBuilder},
-    p3_field::FieldAlgebra,
-    verifier::VerificationError,
+    keygen::{
+        types::{LinearConstraint, OpenedMatrixLayout},
+        MultiStarkKeygenBuilder,
+    },
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    verifier::{OpeningRound, VerificationError},
 };
 use openvm_stark_sdk::{
     any_rap_arc_vec, config,
-    dummy_airs::interaction::{dummy_interaction_air::DummyInteractionAir, verify_interactions},
+    config::baby_bear_poseidon2::BabyBearPoseidon2Config,
+    dummy_airs::interaction::{
+        dummy_interaction_air::{DummyInteractionAir, DummyInteractionChip, DummyInteractionData},
+        precheck_interactions, verify_interactions,
+    },
 };
 use p3_baby_bear::BabyBear;
 use p3_field::PrimeField32;
@@ -76,6 +83,112 @@ fn test_interaction_trace_height_constraints() {
     );
 }
 
+#[test]
+fn test_opened_values_layout() {
+    let sels: Vec<bool> = (0..8).map(|i| i % 2 == 0).collect();
+    let fib_air = FibonacciSelectorAir::new(sels, true);
+    let sender_air = DummyInteractionAir::new(1, true, 0);
+
+    let perm = config::baby_bear_poseidon2::random_perm();
+    let config = config::baby_bear_poseidon2::default_config(&perm);
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    keygen_builder.add_air(Arc::new(fib_air));
+    keygen_builder.add_air(Arc::new(sender_air));
+    let vk = keygen_builder.generate_pk().get_vk();
+
+    type Challenge = <BabyBearPoseidon2Config as StarkGenericConfig>::Challenge;
+    let ext_degree = <Challenge as FieldExtensionAlgebra<Val>>::D;
+    let trace = |air_idx, width| OpenedMatrixLayout {
+        air_idx,
+        width,
+        num_rotations: 2,
+    };
+    let quotient_degrees = vk
+        .inner
+        .per_air
+        .iter()
+        .map(|vk| vk.quotient_degree)
+        .collect_vec();
+    let layout = vk.opened_values_layout(&quotient_degrees);
+    let rounds = layout.commitments.iter().map(|c| c.round).collect_vec();
+    assert_eq!(
+        rounds,
+        vec![
+            OpeningRound::Preprocessed,
+            OpeningRound::Main,
+            OpeningRound::AfterChallenge,
+            OpeningRound::Quotient
+        ]
+    );
+    // The selector column of the Fibonacci AIR is preprocessed
+    assert_eq!(layout.commitments[0].matrices, vec![trace(0, 1)]);
+    assert_eq!(layout.commitments[1].matrices, vec![trace(0, 2), trace(1, 2)]);
+    // Both AIRs have an interaction, so both have a permutation trace
+    let perm_widths = vk
+        .inner
+        .per_air
+        .iter()
+        .map(|vk| vk.params.width.after_challenge[0] * ext_degree);
+    assert_eq!(
+        layout.commitments[2].matrices,
+        perm_widths
+            .enumerate()
+            .map(|(air_idx, width)| trace(air_idx, width))
+            .collect_vec()
+    );
+    let quotient = &layout.commitments[3].matrices;
+    assert_eq!(
+        quotient.len(),
+        quotient_degrees.iter().map(|&d| d as usize).sum::<usize>()
+    );
+    assert!(quotient
+        .iter()
+        .all(|m| m.width == ext_degree && m.num_rotations == 1));
+    assert!(quotient.is_sorted_by_key(|m| m.air_idx));
+    // A prover capping the quotient chunks opens one chunk per AIR
+    let layout = vk.opened_values_layout(&[1, 1]);
+    assert_eq!(layout.commitments[3].matrices.len(), 2);
+}
+
+#[test]
+fn test_bus_graph_dot() {
+    let mut sender_air = DummyInteractionAir::new(1, true, 0);
+    sender_air.count_weight = 3;
+    let receiver_air = DummyInteractionAir::new(1, false, 0);
+
+    let perm = config::baby_bear_poseidon2::random_perm();
+    let config = config::baby_bear_poseidon2::default_config(&perm);
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    keygen_builder.add_air_with_name(Arc::new(sender_air), "Sender");
+    keygen_builder.add_air_with_name(Arc::new(receiver_air), "Receiver");
+    let dot = keygen_builder.generate_pk().bus_graph_dot();
+
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains("air0 [label=\"Sender\"];"));
+    assert!(dot.contains("air1 [label=\"Receiver\"];"));
+    assert!(dot.contains("air0 -> air1 [label=\"bus 0, weights 3/0\"];"));
+    assert!(!dot.contains("air1 -> air0"));
+}
+
+#[test]
+fn test_dangling_buses() {
+    use openvm_stark_backend::interaction::PermutationInteractionType;
+
+    // Bus 0 is balanced, bus 1 only has a receiver
+    let perm = config::baby_bear_poseidon2::random_perm();
+    let config = config::baby_bear_poseidon2::default_config(&perm);
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 1)));
+    let pk = keygen_builder.generate_pk();
+
+    assert_eq!(
+        pk.dangling_buses(),
+        vec![(1, PermutationInteractionType::Receive)]
+    );
+}
+
 #[test]
 fn test_interaction_fib_selector_happy_path() {
     let log_trace_degree = 3;
@@ -152,6 +265,73 @@ fn test_interaction_stark_multi_rows_happy_path() {
     .expect("Verification failed");
 }
 
+#[test]
+fn test_interaction_stark_invariants() {
+    use openvm_stark_backend::prover::types::AirProofInput;
+    use openvm_stark_sdk::{
+        config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+        engine::StarkFriEngine,
+        utils::prove_verify_with_invariants,
+    };
+
+    let sender_trace =
+        RowMajorMatrix::new(to_field_vec::<Val>(vec![0, 1, 3, 5, 7, 4, 546, 889]), 2);
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![
+            1, 5, 3, 4, 4, 4, 2, 5, 0, 123, 545, 889, 1, 889, 0, 456,
+        ]),
+        2,
+    );
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let data = prove_verify_with_invariants(
+        &engine,
+        any_rap_arc_vec![
+            DummyInteractionAir::new(1, true, 0),
+            DummyInteractionAir::new(1, false, 0)
+        ],
+        AirProofInput::multiple_simple_no_pis(vec![sender_trace, receiver_trace]),
+    )
+    .expect("Verification failed");
+    // Both AIRs expose a cumulative sum, and the interactions add trace height constraints
+    assert!(data
+        .proof
+        .per_air
+        .iter()
+        .all(|ap| ap.exposed_values_after_challenge.len() == 1));
+    assert!(!data.vk.inner.trace_height_constraints.is_empty());
+}
+
+#[test]
+fn test_interaction_stark_invariants_neg() {
+    use openvm_stark_backend::{prover::types::AirProofInput, utils::disable_debug_builder};
+    use openvm_stark_sdk::{
+        config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+        engine::StarkFriEngine,
+        utils::{prove_verify_with_invariants, InvariantError},
+    };
+
+    // The receiver receives the message 4 twice less than it is sent
+    let sender_trace =
+        RowMajorMatrix::new(to_field_vec::<Val>(vec![0, 1, 3, 5, 7, 4, 546, 889]), 2);
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![
+            1, 5, 1, 4, 4, 4, 2, 5, 0, 123, 545, 889, 1, 889, 0, 456,
+        ]),
+        2,
+    );
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    disable_debug_builder();
+    let result = prove_verify_with_invariants(
+        &engine,
+        any_rap_arc_vec![
+            DummyInteractionAir::new(1, true, 0),
+            DummyInteractionAir::new(1, false, 0)
+        ],
+        AirProofInput::multiple_simple_no_pis(vec![sender_trace, receiver_trace]),
+    );
+    assert_eq!(result.err(), Some(InvariantError::CumulativeSum));
+}
+
 #[test]
 fn test_interaction_stark_multi_rows_neg() {
     // Mul  Val
@@ -280,6 +460,35 @@ fn test_interaction_stark_multi_senders_neg() {
     assert_eq!(res, Err(VerificationError::ChallengePhaseError));
 }
 
+#[test]
+fn test_precheck_interactions_multi_senders_neg() {
+    // Same data as `test_interaction_stark_multi_senders_neg`: value 4 is sent 6 times but
+    // received 7 times.
+    let sender_trace1 = RowMajorMatrix::new(to_field_vec(vec![0, 1, 3, 5, 5, 4, 333, 889]), 2);
+    let sender_trace2 = RowMajorMatrix::new(to_field_vec(vec![1, 4, 213, 889]), 2);
+    let sender_air = DummyInteractionAir::new(1, true, 0);
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![
+            1, 5, 3, 4, 4, 4, 2, 5, 0, 123, 545, 889, 1, 889, 0, 456,
+        ]),
+        2,
+    );
+    let receiver_air = DummyInteractionAir::new(1, false, 0);
+
+    let imbalances = precheck_interactions(
+        vec![sender_trace1, sender_trace2, receiver_trace],
+        any_rap_arc_vec![sender_air, sender_air, receiver_air],
+        vec![vec![]; 3],
+    )
+    .unwrap_err();
+    assert_eq!(imbalances.len(), 1);
+    let imbalance = &imbalances[0];
+    assert_eq!(imbalance.bus_index, 0);
+    assert_eq!(imbalance.fields, vec![Val::from_canonical_u32(4)]);
+    let sum: Val = imbalance.connections.iter().map(|&(_, count)| count).sum();
+    assert_eq!(sum, Val::NEG_ONE);
+}
+
 #[test]
 fn test_interaction_stark_multi_sender_receiver_happy_path() {
     // Mul  Val
@@ -327,3 +536,193 @@ fn test_interaction_stark_multi_sender_receiver_happy_path() {
     )
     .expect("Verification failed");
 }
+
+#[test]
+fn test_interaction_horner_fingerprint() {
+    use openvm_stark_backend::{
+        engine::StarkEngine,
+        interaction::fri_log_up::{FingerprintScheme, FriLogUpPhase},
+    };
+    use openvm_stark_sdk::{
+        config::{
+            baby_bear_poseidon2::BabyBearPoseidon2Engine,
+            log_up_params::log_up_security_params_baby_bear_100_bits, FriParameters,
+        },
+        engine::StarkFriEngine,
+    };
+
+    let fri_params = FriParameters::standard_fast();
+    let mut engine = BabyBearPoseidon2Engine::new(fri_params);
+    let rap_phase = FriLogUpPhase::new(
+        log_up_security_params_baby_bear_100_bits(),
+        fri_params.log_blowup,
+    )
+    .with_fingerprint_scheme(FingerprintScheme::Horner);
+    engine.config = engine.config.with_rap_phase(rap_phase);
+
+    // Rows of `[count, a, b]`
+    let sender_trace = RowMajorMatrix::new(to_field_vec::<Val>(vec![1, 2, 3, 4, 5, 6]), 3);
+    let receiver_trace = RowMajorMatrix::new(to_field_vec::<Val>(vec![4, 5, 6, 1, 2, 3]), 3);
+    let airs = any_rap_arc_vec![
+        DummyInteractionAir::new(2, true, 0),
+        DummyInteractionAir::new(2, false, 0)
+    ];
+    let data = StarkFriEngine::<_>::run_simple_test_impl(
+        &engine,
+        airs.clone(),
+        vec![sender_trace, receiver_trace],
+        vec![vec![], vec![]],
+    )
+    .expect("Verification failed")
+    .data;
+
+    // The LogUp constraints in the verifying key follow the scheme of the config
+    let default_engine = BabyBearPoseidon2Engine::new(fri_params);
+    let mut keygen_builder = default_engine.keygen_builder();
+    default_engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let default_vk = keygen_builder.generate_pk().get_vk();
+    assert_ne!(
+        data.vk.inner.per_air[0].symbolic_constraints.constraints,
+        default_vk.inner.per_air[0].symbolic_constraints.constraints
+    );
+    assert!(default_engine.verify(&default_vk, &data.proof).is_err());
+}
+
+#[test]
+fn test_interaction_grinding_seed() {
+    use openvm_stark_backend::interaction::fri_log_up::FriLogUpPhase;
+    use openvm_stark_sdk::{
+        config::{
+            baby_bear_poseidon2::BabyBearPoseidon2Engine,
+            log_up_params::log_up_security_params_baby_bear_100_bits, FriParameters,
+        },
+        engine::StarkFriEngine,
+    };
+
+    let fri_params = FriParameters::standard_fast();
+    let engine_with_seed = |seed: u64| {
+        let mut engine = BabyBearPoseidon2Engine::new(fri_params);
+        let rap_phase = FriLogUpPhase::new(
+            log_up_security_params_baby_bear_100_bits(),
+            fri_params.log_blowup,
+        )
+        .with_grinding_seed(seed);
+        engine.config = engine.config.with_rap_phase(rap_phase);
+        engine
+    };
+    let logup_pow_witness = |engine: &BabyBearPoseidon2Engine| {
+        // Rows of `[count, a, b]`
+        let sender_trace = RowMajorMatrix::new(to_field_vec::<Val>(vec![1, 2, 3, 4, 5, 6]), 3);
+        let receiver_trace = RowMajorMatrix::new(to_field_vec::<Val>(vec![4, 5, 6, 1, 2, 3]), 3);
+        let proof = StarkFriEngine::<_>::run_simple_test_impl(
+            engine,
+            any_rap_arc_vec![
+                DummyInteractionAir::new(2, true, 0),
+                DummyInteractionAir::new(2, false, 0)
+            ],
+            vec![sender_trace, receiver_trace],
+            vec![vec![], vec![]],
+        )
+        .expect("Verification failed")
+        .data
+        .proof;
+        proof.rap_phase_seq_proof.unwrap().logup_pow_witness
+    };
+
+    let seed = 1 << 20;
+    let witness = logup_pow_witness(&engine_with_seed(seed)).as_canonical_u32() as u64;
+    assert_eq!(logup_pow_witness(&engine_with_seed(seed)).as_canonical_u32() as u64, witness);
+    // The search starts at the seed
+    assert!(witness >= seed);
+    let next_witness = logup_pow_witness(&engine_with_seed(witness + 1)).as_canonical_u32() as u64;
+    assert!(next_witness > witness);
+}
+
+#[test]
+fn test_optional_air_bus_balance_within_subset() {
+    use openvm_stark_backend::{
+        engine::StarkEngine, prover::types::ProofInput, utils::disable_debug_builder, Chip,
+    };
+    use openvm_stark_sdk::{
+        config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+        engine::StarkFriEngine,
+    };
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut send_chip0 = DummyInteractionChip::new_without_partition(1, true, 0);
+    let mut recv_chip0 = DummyInteractionChip::new_without_partition(1, false, 0);
+    let mut send_chip1 = DummyInteractionChip::new_without_partition(1, true, 1);
+    let mut recv_chip1 = DummyInteractionChip::new_without_partition(1, false, 1);
+    let mut keygen_builder = engine.keygen_builder();
+    let send_chip0_id = keygen_builder.add_air(send_chip0.air());
+    let recv_chip0_id = keygen_builder.add_air(recv_chip0.air());
+    let send_chip1_id = keygen_builder.add_air(send_chip1.air());
+    let recv_chip1_id = keygen_builder.add_air(recv_chip1.air());
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    for chip in [&mut send_chip0, &mut recv_chip0, &mut send_chip1, &mut recv_chip1] {
+        chip.load_data(DummyInteractionData {
+            count: vec![1, 2, 4],
+            fields: vec![vec![1], vec![2], vec![3]],
+        });
+    }
+
+    // Both sides of bus 1 are omitted: bus 0 balances on its own.
+    let proof = engine.prove(
+        &pk,
+        ProofInput {
+            per_air: vec![
+                send_chip0.clone().generate_air_proof_input_with_id(send_chip0_id),
+                recv_chip0.clone().generate_air_proof_input_with_id(recv_chip0_id),
+            ],
+        },
+    );
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // Only the receiver of bus 0 is omitted: the sends on bus 0 are not matched within the
+    // subset, even though the receiver AIR exists in the vk.
+    disable_debug_builder();
+    let proof = engine.prove(
+        &pk,
+        ProofInput {
+            per_air: vec![
+                send_chip0.clone().generate_air_proof_input_with_id(send_chip0_id),
+                send_chip1.clone().generate_air_proof_input_with_id(send_chip1_id),
+                recv_chip1.clone().generate_air_proof_input_with_id(recv_chip1_id),
+            ],
+        },
+    );
+    assert_eq!(
+        engine.verify(&vk, &proof),
+        Err(VerificationError::ChallengePhaseError)
+    );
+}
+
+#[test]
+fn test_dummy_interaction_data_from_multiset() {
+    use std::collections::BTreeMap;
+
+    use openvm_stark_backend::Chip;
+    use openvm_stark_sdk::{
+        config::baby_bear_poseidon2::BabyBearPoseidon2Engine, engine::StarkFriEngine,
+    };
+
+    let multiset = BTreeMap::from([(vec![4, 5], 1), (vec![1, 2], 3), (vec![7, 0], 0)]);
+    let data = DummyInteractionData::from_multiset(multiset);
+    assert_eq!(data.count, vec![3, 1]);
+    assert_eq!(data.fields, vec![vec![1, 2], vec![4, 5]]);
+
+    let mut send_chip = DummyInteractionChip::new_without_partition(2, true, 0);
+    let mut recv_chip = DummyInteractionChip::new_without_partition(2, false, 0);
+    send_chip.load_data(data.clone());
+    recv_chip.load_data(data);
+    BabyBearPoseidon2Engine::run_test_fast(
+        vec![send_chip.air(), recv_chip.air()],
+        vec![
+            send_chip.generate_air_proof_input(),
+            recv_chip.generate_air_proof_input(),
+        ],
+    )
+    .expect("Verification failed");
+}
@@ -0,0 +1,213 @@
+//! Keccak-f AIR from Plonky3, wrapped to implement the extended AIR traits.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    air_builders::symbolic::{assert_dags_equal, get_symbolic_builder, SymbolicConstraintsDag},
+    engine::StarkEngine,
+    interaction::RapPhaseSeqKind,
+    keygen::types::TraceWidth,
+    p3_field::Field,
+    rap::{BaseAirWithPublicValues, ColumnsAir, P3AirAdapter, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::StarkFriEngine,
+    utils::{create_seeded_rng, prove_on_device},
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_keccak_air::KeccakAir;
+use rand::Rng;
+
+pub struct TestKeccakAir(pub KeccakAir);
+
+impl<F: Field> BaseAir<F> for TestKeccakAir {
+    fn width(&self) -> usize {
+        BaseAir::<F>::width(&self.0)
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for TestKeccakAir {}
+impl<F: Field> PartitionedBaseAir<F> for TestKeccakAir {}
+impl<F: Field> ColumnsAir<F> for TestKeccakAir {}
+
+impl<AB: AirBuilder> Air<AB> for TestKeccakAir {
+    fn eval(&self, builder: &mut AB) {
+        self.0.eval(builder);
+    }
+}
+
+#[test]
+fn test_max_constraint_degrees() {
+    use openvm_stark_sdk::dummy_airs::fib_air::air::FibonacciAir;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(TestKeccakAir(KeccakAir {})));
+    let vk = keygen_builder.generate_pk().get_vk();
+
+    // Fibonacci is constrained on its first row, Keccak has degree 3 constraints
+    assert_eq!(vk.max_constraint_degrees(), vec![2, 3]);
+}
+
+#[test]
+fn test_keccak_prover_and_verifier_dags_equal() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(TestKeccakAir(KeccakAir {})));
+    let pk = keygen_builder.generate_pk();
+    let prover_dag = &pk.per_air[air_id].vk.symbolic_constraints.constraints;
+
+    // Rebuild the constraint DAG from scratch, independently of keygen.
+    let air = TestKeccakAir(KeccakAir {});
+    let width = TraceWidth {
+        preprocessed: None,
+        cached_mains: vec![],
+        common_main: BaseAir::<BabyBear>::width(&air),
+        after_challenge: vec![],
+    };
+    let verifier_dag: SymbolicConstraintsDag<BabyBear> = get_symbolic_builder(
+        &air,
+        &width,
+        &[],
+        &[],
+        RapPhaseSeqKind::FriLogUp,
+        pk.max_constraint_degree,
+    )
+    .constraints()
+    .into();
+
+    assert_dags_equal(prover_dag, &verifier_dag.constraints);
+    assert_dags_equal(
+        prover_dag,
+        &pk.get_vk().inner.per_air[air_id].symbolic_constraints.constraints,
+    );
+}
+
+#[test]
+fn test_keccak_air_via_adapter() {
+    let mut rng = create_seeded_rng();
+    let inputs = (0..4).map(|_| rng.gen()).collect::<Vec<_>>();
+    let trace = p3_keccak_air::generate_trace_rows::<BabyBear>(inputs, 0);
+
+    BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+        any_rap_arc_vec![P3AirAdapter(KeccakAir {})],
+        vec![trace],
+    )
+    .expect("Verification failed");
+}
+
+#[test]
+fn test_keccak_quotient_batch_multiplier() {
+    use openvm_stark_backend::prover::cpu::CpuDevice;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut rng = create_seeded_rng();
+    let inputs = (0..4).map(|_| rng.gen()).collect::<Vec<_>>();
+    let trace = p3_keccak_air::generate_trace_rows::<BabyBear>(inputs, 0);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(P3AirAdapter(KeccakAir {})));
+    let pk = keygen_builder.generate_pk();
+
+    let prove = |batch_multiplier: usize| {
+        let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+            .with_quotient_batch_multiplier(batch_multiplier);
+        let inputs = vec![(air_id, trace.clone(), vec![])];
+        let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+            .expect("Proving failed");
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        bitcode::serialize(&proof.commitments).unwrap()
+    };
+    assert_eq!(prove(1), prove(4));
+}
+
+#[test]
+fn test_keccak_coarse_quotient_chunks() {
+    use openvm_stark_backend::{prover::cpu::CpuDevice, verifier::VerificationError};
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut rng = create_seeded_rng();
+    let inputs = (0..4).map(|_| rng.gen()).collect::<Vec<_>>();
+    let trace = p3_keccak_air::generate_trace_rows::<BabyBear>(inputs, 0);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(TestKeccakAir(KeccakAir {})));
+    let pk = keygen_builder.generate_pk();
+    assert_eq!(pk.per_air[air_id].vk.quotient_degree, 2);
+
+    // The quotient polynomial is committed as a single chunk on a domain twice the trace height
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_max_quotient_chunks(1)
+        .with_quotient_identity_check(true);
+    let inputs = vec![(air_id, trace, vec![])];
+    let (mut proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+    assert_eq!(proof.per_air[0].num_quotient_chunks, 1);
+    assert_eq!(proof.opening.values.quotient[0].len(), 1);
+    let vk = pk.get_vk();
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // The number of chunks must be a power of two dividing the quotient degree
+    proof.per_air[0].num_quotient_chunks = 3;
+    assert_eq!(
+        engine.verify(&vk, &proof),
+        Err(VerificationError::InvalidProofShape)
+    );
+}
+
+#[test]
+fn test_quotient_eval_strategy() {
+    use openvm_stark_backend::{
+        p3_field::FieldAlgebra,
+        p3_matrix::dense::RowMajorMatrix,
+        prover::cpu::{quotient::QuotientEvalStrategy, CpuDevice},
+    };
+
+    use crate::constant_constraint_air::ConstantConstraintAir;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut rng = create_seeded_rng();
+    let inputs = (0..4).map(|_| rng.gen()).collect::<Vec<_>>();
+    let keccak_trace = p3_keccak_air::generate_trace_rows::<BabyBear>(inputs, 0);
+    let bool_trace = RowMajorMatrix::new_col(
+        (0..8).map(|i| BabyBear::from_canonical_u32(i % 2)).collect(),
+    );
+
+    let mut keygen_builder = engine.keygen_builder();
+    let small_air_id = keygen_builder.add_air(Arc::new(ConstantConstraintAir {
+        unsatisfiable: false,
+    }));
+    let keccak_air_id = keygen_builder.add_air(Arc::new(TestKeccakAir(KeccakAir {})));
+    let pk = keygen_builder.generate_pk();
+    let num_constraints = |air_id: usize| {
+        pk.per_air[air_id]
+            .vk
+            .symbolic_constraints
+            .constraints
+            .constraint_idx
+            .len()
+    };
+    assert_eq!(num_constraints(small_air_id), 2);
+    assert!(num_constraints(keccak_air_id) > 8);
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let inputs = vec![
+        (small_air_id, bool_trace, vec![]),
+        (keccak_air_id, keccak_trace, vec![]),
+    ];
+    let (proof, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+
+    // The small AIR is evaluated directly and Keccak with the DAG interpreter
+    let stats = prover.device.quotient_eval_stats();
+    assert_eq!(stats.count(QuotientEvalStrategy::Direct), 1);
+    assert_eq!(stats.count(QuotientEvalStrategy::Dag), 1);
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    rap::{BaseAirWithPublicValues, ColumnsAir, P3AirAdapter, PartitionedBaseAir},
+    AirRef,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows as fib_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{CachingEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_keccak_air::KeccakAir;
+use p3_matrix::Matrix;
+
+use crate::{
+    fib_selector_air::{air::FibonacciSelectorAir, trace::generate_trace_rows},
+    get_conditional_fib_number, get_fib_number,
+};
+
+#[test]
+fn test_recursion_estimate_scales_with_constraints() {
+    let fri_params = FriParameters::standard_fast();
+    let engine = BabyBearPoseidon2Engine::new(fri_params);
+    let estimate = |air: AirRef<BabyBearPoseidon2Config>| {
+        let mut keygen_builder = engine.keygen_builder();
+        keygen_builder.add_air(air);
+        let vk = keygen_builder.generate_pk().get_vk();
+        let estimate =
+            vk.recursion_estimate(fri_params.log_final_poly_len, fri_params.num_queries, 10);
+        assert_eq!(estimate.num_constraints, vk.num_constraints_per_air()[0]);
+        estimate
+    };
+    let fib = estimate(Arc::new(FibonacciAir));
+    let keccak = estimate(Arc::new(P3AirAdapter(KeccakAir {})));
+
+    assert!(keccak.num_constraints > fib.num_constraints);
+    assert!(keccak.num_constraint_nodes > fib.num_constraint_nodes);
+    assert!(keccak.num_opened_values > fib.num_opened_values);
+    // FRI work depends only on the FRI parameters and trace height
+    assert_eq!(keccak.num_fri_rounds, fib.num_fri_rounds);
+    assert_eq!(keccak.num_fri_queries, fri_params.num_queries);
+}
+
+#[test]
+fn test_vk_diff_reports_added_air() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let keygen = |airs: Vec<AirRef<BabyBearPoseidon2Config>>| {
+        let mut keygen_builder = engine.keygen_builder();
+        for air in airs {
+            keygen_builder.add_air(air);
+        }
+        keygen_builder.generate_pk().get_vk()
+    };
+    let vk = keygen(vec![Arc::new(FibonacciAir)]);
+    let extended_vk = keygen(vec![
+        Arc::new(FibonacciAir),
+        Arc::new(DummyInteractionAir::new(1, true, 0)),
+    ]);
+
+    assert!(vk.diff(&vk).is_empty());
+    let diff = vk.diff(&extended_vk);
+    assert_eq!(diff.added_airs, vec![1]);
+    assert!(diff.removed_airs.is_empty());
+    assert!(diff.changed_airs.is_empty());
+    assert!(diff.pre_hash_changed);
+    assert_eq!(extended_vk.diff(&vk).removed_airs, vec![1]);
+}
+
+#[test]
+fn test_num_constraints_per_air() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    let vk = keygen_builder.generate_pk().get_vk();
+
+    // Fibonacci AIR: 2 first row, 2 transition and 1 last row constraints
+    let constraints = vk.num_constraints_per_air();
+    assert_eq!(constraints.len(), 2);
+    assert_eq!(constraints[0], 5);
+    for (air_id, num_constraints) in constraints.into_iter().enumerate() {
+        assert_eq!(vk.inner.per_air[air_id].num_constraints(), num_constraints);
+    }
+}
+
+#[test]
+fn test_caching_engine_reuses_proving_key() {
+    let n = 1usize << 3;
+    let sels: Vec<bool> = (0..n).map(|i| i % 2 == 0).collect();
+    let pis1 = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [0, 1, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air1 = FibonacciAir;
+    let air2 = FibonacciSelectorAir::new(sels, false);
+    let trace1 = fib_trace_rows::<BabyBear>(0, 1, n);
+    let trace2 = generate_trace_rows::<BabyBear>(0, 1, air2.sels());
+    let airs = any_rap_arc_vec![air1, air2];
+
+    let engine = CachingEngine::<_, BabyBearPoseidon2Engine>::new(FriParameters::standard_fast());
+    for _ in 0..2 {
+        engine
+            .run_simple_test_impl(
+                airs.clone(),
+                vec![trace1.clone(), trace2.clone()],
+                vec![pis1.clone(), pis2.clone()],
+            )
+            .expect("Verification failed");
+    }
+    assert_eq!(engine.num_keygens(), 1);
+}
+
+#[test]
+fn test_caching_engine_distinguishes_airs() {
+    // Two AIRs with the same width and constraints
+    macro_rules! bool_air {
+        ($name:ident) => {
+            struct $name;
+
+            impl<F> BaseAir<F> for $name {
+                fn width(&self) -> usize {
+                    1
+                }
+            }
+            impl<F> BaseAirWithPublicValues<F> for $name {}
+            impl<F> PartitionedBaseAir<F> for $name {}
+            impl<F> ColumnsAir<F> for $name {}
+
+            impl<AB: AirBuilder> Air<AB> for $name {
+                fn eval(&self, builder: &mut AB) {
+                    let main = builder.main();
+                    let x = main.row_slice(0)[0];
+                    builder.assert_bool(x);
+                }
+            }
+        };
+    }
+    bool_air!(BoolAir);
+    bool_air!(OtherBoolAir);
+
+    let engine = CachingEngine::<_, BabyBearPoseidon2Engine>::new(FriParameters::standard_fast());
+    let pk = engine.keygen(&any_rap_arc_vec![BoolAir]);
+    let other_pk = engine.keygen(&any_rap_arc_vec![OtherBoolAir]);
+    assert_eq!(engine.num_keygens(), 2);
+    assert_eq!(pk.per_air[0].air_name, "BoolAir");
+    assert_eq!(other_pk.per_air[0].air_name, "OtherBoolAir");
+}
@@ -0,0 +1,74 @@
+//! AIR with a constraint filtered by a rare selector through
+//! [ConditionalAirBuilder::when_lazy], counting how often the filtered constraint is evaluated.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use openvm_stark_backend::{
+    air_builders::{debug::debug_constraints_and_interactions, ConditionalAirBuilder},
+    keygen::MultiStarkKeygenBuilder,
+    p3_field::{Field, FieldAlgebra},
+    rap::{AnyRap, BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{self, BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+    engine::StarkFriEngine,
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+/// Columns `[selector, x]` with `x = 0` on the rows where `selector` is nonzero.
+#[derive(Default)]
+struct LazyFilterAir {
+    /// Number of evaluations of the filtered constraint.
+    num_evals: Arc<AtomicUsize>,
+}
+
+impl<F: Field> BaseAir<F> for LazyFilterAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for LazyFilterAir {}
+impl<F: Field> PartitionedBaseAir<F> for LazyFilterAir {}
+impl<F: Field> ColumnsAir<F> for LazyFilterAir {}
+
+impl<AB: ConditionalAirBuilder> Air<AB> for LazyFilterAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (selector, x) = (local[0], local[1]);
+
+        builder.when_lazy(selector, |builder| {
+            self.num_evals.fetch_add(1, Ordering::Relaxed);
+            builder.assert_zero(x);
+        });
+    }
+}
+
+#[test]
+fn test_debug_builder_skips_lazy_filter() {
+    let air = LazyFilterAir::default();
+    let num_evals = air.num_evals.clone();
+    let airs: Vec<Arc<dyn AnyRap<BabyBearPoseidon2Config>>> = vec![Arc::new(air)];
+
+    let perm = baby_bear_poseidon2::random_perm();
+    let config = baby_bear_poseidon2::default_config(&perm);
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    keygen_builder.add_air(airs[0].clone());
+    let pk = keygen_builder.generate_pk();
+
+    // Only the first row is selected, `x` is nonzero on all others
+    let values = [1, 0, 0, 5, 0, 6, 0, 7].map(BabyBear::from_canonical_u32);
+    let trace = RowMajorMatrix::new(values.to_vec(), 2);
+    num_evals.store(0, Ordering::Relaxed);
+    debug_constraints_and_interactions(&airs, &pk.per_air, &[vec![trace.as_view()]], &[vec![]]);
+    assert_eq!(num_evals.load(Ordering::Relaxed), 1);
+
+    // The filtered constraint is still enforced by the proof
+    BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(airs, vec![trace])
+        .expect("Verification failed");
+}
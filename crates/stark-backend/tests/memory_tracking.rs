@@ -0,0 +1,49 @@
+//! Peak memory of proving. The tracking allocator counts the allocations of the whole process,
+//! so these tests have their own binary to not measure the allocations of other tests, and run
+//! one at a time under [MEASUREMENT_LOCK] even when the test harness runs them in parallel.
+
+use std::{alloc::System, sync::Mutex};
+
+use openvm_stark_backend::{engine::StarkEngine, prover::types::ProofInput, Chip};
+use openvm_stark_sdk::{
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    dummy_airs::fib_air::chip::FibonacciChip,
+    engine::StarkFriEngine,
+    memory::{StarkEngineWithMemoryTracking, TrackingAllocator},
+};
+use p3_baby_bear::BabyBear;
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator::new(System);
+
+/// Held by every test of this binary while it runs, so that no other test allocates while the
+/// peak memory of a proof is measured.
+static MEASUREMENT_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_prove_with_memory_tracking() {
+    let _guard = MEASUREMENT_LOCK.lock().unwrap();
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let peak_bytes = |n: usize| {
+        let chip = FibonacciChip::new(0, 1, n);
+        let mut keygen_builder = engine.keygen_builder();
+        let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &[chip.air()]);
+        let pk = keygen_builder.generate_pk();
+        let proof_input = ProofInput::new(vec![(air_ids[0], chip.generate_air_proof_input())]);
+        let (proof, peak_bytes) = engine.prove_with_memory_tracking(&pk, proof_input);
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        peak_bytes
+    };
+
+    let small_peak = peak_bytes(1 << 6);
+    let n = 1 << 14;
+    let large_peak = peak_bytes(n);
+    assert!(small_peak > 0);
+    assert!(large_peak > small_peak);
+    // The low-degree extension of the 2-column trace is allocated while proving
+    let trace_bytes = 2 * n * size_of::<BabyBear>();
+    assert!(large_peak > trace_bytes << engine.fri_params.log_blowup);
+}
@@ -94,3 +94,146 @@ fn test_partitioned_sum_air_happy_neg() {
         Err(VerificationError::OodEvaluationMismatch)
     );
 }
+
+#[test]
+fn test_partitioned_sum_air_split_main() {
+    use openvm_stark_backend::{
+        proof::Proof,
+        prover::{
+            cpu::{CpuBackend, CpuDevice},
+            hal::DeviceDataTransporter,
+            types::{AirProvingContext, ProvingContext},
+            MultiTraceStarkProver, Prover,
+        },
+    };
+    use openvm_stark_sdk::{
+        config::{
+            baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+            FriParameters,
+        },
+        engine::StarkFriEngine,
+    };
+
+    let rng = StdRng::seed_from_u64(0);
+    let n = 1 << 3;
+    let y_width = 16;
+    let ys = generate_random_matrix::<Val>(rng, n, y_width);
+    let x = ys
+        .iter()
+        .map(|row| row.iter().fold(Val::ZERO, |sum, x| sum + *x))
+        .collect_vec();
+    let x_trace = Arc::new(RowMajorMatrix::new(x, 1));
+    let y_trace = Arc::new(RowMajorMatrix::new(ys.concat(), y_width));
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(SumAir(y_width)));
+    let pk = keygen_builder.generate_pk();
+
+    let backend = CpuBackend::default();
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let ctx = AirProvingContext::from_split_main(&device, vec![y_trace], Some(x_trace), vec![]);
+    let mut prover = MultiTraceStarkProver::new(backend, device, engine.new_challenger());
+    let mpk = backend.transport_pk_to_device(&pk, vec![air_id]);
+    let proof: Proof<BabyBearPoseidon2Config> = prover
+        .prove(mpk, ProvingContext::new(vec![(air_id, ctx)]))
+        .into();
+
+    // One commitment for the y columns and one for the common main x column
+    assert_eq!(proof.commitments.main_trace.len(), 2);
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_main_commit_hook() {
+    use std::sync::Mutex;
+
+    use openvm_stark_backend::{
+        challenger::MainCommitHook,
+        config::{Com, StarkGenericConfig},
+        p3_challenger::{CanObserve, CanSample},
+        proof::Proof,
+        prover::{
+            cpu::{CpuBackend, CpuDevice},
+            hal::DeviceDataTransporter,
+            types::{AirProvingContext, ProvingContext},
+            MultiTraceStarkProver, Prover,
+        },
+    };
+    use openvm_stark_sdk::{
+        config::{
+            baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+            FriParameters,
+        },
+        engine::StarkFriEngine,
+    };
+
+    type SC = BabyBearPoseidon2Config;
+    type Challenger = <SC as StarkGenericConfig>::Challenger;
+
+    // Observes the index of each main trace commitment and samples a challenge bound to it
+    fn hook(samples: Arc<Mutex<Vec<Val>>>) -> MainCommitHook<Challenger, Com<SC>> {
+        Arc::new(
+            move |challenger: &mut Challenger, commit_idx: usize, _: &Com<SC>| {
+                challenger.observe(Val::from_canonical_usize(commit_idx));
+                samples.lock().unwrap().push(challenger.sample());
+            },
+        )
+    }
+
+    let rng = StdRng::seed_from_u64(0);
+    let n = 1 << 3;
+    let y_width = 4;
+    let ys = generate_random_matrix::<Val>(rng, n, y_width);
+    let x = ys
+        .iter()
+        .map(|row| row.iter().fold(Val::ZERO, |sum, x| sum + *x))
+        .collect_vec();
+    let x_trace = Arc::new(RowMajorMatrix::new(x, 1));
+    let y_trace = Arc::new(RowMajorMatrix::new(ys.concat(), y_width));
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(SumAir(y_width)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let prover_samples = Arc::new(Mutex::new(vec![]));
+    let backend = CpuBackend::default();
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let ctx = AirProvingContext::from_split_main(&device, vec![y_trace], Some(x_trace), vec![]);
+    let mut prover = MultiTraceStarkProver::new(backend, device, engine.new_challenger())
+        .with_main_commit_hook(hook(prover_samples.clone()));
+    let mpk = backend.transport_pk_to_device(&pk, vec![air_id]);
+    let proof: Proof<SC> = prover
+        .prove(mpk, ProvingContext::new(vec![(air_id, ctx)]))
+        .into();
+
+    // The verifier with the same hook derives the same challenges after each main commitment
+    let verifier_samples = Arc::new(Mutex::new(vec![]));
+    engine
+        .verifier()
+        .with_main_commit_hook(hook(verifier_samples.clone()))
+        .verify(&mut engine.new_challenger(), &vk, &proof)
+        .expect("Verification failed");
+    let prover_samples = prover_samples.lock().unwrap();
+    assert_eq!(prover_samples.len(), 2);
+    assert_eq!(*verifier_samples.lock().unwrap(), *prover_samples);
+
+    // Without the hook, the transcript of the verifier diverges from the prover's
+    disable_debug_builder();
+    assert!(engine.verify(&vk, &proof).is_err());
+}
+
+#[test]
+fn test_default_column_names() {
+    use openvm_stark_backend::rap::ColumnsAir;
+    use openvm_stark_sdk::dummy_airs::fib_air::air::FibonacciAir;
+
+    let names = ColumnsAir::<Val>::column_names(&SumAir(5));
+    assert_eq!(names.len(), 6);
+    assert_eq!(names[0], "col_0");
+    assert_eq!(ColumnsAir::<Val>::column_names(&FibonacciAir).len(), 2);
+}
@@ -0,0 +1,133 @@
+//! A range table AIR with only a preprocessed trace, looked up by an AIR with a main trace.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    interaction::{InteractionBuilder, LookupBus},
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, AirProofRawInput},
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+const BUS: LookupBus = LookupBus::new(0);
+
+/// Table of the values `0..num_lookups.len()`, where value `i` is looked up `num_lookups[i]`
+/// times. Both columns are preprocessed, so the AIR has no main trace.
+struct RangeTableAir {
+    num_lookups: Vec<u32>,
+}
+
+impl<F: Field> BaseAir<F> for RangeTableAir {
+    fn width(&self) -> usize {
+        0
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = self
+            .num_lookups
+            .iter()
+            .enumerate()
+            .flat_map(|(value, &count)| {
+                [F::from_canonical_usize(value), F::from_canonical_u32(count)]
+            })
+            .collect();
+        Some(RowMajorMatrix::new(values, 2))
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for RangeTableAir {}
+impl<F: Field> PartitionedBaseAir<F> for RangeTableAir {}
+impl<F: Field> ColumnsAir<F> for RangeTableAir {}
+
+impl<AB: PairBuilder + InteractionBuilder> Air<AB> for RangeTableAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let local = preprocessed.row_slice(0);
+        BUS.add_key_with_lookups(builder, [local[0]], local[1]);
+    }
+}
+
+/// Looks up every value of its single main column in the range table.
+struct RangeCheckAir;
+
+impl<F: Field> BaseAir<F> for RangeCheckAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for RangeCheckAir {}
+impl<F: Field> PartitionedBaseAir<F> for RangeCheckAir {}
+impl<F: Field> ColumnsAir<F> for RangeCheckAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for RangeCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let value = main.row_slice(0)[0];
+        BUS.lookup_key(builder, [value], AB::Expr::ONE);
+    }
+}
+
+/// AIR without any columns.
+struct ZeroWidthAir;
+
+impl<F: Field> BaseAir<F> for ZeroWidthAir {
+    fn width(&self) -> usize {
+        0
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for ZeroWidthAir {}
+impl<F: Field> PartitionedBaseAir<F> for ZeroWidthAir {}
+impl<F: Field> ColumnsAir<F> for ZeroWidthAir {}
+
+impl<AB: AirBuilder> Air<AB> for ZeroWidthAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+#[test]
+fn test_preprocessed_only_range_table() {
+    let values = [0u32, 1, 3, 3, 5, 7, 7, 7];
+    let mut num_lookups = vec![0; 8];
+    for &value in &values {
+        num_lookups[value as usize] += 1;
+    }
+    let table_air = RangeTableAir { num_lookups };
+    let checked = RowMajorMatrix::new_col(
+        values
+            .into_iter()
+            .map(BabyBear::from_canonical_u32)
+            .collect(),
+    );
+
+    let table_input = AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![],
+            common_main: None,
+            public_values: vec![],
+        },
+    };
+    let table_trace = BaseAir::<BabyBear>::preprocessed_trace(&table_air);
+    assert_eq!(table_input.main_trace_height(table_trace.as_ref()), 8);
+    let data = BabyBearPoseidon2Engine::run_test_fast(
+        any_rap_arc_vec![table_air, RangeCheckAir],
+        vec![table_input, AirProofInput::simple_no_pis(checked)],
+    )
+    .expect("Verification failed");
+    // Only the range check AIR has a common main trace
+    assert_eq!(data.data.proof.opening.values.main.last().unwrap().len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "AIR ZeroWidthAir has width 0 and no preprocessed trace")]
+fn test_zero_width_air_rejected() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(ZeroWidthAir));
+}
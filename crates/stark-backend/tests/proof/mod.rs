@@ -0,0 +1,319 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+};
+
+use metrics_util::debugging::DebuggingRecorder;
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    p3_matrix::dense::RowMajorMatrix,
+    proof::{
+        io::{read_proof, write_proof},
+        Proof, SerdeFormat,
+    },
+    prover::types::ProofInput,
+    utils::force_serial,
+    verifier::{OpeningRound, ProofShapeError, VerificationError},
+    Chip,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, chip::FibonacciChip, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkFriEngine,
+    utils,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air, fib_selector_air::air::FibonacciSelectorAir, get_conditional_fib_number,
+    get_fib_number,
+};
+
+#[test]
+fn test_fib_golden_proof() {
+    let n = 8;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    utils::assert_golden_proof(
+        &engine,
+        any_rap_arc_vec![FibonacciAir],
+        vec![generate_trace_rows::<BabyBear>(0, 1, n)],
+        vec![pis],
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/golden/fib_baby_bear_poseidon2.json"
+        ),
+    );
+}
+
+#[test]
+fn test_validate_proof_shape() {
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let data = BabyBearPoseidon2Engine::run_test_fast(
+        vec![fib_chip.air()],
+        vec![fib_chip.generate_air_proof_input()],
+    )
+    .expect("Verification failed")
+    .data;
+    let (vk, proof) = (data.vk, data.proof);
+    assert_eq!(proof.validate_shape(&vk), Ok(()));
+
+    // Move the only common main matrix into a commitment of its own
+    let mut nested = proof.clone();
+    let values = nested.opening.values.main[0].pop().unwrap();
+    nested.opening.values.main.push(vec![values]);
+    assert_eq!(
+        nested.validate_shape(&vk),
+        Err(ProofShapeError::NumOpenedCommitments {
+            round: OpeningRound::Main,
+            expected: 1,
+            actual: 2,
+        })
+    );
+
+    // Open one value too many at the next row
+    let mut widened = proof.clone();
+    widened.opening.values.main[0][0].next.push(Default::default());
+    assert_eq!(
+        widened.validate_shape(&vk),
+        Err(ProofShapeError::OpenedWidth {
+            round: OpeningRound::Main,
+            commit_idx: 0,
+            matrix_idx: 0,
+            expected: 2,
+            actual: 3,
+        })
+    );
+
+    let mut missing_air = proof;
+    missing_air.per_air[0].air_id = 1;
+    assert_eq!(
+        missing_air.validate_shape(&vk),
+        Err(ProofShapeError::AirIdOutOfRange {
+            air_id: 1,
+            num_airs: 1,
+        })
+    );
+}
+
+#[test]
+fn test_proof_size_per_air() {
+    // The Fibonacci AIR has 2 columns, the interaction AIRs 9 and a permutation trace
+    let n = 8;
+    let fib_trace = generate_trace_rows::<BabyBear>(0, 1, n);
+    let fib_pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let field_width = 8;
+    let interaction_trace = RowMajorMatrix::new(
+        (0..n as u32)
+            .flat_map(|i| [1].into_iter().chain((0..field_width).map(move |j| i + j)))
+            .map(BabyBear::from_canonical_u32)
+            .collect(),
+        field_width as usize + 1,
+    );
+    let data = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![
+            FibonacciAir,
+            DummyInteractionAir::new(field_width as usize, true, 0),
+            DummyInteractionAir::new(field_width as usize, false, 0)
+        ],
+        vec![fib_trace, interaction_trace.clone(), interaction_trace],
+        vec![fib_pis, vec![], vec![]],
+    )
+    .expect("Verification failed")
+    .data;
+
+    let sizes = data.proof.size_per_air(&data.vk);
+    assert_eq!(sizes.len(), 3);
+    assert!(sizes[1] > sizes[0]);
+    assert_eq!(sizes[1], sizes[2]);
+    // Only parts of the serialized proof are attributed
+    let proof_len = bitcode::serialize(&data.proof).unwrap().len();
+    assert!(sizes.iter().sum::<usize>() < proof_len);
+}
+
+#[test]
+fn test_proof_serde_formats() {
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let data = BabyBearPoseidon2Engine::run_test_fast(
+        vec![fib_chip.air()],
+        vec![fib_chip.generate_air_proof_input()],
+    )
+    .expect("Verification failed")
+    .data;
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    for format in [SerdeFormat::Bincode, SerdeFormat::Postcard, SerdeFormat::Json] {
+        let bytes = data.proof.to_bytes(format);
+        let proof = Proof::<BabyBearPoseidon2Config>::from_bytes(format, &bytes)
+            .unwrap_or_else(|err| panic!("{format:?} deserialization failed: {err}"));
+        engine
+            .verify(&data.vk, &proof)
+            .expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_proof_streaming_serde() {
+    // Without interactions nor preprocessed trace, so without after challenge commitments
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let fib_proof = BabyBearPoseidon2Engine::run_test_fast(
+        vec![fib_chip.air()],
+        vec![fib_chip.generate_air_proof_input()],
+    )
+    .expect("Verification failed")
+    .data
+    .proof;
+    assert!(fib_proof.commitments.after_challenge.is_empty());
+
+    // Rows of (count, field), sent and received alike
+    let values = [1, 4, 2, 5, 0, 6, 3, 7].map(BabyBear::from_canonical_u32);
+    let trace = RowMajorMatrix::new(values.to_vec(), 2);
+    let interaction_proof = BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+        any_rap_arc_vec![
+            DummyInteractionAir::new(1, true, 0),
+            DummyInteractionAir::new(1, false, 0)
+        ],
+        vec![trace.clone(), trace],
+    )
+    .expect("Verification failed")
+    .data
+    .proof;
+    assert!(!interaction_proof.commitments.after_challenge.is_empty());
+
+    let dir = tempfile::tempdir().unwrap();
+    for (name, proof) in [("fib", fib_proof), ("interaction", interaction_proof)] {
+        // The proof I/O functions stream the bincode encoding
+        let path = dir.path().join(format!("{name}.proof"));
+        let mut writer = BufWriter::new(File::create(&path).unwrap());
+        write_proof(&proof, &mut writer).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            proof.to_bytes(SerdeFormat::Bincode)
+        );
+        let reader = BufReader::new(File::open(&path).unwrap());
+        let deserialized: Proof<BabyBearPoseidon2Config> = read_proof(reader).unwrap();
+        assert!(deserialized.semantically_eq(&proof));
+
+        for format in [SerdeFormat::Bincode, SerdeFormat::Postcard, SerdeFormat::Json] {
+            let path = dir.path().join(format!("{name}_{format:?}.proof"));
+            let mut writer = BufWriter::new(File::create(&path).unwrap());
+            proof.serialize_to(format, &mut writer).unwrap();
+            writer.flush().unwrap();
+            // Streaming does not change the encoding
+            assert_eq!(std::fs::read(&path).unwrap(), proof.to_bytes(format));
+
+            let reader = BufReader::new(File::open(&path).unwrap());
+            let deserialized =
+                Proof::<BabyBearPoseidon2Config>::deserialize_from(format, reader).unwrap();
+            assert!(deserialized.semantically_eq(&proof));
+        }
+    }
+}
+
+#[test]
+fn test_proof_semantically_eq_with_metrics() {
+    let prove = |n: usize| {
+        let fib_chip = FibonacciChip::new(0, 1, n);
+        BabyBearPoseidon2Engine::run_test_fast(
+            vec![fib_chip.air()],
+            vec![fib_chip.generate_air_proof_input()],
+        )
+        .expect("Verification failed")
+        .data
+        .proof
+    };
+    // Proving in parallel may find different proof-of-work witnesses
+    let recorder = DebuggingRecorder::new();
+    let with_metrics = force_serial(|| metrics::with_local_recorder(&recorder, || prove(8)));
+    let without_metrics = force_serial(|| prove(8));
+    assert!(with_metrics.semantically_eq(&without_metrics));
+    assert!(!with_metrics.semantically_eq(&prove(16)));
+}
+
+#[test]
+fn test_proof_records_quotient_degrees() {
+    let a = 0u32;
+    let b = 1u32;
+    let n1 = 1usize << 3;
+    let n2 = 1usize << 5;
+    let sels: Vec<bool> = (0..n2).map(|i| i % 2 == 0).collect();
+    let pis1 = [a, b, get_fib_number(n1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [a, b, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air2 = FibonacciSelectorAir::new(sels, false);
+    let trace1 = generate_trace_rows::<BabyBear>(a, b, n1);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<BabyBear>(a, b, air2.sels());
+
+    let data = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir, air2],
+        vec![trace1, trace2],
+        vec![pis1, pis2],
+    )
+    .expect("Verification failed")
+    .data;
+    let expected = data
+        .proof
+        .get_air_ids()
+        .into_iter()
+        .map(|air_id| data.vk.per_air[air_id].quotient_degree)
+        .collect::<Vec<_>>();
+    assert_eq!(data.proof.get_quotient_degrees(), expected);
+
+    // A proof whose recorded quotient degree disagrees with the vk is rejected
+    let mut proof = data.proof;
+    proof.per_air[0].quotient_degree += 1;
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    assert_eq!(
+        engine.verify(&data.vk, &proof),
+        Err(VerificationError::InvalidProofShape)
+    );
+}
+
+#[test]
+fn test_proof_trace_log_heights() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let airs = vec![fib_chip.air()];
+    let air_proof_inputs = vec![fib_chip.generate_air_proof_input()];
+    let data = utils::prove_verify_with_invariants(&engine, airs, air_proof_inputs)
+        .expect("Verification failed");
+
+    // A single common main trace commitment with the trace of height 8
+    assert_eq!(data.proof.trace_log_heights(&data.vk), vec![vec![3]]);
+}
+
+#[test]
+fn test_deterministic_proof() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(fib_chip.air());
+    let pk = keygen_builder.generate_pk();
+
+    let prove = || {
+        let proof_input = ProofInput {
+            per_air: vec![fib_chip.clone().generate_air_proof_input_with_id(air_id)],
+        };
+        let proof = force_serial(|| engine.prove(&pk, proof_input));
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        bitcode::serialize(&proof).unwrap()
+    };
+    assert_eq!(prove(), prove());
+}
@@ -0,0 +1,509 @@
+use std::{
+    iter::zip,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, ThreadId},
+};
+
+use openvm_stark_backend::{
+    config::Com,
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    proof::Proof,
+    prover::{
+        cpu::{CpuBackend, CpuDevice, PcsData},
+        hal::{DeviceDataTransporter, ProverBackend, TraceCommitter},
+        remote::{CommitHandle, RemoteCommitDevice, RemoteCommitter},
+        types::{AirProvingContext, LazyAirProvingContext, ProvingContext},
+        MultiTraceStarkProver, Prover, ProverError,
+    },
+    utils::force_serial,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    utils::prove_on_device,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air, fib_selector_air::air::FibonacciSelectorAir, get_conditional_fib_number,
+    get_fib_number,
+};
+
+#[test]
+fn test_fib_public_value_count_mismatch() {
+    let n = 8;
+    let pis = [0, 1, get_fib_number(n)].map(BabyBear::from_canonical_u32);
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let prove = |public_values: Vec<BabyBear>| {
+        let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+        let inputs = vec![(air_id, trace.clone(), public_values)];
+        prove_on_device(device, engine.new_challenger(), &pk, inputs)
+            .map(|(proof, _)| proof)
+    };
+    let mismatch = |actual| ProverError::PublicValueCountMismatch {
+        air_id,
+        expected: 3,
+        actual,
+    };
+    assert_eq!(prove(pis[..2].to_vec()).err(), Some(mismatch(2)));
+    assert_eq!(
+        prove([&pis[..], &[BabyBear::ZERO]].concat()).err(),
+        Some(mismatch(4))
+    );
+
+    // The verifier rejects a proof whose public values were truncated or extended
+    let proof = prove(pis.to_vec()).unwrap();
+    let vk = pk.get_vk();
+    let mismatch = |actual| VerificationError::PublicValueCountMismatch {
+        air_id,
+        expected: 3,
+        actual,
+    };
+    let mut too_few = proof.clone();
+    too_few.per_air[0].public_values.pop();
+    assert_eq!(engine.verify(&vk, &too_few), Err(mismatch(2)));
+    let mut too_many = proof;
+    too_many.per_air[0].public_values.push(BabyBear::ZERO);
+    assert_eq!(engine.verify(&vk, &too_many), Err(mismatch(4)));
+}
+
+#[test]
+fn test_quotient_selectors_shared_by_equal_heights() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    // Three AIRs of height 8 and one of height 32, all with the same quotient degree
+    let heights = [8, 8, 32, 8];
+    let airs = any_rap_arc_vec![FibonacciAir, FibonacciAir, FibonacciAir, FibonacciAir];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let inputs = air_ids
+        .into_iter()
+        .zip(heights)
+        .map(|(air_id, n)| {
+            let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+            let public_values = [0, 1, get_fib_number(n)]
+                .map(BabyBear::from_canonical_u32)
+                .to_vec();
+            (air_id, trace, public_values)
+        })
+        .collect();
+    let (proof, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+
+    let stats = prover.device.quotient_eval_stats();
+    assert_eq!(stats.num_selector_computations(), 2);
+}
+
+#[test]
+fn test_max_cost_cells_air_is_tallest() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let heights = [1usize << 3, 1 << 10, 1 << 5];
+    let airs = any_rap_arc_vec![FibonacciAir, FibonacciAir, FibonacciAir];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    assert!(engine.prover().trace_metrics().is_none());
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let inputs = zip(air_ids.clone(), heights)
+        .map(|(air_id, n)| {
+            let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+            // The Fibonacci numbers overflow u32, so read the last one from the trace
+            let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(n - 1, 1)];
+            (air_id, trace, public_values)
+        })
+        .collect();
+    let (_, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    let max_cost_air = prover
+        .trace_metrics()
+        .expect("trace metrics are recorded by prove")
+        .max_cost_cells_air()
+        .unwrap();
+    assert_eq!(max_cost_air.air_id, air_ids[1]);
+    assert_eq!(max_cost_air.height, 1 << 10);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_proof_stats_num_threads() {
+    use metrics_util::debugging::DebuggingRecorder;
+    use openvm_stark_sdk::bench::serialize_metric_snapshot;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let num_threads = 3;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .unwrap();
+    // The recorder is local to the thread proving inside the pool
+    let (stats, snapshot) = pool.install(|| {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let stats = metrics::with_local_recorder(&recorder, || {
+            assert!(engine.prover().proof_stats().is_none());
+            let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+            let trace = generate_trace_rows::<BabyBear>(0, 1, 16);
+            let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(15, 1)];
+            let inputs = vec![(air_id, trace, public_values)];
+            let (_, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+                .expect("Proving failed");
+            prover.proof_stats().cloned().unwrap()
+        });
+        (stats, serialize_metric_snapshot(snapshotter.snapshot()))
+    });
+    assert_eq!(stats.num_threads, num_threads);
+    assert_eq!(stats.trace_metrics.per_air[0].height, 16);
+
+    if cfg!(feature = "bench-metrics") {
+        let emitted = snapshot["counter"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|metric| metric["metric"] == "num_threads")
+            .expect("num_threads is emitted");
+        assert_eq!(emitted["value"], num_threads.to_string());
+    }
+}
+
+#[test]
+fn test_air_name_override() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let mut keygen_builder = engine.keygen_builder();
+    let main_id = keygen_builder.add_air_with_name(Arc::new(FibonacciAir), "FibChip[main]");
+    let aux_id = keygen_builder.add_air_with_name(Arc::new(FibonacciAir), "FibChip[aux]");
+    let pk = keygen_builder.generate_pk();
+    assert_eq!(pk.per_air[main_id].air_name, pk.per_air[aux_id].air_name);
+    assert_eq!(pk.per_air[main_id].name(), "FibChip[main]");
+    assert_eq!(pk.per_air[aux_id].name(), "FibChip[aux]");
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let inputs = [main_id, aux_id]
+        .into_iter()
+        .map(|air_id| {
+            let trace = generate_trace_rows::<BabyBear>(0, 1, 16);
+            let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(15, 1)];
+            (air_id, trace, public_values)
+        })
+        .collect();
+    let (_, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    let metrics = prover.trace_metrics().unwrap();
+    let labels: Vec<_> = metrics
+        .per_air
+        .iter()
+        .map(|m| (m.air_id, m.air_name.as_str()))
+        .collect();
+    assert_eq!(labels, vec![(main_id, "FibChip[main]"), (aux_id, "FibChip[aux]")]);
+}
+
+#[cfg(feature = "bench-metrics")]
+#[test]
+fn test_per_air_metric_labels() {
+    use metrics_util::debugging::DebuggingRecorder;
+    use openvm_stark_sdk::bench::serialize_metric_snapshot;
+
+    let n = 1usize << 3;
+    let sels: Vec<bool> = (0..n).map(|i| i % 2 == 0).collect();
+    let pis1 = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [0, 1, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air1 = FibonacciAir;
+    let air2 = FibonacciSelectorAir::new(sels, false);
+    let trace1 = generate_trace_rows::<BabyBear>(0, 1, n);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<BabyBear>(0, 1, air2.sels());
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        BabyBearPoseidon2Engine::run_simple_test_fast(
+            any_rap_arc_vec![air1, air2],
+            vec![trace1, trace2],
+            vec![pis1, pis2],
+        )
+        .expect("Verification failed");
+    });
+
+    let snapshot = serialize_metric_snapshot(snapshotter.snapshot());
+    let quotient_labels = snapshot["gauge"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|metric| metric["metric"] == "quotient_poly_compute_time_ms")
+        .map(|metric| metric["labels"].clone())
+        .collect::<Vec<_>>();
+    let mut air_ids = quotient_labels
+        .iter()
+        .map(|labels| {
+            let labels = labels.as_array().unwrap();
+            assert!(labels.iter().any(|label| label[0] == "air_name"));
+            let air_id = labels.iter().find(|label| label[0] == "air_id").unwrap();
+            air_id[1].as_str().unwrap().to_owned()
+        })
+        .collect::<Vec<_>>();
+    air_ids.sort();
+    assert_eq!(air_ids, ["0", "1"]);
+}
+
+#[test]
+fn test_prove_single_matches_prove() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let n = 1usize << 3;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let inputs = vec![(air_id, trace.clone(), pis.clone())];
+    let (proof, _) = force_serial(|| prove_on_device(device, engine.new_challenger(), &pk, inputs))
+        .expect("Proving failed");
+
+    let backend = CpuBackend::default();
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let mut prover = MultiTraceStarkProver::new(backend, device, engine.new_challenger());
+    let ctx = AirProvingContext {
+        cached_mains: vec![],
+        common_main: Some(Arc::new(trace)),
+        public_values: pis,
+        cached_lifetime: PhantomData,
+    };
+    let single_proof: Proof<BabyBearPoseidon2Config> =
+        force_serial(|| prover.prove_single(&pk, air_id, ctx)).into();
+
+    assert_eq!(
+        bitcode::serialize(&single_proof).unwrap(),
+        bitcode::serialize(&proof).unwrap()
+    );
+    engine
+        .verify(&pk.get_vk(), &single_proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_device_log_blowup_mismatch() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let log_blowup = engine.fri_params.log_blowup;
+    assert!(CpuDevice::try_new(engine.config(), log_blowup, log_blowup).is_ok());
+    assert_eq!(
+        CpuDevice::try_new(engine.config(), log_blowup + 1, log_blowup).err(),
+        Some(ProverError::LogBlowupMismatch {
+            device: log_blowup + 1,
+            pcs: log_blowup,
+        })
+    );
+}
+
+#[test]
+fn test_reuse_transported_pk() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let sels = (0..8).map(|i| i % 2 == 0).collect::<Vec<_>>();
+    let pis = [0, 1, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air = FibonacciSelectorAir::new(sels, false);
+    let trace = Arc::new(fib_selector_air::trace::generate_trace_rows::<BabyBear>(
+        0,
+        1,
+        air.sels(),
+    ));
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(air));
+    let pk = keygen_builder.generate_pk();
+    let preprocessed_trace = &pk.per_air[air_id].preprocessed_data.as_ref().unwrap().trace;
+
+    let backend = CpuBackend::default();
+    let mpk = backend.transport_pk_to_device(&pk, vec![air_id]);
+    let device_trace = &mpk.per_air[0].preprocessed_data.as_ref().unwrap().trace;
+    assert!(Arc::ptr_eq(device_trace, preprocessed_trace));
+    let strong_count = Arc::strong_count(preprocessed_trace);
+
+    for _ in 0..2 {
+        let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+        let mut prover = MultiTraceStarkProver::new(backend, device, engine.new_challenger());
+        let ctx = AirProvingContext {
+            cached_mains: vec![],
+            common_main: Some(trace.clone()),
+            public_values: pis.clone(),
+            cached_lifetime: PhantomData,
+        };
+        let proof: Proof<BabyBearPoseidon2Config> = prover
+            .prove(mpk.clone(), ProvingContext::new(vec![(air_id, ctx)]))
+            .into();
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        // The clone of the transported key shared the preprocessed trace and has been dropped
+        assert_eq!(Arc::strong_count(preprocessed_trace), strong_count);
+    }
+}
+
+#[test]
+fn test_prove_with_remote_committer() {
+    type SC = BabyBearPoseidon2Config;
+
+    /// Handle to a commitment which is already available.
+    struct Ready<T>(T);
+    impl<PB: ProverBackend> CommitHandle<PB> for Ready<(PB::Commitment, PB::PcsData)> {
+        fn wait(self) -> (PB::Commitment, PB::PcsData) {
+            self.0
+        }
+    }
+
+    /// In-process stand-in for a remote service, committing with the CPU device.
+    struct MockRemoteCommitter<'a> {
+        device: CpuDevice<'a, SC>,
+        num_submitted: AtomicUsize,
+    }
+    impl RemoteCommitter<CpuBackend<SC>> for MockRemoteCommitter<'_> {
+        type Handle = Ready<(Com<SC>, PcsData<SC>)>;
+
+        fn submit(&self, traces: &[Arc<RowMajorMatrix<BabyBear>>]) -> Self::Handle {
+            self.num_submitted.fetch_add(1, Ordering::Relaxed);
+            Ready(self.device.commit(traces))
+        }
+    }
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let committer = MockRemoteCommitter {
+        device: CpuDevice::new(engine.config(), engine.fri_params.log_blowup),
+        num_submitted: AtomicUsize::new(0),
+    };
+    let device = RemoteCommitDevice::new(
+        CpuDevice::new(engine.config(), engine.fri_params.log_blowup),
+        committer,
+    );
+    let trace = generate_trace_rows::<BabyBear>(0, 1, 16);
+    let public_values = vec![trace.get(0, 0), trace.get(0, 1), trace.get(15, 1)];
+    let inputs = vec![(air_id, trace, public_values)];
+    let (proof, prover) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+    // Only the common main trace is committed through the trace committer
+    assert_eq!(prover.device.committer.num_submitted.load(Ordering::Relaxed), 1);
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_prove_lazy_generates_traces_while_proving() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let heights = [1usize << 3, 1 << 5, 1 << 4];
+    let airs = any_rap_arc_vec![FibonacciAir, FibonacciAir, FibonacciAir];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let backend = CpuBackend::default();
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+    let mut prover = MultiTraceStarkProver::new(backend, device, engine.new_challenger());
+    let mpk = backend.transport_pk_to_device(&pk, air_ids.clone());
+    // Each producer records its AIR id and the thread it runs on when it is invoked
+    let invoked: Mutex<Vec<(usize, ThreadId)>> = Mutex::new(vec![]);
+    let ctx = zip(air_ids.clone(), heights)
+        .map(|(air_id, n)| {
+            let invoked = &invoked;
+            let common_main = Box::new(move || {
+                invoked.lock().unwrap().push((air_id, thread::current().id()));
+                generate_trace_rows::<BabyBear>(0, 1, n)
+            });
+            let public_values = [0, 1, get_fib_number(n)]
+                .map(BabyBear::from_canonical_u32)
+                .to_vec();
+            (air_id, LazyAirProvingContext::new(common_main, public_values))
+        })
+        .collect();
+    let proof: Proof<BabyBearPoseidon2Config> = prover.prove_lazy(mpk, ctx).into();
+
+    let invoked = invoked.into_inner().unwrap();
+    assert_eq!(invoked.iter().map(|(air_id, _)| *air_id).collect::<Vec<_>>(), air_ids);
+    // The traces are generated on one thread concurrently with the prover, not before it starts
+    let prover_thread = thread::current().id();
+    assert!(invoked.iter().all(|(_, thread)| *thread != prover_thread));
+    assert!(invoked.iter().all(|(_, thread)| *thread == invoked[0].1));
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_quotient_identity_check_names_air() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let n1 = 1usize << 3;
+    let n2 = 1usize << 5;
+    let sels: Vec<bool> = (0..n2).map(|i| i % 2 == 0).collect();
+    let pis1 = [0, 1, get_fib_number(n1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    // Wrong final value, so the last row constraint of the second AIR is violated.
+    let pis2 = [0, 1, get_conditional_fib_number(&sels) + 1]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air2 = FibonacciSelectorAir::new(sels, false);
+    let trace1 = generate_trace_rows::<BabyBear>(0, 1, n1);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<BabyBear>(0, 1, air2.sels());
+
+    let airs = any_rap_arc_vec![FibonacciAir, air2];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_quotient_identity_check(true);
+    let inputs = vec![(air_ids[0], trace1, pis1), (air_ids[1], trace2, pis2)];
+    let Err(err) = prove_on_device(device, engine.new_challenger(), &pk, inputs) else {
+        panic!("quotient identity check should fail");
+    };
+    assert_eq!(
+        err,
+        ProverError::QuotientIdentity {
+            air_name: "FibonacciSelectorAir".to_string(),
+            source: VerificationError::OodEvaluationMismatch,
+        }
+    );
+}
@@ -0,0 +1,111 @@
+//! Memory-like AIR where each row accesses one address, receiving the previous value of the
+//! address with the timestamp of its last access and sending the new value with the timestamp of
+//! the row.
+
+use openvm_stark_backend::{
+    interaction::InteractionBuilder,
+    p3_field::{Field, FieldAlgebra},
+    rap::{BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec, config::baby_bear_poseidon2::BabyBearPoseidon2Engine,
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir, engine::StarkFriEngine,
+};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+const MEMORY_BUS: u16 = 0;
+
+/// Columns `[addr, prev_value, prev_timestamp, value, timestamp]`.
+struct MemoryAir;
+
+impl<F: Field> BaseAir<F> for MemoryAir {
+    fn width(&self) -> usize {
+        5
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for MemoryAir {}
+impl<F: Field> PartitionedBaseAir<F> for MemoryAir {}
+impl<F: Field> ColumnsAir<F> for MemoryAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for MemoryAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let [addr, prev_value, prev_timestamp, value, timestamp] =
+            [0, 1, 2, 3, 4].map(|i| local[i]);
+        let next_timestamp = next[4];
+
+        builder.push_interaction(
+            MEMORY_BUS,
+            [addr, prev_value, prev_timestamp],
+            AB::Expr::NEG_ONE,
+            1,
+        );
+        builder.push_timed_interaction(
+            MEMORY_BUS,
+            [addr, value],
+            timestamp,
+            next_timestamp,
+            AB::Expr::ONE,
+            1,
+        );
+    }
+}
+
+/// Proves the `accesses` of `(addr, value)` by [MemoryAir] at timestamps `timestamps`, with the
+/// initial and final memory of two addresses sent and received by dummy AIRs.
+fn prove_and_verify(
+    accesses: [(u32, u32); 4],
+    timestamps: [u32; 4],
+) -> Result<(), VerificationError> {
+    // The value and timestamp of the last access of each address
+    let mut memory = [(0, 0); 2];
+    let values = accesses
+        .into_iter()
+        .zip(timestamps)
+        .flat_map(|((addr, value), timestamp)| {
+            let (prev_value, prev_timestamp) = memory[addr as usize];
+            memory[addr as usize] = (value, timestamp);
+            [addr, prev_value, prev_timestamp, value, timestamp]
+        })
+        .map(BabyBear::from_canonical_u32)
+        .collect();
+    let memory_trace = RowMajorMatrix::new(values, 5);
+    // Rows of `[count, addr, value, timestamp]`
+    let boundary_trace = |memory: [(u32, u32); 2]| {
+        let values = (0..2).flat_map(|addr| {
+            let (value, timestamp) = memory[addr as usize];
+            [1, addr, value, timestamp]
+        });
+        RowMajorMatrix::new(values.map(BabyBear::from_canonical_u32).collect(), 4)
+    };
+    BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+        any_rap_arc_vec![
+            MemoryAir,
+            DummyInteractionAir::new(3, true, MEMORY_BUS),
+            DummyInteractionAir::new(3, false, MEMORY_BUS)
+        ],
+        vec![memory_trace, boundary_trace([(0, 0); 2]), boundary_trace(memory)],
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn test_timed_memory_air() {
+    prove_and_verify([(0, 5), (1, 7), (0, 6), (1, 7)], [1, 2, 3, 4])
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_timed_memory_air_unordered_timestamps() {
+    // The bus is balanced, but the timestamps do not increase by one on each row
+    disable_debug_builder();
+    assert_eq!(
+        prove_and_verify([(0, 5), (1, 7), (0, 6), (1, 7)], [1, 2, 4, 3]),
+        Err(VerificationError::OodEvaluationMismatch)
+    );
+}
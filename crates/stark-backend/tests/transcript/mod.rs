@@ -0,0 +1,245 @@
+use std::{iter::zip, sync::Arc};
+
+use openvm_stark_backend::{
+    challenger::{ChallengeLog, ChallengeLogger},
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    keygen::{types::MultiStarkProvingKey, MultiStarkKeygenBuilder},
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    p3_matrix::dense::RowMajorMatrix,
+    proof::Proof,
+    prover::cpu::CpuDevice,
+    utils::force_serial,
+    verifier::MultiTraceStarkVerifier,
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            default_perm, logged_config_from_perm, BabyBearPermutationLoggedConfig,
+            BabyBearPoseidon2Config, BabyBearPoseidon2Engine, Challenger,
+        },
+        fri_params::SecurityParameters,
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkFriEngine,
+    utils::prove_on_device,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+
+use crate::{fib_triples_air, get_fib_number};
+
+#[test]
+fn test_recover_challenges() {
+    type SC = BabyBearPermutationLoggedConfig<Poseidon2BabyBear<16>>;
+    type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+    let perm = default_perm();
+    let security_params = SecurityParameters::standard_fast();
+    let log_blowup = security_params.fri_params.log_blowup;
+    let config = logged_config_from_perm(&perm, security_params);
+
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    let air_ids = vec![
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0))),
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0))),
+    ];
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    // Rows of (count, field), sent and received alike
+    let values = [1, 4, 2, 5, 0, 6, 3, 7].map(BabyBear::from_canonical_u32);
+    let trace = RowMajorMatrix::new(values.to_vec(), 2);
+    let device = CpuDevice::new(&config, log_blowup);
+    let challenger = ChallengeLogger::record(Challenger::new(perm.clone()));
+    let inputs = air_ids
+        .into_iter()
+        .map(|air_id| (air_id, trace.clone(), vec![]))
+        .collect();
+    let (proof, prover) = prove_on_device(device, challenger, &pk, inputs)
+        .expect("Proving failed");
+    let prover_log = prover.into_challenger().into_log();
+
+    let verifier = MultiTraceStarkVerifier::new(&config);
+    let new_challenger = || ChallengeLogger::record(Challenger::new(perm.clone()));
+    let recover = |proof| {
+        verifier
+            .recover_challenges(&mut new_challenger(), &vk, proof)
+            .expect("challenges should be recovered")
+    };
+    let challenges = recover(&proof);
+    assert_eq!(challenges.alphas.len(), 2);
+    assert_eq!(challenges.alphas[0], challenges.alphas[1]);
+    assert_eq!(challenges.challenges_per_phase.len(), 1);
+    assert!(!challenges.challenges_per_phase[0].is_empty());
+
+    // The prover samples the LogUp challenges, then the global `alpha`, then `zeta`, before any
+    // FRI challenge
+    let expected: Vec<Challenge> = challenges.challenges_per_phase[0]
+        .iter()
+        .copied()
+        .chain([challenges.alphas[0], challenges.zeta])
+        .collect();
+    let expected_samples: Vec<BabyBear> = expected
+        .iter()
+        .flat_map(|challenge| challenge.as_base_slice().to_vec())
+        .collect();
+    assert_eq!(prover_log.samples[..expected_samples.len()], expected_samples[..]);
+
+    // The quotient openings are not observed before `zeta`, so a proof with a wrong opening fails
+    // verification but has the same challenges
+    let mut bad_proof = proof.clone();
+    bad_proof.opening.values.quotient[0][0][0] += Challenge::ONE;
+    assert!(verifier
+        .verify(&mut new_challenger(), &vk, &bad_proof)
+        .is_err());
+    assert_eq!(recover(&bad_proof), challenges);
+}
+
+#[test]
+fn test_challenge_log_replay() {
+    let perm = default_perm();
+    let security_params = SecurityParameters::standard_fast();
+    let log_blowup = security_params.fri_params.log_blowup;
+    let config = logged_config_from_perm(&perm, security_params);
+
+    let n = 1usize << 3;
+    let fib_pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let fib_trace = generate_trace_rows::<BabyBear>(0, 1, n);
+    // Rows of (count, field), sent by one AIR and received by the other
+    let interaction_trace = RowMajorMatrix::new(
+        [1, 4, 2, 5, 0, 6, 3, 7]
+            .map(BabyBear::from_canonical_u32)
+            .to_vec(),
+        2,
+    );
+
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    let air_ids = vec![
+        keygen_builder.add_air(Arc::new(FibonacciAir)),
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0))),
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0))),
+    ];
+    let pk = keygen_builder.generate_pk();
+    let inputs = vec![
+        (air_ids[0], fib_trace, fib_pis),
+        (air_ids[1], interaction_trace.clone(), vec![]),
+        (air_ids[2], interaction_trace, vec![]),
+    ];
+
+    let prove = |challenger| {
+        let device = CpuDevice::new(&config, log_blowup);
+        let (proof, prover) =
+            force_serial(|| prove_on_device(device, challenger, &pk, inputs.clone()))
+                .expect("Proving failed");
+        (bitcode::serialize(&proof).unwrap(), prover.into_challenger().into_log())
+    };
+
+    let (proof, log) = prove(ChallengeLogger::record(Challenger::new(perm.clone())));
+    assert!(!log.samples.is_empty());
+    let (replayed_proof, _) =
+        prove(ChallengeLogger::replay(Challenger::new(perm.clone()), log.clone()));
+    assert_eq!(replayed_proof, proof);
+
+    // Replayed samples take the place of the samples of the challenger. The last samples are FRI
+    // query indices.
+    let mut tampered_log = log;
+    *tampered_log.samples.last_mut().unwrap() += BabyBear::ONE;
+    let (tampered_proof, _) = prove(ChallengeLogger::replay(Challenger::new(perm), tampered_log));
+    assert_ne!(tampered_proof, proof);
+}
+
+#[test]
+fn test_quotient_commitment_observed_before_zeta() {
+    type SC = BabyBearPermutationLoggedConfig<Poseidon2BabyBear<16>>;
+
+    let perm = default_perm();
+    let security_params = SecurityParameters::standard_fast();
+    let log_blowup = security_params.fri_params.log_blowup;
+    let config = logged_config_from_perm(&perm, security_params);
+
+    let n = 1usize << 3;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let device = CpuDevice::new(&config, log_blowup);
+    let challenger = ChallengeLogger::record(Challenger::new(perm.clone()));
+    let inputs = vec![(air_id, trace, pis)];
+    let (proof, prover) = prove_on_device(device, challenger, &pk, inputs)
+        .expect("Proving failed");
+    let prover_log = prover.into_challenger().into_log();
+
+    let verifier_log = |proof: &Proof<SC>| -> ChallengeLog<BabyBear> {
+        let mut challenger = ChallengeLogger::record(Challenger::new(perm.clone()));
+        let _ = MultiTraceStarkVerifier::new(&config).verify(&mut challenger, &vk, proof);
+        challenger.into_log()
+    };
+    // The prover and verifier draw the same challenges, `zeta` included
+    assert_eq!(verifier_log(&proof), prover_log);
+
+    // Changing the quotient commitment changes `zeta`, but none of the challenges before it
+    let mut tampered_proof = proof.clone();
+    tampered_proof.commitments.quotient[0] = proof.commitments.main_trace[0];
+    let tampered_log = verifier_log(&tampered_proof);
+    let num_common = zip(&tampered_log.samples, &prover_log.samples)
+        .take_while(|(a, b)| a == b)
+        .count();
+    assert!(num_common > 0 && num_common < tampered_log.samples.len());
+}
+
+#[test]
+fn test_chained_proof_transcripts() {
+    type SC = BabyBearPoseidon2Config;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let keygen = |air: AirRef<SC>| {
+        let mut keygen_builder = engine.keygen_builder();
+        keygen_builder.add_air(air);
+        keygen_builder.generate_pk()
+    };
+    // Proves `inputs` starting from `challenger`, returning the proof and final challenger
+    let prove = |pk: &MultiStarkProvingKey<SC>, inputs, challenger| {
+        let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup);
+        let (proof, prover) = prove_on_device(device, challenger, pk, inputs)
+            .expect("Proving failed");
+        (proof, prover.into_challenger())
+    };
+
+    let n = 1usize << 3;
+    let pk1 = keygen(Arc::new(FibonacciAir));
+    let pis1 = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace1 = generate_trace_rows::<BabyBear>(0, 1, n);
+    let (proof1, challenger) = prove(&pk1, vec![(0, trace1, pis1)], engine.new_challenger());
+
+    let pk2 = keygen(Arc::new(fib_triples_air::air::FibonacciAir));
+    let pis2 = [0, 1, get_fib_number(n + 1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace2 = fib_triples_air::trace::generate_trace_rows::<BabyBear>(0, 1, n);
+    let (proof2, _) = prove(&pk2, vec![(0, trace2, pis2)], challenger);
+
+    let verifier = engine.verifier();
+    let mut challenger = engine.new_challenger();
+    verifier
+        .verify(&mut challenger, &pk1.get_vk(), &proof1)
+        .expect("Verification failed");
+    verifier
+        .verify(&mut challenger, &pk2.get_vk(), &proof2)
+        .expect("Verification failed");
+    // The second proof is bound to the transcript of the first
+    assert!(engine.verify(&pk2.get_vk(), &proof2).is_err());
+}
@@ -0,0 +1,87 @@
+//! AIR with a single column `x` and a variable number of public values, at most
+//! [MAX_NUM_PUBLIC_VALUES]. Constrains `x` on the first row to equal the sum of the public value
+//! slots, so inactive (zero) slots do not contribute.
+
+use openvm_stark_backend::{
+    rap::{pad_public_values, BaseAirWithPublicValues, ColumnsAir, PartitionedBaseAir},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::StarkFriEngine,
+    utils::to_field_vec,
+};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+const MAX_NUM_PUBLIC_VALUES: usize = 4;
+
+pub struct SumPublicValuesAir;
+
+impl<F> BaseAir<F> for SumPublicValuesAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F> BaseAirWithPublicValues<F> for SumPublicValuesAir {
+    fn num_public_values(&self) -> usize {
+        MAX_NUM_PUBLIC_VALUES
+    }
+    fn has_variable_public_values(&self) -> bool {
+        true
+    }
+}
+impl<F> PartitionedBaseAir<F> for SumPublicValuesAir {}
+impl<F> ColumnsAir<F> for SumPublicValuesAir {}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for SumPublicValuesAir {
+    fn eval(&self, builder: &mut AB) {
+        let pis = builder.public_values();
+        // The last public value is the active count
+        assert_eq!(pis.len(), MAX_NUM_PUBLIC_VALUES + 1);
+        let sum: AB::Expr = pis[..MAX_NUM_PUBLIC_VALUES]
+            .iter()
+            .map(|&pv| -> AB::Expr { pv.into() })
+            .sum();
+
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let x = local[0];
+        drop(local);
+        builder.when_first_row().assert_eq(x, sum);
+    }
+}
+
+fn trace_with_first_row(x: u32) -> RowMajorMatrix<BabyBear> {
+    RowMajorMatrix::new(to_field_vec(vec![x, 0, 0, 0]), 1)
+}
+
+#[test]
+fn test_variable_public_values_padded() {
+    let public_values =
+        pad_public_values::<BabyBear>(to_field_vec(vec![3, 4]), MAX_NUM_PUBLIC_VALUES);
+    assert_eq!(public_values, to_field_vec(vec![3, 4, 0, 0, 2]));
+
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![SumPublicValuesAir],
+        vec![trace_with_first_row(7)],
+        vec![public_values],
+    )
+    .expect("Verification failed");
+}
+
+#[test]
+fn test_variable_public_values_nonzero_padding() {
+    // Active count says 1, but the second slot is nonzero. The AIR constraints are still satisfied.
+    let public_values = to_field_vec(vec![3, 4, 0, 0, 1]);
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let result = StarkFriEngine::run_simple_test_impl(
+        &engine,
+        any_rap_arc_vec![SumPublicValuesAir],
+        vec![trace_with_first_row(7)],
+        vec![public_values],
+    );
+    assert_eq!(result.err(), Some(VerificationError::InvalidProofShape));
+}
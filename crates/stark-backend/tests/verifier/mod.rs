@@ -0,0 +1,243 @@
+use std::{iter::zip, sync::Arc};
+
+use openvm_stark_backend::{
+    accumulation::AccumulationStrategy,
+    config::{AlphaSampling, StarkGenericConfig},
+    engine::StarkEngine,
+    p3_commit::{Pcs, PolynomialSpace},
+    p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra},
+    prover::{
+        cpu::CpuDevice,
+        types::{AirProofInput, ProofInput},
+    },
+    utils::disable_debug_builder,
+    verifier::{
+        constraints::{vanishing_eval_at, vanishing_evals_at},
+        VerificationError, VerifyAllError,
+    },
+    Chip,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, chip::FibonacciChip, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    utils::{prove_on_device, prove_verify_with_invariants},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{fib_selector_air, get_conditional_fib_number, get_fib_number};
+
+type Challenge = <BabyBearPoseidon2Config as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_vanishing_eval_at_fib_domain() {
+    type SC = BabyBearPoseidon2Config;
+
+    let n = 1usize << 3;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let data = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![generate_trace_rows::<BabyBear>(0, 1, n)],
+        vec![pis],
+    )
+    .expect("Verification failed")
+    .data;
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let pcs = engine.config().pcs();
+    let zeta = Challenge::from_base_fn(|i| BabyBear::from_canonical_usize(7 * i + 3));
+    let domain = pcs.natural_domain_for_degree(n);
+    let z_h = vanishing_eval_at::<SC>(domain, zeta);
+    // The verifier divides the folded constraints by `Z_H(zeta)` via the selectors at `zeta`
+    assert_eq!(z_h, domain.selectors_at_point(zeta).inv_zeroifier.inverse());
+    assert_eq!(vanishing_evals_at::<SC>(pcs, &data.proof, zeta), vec![z_h]);
+}
+
+#[test]
+fn test_verify_batch() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let fib_chip = FibonacciChip::new(0, 1, 8);
+    let airs = vec![fib_chip.air()];
+    let air_proof_inputs = vec![fib_chip.generate_air_proof_input()];
+    let data = prove_verify_with_invariants(&engine, airs, air_proof_inputs)
+        .expect("Verification failed");
+    let (vk, proof) = (data.vk, data.proof);
+
+    // A taller trace of the same AIR, whose domains differ from those of the other proofs
+    let tall_chip = FibonacciChip::new(0, 1, 32);
+    let tall_proof = prove_verify_with_invariants(
+        &engine,
+        vec![tall_chip.air()],
+        vec![tall_chip.generate_air_proof_input()],
+    )
+    .expect("Verification failed")
+    .proof;
+
+    // A wrong claimed Fibonacci number in the middle of the batch
+    let mut bad_proof = proof.clone();
+    bad_proof.per_air[0].public_values[2] += BabyBear::ONE;
+    let results = engine.verify_batch(&vk, &[proof.clone(), bad_proof, tall_proof, proof]);
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+    assert!(results[3].is_ok());
+}
+
+#[test]
+fn test_per_air_alphas() {
+    let fri_params = FriParameters::standard_fast();
+    let mut engine = BabyBearPoseidon2Engine::new(fri_params);
+    engine.config = engine.config.with_alpha_sampling(AlphaSampling::PerAir);
+
+    let a = 0u32;
+    let b = 1u32;
+    let n1 = 1usize << 3;
+    let n2 = 1usize << 5;
+    let sels: Vec<bool> = (0..n2).map(|i| i % 2 == 0).collect();
+    let pis1 = [a, b, get_fib_number(n1)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let pis2 = [a, b, get_conditional_fib_number(&sels)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let air2 = fib_selector_air::air::FibonacciSelectorAir::new(sels, false);
+    let trace1 = generate_trace_rows::<BabyBear>(a, b, n1);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<BabyBear>(a, b, air2.sels());
+
+    let data = engine
+        .run_simple_test_impl(
+            any_rap_arc_vec![FibonacciAir, air2],
+            vec![trace1, trace2],
+            vec![pis1, pis2],
+        )
+        .expect("Verification failed")
+        .data;
+
+    // The verifier samples alphas as in its own config, which the prover cannot choose. A single
+    // global alpha changes the transcript, so the openings no longer verify.
+    let global_engine = BabyBearPoseidon2Engine::new(fri_params);
+    assert!(matches!(
+        global_engine.verify(&data.vk, &data.proof),
+        Err(VerificationError::InvalidOpeningArgument(_))
+    ));
+}
+
+#[test]
+fn test_custom_accumulation_strategy() {
+    /// Folds constraint `i` with `alpha^(2i + 1)`.
+    struct OddAlphaPowers;
+
+    impl<EF: Field> AccumulationStrategy<EF> for OddAlphaPowers {
+        fn coefficients(&self, alpha: EF, num_constraints: usize) -> Vec<EF> {
+            alpha
+                .square()
+                .powers()
+                .map(|power| power * alpha)
+                .take(num_constraints)
+                .collect()
+        }
+    }
+
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+
+    let n = 1usize << 4;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+
+    let airs = any_rap_arc_vec![FibonacciAir];
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let device = CpuDevice::new(engine.config(), engine.fri_params.log_blowup)
+        .with_accumulation(Arc::new(OddAlphaPowers));
+    let inputs = vec![(air_ids[0], trace, pis)];
+    let (proof, _) = prove_on_device(device, engine.new_challenger(), &pk, inputs)
+        .expect("Proving failed");
+
+    engine
+        .verifier()
+        .with_accumulation(Arc::new(OddAlphaPowers))
+        .verify(&mut engine.new_challenger(), &vk, &proof)
+        .expect("Verification failed");
+
+    // The default strategy folds the constraints differently, so the quotient does not match
+    assert!(matches!(
+        engine.verify(&vk, &proof),
+        Err(VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_verify_all_reports_every_failing_air() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = [0; 3].map(|_| keygen_builder.add_air(Arc::new(FibonacciAir)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    // The claimed last Fibonacci number is wrong for the first and last AIR
+    let n = 1usize << 3;
+    let per_air = zip(air_ids, [1, 0, 1])
+        .map(|(air_id, error)| {
+            let pis = [0, 1, get_fib_number(n) + error]
+                .map(BabyBear::from_canonical_u32)
+                .to_vec();
+            let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+            (air_id, AirProofInput::simple(trace, pis))
+        })
+        .collect();
+    disable_debug_builder();
+    let proof = engine.prove(&pk, ProofInput { per_air });
+
+    let verifier = engine.verifier();
+    assert_eq!(
+        verifier.verify(&mut engine.new_challenger(), &vk, &proof),
+        Err(VerificationError::OodEvaluationMismatch)
+    );
+    assert_eq!(
+        verifier.verify_all(&mut engine.new_challenger(), &vk, &proof),
+        Err(VerifyAllError::Airs(vec![
+            (air_ids[0], VerificationError::OodEvaluationMismatch),
+            (air_ids[2], VerificationError::OodEvaluationMismatch),
+        ]))
+    );
+
+    // Failures outside the constraints of an AIR are global
+    let mut shape_error = proof.clone();
+    shape_error.per_air[1].quotient_degree += 1;
+    assert_eq!(
+        verifier.verify_all(&mut engine.new_challenger(), &vk, &shape_error),
+        Err(VerifyAllError::Global(VerificationError::InvalidProofShape))
+    );
+}
+
+#[test]
+fn test_verify_sampled_fri_queries() {
+    let fib_chip = FibonacciChip::new(0, 1, 16);
+    let data = BabyBearPoseidon2Engine::run_test_fast(
+        vec![fib_chip.air()],
+        vec![fib_chip.generate_air_proof_input()],
+    )
+    .expect("Verification failed")
+    .data;
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    engine
+        .verify_sampled(&data.vk, &data.proof, 0.5)
+        .expect("Sampled verification failed");
+
+    // A tampered query among the sampled ones is rejected
+    let mut proof = data.proof;
+    proof.opening.proof.query_proofs[0].commit_phase_openings[0].sibling_value += Challenge::ONE;
+    assert!(engine.verify_sampled(&data.vk, &proof, 0.5).is_err());
+}
@@ -4,14 +4,12 @@ use std::{sync::Arc, time::Instant};
 
 use openvm_stark_backend::{
     config::{Domain, StarkGenericConfig},
-    p3_air::{Air, AirBuilder, BaseAir},
     p3_challenger::FieldChallenger,
     p3_commit::{Pcs, PolynomialSpace},
-    p3_field::Field,
     p3_matrix::Matrix,
     p3_util::log2_strict_usize,
     prover::{cpu::quotient::QuotientCommitter, types::RapView},
-    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    rap::P3AirAdapter,
 };
 use openvm_stark_sdk::{
     config::{
@@ -29,30 +27,13 @@ use rand::Rng;
 const NUM_PERMUTATIONS: usize = 1 << 10;
 const LOG_BLOWUP: usize = 1;
 
-// Newtype to implement extended traits
-struct TestAir(KeccakAir);
-
-impl<F: Field> BaseAir<F> for TestAir {
-    fn width(&self) -> usize {
-        BaseAir::<F>::width(&self.0)
-    }
-}
-impl<F: Field> BaseAirWithPublicValues<F> for TestAir {}
-impl<F: Field> PartitionedBaseAir<F> for TestAir {}
-
-impl<AB: AirBuilder> Air<AB> for TestAir {
-    fn eval(&self, builder: &mut AB) {
-        self.0.eval(builder);
-    }
-}
-
 fn main() {
     type SC = BabyBearPoseidon2Config;
     type Challenge = <SC as StarkGenericConfig>::Challenge;
     type Challenger = <SC as StarkGenericConfig>::Challenger;
     setup_tracing();
     let mut rng = create_seeded_rng();
-    let air = TestAir(KeccakAir {});
+    let air = P3AirAdapter(KeccakAir {});
 
     let engine = BabyBearPoseidon2Engine::new(
         FriParameters::standard_with_100_bits_conjectured_security(LOG_BLOWUP),
@@ -91,6 +72,7 @@ fn main() {
         &[constraints_dag],
         vec![extended_view],
         &[quotient_degree as u8],
+        &[vec![]],
     );
     println!(
         "compute quotient values with DAG interpreter took: {:?}",
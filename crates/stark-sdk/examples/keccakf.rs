@@ -3,10 +3,8 @@
 use std::sync::Arc;
 
 use openvm_stark_backend::{
-    p3_air::{Air, AirBuilder, BaseAir},
-    p3_field::Field,
     prover::types::{AirProofInput, ProofInput},
-    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    rap::P3AirAdapter,
     utils::metrics_span,
 };
 use openvm_stark_sdk::{
@@ -22,27 +20,10 @@ use rand::Rng;
 const NUM_PERMUTATIONS: usize = 1 << 10;
 const LOG_BLOWUP: usize = 1;
 
-// Newtype to implement extended traits
-struct TestAir(KeccakAir);
-
-impl<F: Field> BaseAir<F> for TestAir {
-    fn width(&self) -> usize {
-        BaseAir::<F>::width(&self.0)
-    }
-}
-impl<F: Field> BaseAirWithPublicValues<F> for TestAir {}
-impl<F: Field> PartitionedBaseAir<F> for TestAir {}
-
-impl<AB: AirBuilder> Air<AB> for TestAir {
-    fn eval(&self, builder: &mut AB) {
-        self.0.eval(builder);
-    }
-}
-
 fn main() {
     setup_tracing();
     let mut rng = create_seeded_rng();
-    let air = TestAir(KeccakAir {});
+    let air = P3AirAdapter(KeccakAir {});
 
     let engine = BabyBearPoseidon2Engine::new(
         FriParameters::standard_with_100_bits_conjectured_security(LOG_BLOWUP),
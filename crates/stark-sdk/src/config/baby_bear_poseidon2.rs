@@ -1,6 +1,7 @@
 use std::any::type_name;
 
 use openvm_stark_backend::{
+    challenger::ChallengeLogger,
     config::StarkConfig,
     interaction::fri_log_up::FriLogUpPhase,
     p3_challenger::DuplexChallenger,
@@ -23,6 +24,14 @@ use zkhash::{
     poseidon2::poseidon2_instance_babybear::RC16,
 };
 
+#[cfg(feature = "unsound-sampled-verification")]
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    interaction::RapPhaseSeq,
+    keygen::types::MultiStarkVerifyingKey,
+    proof::Proof,
+    verifier::{MultiTraceStarkVerifier, VerificationError},
+};
 use super::{
     instrument::{HashStatistics, InstrumentCounter, Instrumented, StarkHashStatistics},
     FriParameters,
@@ -49,7 +58,8 @@ type InstrPerm = Instrumented<Perm>;
 // Generic over P: CryptographicPermutation<[F; WIDTH]>
 type Hash<P> = PaddingFreeSponge<P, WIDTH, RATE, DIGEST_WIDTH>;
 type Compress<P> = TruncatedPermutation<P, 2, DIGEST_WIDTH, WIDTH>;
-type ValMmcs<P> =
+/// Merkle tree MMCS committing to the trace matrices, see [val_mmcs_from_perm].
+pub type ValMmcs<P> =
     MerkleTreeMmcs<PackedVal, <Val as Field>::Packing, Hash<P>, Compress<P>, DIGEST_WIDTH>;
 type ChallengeMmcs<P> = ExtensionMmcs<Val, Challenge, ValMmcs<P>>;
 pub type Challenger<P> = DuplexChallenger<Val, P, WIDTH, RATE>;
@@ -59,6 +69,15 @@ type RapPhase<P> = FriLogUpPhase<Val, Challenge, Challenger<P>>;
 
 pub type BabyBearPermutationConfig<P> = StarkConfig<Pcs<P>, RapPhase<P>, Challenge, Challenger<P>>;
 pub type BabyBearPoseidon2Config = BabyBearPermutationConfig<Perm>;
+/// Challenger recording or replaying the challenges it samples, see [ChallengeLogger].
+pub type LoggedChallenger<P> = ChallengeLogger<Val, Challenger<P>>;
+/// Same as [BabyBearPermutationConfig], but with a [LoggedChallenger].
+pub type BabyBearPermutationLoggedConfig<P> = StarkConfig<
+    Pcs<P>,
+    FriLogUpPhase<Val, Challenge, LoggedChallenger<P>>,
+    Challenge,
+    LoggedChallenger<P>,
+>;
 pub type BabyBearPoseidon2Engine = BabyBearPermutationEngine<Perm>;
 
 assert_sc_compatible_with_serde!(BabyBearPoseidon2Config);
@@ -69,6 +88,9 @@ where
         + CryptographicPermutation<[PackedVal; WIDTH]>
         + Clone,
 {
+    /// FRI parameters of `config`, fixed for the engine: the PCS is configured with them, and
+    /// proving keys depend on the blowup through their preprocessed commitments and quotient
+    /// degrees. Proving with other FRI parameters needs a separate engine and proving keys.
     pub fri_params: FriParameters,
     pub config: BabyBearPermutationConfig<P>,
     pub perm: P,
@@ -89,11 +111,10 @@ where
     where
         Self: 'a,
     {
-        MultiTraceStarkProver::new(
-            CpuBackend::default(),
-            CpuDevice::new(self.config(), self.fri_params.log_blowup),
-            self.new_challenger(),
-        )
+        let log_blowup = self.fri_params.log_blowup;
+        let device = CpuDevice::try_new(self.config(), log_blowup, log_blowup)
+            .expect("device log blowup must match the FRI parameters of the PCS");
+        MultiTraceStarkProver::new(CpuBackend::default(), device, self.new_challenger())
     }
 
     fn max_constraint_degree(&self) -> Option<usize> {
@@ -105,6 +126,55 @@ where
     }
 }
 
+#[cfg(feature = "unsound-sampled-verification")]
+impl<P> BabyBearPermutationEngine<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    /// Verifies `proof` checking only the first `query_fraction` of its FRI queries, rounded up.
+    /// This is **unsound** and only meant for fast smoke tests of large proofs: a proof accepted
+    /// here may be rejected by a full verification.
+    ///
+    /// # Panics
+    /// If `query_fraction` is not in `(0, 1]`.
+    pub fn verify_sampled(
+        &self,
+        vk: &MultiStarkVerifyingKey<BabyBearPermutationConfig<P>>,
+        proof: &Proof<BabyBearPermutationConfig<P>>,
+        query_fraction: f64,
+    ) -> Result<(), VerificationError> {
+        assert!(
+            query_fraction > 0.0 && query_fraction <= 1.0,
+            "query fraction must be in (0, 1]"
+        );
+        let num_queries = ((self.fri_params.num_queries as f64 * query_fraction).ceil() as usize)
+            .clamp(1, self.fri_params.num_queries);
+        let fri_params = FriParameters {
+            num_queries,
+            ..self.fri_params
+        };
+        // The queries are sampled after all other challenges, so the remaining queries are
+        // verified against the same challenges as in a full verification
+        let rap_phase = self.config.rap_phase_seq();
+        let log_up_params = rap_phase.log_up_security_params().clone();
+        let sampled_rap_phase = FriLogUpPhase::new(log_up_params.clone(), fri_params.log_blowup)
+            .with_fingerprint_scheme(rap_phase.fingerprint_scheme());
+        let security_params = SecurityParameters {
+            fri_params,
+            log_up_params,
+        };
+        let config = config_from_perm(&self.perm, security_params)
+            .with_rap_phase(sampled_rap_phase)
+            .with_alpha_sampling(self.config.alpha_sampling());
+
+        let mut proof = proof.clone();
+        proof.opening.proof.query_proofs.truncate(num_queries);
+        MultiTraceStarkVerifier::new(&config).verify(&mut self.new_challenger(), vk, &proof)
+    }
+}
+
 impl<P> StarkEngineWithHashInstrumentation<BabyBearPermutationConfig<Instrumented<P>>>
     for BabyBearPermutationEngine<Instrumented<P>>
 where
@@ -115,6 +185,9 @@ where
     fn clear_instruments(&mut self) {
         self.perm.input_lens_by_type.lock().unwrap().clear();
     }
+    fn instrument_counter(&self) -> InstrumentCounter {
+        self.perm.input_lens_by_type.clone()
+    }
     fn stark_hash_statistics<T>(&self, custom: T) -> StarkHashStatistics<T> {
         let counter = self.perm.input_lens_by_type.lock().unwrap();
         let permutations = counter.iter().fold(0, |total, (name, lens)| {
@@ -180,6 +253,30 @@ pub fn config_from_perm<P>(
     perm: &P,
     security_params: SecurityParameters,
 ) -> BabyBearPermutationConfig<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    config_with_challenger_from_perm(perm, security_params)
+}
+
+/// Same as [config_from_perm], but for a config whose challenger records or replays the sampled
+/// challenges.
+pub fn logged_config_from_perm<P>(
+    perm: &P,
+    security_params: SecurityParameters,
+) -> BabyBearPermutationLoggedConfig<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    config_with_challenger_from_perm(perm, security_params)
+}
+
+/// The MMCS used by the PCS of the configs built from `perm`.
+pub fn val_mmcs_from_perm<P>(perm: &P) -> ValMmcs<P>
 where
     P: CryptographicPermutation<[Val; WIDTH]>
         + CryptographicPermutation<[PackedVal; WIDTH]>
@@ -187,7 +284,19 @@ where
 {
     let hash = Hash::new(perm.clone());
     let compress = Compress::new(perm.clone());
-    let val_mmcs = ValMmcs::new(hash, compress);
+    ValMmcs::new(hash, compress)
+}
+
+fn config_with_challenger_from_perm<P, C>(
+    perm: &P,
+    security_params: SecurityParameters,
+) -> StarkConfig<Pcs<P>, FriLogUpPhase<Val, Challenge, C>, Challenge, C>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    let val_mmcs = val_mmcs_from_perm(perm);
     let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
     let dft = Dft::default();
     let SecurityParameters {
@@ -203,7 +312,7 @@ where
     };
     let pcs = Pcs::new(dft, val_mmcs, fri_config);
     let rap_phase = FriLogUpPhase::new(log_up_params, fri_params.log_blowup);
-    BabyBearPermutationConfig::new(pcs, rap_phase)
+    StarkConfig::new(pcs, rap_phase)
 }
 
 /// Uses HorizenLabs Poseidon2 round constants, but plonky3 Mat4 and also
@@ -79,11 +79,10 @@ where
     where
         Self: 'a,
     {
-        MultiTraceStarkProver::new(
-            CpuBackend::default(),
-            CpuDevice::new(self.config(), self.fri_params.log_blowup),
-            self.new_challenger(),
-        )
+        let log_blowup = self.fri_params.log_blowup;
+        let device = CpuDevice::try_new(self.config(), log_blowup, log_blowup)
+            .expect("device log blowup must match the FRI parameters of the PCS");
+        MultiTraceStarkProver::new(CpuBackend::default(), device, self.new_challenger())
     }
 
     fn max_constraint_degree(&self) -> Option<usize> {
@@ -1,8 +1,19 @@
-use openvm_stark_backend::interaction::LogUpSecurityParameters;
+use openvm_stark_backend::{config::StarkGenericConfig, interaction::LogUpSecurityParameters};
 use serde::{Deserialize, Serialize};
 
 use crate::config::log_up_params::log_up_security_params_baby_bear_100_bits;
 
+/// Number of base field elements in a Merkle tree digest, used for proof size estimates.
+const DIGEST_WIDTH: usize = 8;
+/// Degree of the challenge field over the base field, used for proof size estimates.
+const CHALLENGE_EXT_DEGREE: usize = 4;
+
+/// Parameters of the FRI low degree test.
+///
+/// The folding arity of the commit phase is not configurable: `TwoAdicFriPcs` of the pinned
+/// Plonky3 revision always folds by 2, so each round halves the degree and there are
+/// [num_commit_phase_rounds](Self::num_commit_phase_rounds) rounds. The number of rounds can be
+/// reduced with a longer final polynomial instead, see [Self::optimal_final_poly_len].
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct FriParameters {
     pub log_blowup: usize,
@@ -22,6 +33,12 @@ impl FriParameters {
         challenge_field_bits.min(fri_query_security_bits)
     }
 
+    /// Conjectured bits of security with the challenge field of the STARK config `SC`. See
+    /// [Self::get_conjectured_security_bits].
+    pub fn conjectured_security_bits<SC: StarkGenericConfig>(&self) -> usize {
+        self.get_conjectured_security_bits(SC::challenge_field_bits())
+    }
+
     pub fn standard_fast() -> Self {
         standard_fri_params_with_100_bits_conjectured_security(1)
     }
@@ -34,6 +51,64 @@ impl FriParameters {
         (1 << self.log_blowup) + 1
     }
 
+    /// Number of commit phase rounds of FRI when the largest trace has height `2^max_log_height`.
+    /// Each round folds by 2, until the final polynomial of `2^log_final_poly_len` coefficients.
+    pub fn num_commit_phase_rounds(&self, max_log_height: usize) -> usize {
+        max_log_height.saturating_sub(self.log_final_poly_len)
+    }
+
+    /// Estimated size, in base field elements, of the parts of a FRI proof that depend on
+    /// `log_final_poly_len`, when the largest trace has height `2^max_log_height`.
+    ///
+    /// Each commit phase round adds a commitment and, for every query, a sibling value and a
+    /// Merkle path, while the final polynomial adds its coefficients. Digests are assumed to have
+    /// 8 base field elements and challenges 4 base field elements, as in the BabyBear
+    /// configurations.
+    pub fn fri_proof_size_estimate(&self, max_log_height: usize) -> usize {
+        let log_lde_height = max_log_height + self.log_blowup;
+        let num_rounds = self.num_commit_phase_rounds(max_log_height);
+        let query_size = (0..num_rounds)
+            .map(|round| (log_lde_height - round - 1) * DIGEST_WIDTH + CHALLENGE_EXT_DEGREE)
+            .sum::<usize>();
+        num_rounds * DIGEST_WIDTH
+            + self.num_queries * query_size
+            + (1 << self.log_final_poly_len) * CHALLENGE_EXT_DEGREE
+    }
+
+    /// Suggests the `log_final_poly_len` minimizing [Self::fri_proof_size_estimate] when the
+    /// largest trace has height `2^max_log_height`. A longer final polynomial means fewer commit
+    /// phase rounds, each of which costs a Merkle path per query.
+    ///
+    /// The final polynomial cannot be longer than any committed trace, so when proving traces of
+    /// different heights the result should be capped by the smallest log trace height.
+    pub fn optimal_final_poly_len(&self, max_log_height: usize) -> usize {
+        (0..=max_log_height)
+            .min_by_key(|&log_final_poly_len| {
+                Self {
+                    log_final_poly_len,
+                    ..*self
+                }
+                .fri_proof_size_estimate(max_log_height)
+            })
+            .unwrap()
+    }
+
+    /// Returns these parameters with the given `log_final_poly_len`, the log_2 of the number of
+    /// coefficients of the final FRI polynomial sent in the proof. The final polynomial cannot be
+    /// longer than the smallest committed trace.
+    pub fn with_log_final_poly_len(self, log_final_poly_len: usize) -> Self {
+        Self {
+            log_final_poly_len,
+            ..self
+        }
+    }
+
+    /// Returns these parameters with `log_final_poly_len` set by [Self::optimal_final_poly_len].
+    /// This does not change the security level, which does not depend on `log_final_poly_len`.
+    pub fn with_optimal_final_poly_len(self, max_log_height: usize) -> Self {
+        self.with_log_final_poly_len(self.optimal_final_poly_len(max_log_height))
+    }
+
     /// New FRI parameters for testing usage with the specific `log_blowup`.
     /// If the environment variable `OPENVM_FAST_TEST` is set to "1", then the parameters are **not secure** and meant for fast testing only.
     ///
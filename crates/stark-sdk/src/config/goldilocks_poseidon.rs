@@ -19,7 +19,7 @@ use p3_poseidon::Poseidon;
 use p3_symmetric::{CryptographicPermutation, PaddingFreeSponge, TruncatedPermutation};
 use rand::{rngs::StdRng, SeedableRng};
 
-use super::instrument::{HashStatistics, Instrumented, StarkHashStatistics};
+use super::instrument::{HashStatistics, InstrumentCounter, Instrumented, StarkHashStatistics};
 use crate::{
     assert_sc_compatible_with_serde,
     config::fri_params::SecurityParameters,
@@ -81,11 +81,10 @@ where
     where
         Self: 'a,
     {
-        MultiTraceStarkProver::new(
-            CpuBackend::default(),
-            CpuDevice::new(self.config(), self.security_params.fri_params.log_blowup),
-            self.new_challenger(),
-        )
+        let log_blowup = self.security_params.fri_params.log_blowup;
+        let device = CpuDevice::try_new(self.config(), log_blowup, log_blowup)
+            .expect("device log blowup must match the FRI parameters of the PCS");
+        MultiTraceStarkProver::new(CpuBackend::default(), device, self.new_challenger())
     }
 
     fn max_constraint_degree(&self) -> Option<usize> {
@@ -107,6 +106,9 @@ where
     fn clear_instruments(&mut self) {
         self.perm.input_lens_by_type.lock().unwrap().clear();
     }
+    fn instrument_counter(&self) -> InstrumentCounter {
+        self.perm.input_lens_by_type.clone()
+    }
     fn stark_hash_statistics<T>(&self, custom: T) -> StarkHashStatistics<T> {
         let counter = self.perm.input_lens_by_type.lock().unwrap();
         let permutations = counter.iter().fold(0, |total, (name, lens)| {
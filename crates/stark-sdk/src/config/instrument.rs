@@ -4,6 +4,16 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use openvm_stark_backend::prover::{
+    hal::{
+        OpeningProver, ProverBackend, ProverDevice, QuotientCommitter, RapPartialProver,
+        TraceCommitter,
+    },
+    types::{
+        AirView, DeviceMultiStarkProvingKey, DeviceStarkProvingKey, ProverDataAfterRapPhases,
+        ProverQuotientData,
+    },
+};
 use p3_symmetric::{
     CryptographicHasher, CryptographicPermutation, Permutation, PseudoCompressionFunction,
 };
@@ -44,6 +54,11 @@ impl<T> Instrumented<T> {
     }
 }
 
+/// Number of calls recorded by `counter`, for all instrumented types.
+pub fn num_instrumented_calls(counter: &InstrumentCounter) -> usize {
+    counter.lock().unwrap().values().map(Vec::len).sum()
+}
+
 impl<T: Clone, P: Permutation<T>> Permutation<T> for Instrumented<P> {
     fn permute_mut(&self, input: &mut T) {
         self.add_len_for_type::<T>(1);
@@ -100,3 +115,146 @@ pub struct StarkHashStatistics<T> {
     pub fri_params: FriParameters,
     pub custom: T,
 }
+
+/// Number of instrumented hash calls made by each phase of proving, see
+/// [prove_with_hash_counts](crate::engine::StarkEngineWithHashInstrumentation::prove_with_hash_counts).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseHashCounts {
+    /// Commitments of the cached and common main traces.
+    pub main_trace_commit: usize,
+    /// RAP challenge phases, including the commitments of their traces.
+    pub rap_phases: usize,
+    /// Evaluation and commitment of the quotient polynomials.
+    pub quotient_commit: usize,
+    /// FRI opening proof.
+    pub opening: usize,
+    /// Challenger observations and samples outside of the other phases.
+    pub transcript: usize,
+}
+
+impl PhaseHashCounts {
+    pub fn total(&self) -> usize {
+        self.main_trace_commit
+            + self.rap_phases
+            + self.quotient_commit
+            + self.opening
+            + self.transcript
+    }
+}
+
+/// Prover device which counts the calls recorded by `counter` during each proving step of
+/// `device`, which it delegates all proving to.
+pub struct HashCountingDevice<D> {
+    pub device: D,
+    counter: InstrumentCounter,
+    counts: Mutex<PhaseHashCounts>,
+}
+
+impl<D> HashCountingDevice<D> {
+    pub fn new(device: D, counter: InstrumentCounter) -> Self {
+        Self {
+            device,
+            counter,
+            counts: Mutex::new(PhaseHashCounts::default()),
+        }
+    }
+
+    /// The counts of the proving steps so far. Calls outside the device are not seen, so
+    /// `transcript` is always zero.
+    pub fn counts(&self) -> PhaseHashCounts {
+        *self.counts.lock().unwrap()
+    }
+
+    fn count<R>(
+        &self,
+        phase: impl FnOnce(&mut PhaseHashCounts) -> &mut usize,
+        step: impl FnOnce() -> R,
+    ) -> R {
+        let start = num_instrumented_calls(&self.counter);
+        let res = step();
+        let num_calls = num_instrumented_calls(&self.counter) - start;
+        *phase(&mut self.counts.lock().unwrap()) += num_calls;
+        res
+    }
+}
+
+impl<PB: ProverBackend, D: ProverDevice<PB>> ProverDevice<PB> for HashCountingDevice<D> {}
+
+impl<PB: ProverBackend, D: TraceCommitter<PB>> TraceCommitter<PB> for HashCountingDevice<D> {
+    fn commit(&self, traces: &[PB::Matrix]) -> (PB::Commitment, PB::PcsData) {
+        self.count(
+            |counts| &mut counts.main_trace_commit,
+            || self.device.commit(traces),
+        )
+    }
+}
+
+impl<PB: ProverBackend, D: RapPartialProver<PB>> RapPartialProver<PB> for HashCountingDevice<D> {
+    fn partially_prove(
+        &self,
+        challenger: &mut PB::Challenger,
+        mpk: &DeviceMultiStarkProvingKey<'_, PB>,
+        trace_views: Vec<AirView<PB::Matrix, PB::Val>>,
+    ) -> (PB::RapPartialProof, ProverDataAfterRapPhases<PB>) {
+        self.count(
+            |counts| &mut counts.rap_phases,
+            || self.device.partially_prove(challenger, mpk, trace_views),
+        )
+    }
+}
+
+impl<PB: ProverBackend, D: QuotientCommitter<PB>> QuotientCommitter<PB>
+    for HashCountingDevice<D>
+{
+    fn eval_and_commit_quotient(
+        &self,
+        challenger: &mut PB::Challenger,
+        pk_views: &[DeviceStarkProvingKey<PB>],
+        public_values: &[Vec<PB::Val>],
+        cached_pcs_datas_per_air: &[Vec<PB::PcsData>],
+        common_main_pcs_data: &PB::PcsData,
+        prover_data_after: &ProverDataAfterRapPhases<PB>,
+    ) -> ProverQuotientData<PB> {
+        self.count(
+            |counts| &mut counts.quotient_commit,
+            || {
+                self.device.eval_and_commit_quotient(
+                    challenger,
+                    pk_views,
+                    public_values,
+                    cached_pcs_datas_per_air,
+                    common_main_pcs_data,
+                    prover_data_after,
+                )
+            },
+        )
+    }
+}
+
+impl<PB: ProverBackend, D: OpeningProver<PB>> OpeningProver<PB> for HashCountingDevice<D> {
+    fn open(
+        &self,
+        challenger: &mut PB::Challenger,
+        preprocessed: Vec<(PB::PcsData, Vec<usize>)>,
+        main: Vec<(PB::PcsData, Vec<usize>)>,
+        after_phase: Vec<(PB::PcsData, Vec<usize>)>,
+        quotient_data: Vec<PB::PcsData>,
+        num_quotient_chunks: &[u8],
+        quotient_group_idx_per_air: &[usize],
+    ) -> PB::OpeningProof {
+        self.count(
+            |counts| &mut counts.opening,
+            || {
+                self.device.open(
+                    challenger,
+                    preprocessed,
+                    main,
+                    after_phase,
+                    quotient_data,
+                    num_quotient_chunks,
+                    quotient_group_idx_per_air,
+                )
+            },
+        )
+    }
+}
@@ -4,7 +4,7 @@
 //! Chip will either send or receive the fields with multiplicity count.
 //! The main Air has no constraints, the only constraints are specified by the Chip trait
 
-use std::{iter, sync::Arc};
+use std::{collections::BTreeMap, iter, sync::Arc};
 
 use derivative::Derivative;
 use itertools::izip;
@@ -115,14 +115,9 @@ impl<AB: InteractionBuilder + PartitionedAirBuilder> Air<AB> for DummyInteractio
             (fields, count)
         };
         if self.is_send {
-            builder.push_interaction(self.bus_index, fields, count, self.count_weight);
+            builder.push_send(self.bus_index, fields, count, self.count_weight);
         } else {
-            builder.push_interaction(
-                self.bus_index,
-                fields,
-                AB::Expr::NEG_ONE * count,
-                self.count_weight,
-            );
+            builder.push_receive(self.bus_index, fields, count, self.count_weight);
         }
     }
 }
@@ -144,6 +139,28 @@ pub struct DummyInteractionData {
     pub fields: Vec<Vec<u32>>,
 }
 
+impl DummyInteractionData {
+    /// Expands a map from each message to its multiplicity into one row per message, ordered by
+    /// message. Messages with multiplicity zero are skipped.
+    ///
+    /// Whether the messages are sent or received is determined by the chip the data is loaded
+    /// into, so the multiplicities are counts and must be nonnegative. Loading the same map into a
+    /// sender and a receiver on the same bus gives balanced interactions.
+    pub fn from_multiset(multiset: BTreeMap<Vec<u32>, i64>) -> Self {
+        let (count, fields) = multiset
+            .into_iter()
+            .filter(|&(_, multiplicity)| multiplicity != 0)
+            .map(|(message, multiplicity)| {
+                let count = u32::try_from(multiplicity).unwrap_or_else(|_| {
+                    panic!("multiplicity {multiplicity} of message {message:?} is not a count")
+                });
+                (count, message)
+            })
+            .unzip();
+        Self { count, fields }
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> DummyInteractionChip<'a, SC>
 where
     Val<SC>: FieldAlgebra,
@@ -2,7 +2,10 @@ use std::{marker::PhantomData, sync::Arc};
 
 use itertools::{izip, Itertools};
 use openvm_stark_backend::{
+    air_builders::symbolic::SymbolicConstraints,
+    interaction::debug::{generate_logical_interactions, Imbalance, LogicalInteractions},
     keygen::MultiStarkKeygenBuilder,
+    p3_air::BaseAir,
     p3_matrix::dense::RowMajorMatrix,
     prover::{
         cpu::{CpuBackend, CpuDevice},
@@ -63,3 +66,43 @@ pub fn verify_interactions(
     let verifier = MultiTraceStarkVerifier::new(prover.device.config());
     verifier.verify(&mut challenger, &vk, &proof.into())
 }
+
+/// Checks, without proving, that the sends and receives of `airs` on `traces` balance on every
+/// bus. Returns all messages whose multiplicities do not sum to zero.
+pub fn precheck_interactions(
+    traces: Vec<RowMajorMatrix<Val>>,
+    airs: Vec<AirRef<BabyBearPoseidon2Config>>,
+    pis: Vec<Vec<Val>>,
+) -> Result<(), Vec<Imbalance<Val>>> {
+    let perm = config::baby_bear_poseidon2::random_perm();
+    let config = config::baby_bear_poseidon2::default_config(&perm);
+
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    for air in &airs {
+        keygen_builder.add_air(air.clone());
+    }
+    let pk = keygen_builder.generate_pk();
+
+    let mut logical_interactions = LogicalInteractions::default();
+    for (air_idx, (air, air_pk, trace, pvs)) in
+        izip!(&airs, &pk.per_air, &traces, &pis).enumerate()
+    {
+        let interactions = SymbolicConstraints::from(&air_pk.vk.symbolic_constraints).interactions;
+        let preprocessed = air.preprocessed_trace();
+        generate_logical_interactions(
+            air_idx,
+            &interactions,
+            &preprocessed.as_ref().map(|trace| trace.as_view()),
+            &[trace.as_view()],
+            pvs,
+            &mut logical_interactions,
+        );
+    }
+
+    let imbalances = logical_interactions.imbalances();
+    if imbalances.is_empty() {
+        Ok(())
+    } else {
+        Err(imbalances)
+    }
+}
@@ -1,19 +1,64 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use itertools::zip_eq;
 pub use openvm_stark_backend::engine::StarkEngine;
 use openvm_stark_backend::{
+    air_builders::symbolic::{get_symbolic_builder, SymbolicConstraintsDag},
     config::{StarkGenericConfig, Val},
     engine::VerificationData,
-    p3_matrix::dense::RowMajorMatrix,
-    prover::types::AirProofInput,
+    interaction::RapPhaseSeq,
+    keygen::types::{MultiStarkProvingKey, TraceWidth},
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    proof::Proof,
+    prover::{
+        coordinator::Coordinator,
+        types::{AirProofInput, ProofInput},
+        MultiTraceStarkProver,
+    },
     verifier::VerificationError,
     AirRef,
 };
 use tracing::Level;
 
-use crate::config::{instrument::StarkHashStatistics, setup_tracing_with_log_level, FriParameters};
+use crate::config::{
+    instrument::{
+        num_instrumented_calls, HashCountingDevice, InstrumentCounter, PhaseHashCounts,
+        StarkHashStatistics,
+    },
+    setup_tracing_with_log_level, FriParameters,
+};
 
 pub trait StarkEngineWithHashInstrumentation<SC: StarkGenericConfig>: StarkEngine<SC> {
     fn clear_instruments(&mut self);
     fn stark_hash_statistics<T>(&self, custom: T) -> StarkHashStatistics<T>;
+    /// Counter of the instrumented hash calls made with the config.
+    fn instrument_counter(&self) -> InstrumentCounter;
+
+    /// Same as [prove](StarkEngine::prove), but also returns the number of instrumented hash
+    /// calls made by each phase of proving.
+    fn prove_with_hash_counts(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> (Proof<SC>, PhaseHashCounts) {
+        let counter = self.instrument_counter();
+        let start = num_instrumented_calls(&counter);
+        let prover = self.prover();
+        let device = HashCountingDevice::new(prover.device, counter.clone());
+        let mut prover = Coordinator::new(prover.backend, device, self.new_challenger());
+        let proof = self.prove_with(&mut prover, mpk, proof_input);
+        let mut counts = prover.device.counts();
+        counts.transcript = num_instrumented_calls(&counter) - start - counts.total();
+        (proof, counts)
+    }
 }
 
 /// All necessary data to verify a Stark proof.
@@ -82,6 +127,133 @@ pub trait StarkFriEngine<SC: StarkGenericConfig>: StarkEngine<SC> + Sized {
     }
 }
 
+/// Engine wrapper that caches proving keys by AIR set.
+///
+/// The cache key is the ordered list of per-AIR hashes of the AIR's type and name, its trace
+/// widths, its symbolic constraint DAG and its preprocessed trace, if any, so registering the
+/// same AIRs again
+/// returns the previously generated [MultiStarkProvingKey] instead of re-running keygen.
+pub struct CachingEngine<SC: StarkGenericConfig, E> {
+    pub engine: E,
+    cache: Mutex<HashMap<Vec<u64>, Arc<MultiStarkProvingKey<SC>>>>,
+    num_keygens: AtomicUsize,
+    _marker: PhantomData<SC>,
+}
+
+impl<SC: StarkGenericConfig, E: StarkEngine<SC>> CachingEngine<SC, E> {
+    pub fn from_engine(engine: E) -> Self {
+        Self {
+            engine,
+            cache: Mutex::new(HashMap::new()),
+            num_keygens: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of times keygen has actually been run, i.e., the number of cache misses.
+    pub fn num_keygens(&self) -> usize {
+        self.num_keygens.load(Ordering::Relaxed)
+    }
+
+    /// Returns the proving key for `airs`, generating it only if this ordered AIR set
+    /// has not been seen before. AIR ids are the indices into `airs`.
+    pub fn keygen(&self, airs: &[AirRef<SC>]) -> Arc<MultiStarkProvingKey<SC>> {
+        let key = airs.iter().map(air_hash).collect::<Vec<_>>();
+        let mut cache = self.cache.lock().unwrap();
+        cache
+            .entry(key)
+            .or_insert_with(|| {
+                self.num_keygens.fetch_add(1, Ordering::Relaxed);
+                let mut keygen_builder = self.engine.keygen_builder();
+                self.engine.set_up_keygen_builder(&mut keygen_builder, airs);
+                Arc::new(keygen_builder.generate_pk())
+            })
+            .clone()
+    }
+}
+
+impl<SC: StarkGenericConfig, E: StarkEngine<SC>> StarkEngine<SC> for CachingEngine<SC, E> {
+    fn config(&self) -> &SC {
+        self.engine.config()
+    }
+
+    fn max_constraint_degree(&self) -> Option<usize> {
+        self.engine.max_constraint_degree()
+    }
+
+    fn new_challenger(&self) -> SC::Challenger {
+        self.engine.new_challenger()
+    }
+
+    fn prover<'a>(&'a self) -> MultiTraceStarkProver<'a, SC>
+    where
+        Self: 'a,
+    {
+        self.engine.prover()
+    }
+
+    fn run_test_impl(
+        &self,
+        airs: Vec<AirRef<SC>>,
+        air_proof_inputs: Vec<AirProofInput<SC>>,
+    ) -> Result<VerificationData<SC>, VerificationError> {
+        let pk = self.keygen(&airs);
+        self.debug(&airs, &pk.per_air, &air_proof_inputs);
+        let vk = pk.get_vk();
+        let proof_input = ProofInput {
+            per_air: zip_eq(0..airs.len(), air_proof_inputs).collect(),
+        };
+        let proof = self.prove(&pk, proof_input);
+        self.verify(&vk, &proof)?;
+        Ok(VerificationData { vk, proof })
+    }
+}
+
+impl<SC: StarkGenericConfig, E: StarkFriEngine<SC>> StarkFriEngine<SC> for CachingEngine<SC, E> {
+    fn new(fri_params: FriParameters) -> Self {
+        Self::from_engine(E::new(fri_params))
+    }
+
+    fn fri_params(&self) -> FriParameters {
+        self.engine.fri_params()
+    }
+}
+
+/// Hash of the AIR's identity, trace widths, symbolic constraints DAG and preprocessed trace.
+///
+/// The identity is part of the hash so that distinct AIRs with equal widths and constraints do
+/// not share a cache entry.
+fn air_hash<SC: StarkGenericConfig>(air: &AirRef<SC>) -> u64 {
+    let preprocessed_trace = air.preprocessed_trace();
+    let width = TraceWidth {
+        preprocessed: preprocessed_trace.as_ref().map(|trace| trace.width()),
+        cached_mains: air.cached_main_widths(),
+        common_main: air.common_main_width(),
+        after_challenge: vec![],
+    };
+    let constraints: SymbolicConstraintsDag<Val<SC>> = get_symbolic_builder(
+        air.as_ref(),
+        &width,
+        &[],
+        &[],
+        SC::RapPhaseSeq::ID,
+        0,
+    )
+    .constraints()
+    .into();
+    let mut hasher = DefaultHasher::new();
+    air.as_any().type_id().hash(&mut hasher);
+    air.name().hash(&mut hasher);
+    width.preprocessed.hash(&mut hasher);
+    width.cached_mains.hash(&mut hasher);
+    width.common_main.hash(&mut hasher);
+    constraints.hash(&mut hasher);
+    preprocessed_trace
+        .map(|trace| (trace.width, trace.values))
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
 #[macro_export]
 macro_rules! collect_airs_and_inputs {
     ($($chip:expr),+ $(,)?) => {
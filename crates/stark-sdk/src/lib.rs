@@ -12,4 +12,7 @@ pub mod config;
 pub mod cost_estimate;
 pub mod dummy_airs;
 pub mod engine;
+/// Peak heap memory of proving
+#[cfg(feature = "memory-tracking")]
+pub mod memory;
 pub mod utils;
@@ -0,0 +1,111 @@
+//! Measurement of the peak heap memory of proving.
+//!
+//! [TrackingAllocator] must be installed as the `#[global_allocator]` of the binary, which
+//! excludes the `jemalloc` and `mimalloc` features that install their own. Allocations are
+//! tracked process-wide, so allocations of other threads running concurrently with proving,
+//! such as other tests, are included in the measurement.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig, engine::StarkEngine, keygen::types::MultiStarkProvingKey,
+    proof::Proof, prover::types::ProofInput,
+};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator wrapping `A` and tracking the number of allocated bytes and its peak.
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn add(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+
+    fn sub(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        Self::sub(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        // Only the net change counts towards the peak, as the old allocation is released
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                Self::add(new_size - layout.size());
+            } else {
+                Self::sub(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Number of heap bytes currently allocated through the [TrackingAllocator].
+pub fn allocated_bytes() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Peak number of heap bytes allocated through the [TrackingAllocator] since the last
+/// [reset_peak_bytes].
+pub fn peak_bytes() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+
+/// Resets the peak to the number of bytes currently allocated.
+pub fn reset_peak_bytes() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+pub trait StarkEngineWithMemoryTracking<SC: StarkGenericConfig>: StarkEngine<SC> {
+    /// Same as [prove](StarkEngine::prove), but also returns the peak heap memory in bytes
+    /// allocated during proving, on top of the memory allocated before, such as for the traces
+    /// in `proof_input`.
+    ///
+    /// Returns a peak of 0 if [TrackingAllocator] is not the global allocator.
+    fn prove_with_memory_tracking(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> (Proof<SC>, usize) {
+        reset_peak_bytes();
+        let start = allocated_bytes();
+        let proof = self.prove(mpk, proof_input);
+        (proof, peak_bytes().saturating_sub(start))
+    }
+}
+
+impl<SC: StarkGenericConfig, E: StarkEngine<SC>> StarkEngineWithMemoryTracking<SC> for E {}
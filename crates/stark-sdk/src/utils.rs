@@ -1,11 +1,27 @@
-use std::{cmp::Reverse, iter::zip};
+use std::{cmp::Reverse, env, fs, iter::zip, marker::PhantomData, path::Path, sync::Arc};
 
-use itertools::Itertools;
+use itertools::{zip_eq, Itertools};
 use openvm_stark_backend::{
-    config::StarkGenericConfig, p3_field::FieldAlgebra, p3_matrix::Matrix,
-    prover::types::AirProofInput, verifier::VerificationError, AirRef,
+    air_builders::debug::debug_constraints_on_row,
+    config::{StarkGenericConfig, Val},
+    engine::{StarkEngine, VerificationData},
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    proof::Proof,
+    prover::{
+        coordinator::Coordinator,
+        cpu::CpuBackend,
+        hal::{DeviceDataTransporter, ProverDevice},
+        types::{AirProofInput, AirProvingContext, ProofInput, ProvingContext},
+        ProverError,
+    },
+    utils::force_serial,
+    verifier::VerificationError,
+    AirRef,
 };
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use thiserror::Error;
 
 use crate::engine::{StarkFriEngine, VerificationDataWithFriParams};
 
@@ -79,6 +95,241 @@ pub fn to_field_vec<F: FieldAlgebra>(v: Vec<u32>) -> Vec<F> {
     v.into_iter().map(F::from_canonical_u32).collect()
 }
 
+/// Environment variable which makes [assert_golden] overwrite golden files instead of comparing
+/// against them, e.g. `UPDATE_GOLDEN=1 cargo test`.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// Asserts that `actual` equals the contents of the golden file at `path`.
+///
+/// If [UPDATE_GOLDEN_ENV] is set, the golden file is written with `actual` instead, and should be
+/// checked in.
+///
+/// # Panics
+/// If the golden file does not exist and [UPDATE_GOLDEN_ENV] is not set, so that a golden file
+/// missing from the repository fails the test instead of being silently created.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(path, actual).unwrap();
+        tracing::info!("wrote golden file {}", path.display());
+        return;
+    }
+    assert!(
+        path.exists(),
+        "golden file {} does not exist, run with {UPDATE_GOLDEN_ENV}=1 to create it",
+        path.display()
+    );
+    let expected = fs::read_to_string(path).unwrap();
+    assert!(
+        expected == actual,
+        "output differs from golden file {}, rerun with {UPDATE_GOLDEN_ENV}=1 to update it",
+        path.display()
+    );
+}
+
+/// Proves and verifies `airs` with `engine` in a single thread, so that the proof is
+/// deterministic, and asserts that the proof serialized as JSON matches the golden file at
+/// `golden_path`. See [assert_golden].
+///
+/// The engine must start from a deterministic transcript, such as a Poseidon2 permutation with
+/// fixed round constants.
+pub fn assert_golden_proof<SC, E>(
+    engine: &E,
+    airs: Vec<AirRef<SC>>,
+    traces: Vec<RowMajorMatrix<Val<SC>>>,
+    public_values: Vec<Vec<Val<SC>>>,
+    golden_path: impl AsRef<Path>,
+) where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC> + Sync,
+    AirProofInput<SC>: Send + Sync,
+{
+    let proof = force_serial(|| {
+        let data = engine
+            .run_simple_test_impl(airs, traces, public_values)
+            .expect("Verification failed");
+        serde_json::to_string_pretty(&data.data.proof).unwrap()
+    });
+    assert_golden(golden_path, &proof);
+}
+
+/// A proof invariant checked by [prove_verify_with_invariants] that does not hold.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvariantError {
+    #[error("cumulative sums do not add up to zero")]
+    CumulativeSum,
+    #[error("AIR {air_id} has quotient degree {actual}, expected {expected}")]
+    QuotientDegree {
+        air_id: usize,
+        expected: u8,
+        actual: u8,
+    },
+    #[error("trace height constraint {index} is violated: {weighted_sum} >= {threshold}")]
+    TraceHeightConstraint {
+        index: usize,
+        weighted_sum: u64,
+        threshold: u32,
+    },
+    #[error(transparent)]
+    Verification(#[from] VerificationError),
+}
+
+/// Proves `airs` with `engine` like [StarkEngine::run_test_impl], and checks invariants of the
+/// proof before verifying it:
+/// - the LogUp cumulative sums of all AIRs add up to zero,
+/// - the quotient degree of each AIR matches its verifying key,
+/// - the trace heights satisfy the trace height constraints of the verifying key.
+///
+/// The invariants are also checked by the verifier, but checking them separately reports which
+/// one is broken as an [InvariantError].
+pub fn prove_verify_with_invariants<SC, E>(
+    engine: &E,
+    airs: Vec<AirRef<SC>>,
+    air_proof_inputs: Vec<AirProofInput<SC>>,
+) -> Result<VerificationData<SC>, InvariantError>
+where
+    SC: StarkGenericConfig,
+    E: StarkEngine<SC>,
+{
+    let mut keygen_builder = engine.keygen_builder();
+    let air_ids = engine.set_up_keygen_builder(&mut keygen_builder, &airs);
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+    let proof_input = ProofInput {
+        per_air: zip_eq(air_ids, air_proof_inputs).collect(),
+    };
+    let proof = engine.prove(&pk, proof_input);
+
+    // AIRs without interactions expose no cumulative sum
+    let cumulative_sum = proof
+        .per_air
+        .iter()
+        .filter_map(|ap| ap.exposed_values_after_challenge.first()?.first().copied())
+        .sum::<SC::Challenge>();
+    if cumulative_sum != SC::Challenge::ZERO {
+        return Err(InvariantError::CumulativeSum);
+    }
+    for ap in &proof.per_air {
+        let expected = vk.inner.per_air[ap.air_id].quotient_degree;
+        if ap.quotient_degree != expected {
+            return Err(InvariantError::QuotientDegree {
+                air_id: ap.air_id,
+                expected,
+                actual: ap.quotient_degree,
+            });
+        }
+    }
+    for (index, constraint) in vk.inner.trace_height_constraints.iter().enumerate() {
+        let weighted_sum = proof
+            .per_air
+            .iter()
+            .map(|ap| constraint.coefficients[ap.air_id] as u64 * ap.degree as u64)
+            .sum::<u64>();
+        if weighted_sum >= constraint.threshold as u64 {
+            return Err(InvariantError::TraceHeightConstraint {
+                index,
+                weighted_sum,
+                threshold: constraint.threshold,
+            });
+        }
+    }
+
+    engine.verify(&vk, &proof)?;
+    Ok(VerificationData { vk, proof })
+}
+
+/// Proves the common main trace and public values of each AIR in `inputs`, given as
+/// `(air_id, trace, public_values)` ordered by AIR id, with a prover on `device` starting from
+/// `challenger`. The prover is returned with the proof, to inspect its device, trace metrics or
+/// challenger after proving.
+pub fn prove_on_device<SC, PD>(
+    device: PD,
+    challenger: SC::Challenger,
+    pk: &MultiStarkProvingKey<SC>,
+    inputs: Vec<(usize, RowMajorMatrix<Val<SC>>, Vec<Val<SC>>)>,
+) -> Result<(Proof<SC>, Coordinator<SC, CpuBackend<SC>, PD>), ProverError>
+where
+    SC: StarkGenericConfig,
+    PD: ProverDevice<CpuBackend<SC>>,
+{
+    let backend = CpuBackend::default();
+    let mut prover = Coordinator::new(backend, device, challenger);
+    let air_ids = inputs.iter().map(|(air_id, _, _)| *air_id).collect();
+    let per_air = inputs
+        .into_iter()
+        .map(|(air_id, trace, public_values)| {
+            let ctx = AirProvingContext {
+                cached_mains: vec![],
+                common_main: Some(Arc::new(trace)),
+                public_values,
+                cached_lifetime: PhantomData,
+            };
+            (air_id, ctx)
+        })
+        .collect();
+    let mpk = backend.transport_pk_to_device(pk, air_ids);
+    let proof = prover.try_prove(mpk, ProvingContext::new(per_air))?;
+    Ok((proof.into(), prover))
+}
+
+/// Checks that `air` accepts the traces its trace generation claims are valid. For each of
+/// `num_cases` cases, `generate_case` returns a trace and public values of `air` for the case
+/// index, and every row of the trace must satisfy the constraints of `air`, together with the
+/// preprocessed trace of `air` if it has one. The first `num_proofs` cases are also proven and
+/// verified with `engine`.
+///
+/// # Panics
+/// If a constraint fails on a row of a case, listing the failed constraints, if the height of a
+/// trace differs from the height of the preprocessed trace, or if a proof fails to verify.
+pub fn fuzz_air_satisfiability<SC, E>(
+    engine: &E,
+    air: AirRef<SC>,
+    num_cases: usize,
+    num_proofs: usize,
+    mut generate_case: impl FnMut(usize) -> (RowMajorMatrix<Val<SC>>, Vec<Val<SC>>),
+) where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    AirProofInput<SC>: Send + Sync,
+{
+    let preprocessed_trace = air.preprocessed_trace();
+    let preprocessed = preprocessed_trace.as_ref().map(|trace| trace.as_view());
+    for case in 0..num_cases {
+        let (trace, public_values) = generate_case(case);
+        if let Some(preprocessed) = &preprocessed {
+            assert_eq!(
+                trace.height(),
+                preprocessed.height(),
+                "trace height of case {case} differs from the preprocessed trace height"
+            );
+        }
+        let failed = (0..trace.height())
+            .flat_map(|row| {
+                debug_constraints_on_row::<_, SC>(
+                    air.as_ref(),
+                    &preprocessed,
+                    &[trace.as_view()],
+                    &public_values,
+                    row,
+                )
+            })
+            .collect_vec();
+        assert!(
+            failed.is_empty(),
+            "constraints of {} fail on case {case}: {failed:?}",
+            air.name()
+        );
+        if case < num_proofs {
+            engine
+                .run_simple_test_impl(vec![air.clone()], vec![trace], vec![public_values])
+                .unwrap_or_else(|err| panic!("verification of case {case} failed: {err:?}"));
+        }
+    }
+}
+
 /// A macro to create a `Vec<Arc<dyn AnyRap<_>>>` from a list of AIRs because Rust cannot infer the
 /// type correctly when using `vec!`.
 #[macro_export]